@@ -1,12 +1,11 @@
 #![feature(test)]
-#![feature(bench_black_box)]
 #![cfg(not(tarpaulin_include))]
 
 extern crate rand;
 extern crate rand_pcg;
 extern crate test;
 
-use evalexpr::build_operator_tree;
+use evalexpr::{build_operator_tree, context_map, EvalexprResult, Node, Value};
 use rand::{distributions::Uniform, seq::SliceRandom, Rng, SeedableRng};
 use rand_pcg::Pcg32;
 use std::hint::black_box;
@@ -23,10 +22,16 @@ fn generate_expression<Gen: Rng>(len: usize, gen: &mut Gen) -> String {
     result.push_str(&format!("{}", gen.sample(int_distribution)));
 
     while result.len() < len {
+        let operator = *operators.choose(gen).unwrap();
         result.push_str(whitespaces.choose(gen).unwrap());
-        result.push_str(operators.choose(gen).unwrap());
+        result.push_str(operator);
         result.push_str(whitespaces.choose(gen).unwrap());
-        result.push_str(&format!("{}", gen.sample(int_distribution)));
+        // Integer powers of such operands overflow, so exponents are floats
+        if operator == "^" {
+            result.push_str(&format!("{}.0", gen.sample(int_distribution)));
+        } else {
+            result.push_str(&format!("{}", gen.sample(int_distribution)));
+        }
     }
 
     result
@@ -125,6 +130,95 @@ fn bench_evaluate_many_small_expressions(bencher: &mut Bencher) {
     });
 }
 
+/// Compiled expressions are only comparable with tree walking if both compute the same result.
+/// The results are compared by their debug representation, as `NaN` is not equal to itself.
+fn assert_same_result(compiled: EvalexprResult<Value>, tree: EvalexprResult<Value>) {
+    assert_eq!(format!("{:?}", compiled), format!("{:?}", tree));
+}
+
+#[bench]
+fn bench_evaluate_long_expression_chains_compiled(bencher: &mut Bencher) {
+    let mut gen = Pcg32::seed_from_u64(0);
+    let tree = build_operator_tree(&generate_expression_chain(BENCHMARK_LEN, &mut gen)).unwrap();
+    let long_expression_chain = tree.compile();
+    let mut context = evalexpr::HashMapContext::new();
+    assert_same_result(
+        long_expression_chain.eval_with_context_mut(&mut context),
+        tree.eval(),
+    );
+
+    bencher.iter(|| {
+        long_expression_chain
+            .eval_with_context_mut(&mut context)
+            .unwrap()
+    });
+}
+
+#[bench]
+fn bench_evaluate_deep_expression_trees_compiled(bencher: &mut Bencher) {
+    let mut gen = Pcg32::seed_from_u64(15);
+    let tree = build_operator_tree(&generate_expression(BENCHMARK_LEN, &mut gen)).unwrap();
+    let deep_expression_tree = tree.compile();
+    let mut context = evalexpr::HashMapContext::new();
+    assert_same_result(
+        deep_expression_tree.eval_with_context_mut(&mut context),
+        tree.eval(),
+    );
+
+    bencher.iter(|| {
+        deep_expression_tree
+            .eval_with_context_mut(&mut context)
+            .unwrap()
+    });
+}
+
+#[bench]
+fn bench_evaluate_many_small_expressions_compiled(bencher: &mut Bencher) {
+    let mut gen = Pcg32::seed_from_u64(33);
+    let trees: Vec<_> = generate_small_expressions(BENCHMARK_LEN, &mut gen)
+        .iter()
+        .map(|expression| build_operator_tree(expression).unwrap())
+        .collect();
+    let small_expressions: Vec<_> = trees.iter().map(Node::compile).collect();
+    let mut context = evalexpr::HashMapContext::new();
+    for (tree, expression) in trees.iter().zip(&small_expressions) {
+        assert_same_result(expression.eval_with_context_mut(&mut context), tree.eval());
+    }
+
+    bencher.iter(|| {
+        for expression in &small_expressions {
+            black_box(expression.eval_with_context_mut(&mut context).unwrap());
+        }
+    });
+}
+
+const VARIABLE_EXPRESSION: &str = "a * b + c / (d - a) > 3 && e != a || b == c";
+
+#[bench]
+fn bench_evaluate_with_variables(bencher: &mut Bencher) {
+    let expression = build_operator_tree(VARIABLE_EXPRESSION).unwrap();
+    let context = context_map! { "a" => 1, "b" => 2, "c" => 3, "d" => 4.5, "e" => 5 }.unwrap();
+
+    bencher.iter(|| expression.eval_with_context(&context).unwrap());
+}
+
+#[bench]
+fn bench_evaluate_with_variables_compiled(bencher: &mut Bencher) {
+    let expression = build_operator_tree(VARIABLE_EXPRESSION).unwrap().compile();
+    let context = context_map! { "a" => 1, "b" => 2, "c" => 3, "d" => 4.5, "e" => 5 }.unwrap();
+
+    bencher.iter(|| expression.eval_with_context(&context).unwrap());
+}
+
+#[bench]
+fn bench_evaluate_with_variables_compiled_slots(bencher: &mut Bencher) {
+    let expression = build_operator_tree(VARIABLE_EXPRESSION).unwrap().compile();
+    let slots: Vec<Value> = vec![1.into(), 2.into(), 3.into(), 4.5.into(), 5.into()];
+    let context = evalexpr::EmptyContext;
+
+    bencher.iter(|| expression.eval_with_slots(&slots, &context).unwrap());
+}
+
 #[bench]
 fn bench_evaluate_large_tuple_expression(bencher: &mut Bencher) {
     let mut gen = Pcg32::seed_from_u64(44);
@@ -0,0 +1,165 @@
+//! The `bigint` module contains the arithmetic and conversions of `Value::BigInt`, which requires the `bigint_support` feature flag.
+
+use std::convert::TryFrom;
+
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::{
+    overflow::OverflowPolicy,
+    value::{BigIntType, FloatType, IntType, Value},
+};
+
+/// Parses an integer literal that does not fit into an `IntType`, like `18446744073709551616`.
+/// Returns `None` if the literal does not consist of decimal digits only.
+pub(crate) fn parse_literal(literal: &str) -> Option<BigIntType> {
+    if literal.is_empty() || !literal.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    literal.parse().ok()
+}
+
+/// Returns the operands of a binary operator as big integers, if one of them is a big integer and the other one is a big integer or an integer.
+/// Operations involving floats or decimals are computed with floats instead.
+pub(crate) fn operands(a: &Value, b: &Value) -> Option<(BigIntType, BigIntType)> {
+    match (a, b) {
+        (Value::BigInt(a), Value::BigInt(b)) => Some((a.clone(), b.clone())),
+        (Value::BigInt(a), Value::Int(b)) => Some((a.clone(), BigIntType::from(*b))),
+        (Value::Int(a), Value::BigInt(b)) => Some((BigIntType::from(*a), b.clone())),
+        _ => None,
+    }
+}
+
+/// Like `operands`, but also returns two integers as big integers if the current overflow policy is `OverflowPolicy::PromoteToBigInt`.
+pub(crate) fn arithmetic_operands(a: &Value, b: &Value) -> Option<(BigIntType, BigIntType)> {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) if promotes() => {
+            Some((BigIntType::from(*a), BigIntType::from(*b)))
+        },
+        (a, b) => operands(a, b),
+    }
+}
+
+/// Returns the operand of a unary operator as big integer, if it is a big integer,
+/// or an integer while the current overflow policy is `OverflowPolicy::PromoteToBigInt`.
+pub(crate) fn arithmetic_operand(a: &Value) -> Option<BigIntType> {
+    match a {
+        Value::BigInt(a) => Some(a.clone()),
+        Value::Int(a) if promotes() => Some(BigIntType::from(*a)),
+        _ => None,
+    }
+}
+
+/// Returns true if integer operations are performed on big integers under the current overflow policy.
+fn promotes() -> bool {
+    OverflowPolicy::current() == OverflowPolicy::PromoteToBigInt
+}
+
+/// Converts the given big integer into a `Value::Int` if it fits into an `IntType`, and into a `Value::BigInt` otherwise.
+/// All results of operations on big integers are normalised this way, such that equal numbers are equal values.
+pub(crate) fn normalize(bigint: BigIntType) -> Value {
+    match IntType::try_from(&bigint) {
+        Ok(int) => Value::Int(int),
+        Err(_) => Value::BigInt(bigint),
+    }
+}
+
+/// Returns the quotient of `a` and `b` rounded towards zero, or `None` if `b` is zero.
+pub(crate) fn div(a: BigIntType, b: BigIntType) -> Option<Value> {
+    if b.is_zero() {
+        None
+    } else {
+        Some(normalize(a / b))
+    }
+}
+
+/// Returns the remainder of the division of `a` by `b`, which has the sign of `a`, or `None` if `b` is zero.
+pub(crate) fn rem(a: BigIntType, b: BigIntType) -> Option<Value> {
+    if b.is_zero() {
+        None
+    } else {
+        Some(normalize(a % b))
+    }
+}
+
+/// Returns the quotient of `a` and `b` rounded towards negative infinity, or `None` if `b` is zero.
+pub(crate) fn floor_div(a: BigIntType, b: BigIntType) -> Option<Value> {
+    if b.is_zero() {
+        return None;
+    }
+    let quotient = &a / &b;
+    if !(&a % &b).is_zero() && (a.is_negative() != b.is_negative()) {
+        Some(normalize(quotient - 1))
+    } else {
+        Some(normalize(quotient))
+    }
+}
+
+/// Returns the quotient of the euclidean division of `a` by `b`, or `None` if `b` is zero.
+pub(crate) fn div_euclid(a: BigIntType, b: BigIntType) -> Option<Value> {
+    if b.is_zero() {
+        return None;
+    }
+    let remainder = euclidean_remainder(&a, &b);
+    Some(normalize((a - remainder) / b))
+}
+
+/// Returns the non-negative remainder of the euclidean division of `a` by `b`, or `None` if `b` is zero.
+pub(crate) fn rem_euclid(a: BigIntType, b: BigIntType) -> Option<Value> {
+    if b.is_zero() {
+        None
+    } else {
+        Some(normalize(euclidean_remainder(&a, &b)))
+    }
+}
+
+fn euclidean_remainder(a: &BigIntType, b: &BigIntType) -> BigIntType {
+    let remainder = a % b;
+    if remainder.is_negative() {
+        remainder + b.abs()
+    } else {
+        remainder
+    }
+}
+
+/// Returns `base` raised to the power of the non-negative integer `exponent`, or `None` if the power would be larger than `MAX_POW_SIZE`.
+/// Powers of zero, one and minus one are computed for all exponents.
+pub(crate) fn pow(base: BigIntType, exponent: IntType) -> Option<Value> {
+    debug_assert!(exponent >= 0);
+    if exponent == 0 {
+        return Some(Value::Int(1));
+    }
+    match IntType::try_from(&base) {
+        Ok(0) | Ok(1) => return Some(normalize(base)),
+        Ok(-1) => return Some(Value::Int(if exponent % 2 == 0 { 1 } else { -1 })),
+        _ => {},
+    }
+
+    if pow_size(&base, exponent) > MAX_POW_SIZE {
+        return None;
+    }
+    Some(normalize(base.pow(u32::try_from(exponent).ok()?)))
+}
+
+/// The maximum size in bytes of a power computed by `pow`, which prevents computing powers that take very long or exhaust the memory.
+const MAX_POW_SIZE: usize = 1 << 20;
+
+/// Returns an upper bound of the size in bytes of `base` raised to the power of the non-negative integer `exponent`, without computing it.
+pub(crate) fn pow_size(base: &BigIntType, exponent: IntType) -> usize {
+    debug_assert!(exponent >= 0);
+    if base.bits() <= 1 {
+        return size(base);
+    }
+    let bits = base.bits().saturating_mul(exponent as u64);
+    usize::try_from(bits / 8 + 1).unwrap_or(usize::MAX)
+}
+
+/// Returns the size of the given big integer in bytes, which is the size of its magnitude.
+pub(crate) fn size(bigint: &BigIntType) -> usize {
+    usize::try_from((bigint.bits() + 7) / 8).unwrap_or(usize::MAX)
+}
+
+/// Converts the given big integer to the nearest float, which is infinite if the big integer is too large.
+pub(crate) fn to_float(bigint: &BigIntType) -> FloatType {
+    // Cannot fail, as big integers outside of the range of floats are converted to infinity
+    bigint.to_f64().unwrap()
+}
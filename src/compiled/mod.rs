@@ -0,0 +1,408 @@
+//! The `compiled` module contains the `CompiledExpression`, a flat representation of an operator tree that is evaluated by a stack machine.
+//!
+//! Evaluating a compiled expression does not recurse and needs only a single stack allocation, which makes it faster than evaluating the operator tree directly if the same expression is evaluated many times.
+
+use std::collections::HashMap;
+
+use crate::{
+    error::{EvalexprError, EvalexprResult},
+    function::{Function, FunctionDefinition},
+    limits,
+    operator::{variable_value, Operator},
+    value::Value,
+    Context, ContextWithMutableFunctions, ContextWithMutableVariables, Node,
+};
+
+/// A single instruction of a compiled expression.
+#[derive(Debug, PartialEq, Clone)]
+enum Instruction {
+    /// Push a constant value onto the stack.
+    Push(Value),
+    /// Push the value of the variable in the given slot onto the stack.
+    LoadVariable(usize),
+    /// Pop the given amount of arguments from the stack, apply the operator to them and push the result.
+    Apply {
+        operator: Operator,
+        arguments: usize,
+    },
+    /// Check that the top of the stack is a boolean.
+    /// If it equals `value`, jump to `target` leaving the boolean on the stack, otherwise pop it.
+    /// This implements the short-circuiting of `&&` and `||`.
+    ShortCircuit { value: bool, target: usize },
+    /// Replace the top of the stack by itself, checking that it is a boolean.
+    ExpectBoolean,
+    /// Pop the top of the stack, which must be a boolean, and jump to `target` if it is false.
+    JumpIfFalse(usize),
+    /// Jump to `target`.
+    Jump(usize),
+    /// Push the given function value, after capturing the values of the variables in the given slots that can be loaded.
+    CreateFunction {
+        definition: FunctionDefinition,
+        captured: Vec<usize>,
+    },
+    /// Store the function defined by the given node under the given identifier in the context and push an empty value.
+    DefineFunction {
+        identifier: String,
+        definition: Node,
+    },
+}
+
+/// An operator tree compiled into a flat program for a stack machine.
+///
+/// Variables are resolved into slots when compiling, which allows to evaluate a compiled expression against a slice of values with `CompiledExpression::eval_with_slots` instead of looking them up by identifier in a context.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let compiled = build_operator_tree("a * b + a").unwrap().compile(); // Do proper error handling here
+/// assert_eq!(compiled.variable_identifiers(), &["a".to_string(), "b".to_string()]);
+///
+/// let context = context_map! { "a" => 2, "b" => 3 }.unwrap(); // Do proper error handling here
+/// assert_eq!(compiled.eval_with_context(&context), Ok(Value::from(8)));
+/// assert_eq!(compiled.eval_with_slots(&[4.into(), 5.into()], &EmptyContext), Ok(Value::from(24)));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompiledExpression {
+    instructions: Vec<Instruction>,
+    variable_identifiers: Vec<String>,
+    max_stack_size: usize,
+}
+
+impl CompiledExpression {
+    /// Compiles the operator tree rooted at the given node.
+    pub fn new(node: &Node) -> Self {
+        let mut compiler = Compiler::default();
+        compiler.compile(node);
+
+        Self {
+            instructions: compiler.instructions,
+            variable_identifiers: compiler.variable_identifiers,
+            max_stack_size: compiler.max_stack_size,
+        }
+    }
+
+    /// Returns the identifiers of all variables read by this expression, ordered by their slot.
+    pub fn variable_identifiers(&self) -> &[String] {
+        &self.variable_identifiers
+    }
+
+    /// Returns the slot of the variable with the given identifier, or `None` if the expression does not read the variable.
+    pub fn variable_slot(&self, identifier: &str) -> Option<usize> {
+        self.variable_identifiers
+            .iter()
+            .position(|variable_identifier| variable_identifier == identifier)
+    }
+
+    /// Evaluates this expression with the given context.
+    ///
+    /// Fails, if one of the operators in the expression fails.
+    pub fn eval_with_context<C: Context>(&self, context: &C) -> EvalexprResult<Value> {
+        self.run(&mut ImmutableMachine { context })
+    }
+
+    /// Evaluates this expression with the given mutable context.
+    ///
+    /// Fails, if one of the operators in the expression fails.
+    pub fn eval_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
+        &self,
+        context: &mut C,
+    ) -> EvalexprResult<Value> {
+        self.run(&mut MutableMachine { context })
+    }
+
+    /// Evaluates this expression, reading the value of each variable from the given slots.
+    /// The order of the slots is given by `CompiledExpression::variable_identifiers`.
+    /// Variables whose slot is out of range of `slots` are reported as `EvalexprError::VariableIdentifierNotFound`.
+    ///
+    /// Functions are still looked up in the given context.
+    /// As the slots are immutable, expressions containing assignments fail with `EvalexprError::ContextNotMutable`.
+    ///
+    /// Fails, if one of the operators in the expression fails.
+    pub fn eval_with_slots<C: Context>(
+        &self,
+        slots: &[Value],
+        context: &C,
+    ) -> EvalexprResult<Value> {
+        self.run(&mut SlotMachine { slots, context })
+    }
+
+    fn run<M: Machine>(&self, machine: &mut M) -> EvalexprResult<Value> {
+        let mut stack: Vec<Value> = Vec::with_capacity(self.max_stack_size);
+        let mut instruction_pointer = 0;
+
+        while let Some(instruction) = self.instructions.get(instruction_pointer) {
+            instruction_pointer += 1;
+            limits::count_operation()?;
+
+            match instruction {
+                Instruction::Push(value) => stack.push(value.clone()),
+                Instruction::LoadVariable(slot) => {
+                    let value = machine.load(*slot, &self.variable_identifiers[*slot])?;
+                    stack.push(value);
+                },
+                Instruction::Apply {
+                    operator,
+                    arguments,
+                } => {
+                    let first_argument = stack.len() - arguments;
+                    let result = machine.apply(operator, &stack[first_argument..])?;
+                    limits::check_value_size(&result)?;
+                    stack.truncate(first_argument);
+                    stack.push(result);
+                },
+                Instruction::ShortCircuit { value, target } => {
+                    // The stack cannot be empty, because the left argument was pushed before.
+                    if stack.last().unwrap().as_boolean()? == *value {
+                        instruction_pointer = *target;
+                    } else {
+                        stack.pop();
+                    }
+                },
+                Instruction::ExpectBoolean => {
+                    // The stack cannot be empty, because the right argument was pushed before.
+                    stack.last().unwrap().as_boolean()?;
+                },
+                Instruction::JumpIfFalse(target) => {
+                    // The stack cannot be empty, because the condition was pushed before.
+                    if !stack.pop().unwrap().as_boolean()? {
+                        instruction_pointer = *target;
+                    }
+                },
+                Instruction::Jump(target) => instruction_pointer = *target,
+                Instruction::CreateFunction {
+                    definition,
+                    captured,
+                } => {
+                    let mut definition = definition.clone();
+                    definition.capture(captured.iter().filter_map(|slot| {
+                        let identifier = &self.variable_identifiers[*slot];
+                        let value = machine.load(*slot, identifier).ok()?;
+                        Some((identifier.clone(), value))
+                    }));
+                    stack.push(Value::Function(Function::defined(definition)));
+                },
+                Instruction::DefineFunction {
+                    identifier,
+                    definition,
+                } => {
+                    let definition = FunctionDefinition::from_node(definition)?;
+                    let function = Function::defined(definition).with_name(identifier.clone());
+                    machine.define_function(identifier, function)?;
+                    stack.push(Value::Empty);
+                },
+            }
+        }
+
+        // A compiled expression always leaves exactly one value on the stack.
+        Ok(stack.pop().unwrap_or(Value::Empty))
+    }
+}
+
+impl From<&Node> for CompiledExpression {
+    fn from(node: &Node) -> Self {
+        Self::new(node)
+    }
+}
+
+impl Node {
+    /// Compiles the operator tree rooted at this node into a `CompiledExpression`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let compiled = build_operator_tree("x > 0 && 10 / x > 2").unwrap().compile(); // Do proper error handling here
+    /// assert_eq!(compiled.eval_with_slots(&[0.into()], &EmptyContext), Ok(Value::from(false)));
+    /// assert_eq!(compiled.eval_with_slots(&[3.into()], &EmptyContext), Ok(Value::from(true)));
+    /// ```
+    pub fn compile(&self) -> CompiledExpression {
+        CompiledExpression::new(self)
+    }
+}
+
+/// Translates operator trees into instructions.
+#[derive(Default)]
+struct Compiler {
+    instructions: Vec<Instruction>,
+    variable_identifiers: Vec<String>,
+    variable_slots: HashMap<String, usize>,
+    stack_size: usize,
+    max_stack_size: usize,
+}
+
+impl Compiler {
+    /// Emits the instructions that push the value of the given node onto the stack.
+    fn compile(&mut self, node: &Node) {
+        match (node.operator(), node.children()) {
+            (Operator::RootNode, [child]) => self.compile(child),
+            (Operator::Const { value }, []) => {
+                self.emit(Instruction::Push(value.clone()));
+                self.grow_stack(1);
+            },
+            (Operator::VariableIdentifier { identifier }, []) => {
+                let slot = self.variable_slot(identifier);
+                self.emit(Instruction::LoadVariable(slot));
+                self.grow_stack(1);
+            },
+            (Operator::And, [left, right]) | (Operator::Or, [left, right]) => {
+                let value = node.operator() == &Operator::Or;
+                self.compile(left);
+                let short_circuit = self.emit(Instruction::ShortCircuit { value, target: 0 });
+                self.stack_size -= 1;
+                self.compile(right);
+                self.emit(Instruction::ExpectBoolean);
+                let target = self.instructions.len();
+                self.instructions[short_circuit] = Instruction::ShortCircuit { value, target };
+            },
+            (Operator::FunctionIdentifier { identifier }, [argument])
+                if identifier == "if" && argument.conditional_arguments().is_some() =>
+            {
+                // Checked by the match guard
+                let (condition, then, otherwise) = argument.conditional_arguments().unwrap();
+                self.compile(condition);
+                let jump_if_false = self.emit(Instruction::JumpIfFalse(0));
+                self.stack_size -= 1;
+                self.compile(then);
+                let jump = self.emit(Instruction::Jump(0));
+                self.stack_size -= 1;
+                self.instructions[jump_if_false] =
+                    Instruction::JumpIfFalse(self.instructions.len());
+                self.compile(otherwise);
+                self.instructions[jump] = Instruction::Jump(self.instructions.len());
+            },
+            (Operator::Assign, _) if node.function_assignment().is_some() => {
+                // Checked by the match guard
+                let (identifier, definition) = node.function_assignment().unwrap();
+                self.emit(Instruction::DefineFunction {
+                    identifier: identifier.to_string(),
+                    definition: definition.clone(),
+                });
+                self.grow_stack(1);
+            },
+            // The body of a function definition is only evaluated when the function is called
+            (Operator::FunctionDefinition { .. }, _) => {
+                let instruction = match FunctionDefinition::from_node(node) {
+                    Ok(definition) => {
+                        let captured = definition
+                            .free_variables()
+                            .iter()
+                            .map(|identifier| self.variable_slot(identifier))
+                            .collect();
+                        Instruction::CreateFunction {
+                            definition,
+                            captured,
+                        }
+                    },
+                    Err(_) => Instruction::Apply {
+                        operator: node.operator().clone(),
+                        arguments: 0,
+                    },
+                };
+                self.emit(instruction);
+                self.grow_stack(1);
+            },
+            (operator, children) => {
+                for child in children {
+                    self.compile(child);
+                }
+                self.emit(Instruction::Apply {
+                    operator: operator.clone(),
+                    arguments: children.len(),
+                });
+                self.stack_size -= children.len();
+                self.grow_stack(1);
+            },
+        }
+    }
+
+    /// Appends the instruction to the program and returns its index.
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+
+    fn grow_stack(&mut self, amount: usize) {
+        self.stack_size += amount;
+        self.max_stack_size = self.max_stack_size.max(self.stack_size);
+    }
+
+    fn variable_slot(&mut self, identifier: &str) -> usize {
+        if let Some(slot) = self.variable_slots.get(identifier) {
+            *slot
+        } else {
+            let slot = self.variable_identifiers.len();
+            self.variable_identifiers.push(identifier.to_string());
+            self.variable_slots.insert(identifier.to_string(), slot);
+            slot
+        }
+    }
+}
+
+/// The interface between the stack machine and the environment it evaluates in.
+trait Machine {
+    /// Returns the value of the variable in the given slot.
+    fn load(&mut self, slot: usize, identifier: &str) -> EvalexprResult<Value>;
+
+    /// Applies the given operator to the given arguments.
+    fn apply(&mut self, operator: &Operator, arguments: &[Value]) -> EvalexprResult<Value>;
+
+    /// Stores the given function under the given identifier.
+    fn define_function(&mut self, _identifier: &str, _function: Function) -> EvalexprResult<()> {
+        Err(EvalexprError::ContextNotMutable)
+    }
+}
+
+struct ImmutableMachine<'a, C> {
+    context: &'a C,
+}
+
+impl<'a, C: Context> Machine for ImmutableMachine<'a, C> {
+    fn load(&mut self, _slot: usize, identifier: &str) -> EvalexprResult<Value> {
+        variable_value(self.context, identifier)
+    }
+
+    fn apply(&mut self, operator: &Operator, arguments: &[Value]) -> EvalexprResult<Value> {
+        operator.eval(arguments, self.context)
+    }
+}
+
+struct MutableMachine<'a, C> {
+    context: &'a mut C,
+}
+
+impl<'a, C: ContextWithMutableVariables + ContextWithMutableFunctions> Machine
+    for MutableMachine<'a, C>
+{
+    fn load(&mut self, _slot: usize, identifier: &str) -> EvalexprResult<Value> {
+        variable_value(self.context, identifier)
+    }
+
+    fn apply(&mut self, operator: &Operator, arguments: &[Value]) -> EvalexprResult<Value> {
+        operator.eval_mut(arguments, self.context)
+    }
+
+    fn define_function(&mut self, identifier: &str, function: Function) -> EvalexprResult<()> {
+        self.context.set_function(identifier.to_string(), function)
+    }
+}
+
+struct SlotMachine<'a, C> {
+    slots: &'a [Value],
+    context: &'a C,
+}
+
+impl<'a, C: Context> Machine for SlotMachine<'a, C> {
+    fn load(&mut self, slot: usize, identifier: &str) -> EvalexprResult<Value> {
+        self.slots
+            .get(slot)
+            .cloned()
+            .ok_or_else(|| EvalexprError::VariableIdentifierNotFound(identifier.to_string()))
+    }
+
+    fn apply(&mut self, operator: &Operator, arguments: &[Value]) -> EvalexprResult<Value> {
+        operator.eval(arguments, self.context)
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+use crate::{
+    context::{Context, ContextWithMutableFunctions, ContextWithMutableVariables, HashMapContext},
+    function::{Function, FunctionMetadata},
+    value::{value_type::ValueType, Value},
+    EvalexprError, EvalexprResult,
+};
+
+/// A context that can be shared between threads, which evaluate expressions and assign to variables concurrently.
+///
+/// All methods take `&self`, and the variables and functions are protected by read-write locks.
+/// To evaluate expressions with assignments, pass a mutable reference to a shared reference of the context, like `&mut &context`.
+/// Compound assignments like `a += 1` are atomic, so concurrent updates of the same variable are not lost.
+/// Sequences of assignments are not atomic as a whole.
+///
+/// Like `HashMapContext`, this context is type-safe, meaning that an identifier that is assigned a value of some type once cannot be assigned a value of another type.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+/// use std::{sync::Arc, thread};
+///
+/// let context = Arc::new(ConcurrentContext::new());
+/// context.set_value("counter".into(), Value::from(0)).unwrap(); // Do proper error handling here
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|_| {
+///         let context = Arc::clone(&context);
+///         thread::spawn(move || {
+///             for _ in 0..100 {
+///                 eval_with_context_mut("counter += 1", &mut &*context).unwrap(); // Do proper error handling here
+///             }
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     thread.join().unwrap();
+/// }
+/// assert_eq!(eval_with_context("counter", &*context), Ok(Value::from(400)));
+/// ```
+#[derive(Debug, Default)]
+pub struct ConcurrentContext {
+    variables: RwLock<HashMap<String, Value>>,
+    /// Counts the changes of the variables, such that `update_value` can detect changes while `update` runs.
+    /// It is only changed while the lock of the variables is held for writing.
+    revision: AtomicUsize,
+    functions: RwLock<HashMap<String, Function>>,
+}
+
+impl ConcurrentContext {
+    /// Constructs a `ConcurrentContext` with no mappings.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the variable with the given identifier to the given value.
+    /// Returns `Err` if the variable already holds a value of another type.
+    pub fn set_value(&self, identifier: String, value: Value) -> EvalexprResult<()> {
+        let mut variables = self
+            .variables
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        self.insert_typed(&mut variables, identifier, value)
+    }
+
+    /// Atomically sets the variable with the given identifier to the value that `update` computes from its current value.
+    ///
+    /// No lock is held while `update` runs, so it may access this context.
+    /// If a variable of this context changes in the meantime, `update` is called again with the new current value,
+    /// so it may be called more than once.
+    ///
+    /// Returns `Err` if the variable does not exist, if `update` fails, or if it returns a value of another type.
+    pub fn update_value(
+        &self,
+        identifier: String,
+        update: &mut dyn FnMut(Value) -> EvalexprResult<Value>,
+    ) -> EvalexprResult<()> {
+        loop {
+            let (value, revision) = {
+                let variables = self
+                    .variables
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                match variables.get(&identifier) {
+                    Some(value) => (value.clone(), self.revision.load(Ordering::Relaxed)),
+                    None => return Err(EvalexprError::VariableIdentifierNotFound(identifier)),
+                }
+            };
+            let value = update(value)?;
+
+            let mut variables = self
+                .variables
+                .write()
+                .unwrap_or_else(PoisonError::into_inner);
+            if self.revision.load(Ordering::Relaxed) == revision {
+                return self.insert_typed(&mut variables, identifier, value);
+            }
+        }
+    }
+
+    /// Removes the variable with the given identifier, and returns its value if it existed.
+    pub fn remove_value(&self, identifier: &str) -> Option<Value> {
+        let mut variables = self
+            .variables
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        let value = variables.remove(identifier);
+        if value.is_some() {
+            self.revision.fetch_add(1, Ordering::Relaxed);
+        }
+        value
+    }
+
+    /// Sets the function with the given identifier to the given function.
+    pub fn set_function(&self, identifier: String, function: Function) {
+        self.functions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(identifier, function);
+    }
+
+    /// Removes the function with the given identifier, and returns it if it existed.
+    pub fn remove_function(&self, identifier: &str) -> Option<Function> {
+        self.functions
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(identifier)
+    }
+
+    /// Returns a copy of the variables and functions of this context at a single point in time.
+    pub fn snapshot(&self) -> HashMapContext {
+        let variables = self
+            .variables
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        let functions = self
+            .functions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        HashMapContext {
+            variables: variables.clone(),
+            functions: functions.clone(),
+        }
+    }
+
+    /// Returns a clone of the function with the given identifier, such that the lock is not held while calling it.
+    fn function(&self, identifier: &str) -> Option<Function> {
+        self.functions
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(identifier)
+            .cloned()
+    }
+
+    /// Inserts the given value into the given variables, which must be locked for writing,
+    /// or returns `Err` if the variable already holds a value of another type.
+    fn insert_typed(
+        &self,
+        variables: &mut HashMap<String, Value>,
+        identifier: String,
+        value: Value,
+    ) -> EvalexprResult<()> {
+        match variables.get_mut(&identifier) {
+            Some(existing_value)
+                if ValueType::from(&*existing_value) != ValueType::from(&value) =>
+            {
+                return Err(EvalexprError::expected_type(existing_value, value));
+            },
+            Some(existing_value) => *existing_value = value,
+            None => {
+                variables.insert(identifier, value);
+            },
+        }
+        self.revision.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Context for ConcurrentContext {
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        self.variables
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(identifier)
+            .cloned()
+            .map(Cow::Owned)
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        match self.function(identifier) {
+            Some(function) => function.call(argument, self),
+            None => Err(EvalexprError::FunctionIdentifierNotFound(
+                identifier.to_string(),
+            )),
+        }
+    }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        self.function(identifier).as_ref().map(Function::is_pure)
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        self.function(identifier).as_ref().map(Function::metadata)
+    }
+}
+
+impl ContextWithMutableVariables for ConcurrentContext {
+    fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        ConcurrentContext::set_value(self, identifier, value)
+    }
+
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        Ok(ConcurrentContext::remove_value(self, identifier))
+    }
+
+    fn update_value(
+        &mut self,
+        identifier: String,
+        update: &mut dyn FnMut(Value) -> EvalexprResult<Value>,
+    ) -> EvalexprResult<()> {
+        ConcurrentContext::update_value(self, identifier, update)
+    }
+}
+
+impl ContextWithMutableFunctions for ConcurrentContext {
+    fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+        ConcurrentContext::set_function(self, identifier, function);
+        Ok(())
+    }
+
+    fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+        Ok(ConcurrentContext::remove_function(self, identifier))
+    }
+}
+
+/// Shared references to a `ConcurrentContext` are contexts as well, which allows to assign to variables through `&mut &context`.
+impl Context for &ConcurrentContext {
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        (**self).get_value(identifier)
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        (**self).call_function(identifier, argument)
+    }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        (**self).is_function_pure(identifier)
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        (**self).function_metadata(identifier)
+    }
+}
+
+impl ContextWithMutableVariables for &ConcurrentContext {
+    fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        ConcurrentContext::set_value(self, identifier, value)
+    }
+
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        Ok(ConcurrentContext::remove_value(self, identifier))
+    }
+
+    fn update_value(
+        &mut self,
+        identifier: String,
+        update: &mut dyn FnMut(Value) -> EvalexprResult<Value>,
+    ) -> EvalexprResult<()> {
+        ConcurrentContext::update_value(self, identifier, update)
+    }
+}
+
+impl ContextWithMutableFunctions for &ConcurrentContext {
+    fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+        ConcurrentContext::set_function(self, identifier, function);
+        Ok(())
+    }
+
+    fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+        Ok(ConcurrentContext::remove_function(self, identifier))
+    }
+}
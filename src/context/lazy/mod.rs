@@ -0,0 +1,176 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt};
+
+use crate::{
+    context::{
+        Context, ContextWithMutableFunctions, ContextWithMutableVariables, GetFunctionContext,
+    },
+    function::{Function, FunctionMetadata},
+    value::Value,
+    EvalexprError, EvalexprResult,
+};
+
+/// A context that resolves variables on demand by calling a user-defined function with their identifier.
+///
+/// This avoids computing the values of all variables that an expression might use before evaluating it.
+/// The resolver returns `None` for identifiers it does not know.
+/// If the context is created with `LazyContext::cached`, each identifier is resolved at most once, and the result is remembered until `LazyContext::clear_cache` is called.
+///
+/// Assignments are stored in the context and shadow the values of the resolver. Functions are stored in the context as well.
+/// Unlike `HashMapContext`, this context is not type-safe.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let prices = vec![("apple", 0.5), ("pear", 0.75)];
+/// let mut context = LazyContext::new(move |identifier| {
+///     prices.iter().find(|(name, _)| *name == identifier).map(|(_, price)| Value::from(*price))
+/// });
+/// assert_eq!(eval_with_context("apple * 2 + pear", &context), Ok(Value::from(1.75)));
+/// assert_eq!(eval_with_context("plum", &context), Err(EvalexprError::VariableIdentifierNotFound("plum".into())));
+///
+/// eval_with_context_mut("apple = 1.0", &mut context).unwrap(); // Do proper error handling here
+/// assert_eq!(eval_with_context("apple", &context), Ok(Value::from(1.0)));
+/// ```
+pub struct LazyContext<F> {
+    resolver: F,
+    /// The values returned by the resolver, or `None` if the values are not cached.
+    cache: Option<RefCell<HashMap<String, Option<Value>>>>,
+    variables: HashMap<String, Value>,
+    functions: HashMap<String, Function>,
+}
+
+impl<F: Fn(&str) -> Option<Value>> LazyContext<F> {
+    /// Creates a context that calls `resolver` each time the value of a variable is needed.
+    pub fn new(resolver: F) -> Self {
+        Self {
+            resolver,
+            cache: None,
+            variables: HashMap::new(),
+            functions: HashMap::new(),
+        }
+    }
+
+    /// Creates a context that calls `resolver` the first time the value of a variable is needed, and remembers the result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    /// use std::cell::Cell;
+    ///
+    /// let calls = Cell::new(0);
+    /// let context = LazyContext::cached(|identifier| {
+    ///     calls.set(calls.get() + 1);
+    ///     Some(Value::from(identifier.len() as IntType))
+    /// });
+    /// assert_eq!(eval_with_context("abc + abc + ab", &context), Ok(Value::from(8)));
+    /// assert_eq!(calls.get(), 2);
+    /// ```
+    pub fn cached(resolver: F) -> Self {
+        Self {
+            cache: Some(RefCell::new(HashMap::new())),
+            ..Self::new(resolver)
+        }
+    }
+
+    /// Forgets all values remembered from the resolver, such that they are resolved again when needed.
+    /// Assigned variables are kept.
+    pub fn clear_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.get_mut().clear();
+        }
+    }
+
+    /// Returns the value of the given identifier from the resolver, using the cache if enabled.
+    fn resolve(&self, identifier: &str) -> Option<Value> {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return (self.resolver)(identifier),
+        };
+
+        if let Some(value) = cache.borrow().get(identifier) {
+            return value.clone();
+        }
+        // The cache is not borrowed while resolving, as the resolver may evaluate expressions with this context
+        let value = (self.resolver)(identifier);
+        cache
+            .borrow_mut()
+            .insert(identifier.to_string(), value.clone());
+        value
+    }
+}
+
+impl<F> fmt::Debug for LazyContext<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("LazyContext")
+            .field("cache", &self.cache)
+            .field("variables", &self.variables)
+            .field("functions", &self.functions)
+            .finish()
+    }
+}
+
+impl<F: Fn(&str) -> Option<Value>> Context for LazyContext<F> {
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        match self.variables.get(identifier) {
+            Some(value) => Some(Cow::Borrowed(value)),
+            None => self.resolve(identifier).map(Cow::Owned),
+        }
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        match self.functions.get(identifier) {
+            Some(function) => function.call(argument, self),
+            None => Err(EvalexprError::FunctionIdentifierNotFound(
+                identifier.to_string(),
+            )),
+        }
+    }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        self.functions.get(identifier).map(Function::is_pure)
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        self.functions.get(identifier).map(Function::metadata)
+    }
+}
+
+impl<F: Fn(&str) -> Option<Value>> ContextWithMutableVariables for LazyContext<F> {
+    fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        self.variables.insert(identifier, value);
+        Ok(())
+    }
+
+    /// Removes the assigned value of the variable with the given identifier, such that it is resolved again when needed.
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        Ok(self.variables.remove(identifier))
+    }
+}
+
+impl<F: Fn(&str) -> Option<Value>> ContextWithMutableFunctions for LazyContext<F> {
+    fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+        self.functions.insert(identifier, function);
+        Ok(())
+    }
+
+    fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+        Ok(self.functions.remove(identifier))
+    }
+}
+
+impl<F: Fn(&str) -> Option<Value>> GetFunctionContext for LazyContext<F> {
+    fn get_function(&self, identifier: &str) -> Option<&Function> {
+        self.functions.get(identifier)
+    }
+
+    fn iter_functions(&self) -> Box<dyn Iterator<Item = (&str, &Function)> + '_> {
+        Box::new(
+            self.functions
+                .iter()
+                .map(|(identifier, function)| (identifier.as_str(), function)),
+        )
+    }
+}
@@ -4,24 +4,56 @@
 //! This crate implements two basic variants, the `EmptyContext`, that returns `None` for each identifier and cannot be manipulated, and the `HashMapContext`, that stores its mappings in hash maps.
 //! The HashMapContext is type-safe and returns an error if the user tries to assign a value of a different type than before to an identifier.
 
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap, iter};
 
 use crate::{
-    function::Function,
+    function::{Function, FunctionMetadata},
     value::{value_type::ValueType, Value},
     EvalexprError, EvalexprResult,
 };
 
+pub use self::{
+    concurrent::ConcurrentContext,
+    lazy::LazyContext,
+    recording::{RecordingContext, VariableChange},
+    scoped::ScopedContext,
+};
+
+mod concurrent;
+mod lazy;
 mod predefined;
+mod recording;
+mod scoped;
 
 /// An immutable context.
 pub trait Context {
     /// Returns the value that is linked to the given identifier.
-    fn get_value(&self, identifier: &str) -> Option<&Value>;
+    ///
+    /// The value can be borrowed from the context with `Cow::Borrowed`, or computed on demand and returned as `Cow::Owned`.
+    /// Use `Option::as_deref` to compare the result with a reference to a value.
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>>;
 
     /// Calls the function that is linked to the given identifier with the given argument.
     /// If no function with the given identifier is found, this method returns `EvalexprError::FunctionIdentifierNotFound`.
     fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value>;
+
+    /// Returns `Some(true)` if the function linked to the given identifier is pure, `Some(false)` if it is impure or if this context cannot tell,
+    /// and `None` if no function is linked to the given identifier.
+    ///
+    /// This is used by `Node::optimize_with_context` to decide if a function call can be evaluated ahead of time.
+    /// The default implementation returns `Some(false)`, which prevents function calls from being optimized.
+    fn is_function_pure(&self, _identifier: &str) -> Option<bool> {
+        Some(false)
+    }
+
+    /// Returns the metadata of the function linked to the given identifier,
+    /// or `None` if no function is linked to the given identifier or if this context cannot tell.
+    ///
+    /// Builtin functions are not part of any context, and their metadata is returned by `builtin_function_metadata`.
+    /// The default implementation returns `None`.
+    fn function_metadata(&self, _identifier: &str) -> Option<FunctionMetadata> {
+        None
+    }
 }
 
 /// A context that allows to assign to variables.
@@ -30,6 +62,28 @@ pub trait ContextWithMutableVariables: Context {
     fn set_value(&mut self, _identifier: String, _value: Value) -> EvalexprResult<()> {
         Err(EvalexprError::ContextNotMutable)
     }
+
+    /// Removes the variable with the given identifier, and returns its value if it existed.
+    fn remove_value(&mut self, _identifier: &str) -> EvalexprResult<Option<Value>> {
+        Err(EvalexprError::ContextNotMutable)
+    }
+
+    /// Sets the variable with the given identifier to the value that `update` computes from its current value.
+    /// This is used by compound assignments like `a += 1`.
+    ///
+    /// The default implementation reads the current value with `Context::get_value` and writes the new one with `ContextWithMutableVariables::set_value`.
+    /// Contexts that are shared between threads should override it to update the variable atomically.
+    fn update_value(
+        &mut self,
+        identifier: String,
+        update: &mut dyn FnMut(Value) -> EvalexprResult<Value>,
+    ) -> EvalexprResult<()> {
+        let value = match self.get_value(&identifier) {
+            Some(value) => update(value.into_owned())?,
+            None => return Err(EvalexprError::VariableIdentifierNotFound(identifier)),
+        };
+        self.set_value(identifier, value)
+    }
 }
 
 /// A context that allows to assign to function identifiers.
@@ -38,23 +92,46 @@ pub trait ContextWithMutableFunctions: Context {
     fn set_function(&mut self, _identifier: String, _function: Function) -> EvalexprResult<()> {
         Err(EvalexprError::ContextNotMutable)
     }
+
+    /// Removes the function with the given identifier, and returns it if it existed.
+    fn remove_function(&mut self, _identifier: &str) -> EvalexprResult<Option<Function>> {
+        Err(EvalexprError::ContextNotMutable)
+    }
+}
+
+/// A context that allows to iterate over its variables.
+pub trait IterateVariablesContext: Context {
+    /// Returns an iterator over the identifiers and values of all variables of this context, in no particular order.
+    fn iter_variables(&self) -> Box<dyn Iterator<Item = (&str, &Value)> + '_>;
+
+    /// Returns an iterator over the identifiers of all variables of this context, in no particular order.
+    fn iter_variable_names(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        Box::new(self.iter_variables().map(|(identifier, _)| identifier))
+    }
 }
 
-/*/// A context that allows to retrieve functions programmatically.
+/// A context that allows to retrieve functions programmatically.
 pub trait GetFunctionContext: Context {
     /// Returns the function that is linked to the given identifier.
     ///
-    /// This might not be possible for all functions, as some might be hard-coded.
-    /// In this case, a special error variant should be returned (Not yet implemented).
+    /// Builtin functions are not part of any context, and are not returned.
     fn get_function(&self, identifier: &str) -> Option<&Function>;
-}*/
+
+    /// Returns an iterator over the identifiers and functions of all functions of this context, in no particular order.
+    fn iter_functions(&self) -> Box<dyn Iterator<Item = (&str, &Function)> + '_>;
+
+    /// Returns an iterator over the identifiers of all functions of this context, in no particular order.
+    fn iter_function_names(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        Box::new(self.iter_functions().map(|(identifier, _)| identifier))
+    }
+}
 
 /// A context that returns `None` for each identifier.
 #[derive(Debug, Default)]
 pub struct EmptyContext;
 
 impl Context for EmptyContext {
-    fn get_value(&self, _identifier: &str) -> Option<&Value> {
+    fn get_value(&self, _identifier: &str) -> Option<Cow<'_, Value>> {
         None
     }
 
@@ -63,6 +140,26 @@ impl Context for EmptyContext {
             identifier.to_string(),
         ))
     }
+
+    fn is_function_pure(&self, _identifier: &str) -> Option<bool> {
+        None
+    }
+}
+
+impl IterateVariablesContext for EmptyContext {
+    fn iter_variables(&self) -> Box<dyn Iterator<Item = (&str, &Value)> + '_> {
+        Box::new(iter::empty())
+    }
+}
+
+impl GetFunctionContext for EmptyContext {
+    fn get_function(&self, _identifier: &str) -> Option<&Function> {
+        None
+    }
+
+    fn iter_functions(&self) -> Box<dyn Iterator<Item = (&str, &Function)> + '_> {
+        Box::new(iter::empty())
+    }
 }
 
 /// A context that stores its mappings in hash maps.
@@ -83,22 +180,152 @@ impl HashMapContext {
     pub fn new() -> Self {
         Default::default()
     }
+
+    /// Removes all variables from this context.
+    pub fn clear_variables(&mut self) {
+        self.variables.clear()
+    }
+
+    /// Removes all functions from this context.
+    pub fn clear_functions(&mut self) {
+        self.functions.clear()
+    }
+
+    /// Removes all variables and functions from this context.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let mut context = context_map! {
+    ///     "a" => 1,
+    ///     "f" => Function::new(|argument| Ok(argument.clone()))
+    /// }.unwrap(); // Do proper error handling here
+    /// assert_eq!(context.iter_variable_names().collect::<Vec<_>>(), vec!["a"]);
+    /// assert_eq!(context.iter_function_names().collect::<Vec<_>>(), vec!["f"]);
+    ///
+    /// context.clear();
+    /// assert_eq!(context.iter_variables().count(), 0);
+    /// assert_eq!(context.get_function("f"), None);
+    /// ```
+    pub fn clear(&mut self) {
+        self.clear_variables();
+        self.clear_functions();
+    }
+
+    /// Captures the current variables and functions of this context, so they can be brought back with `HashMapContext::restore`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let mut context = HashMapContext::new();
+    /// context.set_value("a".into(), 1.into()).unwrap(); // Do proper error handling here
+    /// let snapshot = context.snapshot();
+    ///
+    /// eval_with_context_mut("a = 2; b = 3", &mut context).unwrap(); // Do proper error handling here
+    /// assert_eq!(context.get_value("b").as_deref(), Some(&Value::from(3)));
+    ///
+    /// context.restore(snapshot);
+    /// assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(1)));
+    /// assert_eq!(context.get_value("b"), None);
+    /// ```
+    pub fn snapshot(&self) -> ContextSnapshot {
+        ContextSnapshot {
+            context: self.clone(),
+        }
+    }
+
+    /// Replaces all variables and functions of this context with the ones captured in the given snapshot.
+    pub fn restore(&mut self, snapshot: ContextSnapshot) {
+        *self = snapshot.context;
+    }
+
+    /// Writes the variables and functions of this context into the given context.
+    ///
+    /// If the given context rejects a variable or a function, the variables and functions written before are reset to their previous values, and the error is returned.
+    /// The previous functions are retrieved with `ContextWithMutableFunctions::remove_function`,
+    /// so functions are only reset if the given context supports removing them, and are kept otherwise.
+    pub(crate) fn commit_into<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions + ?Sized,
+    >(
+        self,
+        context: &mut C,
+    ) -> EvalexprResult<()> {
+        let mut previous_values = Vec::with_capacity(self.variables.len());
+        let mut result = Ok(());
+        for (identifier, value) in self.variables {
+            let previous_value = context.get_value(&identifier).map(Cow::into_owned);
+            result = context.set_value(identifier.clone(), value);
+            if result.is_err() {
+                break;
+            }
+            previous_values.push((identifier, previous_value));
+        }
+        let mut previous_functions = Vec::with_capacity(self.functions.len());
+        if result.is_ok() {
+            for (identifier, function) in self.functions {
+                let previous_function = context.remove_function(&identifier);
+                result = context.set_function(identifier.clone(), function);
+                // The previous function is reset also if the function was rejected, as it was removed already
+                previous_functions.push((identifier, previous_function));
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+
+        if result.is_err() {
+            for (identifier, previous_function) in previous_functions.into_iter().rev() {
+                // If the context cannot remove functions, the previous function is unknown and cannot be reset
+                let _ = match previous_function {
+                    Ok(Some(function)) => context.set_function(identifier, function),
+                    Ok(None) => context.remove_function(&identifier).map(|_| ()),
+                    Err(_) => Ok(()),
+                };
+            }
+            for (identifier, previous_value) in previous_values.into_iter().rev() {
+                // The previous values were accepted by the context before, so rolling back is best-effort
+                let _ = match previous_value {
+                    Some(value) => context.set_value(identifier, value),
+                    None => context.remove_value(&identifier).map(|_| ()),
+                };
+            }
+        }
+        result
+    }
+}
+
+/// The variables and functions of a `HashMapContext` at some point in time, as created by `HashMapContext::snapshot`.
+#[derive(Clone, Debug)]
+pub struct ContextSnapshot {
+    context: HashMapContext,
 }
 
 impl Context for HashMapContext {
-    fn get_value(&self, identifier: &str) -> Option<&Value> {
-        self.variables.get(identifier)
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        self.variables.get(identifier).map(Cow::Borrowed)
     }
 
     fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
         if let Some(function) = self.functions.get(identifier) {
-            function.call(argument)
+            function.call(argument, self)
         } else {
             Err(EvalexprError::FunctionIdentifierNotFound(
                 identifier.to_string(),
             ))
         }
     }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        self.functions.get(identifier).map(Function::is_pure)
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        self.functions.get(identifier).map(Function::metadata)
+    }
 }
 
 impl ContextWithMutableVariables for HashMapContext {
@@ -116,6 +343,10 @@ impl ContextWithMutableVariables for HashMapContext {
         self.variables.insert(identifier, value);
         Ok(())
     }
+
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        Ok(self.variables.remove(identifier))
+    }
 }
 
 impl ContextWithMutableFunctions for HashMapContext {
@@ -123,6 +354,34 @@ impl ContextWithMutableFunctions for HashMapContext {
         self.functions.insert(identifier, function);
         Ok(())
     }
+
+    fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+        Ok(self.functions.remove(identifier))
+    }
+}
+
+impl IterateVariablesContext for HashMapContext {
+    fn iter_variables(&self) -> Box<dyn Iterator<Item = (&str, &Value)> + '_> {
+        Box::new(
+            self.variables
+                .iter()
+                .map(|(identifier, value)| (identifier.as_str(), value)),
+        )
+    }
+}
+
+impl GetFunctionContext for HashMapContext {
+    fn get_function(&self, identifier: &str) -> Option<&Function> {
+        self.functions.get(identifier)
+    }
+
+    fn iter_functions(&self) -> Box<dyn Iterator<Item = (&str, &Function)> + '_> {
+        Box::new(
+            self.functions
+                .iter()
+                .map(|(identifier, function)| (identifier.as_str(), function)),
+        )
+    }
 }
 
 /// This macro provides a convenient syntax for creating a static context.
@@ -144,16 +403,22 @@ macro_rules! context_map {
     // Termination (allow missing comma at the end of the argument list)
     ( ($ctx:expr) $k:expr => Function::new($($v:tt)*) ) =>
         { $crate::context_map!(($ctx) $k => Function::new($($v)*),) };
+    ( ($ctx:expr) $k:expr => Function::new_pure($($v:tt)*) ) =>
+        { $crate::context_map!(($ctx) $k => Function::new_pure($($v)*),) };
     ( ($ctx:expr) $k:expr => $v:expr ) =>
         { $crate::context_map!(($ctx) $k => $v,)  };
     // Termination
     ( ($ctx:expr) ) => { Ok(()) };
 
-    // The user has to specify a literal 'Function::new' in order to create a function
+    // The user has to specify a literal 'Function::new' or 'Function::new_pure' in order to create a function
     ( ($ctx:expr) $k:expr => Function::new($($v:tt)*) , $($tt:tt)*) => {{
         $crate::ContextWithMutableFunctions::set_function($ctx, $k.into(), $crate::Function::new($($v)*))
             .and($crate::context_map!(($ctx) $($tt)*))
     }};
+    ( ($ctx:expr) $k:expr => Function::new_pure($($v:tt)*) , $($tt:tt)*) => {{
+        $crate::ContextWithMutableFunctions::set_function($ctx, $k.into(), $crate::Function::new_pure($($v)*))
+            .and($crate::context_map!(($ctx) $($tt)*))
+    }};
     // add a value, and chain the eventual error with the ones in the next values
     ( ($ctx:expr) $k:expr => $v:expr , $($tt:tt)*) => {{
         $crate::ContextWithMutableVariables::set_value($ctx, $k.into(), $v.into())
@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+
+use crate::{
+    context::{
+        Context, ContextWithMutableFunctions, ContextWithMutableVariables, GetFunctionContext,
+        IterateVariablesContext,
+    },
+    function::{Function, FunctionMetadata},
+    value::Value,
+    EvalexprResult,
+};
+
+/// A write to a variable, as recorded by a `RecordingContext`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableChange {
+    /// The identifier of the variable that was written.
+    pub identifier: String,
+    /// The value of the variable before the write, or `None` if the variable did not exist.
+    pub old_value: Option<Value>,
+    /// The value of the variable after the write.
+    pub new_value: Value,
+}
+
+/// A context that wraps a mutable context and records every write to a variable, including compound assignments like `a += 1`.
+///
+/// Writes are recorded in the order they happened, with the value of the variable before and after the write.
+/// Writes that are rejected by the wrapped context are not recorded.
+/// Function definitions and removals are passed to the wrapped context without being recorded.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let mut context = context_map! { "a" => 1 }.unwrap(); // Do proper error handling here
+/// let mut recording = RecordingContext::new(&mut context);
+/// eval_with_context_mut("a += 1; b = a * 2", &mut recording).unwrap(); // Do proper error handling here
+///
+/// let changes = recording.into_changes();
+/// assert_eq!(changes.len(), 2);
+/// assert_eq!(changes[0].identifier, "a");
+/// assert_eq!(changes[0].old_value, Some(Value::from(1)));
+/// assert_eq!(changes[0].new_value, Value::from(2));
+/// assert_eq!(changes[1].identifier, "b");
+/// assert_eq!(changes[1].old_value, None);
+/// assert_eq!(changes[1].new_value, Value::from(4));
+/// ```
+#[derive(Debug)]
+pub struct RecordingContext<'a, C: ?Sized> {
+    context: &'a mut C,
+    changes: Vec<VariableChange>,
+}
+
+impl<'a, C: ContextWithMutableVariables + ?Sized> RecordingContext<'a, C> {
+    /// Creates a recording context that writes to the given context.
+    pub fn new(context: &'a mut C) -> Self {
+        Self {
+            context,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Returns the wrapped context.
+    pub fn context(&self) -> &C {
+        self.context
+    }
+
+    /// Returns the writes recorded so far, in the order they happened.
+    pub fn changes(&self) -> &[VariableChange] {
+        &self.changes
+    }
+
+    /// Removes and returns the writes recorded so far, so that recording starts anew.
+    pub fn take_changes(&mut self) -> Vec<VariableChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Consumes this context and returns the recorded writes.
+    pub fn into_changes(self) -> Vec<VariableChange> {
+        self.changes
+    }
+}
+
+impl<'a, C: Context + ?Sized> Context for RecordingContext<'a, C> {
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        self.context.get_value(identifier)
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        self.context.call_function(identifier, argument)
+    }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        self.context.is_function_pure(identifier)
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        self.context.function_metadata(identifier)
+    }
+}
+
+impl<'a, C: ContextWithMutableVariables + ?Sized> ContextWithMutableVariables
+    for RecordingContext<'a, C>
+{
+    fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        let old_value = self.context.get_value(&identifier).map(Cow::into_owned);
+        self.context.set_value(identifier.clone(), value.clone())?;
+        self.changes.push(VariableChange {
+            identifier,
+            old_value,
+            new_value: value,
+        });
+        Ok(())
+    }
+
+    /// Updates the variable through the wrapped context, so the update stays atomic if the wrapped context supports it.
+    fn update_value(
+        &mut self,
+        identifier: String,
+        update: &mut dyn FnMut(Value) -> EvalexprResult<Value>,
+    ) -> EvalexprResult<()> {
+        let mut change = None;
+        self.context
+            .update_value(identifier.clone(), &mut |old_value| {
+                let new_value = update(old_value.clone())?;
+                change = Some((old_value, new_value.clone()));
+                Ok(new_value)
+            })?;
+        if let Some((old_value, new_value)) = change {
+            self.changes.push(VariableChange {
+                identifier,
+                old_value: Some(old_value),
+                new_value,
+            });
+        }
+        Ok(())
+    }
+
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        self.context.remove_value(identifier)
+    }
+}
+
+impl<'a, C: ContextWithMutableVariables + ContextWithMutableFunctions + ?Sized>
+    ContextWithMutableFunctions for RecordingContext<'a, C>
+{
+    fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+        self.context.set_function(identifier, function)
+    }
+
+    fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+        self.context.remove_function(identifier)
+    }
+}
+
+impl<'a, C: IterateVariablesContext + ?Sized> IterateVariablesContext for RecordingContext<'a, C> {
+    fn iter_variables(&self) -> Box<dyn Iterator<Item = (&str, &Value)> + '_> {
+        self.context.iter_variables()
+    }
+}
+
+impl<'a, C: GetFunctionContext + ?Sized> GetFunctionContext for RecordingContext<'a, C> {
+    fn get_function(&self, identifier: &str) -> Option<&Function> {
+        self.context.get_function(identifier)
+    }
+
+    fn iter_functions(&self) -> Box<dyn Iterator<Item = (&str, &Function)> + '_> {
+        self.context.iter_functions()
+    }
+}
@@ -0,0 +1,200 @@
+use std::{borrow::Cow, collections::HashSet};
+
+use crate::{
+    context::{
+        Context, ContextWithMutableFunctions, ContextWithMutableVariables, GetFunctionContext,
+        HashMapContext, IterateVariablesContext,
+    },
+    function::{Function, FunctionMetadata},
+    value::Value,
+    EvalexprResult,
+};
+
+/// A context that stacks layers of variables and functions on top of a parent context, which is not modified.
+///
+/// Lookups search the layers from the innermost to the outermost one, and then the parent context.
+/// Assignments and function definitions always land in the innermost layer, where they shadow entries of the same identifier in outer layers and the parent.
+/// Like `HashMapContext`, each layer is type-safe, but a variable may shadow a variable of another type.
+///
+/// This allows to share a large context of constants and functions between many evaluations without cloning it.
+/// New layers are pushed with `ScopedContext::push_scope` and discarded with `ScopedContext::pop_scope`.
+///
+/// Functions of the layers are called with the whole scoped context, so functions defined in expressions can access all variables.
+/// Functions of the parent context are called by the parent context itself.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let shared = context_map! { "rate" => 0.5 }.unwrap(); // Do proper error handling here
+///
+/// let mut context = ScopedContext::new(&shared);
+/// assert_eq!(eval_with_context_mut("amount = 10; amount * rate", &mut context), Ok(Value::from(5.0)));
+///
+/// context.push_scope();
+/// assert_eq!(eval_with_context_mut("rate = 2.0; amount * rate", &mut context), Ok(Value::from(20.0)));
+/// context.pop_scope();
+/// assert_eq!(eval_with_context("amount * rate", &context), Ok(Value::from(5.0)));
+///
+/// // The shared context is not modified
+/// assert_eq!(shared.get_value("amount"), None);
+/// ```
+#[derive(Debug)]
+pub struct ScopedContext<'a, C: Context + ?Sized> {
+    parent: &'a C,
+    /// The layers of this context, with the innermost one last. There is always at least one layer.
+    layers: Vec<HashMapContext>,
+}
+
+impl<'a, C: Context + ?Sized> ScopedContext<'a, C> {
+    /// Creates a scoped context with a single empty layer on top of the given parent context.
+    pub fn new(parent: &'a C) -> Self {
+        Self {
+            parent,
+            layers: vec![HashMapContext::new()],
+        }
+    }
+
+    /// Returns the parent context.
+    pub fn parent(&self) -> &'a C {
+        self.parent
+    }
+
+    /// Pushes a new empty layer, which receives all assignments until it is popped.
+    pub fn push_scope(&mut self) {
+        self.layers.push(HashMapContext::new());
+    }
+
+    /// Removes the innermost layer and returns it, discarding all assignments made since it was pushed.
+    /// Returns `None` and keeps the layer if it is the only one.
+    pub fn pop_scope(&mut self) -> Option<HashMapContext> {
+        if self.layers.len() > 1 {
+            self.layers.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Returns the amount of layers, which is at least one.
+    pub fn scope_depth(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns the innermost layer.
+    pub fn innermost_scope(&self) -> &HashMapContext {
+        // Cannot fail, as there is always at least one layer
+        self.layers.last().unwrap()
+    }
+
+    /// Consumes this context and returns its innermost layer.
+    pub(crate) fn into_innermost_scope(mut self) -> HashMapContext {
+        // Cannot fail, as there is always at least one layer
+        self.layers.pop().unwrap()
+    }
+
+    /// Returns the innermost layer, which receives all assignments.
+    fn innermost_scope_mut(&mut self) -> &mut HashMapContext {
+        // Cannot fail, as there is always at least one layer
+        self.layers.last_mut().unwrap()
+    }
+
+    /// Returns the function with the given identifier from the innermost layer that contains it.
+    fn layer_function(&self, identifier: &str) -> Option<&Function> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get_function(identifier))
+    }
+}
+
+impl<'a, C: Context + ?Sized> Context for ScopedContext<'a, C> {
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get_value(identifier))
+            .or_else(|| self.parent.get_value(identifier))
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        match self.layer_function(identifier) {
+            Some(function) => function.call(argument, self),
+            None => self.parent.call_function(identifier, argument),
+        }
+    }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        match self.layer_function(identifier) {
+            Some(function) => Some(function.is_pure()),
+            None => self.parent.is_function_pure(identifier),
+        }
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        match self.layer_function(identifier) {
+            Some(function) => Some(function.metadata()),
+            None => self.parent.function_metadata(identifier),
+        }
+    }
+}
+
+impl<'a, C: Context + ?Sized> ContextWithMutableVariables for ScopedContext<'a, C> {
+    fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        self.innermost_scope_mut().set_value(identifier, value)
+    }
+
+    /// Removes the variable with the given identifier from the innermost layer.
+    /// Variables of outer layers and the parent context cannot be removed, and may become visible again.
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        self.innermost_scope_mut().remove_value(identifier)
+    }
+}
+
+impl<'a, C: Context + ?Sized> ContextWithMutableFunctions for ScopedContext<'a, C> {
+    fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+        self.innermost_scope_mut()
+            .set_function(identifier, function)
+    }
+
+    /// Removes the function with the given identifier from the innermost layer.
+    /// Functions of outer layers and the parent context cannot be removed, and may become visible again.
+    fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+        self.innermost_scope_mut().remove_function(identifier)
+    }
+}
+
+/// Iterates over the variables visible in this context, leaving out shadowed ones.
+impl<'a, C: IterateVariablesContext + ?Sized> IterateVariablesContext for ScopedContext<'a, C> {
+    fn iter_variables(&self) -> Box<dyn Iterator<Item = (&str, &Value)> + '_> {
+        let mut seen = HashSet::new();
+        Box::new(
+            self.layers
+                .iter()
+                .rev()
+                .flat_map(|layer| layer.iter_variables())
+                .chain(self.parent.iter_variables())
+                .filter(move |(identifier, _)| seen.insert(*identifier)),
+        )
+    }
+}
+
+/// Returns the functions visible in this context, leaving out shadowed ones.
+impl<'a, C: GetFunctionContext + ?Sized> GetFunctionContext for ScopedContext<'a, C> {
+    fn get_function(&self, identifier: &str) -> Option<&Function> {
+        self.layer_function(identifier)
+            .or_else(|| self.parent.get_function(identifier))
+    }
+
+    fn iter_functions(&self) -> Box<dyn Iterator<Item = (&str, &Function)> + '_> {
+        let mut seen = HashSet::new();
+        Box::new(
+            self.layers
+                .iter()
+                .rev()
+                .flat_map(|layer| layer.iter_functions())
+                .chain(self.parent.iter_functions())
+                .filter(move |(identifier, _)| seen.insert(*identifier)),
+        )
+    }
+}
@@ -0,0 +1,133 @@
+//! The `decimal` module contains the arithmetic and conversions of `Value::Decimal`, which requires the `decimal_support` feature flag.
+
+use std::{convert::TryFrom, str::FromStr};
+
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    RoundingStrategy,
+};
+
+use crate::{
+    error::{EvalexprError, EvalexprResult},
+    value::{value_type::ValueType, DecimalType, FloatType, IntType, Value},
+};
+
+/// Parses a decimal literal, which is a number followed by the suffix `d`, like `1.25d` or `1e-3d`.
+/// Returns `None` if the literal is not a decimal literal, and `Err` if its number cannot be represented as a decimal.
+pub(crate) fn parse_literal(literal: &str) -> Option<EvalexprResult<DecimalType>> {
+    let number = literal.strip_suffix('d')?;
+    if !number.starts_with(|character: char| character.is_ascii_digit())
+        || number.parse::<FloatType>().is_err()
+    {
+        return None;
+    }
+    Some(
+        DecimalType::from_str(number)
+            .or_else(|_| DecimalType::from_scientific(number))
+            .map_err(|_| EvalexprError::InvalidDecimalLiteral(literal.to_string())),
+    )
+}
+
+/// Returns the operands of a binary operator as decimals, if one of them is a decimal and the other one is a decimal or an integer.
+/// Integers are converted exactly, while operations involving floats are computed with floats instead.
+pub(crate) fn operands(a: &Value, b: &Value) -> Option<(DecimalType, DecimalType)> {
+    match (a, b) {
+        (Value::Decimal(a), Value::Decimal(b)) => Some((*a, *b)),
+        (Value::Decimal(a), Value::Int(b)) => Some((*a, DecimalType::from(*b))),
+        (Value::Int(a), Value::Decimal(b)) => Some((DecimalType::from(*a), *b)),
+        _ => None,
+    }
+}
+
+/// Returns the quotient of `a` and `b` rounded towards negative infinity, or `None` if `b` is zero or the quotient does not fit into a decimal.
+pub(crate) fn floor_div(a: DecimalType, b: DecimalType) -> Option<DecimalType> {
+    a.checked_div(b).map(|quotient| quotient.floor())
+}
+
+/// Returns `base` raised to the integer power `exponent`, or `None` if the result does not fit into a decimal.
+pub(crate) fn pow(base: DecimalType, exponent: IntType) -> Option<DecimalType> {
+    // The absolute value of the exponent, which does not fit into an `IntType` for `IntType::MIN`
+    let mut remaining = if exponent < 0 {
+        (exponent as u64).wrapping_neg()
+    } else {
+        exponent as u64
+    };
+    let mut power = base;
+    let mut result = DecimalType::ONE;
+    while remaining > 0 {
+        if remaining % 2 == 1 {
+            result = result.checked_mul(power)?;
+        }
+        remaining /= 2;
+        if remaining > 0 {
+            power = power.checked_mul(power)?;
+        }
+    }
+
+    if exponent < 0 {
+        DecimalType::ONE.checked_div(result)
+    } else {
+        Some(result)
+    }
+}
+
+/// Rounds the given decimal to the given amount of decimal places, using the rounding mode with the given name.
+///
+/// The rounding modes are `half_even`, `half_up` and `half_down`, which round to the nearest neighbour and break ties towards the even neighbour, away from zero or towards zero,
+/// as well as `up`, `down`, `floor` and `ceiling`, which round away from zero, towards zero, towards negative infinity or towards positive infinity.
+pub(crate) fn round(
+    decimal: DecimalType,
+    places: IntType,
+    mode: &str,
+) -> EvalexprResult<DecimalType> {
+    let strategy = match mode {
+        "half_even" => RoundingStrategy::MidpointNearestEven,
+        "half_up" => RoundingStrategy::MidpointAwayFromZero,
+        "half_down" => RoundingStrategy::MidpointTowardZero,
+        "up" => RoundingStrategy::AwayFromZero,
+        "down" => RoundingStrategy::ToZero,
+        "floor" => RoundingStrategy::ToNegativeInfinity,
+        "ceiling" => RoundingStrategy::ToPositiveInfinity,
+        _ => return Err(EvalexprError::InvalidRoundingMode(mode.to_string())),
+    };
+    let places = u32::try_from(places).map_err(|_| EvalexprError::InvalidDecimalPlaces(places))?;
+    Ok(decimal.round_dp_with_strategy(places, strategy))
+}
+
+/// Converts the given value to a decimal.
+///
+/// Integers are converted exactly, and floats to the shortest decimal that represents them.
+/// Strings are parsed as numbers in decimal or scientific notation, without the suffix `d`.
+pub(crate) fn to_decimal(value: &Value) -> EvalexprResult<DecimalType> {
+    let decimal = match value {
+        Value::Decimal(decimal) => Some(*decimal),
+        Value::Int(int) => Some(DecimalType::from(*int)),
+        Value::Float(float) => DecimalType::from_f64(*float),
+        Value::String(string) => DecimalType::from_str(string)
+            .or_else(|_| DecimalType::from_scientific(string))
+            .ok(),
+        value => return Err(EvalexprError::expected_number_or_string(value.clone())),
+    };
+    decimal.ok_or_else(|| EvalexprError::conversion_error(value.clone(), ValueType::Decimal))
+}
+
+/// Converts the given decimal to the nearest float.
+pub(crate) fn to_float(decimal: DecimalType) -> FloatType {
+    // Cannot fail, as all decimals are within the range of floats
+    decimal.to_f64().unwrap()
+}
+
+/// Converts the given integer or decimal to an integer, or returns `Err` if it has a fractional part or does not fit into an `IntType`.
+pub(crate) fn to_int(value: &Value) -> EvalexprResult<IntType> {
+    match value {
+        Value::Int(int) => Ok(*int),
+        Value::Decimal(decimal) => decimal
+            .to_i64()
+            .filter(|_| decimal.fract().is_zero())
+            .ok_or_else(|| EvalexprError::conversion_error(value.clone(), ValueType::Int)),
+        value => Err(EvalexprError::type_error(
+            value.clone(),
+            vec![ValueType::Int, ValueType::Decimal],
+        )),
+    }
+}
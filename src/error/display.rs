@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::EvalexprError;
+use crate::{EvalexprError, TypeSet};
 
 impl fmt::Display for EvalexprError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
@@ -21,6 +21,12 @@ impl fmt::Display for EvalexprError {
             },
             ExpectedInt { actual } => write!(f, "Expected a Value::Int, but got {:?}.", actual),
             ExpectedFloat { actual } => write!(f, "Expected a Value::Float, but got {:?}.", actual),
+            ExpectedDecimal { actual } => {
+                write!(f, "Expected a Value::Decimal, but got {:?}.", actual)
+            },
+            ExpectedBigInt { actual } => {
+                write!(f, "Expected a Value::BigInt, but got {:?}.", actual)
+            },
             ExpectedNumber { actual } => write!(
                 f,
                 "Expected a Value::Float or Value::Int, but got {:?}.",
@@ -43,6 +49,10 @@ impl fmt::Display for EvalexprError {
                 "Expected a Value::Tuple of len {}, but got {:?}.",
                 expected_len, actual
             ),
+            ExpectedMap { actual } => write!(f, "Expected a Value::Map, but got {:?}.", actual),
+            ExpectedFunction { actual } => {
+                write!(f, "Expected a Value::Function, but got {:?}.", actual)
+            },
             ExpectedEmpty { actual } => write!(f, "Expected a Value::Empty, but got {:?}.", actual),
             AppendedToLeafNode => write!(f, "Tried to append a node to a leaf node."),
             PrecedenceViolation => write!(
@@ -59,17 +69,97 @@ impl fmt::Display for EvalexprError {
                 "Function identifier is not bound to anything by context: {:?}.",
                 identifier
             ),
+            KeyNotFound(key) => write!(f, "Map does not contain the key {:?}.", key),
+            IndexOutOfBounds { index, len } => write!(
+                f,
+                "Index {} is out of bounds for a value of length {}.",
+                index, len
+            ),
+            SliceOutOfBounds { start, end, len } => write!(
+                f,
+                "Range {}..{} is out of bounds for a value of length {}.",
+                start, end, len
+            ),
             TypeError { expected, actual } => {
                 write!(f, "Expected one of {:?}, but got {:?}.", expected, actual)
             },
+            TypeMismatch { expected, actual } => write!(
+                f,
+                "Expected a value of type {}, but found a value of type {}.",
+                expected, actual
+            ),
             WrongTypeCombination { operator, actual } => write!(
                 f,
                 "The operator {:?} was called with a wrong combination of types: {:?}",
                 operator, actual
             ),
-            UnmatchedLBrace => write!(f, "Found an unmatched opening parenthesis '('."),
-            UnmatchedRBrace => write!(f, "Found an unmatched closing parenthesis ')'."),
-            MissingOperatorOutsideOfBrace { .. } => write!(
+            UnmatchedLBrace => write!(
+                f,
+                "Found an unmatched opening parenthesis '(', bracket '[' or curly brace '{{'."
+            ),
+            UnmatchedRBrace => write!(
+                f,
+                "Found an unmatched closing parenthesis ')', bracket ']' or curly brace '}}'."
+            ),
+            ExpectedKeyValuePair => write!(
+                f,
+                "Found an entry in a map literal that is not a key-value pair 'key: value'."
+            ),
+            UnexpectedKeyValuePair => write!(
+                f,
+                "Found a key-value pair 'key: value' outside of a map literal."
+            ),
+            UnexpectedRange => write!(
+                f,
+                "Found a range 'start..end' outside of the brackets of a slice."
+            ),
+            ExpectedParameterList => write!(
+                f,
+                "Expected a parenthesized list of distinct parameter identifiers after 'fn' or \
+                 before '->'."
+            ),
+            ExpectedFunctionDefinition => {
+                write!(f, "Expected a function definition 'fn(parameters) body'.")
+            },
+            UnassignedFunctionDefinition => write!(
+                f,
+                "Found a function definition that is not assigned to an identifier."
+            ),
+            RecursionLimitExceeded { limit } => write!(
+                f,
+                "Calls to functions defined in expressions were nested deeper than {} levels.",
+                limit
+            ),
+            LimitExceeded { limit, maximum } => {
+                write!(f, "The {} limit of {} was exceeded.", limit, maximum)
+            },
+            Cancelled => write!(f, "The evaluation was cancelled."),
+            DeadlineExceeded => write!(f, "The evaluation exceeded its deadline."),
+            DependencyCycle { cycle } => {
+                write!(
+                    f,
+                    "The formulas {} depend on each other.",
+                    cycle.join(" -> ")
+                )
+            },
+            IdentifierIsFormula(identifier) => write!(
+                f,
+                "The identifier {:?} names a formula and cannot be assigned a value.",
+                identifier
+            ),
+            IdentifierIsInput(identifier) => write!(
+                f,
+                "The identifier {:?} names an input and cannot be assigned a formula.",
+                identifier
+            ),
+            FormulaWritesVariable { name, identifier } => write!(
+                f,
+                "The formula {:?} assigns to the variable {:?}, but formulas cannot assign to \
+                 variables.",
+                name, identifier
+            ),
+            FormulaError { name, error } => write!(f, "The formula {:?} failed: {}", name, error),
+            MissingOperatorOutsideOfBrace => write!(
                 f,
                 "Found an opening parenthesis that is preceded by something that does not take \
                  any arguments on the right, or found a closing parenthesis that is succeeded by \
@@ -107,6 +197,34 @@ impl fmt::Display for EvalexprError {
             ModulationError { dividend, divisor } => {
                 write!(f, "Error modulating {} % {}", dividend, divisor)
             },
+            ExponentiationError { base, exponent } => {
+                write!(f, "Error exponentiating {} ^ {}", base, exponent)
+            },
+            ShiftError { value, shift } => {
+                write!(f, "Error shifting {} by {} bits", value, shift)
+            },
+            ConversionError { value, target } => write!(
+                f,
+                "Cannot convert {} to {} without losing information",
+                value,
+                TypeSet::from(*target)
+            ),
+            InvalidRoundingMode(mode) => write!(
+                f,
+                "Unknown rounding mode {:?}, expected one of half_even, half_up, half_down, up, \
+                 down, floor or ceiling",
+                mode
+            ),
+            InvalidDecimalPlaces(places) => write!(
+                f,
+                "Cannot round to {} decimal places, as the amount must not be negative",
+                places
+            ),
+            InvalidDecimalLiteral(literal) => write!(
+                f,
+                "The decimal literal {:?} is out of the range of decimals",
+                literal
+            ),
             InvalidRegex { regex, message } => write!(
                 f,
                 "Regular expression {:?} is invalid: {:?}",
@@ -115,6 +233,7 @@ impl fmt::Display for EvalexprError {
             ContextNotMutable => write!(f, "Cannot manipulate context"),
             IllegalEscapeSequence(string) => write!(f, "Illegal escape sequence: {}", string),
             CustomMessage(message) => write!(f, "Error: {}", message),
+            Spanned { span, error } => write!(f, "{} (at {})", error, span),
         }
     }
 }
@@ -5,7 +5,13 @@
 //! The module also contains some helper functions starting with `expect_` that check for a condition and return `Err(_)` if the condition is not fulfilled.
 //! They are meant as shortcuts to not write the same error checking code everywhere.
 
-use crate::{token::PartialToken, value::value_type::ValueType};
+use crate::{
+    limits::Limit,
+    span::Span,
+    token::PartialToken,
+    typecheck::TypeSet,
+    value::{value_type::ValueType, IntType},
+};
 
 use crate::{operator::Operator, value::Value};
 
@@ -51,15 +57,31 @@ pub enum EvalexprError {
         actual: Value,
     },
 
+    /// A decimal value was expected.
+    /// Decimal values only exist with the `decimal_support` feature flag.
+    ExpectedDecimal {
+        /// The actual value.
+        actual: Value,
+    },
+
+    /// A big integer value was expected.
+    /// Big integer values only exist with the `bigint_support` feature flag.
+    ExpectedBigInt {
+        /// The actual value.
+        actual: Value,
+    },
+
     /// A numeric value was expected.
-    /// Numeric values are the variants `Value::Int` and `Value::Float`.
+    /// Numeric values are the variants `Value::Int` and `Value::Float`,
+    /// as well as `Value::Decimal` with the `decimal_support` feature flag and `Value::BigInt` with the `bigint_support` feature flag.
     ExpectedNumber {
         /// The actual value.
         actual: Value,
     },
 
     /// A numeric or string value was expected.
-    /// Numeric values are the variants `Value::Int` and `Value::Float`.
+    /// Numeric values are the variants `Value::Int` and `Value::Float`,
+    /// as well as `Value::Decimal` with the `decimal_support` feature flag and `Value::BigInt` with the `bigint_support` feature flag.
     ExpectedNumberOrString {
         /// The actual value.
         actual: Value,
@@ -85,6 +107,18 @@ pub enum EvalexprError {
         actual: Value,
     },
 
+    /// A map value was expected.
+    ExpectedMap {
+        /// The actual value.
+        actual: Value,
+    },
+
+    /// A function value was expected.
+    ExpectedFunction {
+        /// The actual value.
+        actual: Value,
+    },
+
     /// An empty value was expected.
     ExpectedEmpty {
         /// The actual value.
@@ -106,6 +140,27 @@ pub enum EvalexprError {
     /// A `FunctionIdentifier` operation did not find its value in the context.
     FunctionIdentifierNotFound(String),
 
+    /// A map was accessed with a key that it does not contain.
+    KeyNotFound(String),
+
+    /// A tuple or string was accessed with an index outside of its bounds.
+    IndexOutOfBounds {
+        /// The index as given, which may be negative to count from the end.
+        index: IntType,
+        /// The length of the tuple or string.
+        len: usize,
+    },
+
+    /// A tuple or string was sliced with a range outside of its bounds, or whose start lies behind its end.
+    SliceOutOfBounds {
+        /// The start of the range as given, or zero if it was omitted.
+        start: IntType,
+        /// The end of the range as given, or the length if it was omitted.
+        end: IntType,
+        /// The length of the tuple or string.
+        len: usize,
+    },
+
     /// A value has the wrong type.
     /// Only use this if there is no other error that describes the expected and provided types in more detail.
     TypeError {
@@ -115,6 +170,14 @@ pub enum EvalexprError {
         actual: Value,
     },
 
+    /// The type checker found a value whose possible types do not include any of the expected types.
+    TypeMismatch {
+        /// The expected types.
+        expected: TypeSet,
+        /// The possible types of the value.
+        actual: TypeSet,
+    },
+
     /// An operator is used with a wrong combination of types.
     WrongTypeCombination {
         /// The operator that whose evaluation caused the error.
@@ -124,11 +187,83 @@ pub enum EvalexprError {
     },
 
     /// An opening brace without a matching closing brace was found.
+    /// This also applies to opening brackets `[` and curly braces `{`.
     UnmatchedLBrace,
 
     /// A closing brace without a matching opening brace was found.
+    /// This also applies to closing brackets `]` and curly braces `}`, as well as to closing braces of the wrong kind.
     UnmatchedRBrace,
 
+    /// A map literal contains an entry that is not a key-value pair `key: value`.
+    /// For example, writing `{"a": 1, 2}` would yield this error.
+    ExpectedKeyValuePair,
+
+    /// A key-value pair `key: value` was found outside of a map literal.
+    UnexpectedKeyValuePair,
+
+    /// A range `start..end` was found outside of the brackets of a slicing operator.
+    UnexpectedRange,
+
+    /// The keyword `fn` is not followed by a parenthesized list of distinct parameter identifiers.
+    /// For example, writing `fn x x` or `fn(x, 1) x` would yield this error.
+    ExpectedParameterList,
+
+    /// A string that should contain a function definition `fn(parameters) body` contains something else.
+    /// This error also occurs if `Operator::FunctionDefinition` is evaluated with an already evaluated body.
+    ExpectedFunctionDefinition,
+
+    /// A function definition `fn(...) ...` was found that is not directly assigned to an identifier.
+    UnassignedFunctionDefinition,
+
+    /// The nesting of calls to functions defined in expressions exceeded the given limit.
+    /// This usually happens if such a function recurses without terminating.
+    RecursionLimitExceeded {
+        /// The maximum nesting depth of calls.
+        limit: usize,
+    },
+
+    /// A limit configured with `EvalLimits` was exceeded while parsing or evaluating.
+    LimitExceeded {
+        /// The kind of the exceeded limit.
+        limit: Limit,
+        /// The configured maximum.
+        maximum: usize,
+    },
+
+    /// The evaluation was cancelled with the `CancellationToken` of the enforced `EvalLimits`.
+    Cancelled,
+
+    /// The evaluation was still running at the deadline of the enforced `EvalLimits`.
+    DeadlineExceeded,
+
+    /// The formulas of a `FormulaSet` would depend on each other in a cycle.
+    DependencyCycle {
+        /// The names of the formulas in the cycle, starting and ending with the same formula.
+        cycle: Vec<String>,
+    },
+
+    /// An input of a `FormulaSet` was assigned to an identifier that names a formula.
+    IdentifierIsFormula(String),
+
+    /// A formula of a `FormulaSet` was assigned to an identifier that names an input.
+    IdentifierIsInput(String),
+
+    /// A formula of a `FormulaSet` assigns to a variable, which formulas cannot do.
+    FormulaWritesVariable {
+        /// The name of the formula.
+        name: String,
+        /// The identifier of the variable the formula assigns to.
+        identifier: String,
+    },
+
+    /// The evaluation of a formula of a `FormulaSet` failed.
+    FormulaError {
+        /// The name of the formula.
+        name: String,
+        /// The error that occurred while evaluating the formula.
+        error: Box<EvalexprError>,
+    },
+
     /// Left of an opening brace or right of a closing brace is a token that does not expect the brace next to it.
     /// For example, writing `4(5)` would yield this error, as the `4` does not have any operands.
     MissingOperatorOutsideOfBrace,
@@ -189,6 +324,39 @@ pub enum EvalexprError {
         divisor: Value,
     },
 
+    /// An exponentiation operation performed by Rust failed.
+    ExponentiationError {
+        /// The base of the exponentiation.
+        base: Value,
+        /// The exponent of the exponentiation.
+        exponent: Value,
+    },
+
+    /// A shift performed by the builtin function `shl` or `shr` failed.
+    ShiftError {
+        /// The value that was shifted.
+        value: Value,
+        /// The amount of bits the value was shifted by.
+        shift: Value,
+    },
+
+    /// A value could not be converted to the given type without losing information, for example a float that is not finite to a decimal.
+    ConversionError {
+        /// The value that was converted.
+        value: Value,
+        /// The type the value was converted to.
+        target: ValueType,
+    },
+
+    /// A rounding mode given to a builtin function like `decimal::round` is unknown.
+    InvalidRoundingMode(String),
+
+    /// An amount of decimal places given to a builtin function like `decimal::round` is negative.
+    InvalidDecimalPlaces(IntType),
+
+    /// A decimal literal like `1.25d` is out of the range of decimals.
+    InvalidDecimalLiteral(String),
+
     /// A regular expression could not be parsed
     InvalidRegex {
         /// The invalid regular expression
@@ -205,6 +373,15 @@ pub enum EvalexprError {
 
     /// A custom error explained by its message.
     CustomMessage(String),
+
+    /// An error together with the span of the input string that caused it.
+    /// Errors are only wrapped into this variant by the span-aware functions like `build_operator_tree_spanned` or `Node::eval_spanned_with_context`.
+    Spanned {
+        /// The span of the input string that caused the error.
+        span: Span,
+        /// The error that occurred.
+        error: Box<EvalexprError>,
+    },
 }
 
 impl EvalexprError {
@@ -241,6 +418,16 @@ impl EvalexprError {
         EvalexprError::ExpectedFloat { actual }
     }
 
+    /// Constructs `EvalexprError::ExpectedDecimal{actual}`.
+    pub fn expected_decimal(actual: Value) -> Self {
+        EvalexprError::ExpectedDecimal { actual }
+    }
+
+    /// Constructs `EvalexprError::ExpectedBigInt{actual}`.
+    pub fn expected_bigint(actual: Value) -> Self {
+        EvalexprError::ExpectedBigInt { actual }
+    }
+
     /// Constructs `EvalexprError::ExpectedNumber{actual}`.
     pub fn expected_number(actual: Value) -> Self {
         EvalexprError::ExpectedNumber { actual }
@@ -269,6 +456,16 @@ impl EvalexprError {
         }
     }
 
+    /// Constructs `EvalexprError::ExpectedMap{actual}`.
+    pub fn expected_map(actual: Value) -> Self {
+        EvalexprError::ExpectedMap { actual }
+    }
+
+    /// Constructs `EvalexprError::ExpectedFunction{actual}`.
+    pub fn expected_function(actual: Value) -> Self {
+        EvalexprError::ExpectedFunction { actual }
+    }
+
     /// Constructs `EvalexprError::ExpectedEmpty{actual}`.
     pub fn expected_empty(actual: Value) -> Self {
         EvalexprError::ExpectedEmpty { actual }
@@ -280,8 +477,14 @@ impl EvalexprError {
             ValueType::String => Self::expected_string(actual),
             ValueType::Int => Self::expected_int(actual),
             ValueType::Float => Self::expected_float(actual),
+            #[cfg(feature = "decimal_support")]
+            ValueType::Decimal => Self::expected_decimal(actual),
+            #[cfg(feature = "bigint_support")]
+            ValueType::BigInt => Self::expected_bigint(actual),
             ValueType::Boolean => Self::expected_boolean(actual),
             ValueType::Tuple => Self::expected_tuple(actual),
+            ValueType::Map => Self::expected_map(actual),
+            ValueType::Function => Self::expected_function(actual),
             ValueType::Empty => Self::expected_empty(actual),
         }
     }
@@ -323,10 +526,51 @@ impl EvalexprError {
         EvalexprError::ModulationError { dividend, divisor }
     }
 
+    pub(crate) fn exponentiation_error(base: Value, exponent: Value) -> Self {
+        EvalexprError::ExponentiationError { base, exponent }
+    }
+
+    pub(crate) fn shift_error(value: Value, shift: Value) -> Self {
+        EvalexprError::ShiftError { value, shift }
+    }
+
+    /// Constructs `EvalexprError::ConversionError{value, target}`.
+    pub fn conversion_error(value: Value, target: ValueType) -> Self {
+        EvalexprError::ConversionError { value, target }
+    }
+
     /// Constructs `EvalexprError::InvalidRegex(regex)`
     pub fn invalid_regex(regex: String, message: String) -> Self {
         EvalexprError::InvalidRegex { regex, message }
     }
+
+    /// Wraps `self` into `EvalexprError::Spanned{span, error}`.
+    /// If `self` already carries a span, it is returned unchanged, such that the innermost span is kept.
+    pub fn with_span(self, span: Span) -> Self {
+        match self {
+            EvalexprError::Spanned { .. } => self,
+            error => EvalexprError::Spanned {
+                span,
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Returns the span attached to this error, if any.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            EvalexprError::Spanned { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Removes the span attached to this error, if any.
+    pub fn without_span(self) -> Self {
+        match self {
+            EvalexprError::Spanned { error, .. } => error.without_span(),
+            error => error,
+        }
+    }
 }
 
 /// Returns `Ok(())` if the actual and expected parameters are equal, and `Err(Error::WrongOperatorArgumentAmount)` otherwise.
@@ -358,6 +602,10 @@ pub fn expect_function_argument_amount(actual: usize, expected: usize) -> Evalex
 pub fn expect_number_or_string(actual: &Value) -> EvalexprResult<()> {
     match actual {
         Value::String(_) | Value::Float(_) | Value::Int(_) => Ok(()),
+        #[cfg(feature = "decimal_support")]
+        Value::Decimal(_) => Ok(()),
+        #[cfg(feature = "bigint_support")]
+        Value::BigInt(_) => Ok(()),
         _ => Err(EvalexprError::expected_number_or_string(actual.clone())),
     }
 }
@@ -393,6 +641,10 @@ mod tests {
             EvalexprError::expected_type(&Value::Tuple(vec![]), Value::Empty),
             EvalexprError::expected_tuple(Value::Empty)
         );
+        assert_eq!(
+            EvalexprError::expected_type(&Value::Map(Default::default()), Value::Empty),
+            EvalexprError::expected_map(Value::Empty)
+        );
         assert_eq!(
             EvalexprError::expected_type(&Value::Empty, Value::String("abc".to_string())),
             EvalexprError::expected_empty(Value::String("abc".to_string()))
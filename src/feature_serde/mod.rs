@@ -33,3 +33,24 @@ impl<'de> de::Visitor<'de> for NodeVisitor {
         }
     }
 }
+
+/// Serializes big integers as strings of decimal digits, so the `serde` feature of `num-bigint` is not needed.
+#[cfg(feature = "bigint_support")]
+pub(crate) mod bigint {
+    use crate::value::BigIntType;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        bigint: &BigIntType,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(bigint)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BigIntType, D::Error> {
+        let digits = String::deserialize(deserializer)?;
+        digits.parse().map_err(de::Error::custom)
+    }
+}
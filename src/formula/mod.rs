@@ -0,0 +1,307 @@
+//! The `formula` module contains the `FormulaSet`, which evaluates named expressions that reference each other, like the cells of a spreadsheet.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashSet},
+};
+
+use crate::{
+    build_operator_tree, Context, ContextWithMutableFunctions, ContextWithMutableVariables,
+    Dependencies, EvalexprError, EvalexprResult, Function, HashMapContext, Node, Value,
+};
+
+/// A set of named formulas that can reference each other and the inputs of the set by their names, like the cells of a spreadsheet.
+///
+/// Inputs are variables that are set with `FormulaSet::set_input`, and functions are set with `FormulaSet::set_function`.
+/// The value of each formula is stored as a variable with the name of the formula, so other formulas can read it.
+/// Formulas are evaluated with an immutable context, so formulas assigning to variables are rejected when they are added.
+///
+/// The dependencies of a formula are derived with `Node::dependencies` when it is added.
+/// Adding a formula that would depend on itself, directly or through other formulas, fails with `EvalexprError::DependencyCycle`.
+///
+/// Changing an input, a function or a formula marks all formulas that depend on it as outdated.
+/// `FormulaSet::recompute` then evaluates only the outdated formulas, in an order where each formula is evaluated after the formulas it depends on.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let mut formulas = FormulaSet::new();
+/// formulas.set_input("price".into(), 10.0.into()).unwrap(); // Do proper error handling here
+/// formulas.set_input("count".into(), 3.into()).unwrap(); // Do proper error handling here
+/// formulas.set_formula("total".into(), "net * (1 + tax)").unwrap(); // Do proper error handling here
+/// formulas.set_formula("net".into(), "price * count").unwrap(); // Do proper error handling here
+/// formulas.set_formula("tax".into(), "0.25").unwrap(); // Do proper error handling here
+///
+/// assert_eq!(formulas.recompute(), Ok(vec!["net".to_string(), "tax".to_string(), "total".to_string()]));
+/// assert_eq!(formulas.get_value("total").as_deref(), Some(&Value::from(37.5)));
+///
+/// // Only the formulas depending on the changed input are evaluated again
+/// formulas.set_input("count".into(), 4.into()).unwrap(); // Do proper error handling here
+/// assert_eq!(formulas.recompute(), Ok(vec!["net".to_string(), "total".to_string()]));
+/// assert_eq!(formulas.get_value("total").as_deref(), Some(&Value::from(50.0)));
+///
+/// // Cycles are rejected
+/// assert_eq!(
+///     formulas.set_formula("tax".into(), "total / 100"),
+///     Err(EvalexprError::DependencyCycle { cycle: vec!["tax".into(), "total".into(), "tax".into()] })
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FormulaSet {
+    context: HashMapContext,
+    formulas: BTreeMap<String, Formula>,
+    /// The formulas whose value is missing or outdated.
+    outdated: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug)]
+struct Formula {
+    node: Node,
+    dependencies: Dependencies,
+}
+
+impl FormulaSet {
+    /// Creates an empty formula set.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a formula set whose inputs and functions are the variables and functions of the given context.
+    pub fn with_context(context: HashMapContext) -> Self {
+        Self {
+            context,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the context holding the inputs, the functions and the values of the formulas.
+    pub fn context(&self) -> &HashMapContext {
+        &self.context
+    }
+
+    /// Returns the value of the input or formula with the given name.
+    /// The value of a formula is only available after it was computed by `FormulaSet::recompute`, and may be outdated since.
+    pub fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        self.context.get_value(identifier)
+    }
+
+    /// Sets the input with the given identifier to the given value, and marks all formulas depending on it as outdated.
+    ///
+    /// Like in a `HashMapContext`, an input cannot change its type.
+    /// Fails with `EvalexprError::IdentifierIsFormula` if the identifier names a formula.
+    pub fn set_input(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        if self.formulas.contains_key(&identifier) {
+            return Err(EvalexprError::IdentifierIsFormula(identifier));
+        }
+        self.context.set_value(identifier.clone(), value)?;
+        self.invalidate_dependents(&identifier);
+        Ok(())
+    }
+
+    /// Sets the function with the given identifier, and marks all formulas calling it as outdated.
+    pub fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+        self.context.set_function(identifier.clone(), function)?;
+        self.invalidate_dependents(&identifier);
+        Ok(())
+    }
+
+    /// Parses the given expression and sets it as the formula with the given name, as described by `FormulaSet::set_formula_node`.
+    pub fn set_formula(&mut self, name: String, expression: &str) -> EvalexprResult<()> {
+        self.set_formula_node(name, build_operator_tree(expression)?)
+    }
+
+    /// Sets the formula with the given name to the given operator tree, replacing any formula with the same name.
+    /// The formula and all formulas depending on it are marked as outdated.
+    ///
+    /// Fails with `EvalexprError::IdentifierIsInput` if the name is the identifier of an input,
+    /// with `EvalexprError::FormulaWritesVariable` if the formula assigns to a variable,
+    /// and with `EvalexprError::DependencyCycle` if the formula would depend on itself.
+    /// In all these cases, the formula set is not changed.
+    pub fn set_formula_node(&mut self, name: String, node: Node) -> EvalexprResult<()> {
+        if !self.formulas.contains_key(&name) && self.context.get_value(&name).is_some() {
+            return Err(EvalexprError::IdentifierIsInput(name));
+        }
+        let dependencies = node.dependencies();
+        if let Some(identifier) = dependencies.written_variables().iter().next() {
+            return Err(EvalexprError::FormulaWritesVariable {
+                name,
+                identifier: identifier.clone(),
+            });
+        }
+        if references(&dependencies, &name) {
+            return Err(EvalexprError::DependencyCycle {
+                cycle: vec![name.clone(), name],
+            });
+        }
+        for dependency in self.formula_dependencies(&dependencies) {
+            if let Some(mut path) = self.dependency_path(dependency, &name, &mut HashSet::new()) {
+                path.insert(0, name);
+                return Err(EvalexprError::DependencyCycle { cycle: path });
+            }
+        }
+
+        self.formulas
+            .insert(name.clone(), Formula { node, dependencies });
+        self.outdated.insert(name.clone());
+        self.invalidate_dependents(&name);
+        Ok(())
+    }
+
+    /// Removes the formula with the given name together with its value, and returns its operator tree.
+    /// Formulas depending on it are marked as outdated, and fail to evaluate unless an input or formula with the same name is added.
+    pub fn remove_formula(&mut self, name: &str) -> Option<Node> {
+        let formula = self.formulas.remove(name)?;
+        // Cannot fail, as a `HashMapContext` allows to remove values
+        let _ = self.context.remove_value(name);
+        self.outdated.remove(name);
+        self.invalidate_dependents(name);
+        Some(formula.node)
+    }
+
+    /// Returns the operator tree of the formula with the given name.
+    pub fn formula(&self, name: &str) -> Option<&Node> {
+        self.formulas.get(name).map(|formula| &formula.node)
+    }
+
+    /// Returns the names of all formulas in ascending order.
+    pub fn formula_names(&self) -> impl Iterator<Item = &str> {
+        self.formulas.keys().map(String::as_str)
+    }
+
+    /// Returns the variables and functions the formula with the given name depends on.
+    pub fn dependencies(&self, name: &str) -> Option<&Dependencies> {
+        self.formulas.get(name).map(|formula| &formula.dependencies)
+    }
+
+    /// Returns the names of the formulas that directly depend on the input, function or formula with the given identifier.
+    pub fn dependents(&self, identifier: &str) -> Vec<&str> {
+        self.formulas
+            .iter()
+            .filter(|(_, formula)| references(&formula.dependencies, identifier))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Returns true if the value of the formula with the given name is missing or outdated.
+    pub fn is_outdated(&self, name: &str) -> bool {
+        self.outdated.contains(name)
+    }
+
+    /// Returns the names of all formulas, ordered such that each formula comes after the formulas it depends on.
+    pub fn evaluation_order(&self) -> Vec<&str> {
+        let mut order = Vec::with_capacity(self.formulas.len());
+        let mut visited = HashSet::new();
+        for name in self.formulas.keys() {
+            self.visit_in_order(name, &mut visited, &mut order);
+        }
+        order
+    }
+
+    /// Evaluates all outdated formulas in evaluation order, and returns their names in that order.
+    ///
+    /// If a formula fails, the error is returned as `EvalexprError::FormulaError`.
+    /// The failing formula and the formulas not evaluated yet stay outdated.
+    pub fn recompute(&mut self) -> EvalexprResult<Vec<String>> {
+        let outdated: Vec<String> = self
+            .evaluation_order()
+            .into_iter()
+            .filter(|name| self.outdated.contains(*name))
+            .map(str::to_string)
+            .collect();
+
+        for name in &outdated {
+            let value = self.formulas[name]
+                .node
+                .eval_with_context(&self.context)
+                .map_err(|error| EvalexprError::FormulaError {
+                    name: name.clone(),
+                    error: Box::new(error),
+                })?;
+            // The type of a formula may change, so its old value is removed instead of overwritten
+            self.context.remove_value(name)?;
+            self.context.set_value(name.clone(), value)?;
+            self.outdated.remove(name);
+        }
+        Ok(outdated)
+    }
+
+    /// Returns the names of the formulas that are referenced by the given dependencies.
+    fn formula_dependencies<'a>(&'a self, dependencies: &'a Dependencies) -> Vec<&'a str> {
+        self.formulas
+            .keys()
+            .filter(|name| references(dependencies, name))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Returns the names of the formulas on a path of dependencies from the formula `from` to the formula `to`, including both.
+    fn dependency_path(
+        &self,
+        from: &str,
+        to: &str,
+        visited: &mut HashSet<String>,
+    ) -> Option<Vec<String>> {
+        if from == to {
+            return Some(vec![to.to_string()]);
+        }
+        if !visited.insert(from.to_string()) {
+            return None;
+        }
+
+        let formula = self.formulas.get(from)?;
+        for dependency in self.formula_dependencies(&formula.dependencies) {
+            if let Some(mut path) = self.dependency_path(dependency, to, visited) {
+                path.insert(0, from.to_string());
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    /// Marks all formulas that depend on the given identifier as outdated, directly or through other formulas.
+    fn invalidate_dependents(&mut self, identifier: &str) {
+        let mut pending = vec![identifier.to_string()];
+        while let Some(identifier) = pending.pop() {
+            let dependents: Vec<String> = self
+                .dependents(&identifier)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+            for dependent in dependents {
+                if self.outdated.insert(dependent.clone()) {
+                    pending.push(dependent);
+                }
+            }
+        }
+    }
+
+    fn visit_in_order<'a>(
+        &'a self,
+        name: &'a str,
+        visited: &mut HashSet<&'a str>,
+        order: &mut Vec<&'a str>,
+    ) {
+        if !visited.insert(name) {
+            return;
+        }
+        if let Some(formula) = self.formulas.get(name) {
+            for dependency in self.formula_dependencies(&formula.dependencies) {
+                self.visit_in_order(dependency, visited, order);
+            }
+        }
+        order.push(name);
+    }
+}
+
+/// Returns true if an expression with the given dependencies reads the variable or calls the function with the given identifier.
+/// Reading a field of a map, like `m.x`, counts as reading the map `m`.
+fn references(dependencies: &Dependencies, identifier: &str) -> bool {
+    dependencies.called_functions().contains(identifier)
+        || dependencies.free_variables().iter().any(|variable| {
+            variable == identifier
+                || (variable.starts_with(identifier)
+                    && variable[identifier.len()..].starts_with('.'))
+        })
+}
@@ -1,21 +1,32 @@
 #[cfg(feature = "regex_support")]
 use regex::Regex;
 
+#[cfg(feature = "decimal_support")]
+use crate::decimal;
+#[cfg(feature = "bigint_support")]
+use crate::{bigint, value::BigIntType};
 use crate::{
+    error::EvalexprResult,
+    function::FunctionMetadata,
+    overflow,
+    typecheck::{FunctionSignature, TypeSet},
     value::{FloatType, IntType},
-    EvalexprError, Function, Value, ValueType,
+    Context, EvalexprError, Function, Value, ValueType,
+};
+use std::{
+    cmp::Ordering,
+    ops::{BitAnd, BitOr, BitXor, Not},
 };
-use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Shr};
 
 macro_rules! simple_math {
     ($func:ident) => {
-        Some(Function::new(|argument| {
+        Some(Function::new_pure(|argument| {
             let num = argument.as_number()?;
             Ok(Value::Float(num.$func()))
         }))
     };
     ($func:ident, 2) => {
-        Some(Function::new(|argument| {
+        Some(Function::new_pure(|argument| {
             let tuple = argument.as_fixed_len_tuple(2)?;
             let (a, b) = (tuple[0].as_number()?, tuple[1].as_number()?);
             Ok(Value::Float(a.$func(b)))
@@ -24,20 +35,20 @@ macro_rules! simple_math {
 }
 
 fn float_is(func: fn(f64) -> bool) -> Option<Function> {
-    Some(Function::new(move |argument| {
+    Some(Function::new_pure(move |argument| {
         Ok(func(argument.as_number()?).into())
     }))
 }
 
 macro_rules! int_function {
     ($func:ident) => {
-        Some(Function::new(|argument| {
+        Some(Function::new_pure(|argument| {
             let int = argument.as_int()?;
             Ok(Value::Int(int.$func()))
         }))
     };
     ($func:ident, 2) => {
-        Some(Function::new(|argument| {
+        Some(Function::new_pure(|argument| {
             let tuple = argument.as_fixed_len_tuple(2)?;
             let (a, b) = (tuple[0].as_int()?, tuple[1].as_int()?);
             Ok(Value::Int(a.$func(b)))
@@ -45,6 +56,544 @@ macro_rules! int_function {
     };
 }
 
+fn shift_function(shift: fn(IntType, IntType) -> Option<Value>) -> Option<Function> {
+    Some(Function::new_pure(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        let (a, b) = (tuple[0].as_int()?, tuple[1].as_int()?);
+        shift(a, b).ok_or_else(|| EvalexprError::shift_error(tuple[0].clone(), tuple[1].clone()))
+    }))
+}
+
+/// Returns a builtin function that rounds a number to an integral float with `round`.
+/// With the `decimal_support` feature flag, decimals are rounded to integral decimals instead, using the rounding mode of the given name as understood by `decimal::round`.
+#[cfg_attr(not(feature = "decimal_support"), allow(unused_variables))]
+fn rounding_function(
+    round: fn(FloatType) -> FloatType,
+    decimal_mode: &'static str,
+) -> Option<Function> {
+    Some(Function::new_pure(move |argument| {
+        #[cfg(feature = "decimal_support")]
+        if let Value::Decimal(value) = argument {
+            return decimal::round(*value, 0, decimal_mode).map(Value::Decimal);
+        }
+        Ok(Value::Float(round(argument.as_number()?)))
+    }))
+}
+
+/// A function on big integers, which is only used with the `bigint_support` feature flag.
+#[cfg(feature = "bigint_support")]
+type BigIntFunction = fn(BigIntType, BigIntType) -> Option<Value>;
+#[cfg(not(feature = "bigint_support"))]
+type BigIntFunction = ();
+
+/// Names the function of the `bigint` module with the given name, or expands to `()` without the `bigint_support` feature flag.
+#[cfg(feature = "bigint_support")]
+macro_rules! bigint_function {
+    ($func:ident) => {
+        bigint::$func
+    };
+}
+#[cfg(not(feature = "bigint_support"))]
+macro_rules! bigint_function {
+    ($func:ident) => {
+        ()
+    };
+}
+
+/// Returns a builtin function that applies `int` to two integers, `bigint` to two integers of which at least one is a big integer,
+/// or `float` to two numbers of which at least one is a float.
+/// If `int` or `bigint` returns `None`, the function fails with the error returned by `error`.
+#[cfg_attr(not(feature = "bigint_support"), allow(unused_variables))]
+fn euclid_function(
+    int: fn(IntType, IntType) -> Option<Value>,
+    bigint: BigIntFunction,
+    float: fn(FloatType, FloatType) -> FloatType,
+    error: fn(Value, Value) -> EvalexprError,
+) -> Option<Function> {
+    Some(Function::new_pure(move |argument| {
+        let tuple = argument.as_fixed_len_tuple(2)?;
+        #[cfg(feature = "bigint_support")]
+        if let Some((a, b)) = bigint::arithmetic_operands(&tuple[0], &tuple[1]) {
+            return bigint(a, b).ok_or_else(|| error(tuple[0].clone(), tuple[1].clone()));
+        }
+        match (&tuple[0], &tuple[1]) {
+            (Value::Int(a), Value::Int(b)) => {
+                int(*a, *b).ok_or_else(|| error(tuple[0].clone(), tuple[1].clone()))
+            },
+            (a, b) => Ok(Value::Float(float(a.as_number()?, b.as_number()?))),
+        }
+    }))
+}
+
+/// Returns the arguments of a higher-order function, or `Err` if `argument` is not a tuple of the given length.
+fn higher_order_arguments(argument: &Value, len: usize) -> EvalexprResult<&[Value]> {
+    match argument {
+        Value::Tuple(arguments) if arguments.len() == len => Ok(arguments),
+        Value::Tuple(_) => Err(EvalexprError::expected_fixed_len_tuple(
+            len,
+            argument.clone(),
+        )),
+        _ => Err(EvalexprError::expected_tuple(argument.clone())),
+    }
+}
+
+/// Calls the given function value with the given argument, or returns `Err` if `function` is not a `Value::Function`.
+fn call_value(function: &Value, argument: Value, context: &dyn Context) -> EvalexprResult<Value> {
+    match function {
+        Value::Function(function) => function.call(&argument, context),
+        value => Err(EvalexprError::expected_function(value.clone())),
+    }
+}
+
+/// Compares two keys computed by the function argument of `sort_by`, which must both be numbers or both be strings.
+fn compare_keys(a: &Value, b: &Value) -> EvalexprResult<Ordering> {
+    #[cfg(feature = "decimal_support")]
+    if let Some((a, b)) = decimal::operands(a, b) {
+        return Ok(a.cmp(&b));
+    }
+    #[cfg(feature = "bigint_support")]
+    if let Some((a, b)) = bigint::operands(a, b) {
+        return Ok(a.cmp(&b));
+    }
+
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Ok(a.cmp(b)),
+        (Value::Int(a), Value::Int(b)) => Ok(a.cmp(b)),
+        (a, b) if a.is_number() && b.is_number() => Ok(a
+            .as_number()?
+            .partial_cmp(&b.as_number()?)
+            .unwrap_or(Ordering::Equal)),
+        (Value::String(_), b) => Err(EvalexprError::expected_string(b.clone())),
+        (a, b) if a.is_number() => Err(EvalexprError::expected_number(b.clone())),
+        (a, _) => Err(EvalexprError::type_error(
+            a.clone(),
+            vec![ValueType::String, ValueType::Int, ValueType::Float],
+        )),
+    }
+}
+
+/// Returns the number of the given tuple of numbers that compares as `ordering` to all numbers before it, as computed by `min` and `max`.
+/// If an integer and a float are equal, the float is returned regardless of their order, otherwise the first of equal numbers is returned.
+fn extremum(argument: &Value, ordering: Ordering) -> EvalexprResult<Value> {
+    let mut result: Option<Value> = None;
+    for argument in argument.as_tuple()? {
+        if !argument.is_number() {
+            return Err(EvalexprError::expected_number(argument));
+        }
+        result = match result {
+            Some(result) => match compare_keys(&argument, &result)? {
+                argument_ordering if argument_ordering == ordering => Some(argument),
+                Ordering::Equal
+                    if result.is_int()
+                        && matches!(argument, Value::Float(float) if !float.is_nan()) =>
+                {
+                    Some(argument)
+                },
+                _ => Some(result),
+            },
+            None => Some(argument),
+        };
+    }
+    result.ok_or_else(|| EvalexprError::wrong_function_argument_amount(0, 1))
+}
+
+/// Returns the signature of the builtin function with the given identifier, as used by the type checker.
+pub(crate) fn builtin_signature(identifier: &str) -> Option<FunctionSignature> {
+    use crate::ValueType::*;
+
+    let any = TypeSet::any();
+    let number = TypeSet::number();
+    // Rounding accepts decimals and keeps them as decimals
+    let rounded: TypeSet = [
+        Float,
+        #[cfg(feature = "decimal_support")]
+        Decimal,
+    ]
+    .iter()
+    .copied()
+    .collect();
+    let roundable = number.union(rounded);
+    // `min` and `max` return one of their arguments
+    let comparable: TypeSet = [
+        Int,
+        Float,
+        #[cfg(feature = "decimal_support")]
+        Decimal,
+        #[cfg(feature = "bigint_support")]
+        BigInt,
+    ]
+    .iter()
+    .copied()
+    .collect();
+    // Euclidean division accepts big integers, and returns them if the result does not fit into an integer
+    let euclidean: TypeSet = [
+        Int,
+        Float,
+        #[cfg(feature = "bigint_support")]
+        BigInt,
+    ]
+    .iter()
+    .copied()
+    .collect();
+    let signature = |parameters: &[TypeSet], result: ValueType| {
+        FunctionSignature::new(parameters.to_vec(), result.into())
+    };
+
+    Some(match identifier {
+        "math::ln" | "math::log2" | "math::log10" | "math::exp" | "math::exp2" | "math::cos"
+        | "math::acos" | "math::cosh" | "math::acosh" | "math::sin" | "math::asin"
+        | "math::sinh" | "math::asinh" | "math::tan" | "math::atan" | "math::tanh"
+        | "math::atanh" | "math::sqrt" | "math::cbrt" => signature(&[number], Float),
+        "floor" | "round" | "ceil" => FunctionSignature::new(vec![roundable], rounded),
+        "math::log" | "math::pow" | "math::atan2" | "math::hypot" => {
+            signature(&[number, number], Float)
+        },
+        "math::is_nan" | "math::is_finite" | "math::is_infinite" | "math::is_normal" => {
+            signature(&[number], Boolean)
+        },
+        "typeof" => signature(&[any], String),
+        "min" | "max" => FunctionSignature::variadic(comparable, comparable),
+        "if" => FunctionSignature::new(vec![Boolean.into(), any, any], any),
+        "len" => signature(&[[String, Tuple, Map].iter().copied().collect()], Int),
+        "keys" | "values" => signature(&[Map.into()], Tuple),
+        "contains_key" => signature(&[Map.into(), String.into()], Boolean),
+        "map" | "filter" | "sort_by" => signature(&[Tuple.into(), Function.into()], Tuple),
+        "reduce" => FunctionSignature::new(vec![Tuple.into(), any, Function.into()], any),
+        "any" | "all" => signature(&[Tuple.into(), Function.into()], Boolean),
+        #[cfg(feature = "regex_support")]
+        "str::regex_matches" => signature(&[String.into(), String.into()], Boolean),
+        #[cfg(feature = "regex_support")]
+        "str::regex_replace" => signature(&[String.into(), String.into(), String.into()], String),
+        "str::to_lowercase" | "str::to_uppercase" | "str::trim" => {
+            signature(&[String.into()], String)
+        },
+        "str::from" => FunctionSignature::variadic(any, String.into()),
+        #[cfg(feature = "decimal_support")]
+        "decimal::from" => signature(
+            &[[Int, Float, Decimal, String].iter().copied().collect()],
+            Decimal,
+        ),
+        #[cfg(feature = "decimal_support")]
+        "decimal::round" => signature(&[roundable, Int.into(), String.into()], Decimal),
+        #[cfg(feature = "decimal_support")]
+        "decimal::to_int" => signature(&[[Int, Decimal].iter().copied().collect()], Int),
+        #[cfg(feature = "decimal_support")]
+        "decimal::to_float" => signature(&[roundable], Float),
+        "div_euclid" | "rem_euclid" => {
+            FunctionSignature::new(vec![euclidean, euclidean], euclidean)
+        },
+        "bitand" | "bitor" | "bitxor" | "shl" | "shr" => signature(&[Int.into(), Int.into()], Int),
+        "bitnot" => signature(&[Int.into()], Int),
+        _ => return None,
+    })
+}
+
+/// The identifiers of all builtin functions.
+const BUILTIN_FUNCTIONS: &[&str] = &[
+    "min",
+    "max",
+    "len",
+    "div_euclid",
+    "rem_euclid",
+    "floor",
+    "round",
+    "ceil",
+    "if",
+    "typeof",
+    "keys",
+    "values",
+    "contains_key",
+    "map",
+    "filter",
+    "reduce",
+    "any",
+    "all",
+    "sort_by",
+    "math::is_nan",
+    "math::is_finite",
+    "math::is_infinite",
+    "math::is_normal",
+    "math::ln",
+    "math::log",
+    "math::log2",
+    "math::log10",
+    "math::exp",
+    "math::exp2",
+    "math::pow",
+    "math::cos",
+    "math::acos",
+    "math::cosh",
+    "math::acosh",
+    "math::sin",
+    "math::asin",
+    "math::sinh",
+    "math::asinh",
+    "math::tan",
+    "math::atan",
+    "math::atan2",
+    "math::tanh",
+    "math::atanh",
+    "math::sqrt",
+    "math::cbrt",
+    "math::hypot",
+    #[cfg(feature = "regex_support")]
+    "str::regex_matches",
+    #[cfg(feature = "regex_support")]
+    "str::regex_replace",
+    "str::to_lowercase",
+    "str::to_uppercase",
+    "str::trim",
+    "str::from",
+    #[cfg(feature = "decimal_support")]
+    "decimal::from",
+    #[cfg(feature = "decimal_support")]
+    "decimal::round",
+    #[cfg(feature = "decimal_support")]
+    "decimal::to_int",
+    #[cfg(feature = "decimal_support")]
+    "decimal::to_float",
+    "bitand",
+    "bitor",
+    "bitxor",
+    "bitnot",
+    "shl",
+    "shr",
+];
+
+/// Returns the parameter names and the documentation of the builtin function with the given identifier.
+/// Functions that take any amount of arguments have no parameter names.
+fn builtin_documentation(identifier: &str) -> Option<(&'static [&'static str], &'static str)> {
+    Some(match identifier {
+        "min" => (&[], "Returns the minimum of the arguments."),
+        "max" => (&[], "Returns the maximum of the arguments."),
+        "len" => (
+            &["value"],
+            "Returns the length of a string, or the amount of elements of a tuple or map.",
+        ),
+        "div_euclid" => (
+            &["a", "b"],
+            "Returns the quotient of the euclidean division of a by b, which is rounded such that \
+             the remainder is not negative.",
+        ),
+        "rem_euclid" => (
+            &["a", "b"],
+            "Returns the remainder of the euclidean division of a by b, which is never negative.",
+        ),
+        "floor" => (
+            &["x"],
+            "Returns the largest integer less than or equal to x.",
+        ),
+        "round" => (
+            &["x"],
+            "Returns the nearest integer to x. Rounds half-way cases away from 0.0.",
+        ),
+        "ceil" => (
+            &["x"],
+            "Returns the smallest integer greater than or equal to x.",
+        ),
+        "if" => (
+            &["condition", "then", "else"],
+            "Returns then if the condition is true, and else otherwise. Only the returned branch \
+             is evaluated.",
+        ),
+        "typeof" => (
+            &["value"],
+            "Returns the type of the value as string: string, float, int, boolean, tuple, map, \
+             function or empty.",
+        ),
+        "keys" => (
+            &["map"],
+            "Returns the keys of the map as tuple, in ascending order.",
+        ),
+        "values" => (
+            &["map"],
+            "Returns the values of the map as tuple, in the ascending order of their keys.",
+        ),
+        "contains_key" => (&["map", "key"], "Returns true if the map contains the key."),
+        "map" => (
+            &["tuple", "function"],
+            "Returns the tuple of the results of calling the function with each element of the \
+             tuple.",
+        ),
+        "filter" => (
+            &["tuple", "function"],
+            "Returns the tuple of the elements of the tuple for which the function returns true.",
+        ),
+        "reduce" => (
+            &["tuple", "initial", "function"],
+            "Combines the elements of the tuple by calling the function with the accumulated \
+             value and each element, starting with the initial value.",
+        ),
+        "any" => (
+            &["tuple", "function"],
+            "Returns true if the function returns true for any element of the tuple.",
+        ),
+        "all" => (
+            &["tuple", "function"],
+            "Returns true if the function returns true for all elements of the tuple.",
+        ),
+        "sort_by" => (
+            &["tuple", "function"],
+            "Returns the elements of the tuple sorted by the numbers or strings the function \
+             returns for them.",
+        ),
+        "math::is_nan" => (&["x"], "Returns true if x is NaN."),
+        "math::is_finite" => (&["x"], "Returns true if x is neither infinite nor NaN."),
+        "math::is_infinite" => (
+            &["x"],
+            "Returns true if x is positive or negative infinity.",
+        ),
+        "math::is_normal" => (
+            &["x"],
+            "Returns true if x is neither zero, infinite, subnormal nor NaN.",
+        ),
+        "math::ln" => (&["x"], "Returns the natural logarithm of x."),
+        "math::log" => (
+            &["x", "base"],
+            "Returns the logarithm of x with respect to the base.",
+        ),
+        "math::log2" => (&["x"], "Returns the base 2 logarithm of x."),
+        "math::log10" => (&["x"], "Returns the base 10 logarithm of x."),
+        "math::exp" => (&["x"], "Returns e to the power of x."),
+        "math::exp2" => (&["x"], "Returns 2 to the power of x."),
+        "math::pow" => (
+            &["x", "exponent"],
+            "Returns x to the power of the exponent.",
+        ),
+        "math::cos" => (&["x"], "Returns the cosine of x in radians."),
+        "math::acos" => (&["x"], "Returns the arccosine of x in radians."),
+        "math::cosh" => (&["x"], "Returns the hyperbolic cosine of x."),
+        "math::acosh" => (&["x"], "Returns the inverse hyperbolic cosine of x."),
+        "math::sin" => (&["x"], "Returns the sine of x in radians."),
+        "math::asin" => (&["x"], "Returns the arcsine of x in radians."),
+        "math::sinh" => (&["x"], "Returns the hyperbolic sine of x."),
+        "math::asinh" => (&["x"], "Returns the inverse hyperbolic sine of x."),
+        "math::tan" => (&["x"], "Returns the tangent of x in radians."),
+        "math::atan" => (&["x"], "Returns the arctangent of x in radians."),
+        "math::atan2" => (
+            &["y", "x"],
+            "Returns the four quadrant arctangent of y and x in radians.",
+        ),
+        "math::tanh" => (&["x"], "Returns the hyperbolic tangent of x."),
+        "math::atanh" => (&["x"], "Returns the inverse hyperbolic tangent of x."),
+        "math::sqrt" => (&["x"], "Returns the square root of x."),
+        "math::cbrt" => (&["x"], "Returns the cube root of x."),
+        "math::hypot" => (
+            &["x", "y"],
+            "Returns the length of the hypotenuse of a right-angle triangle with legs of length x \
+             and y.",
+        ),
+        #[cfg(feature = "regex_support")]
+        "str::regex_matches" => (
+            &["string", "regex"],
+            "Returns true if the string matches the regex.",
+        ),
+        #[cfg(feature = "regex_support")]
+        "str::regex_replace" => (
+            &["string", "regex", "replacement"],
+            "Returns the string with all matches of the regex replaced by the replacement.",
+        ),
+        "str::to_lowercase" => (&["string"], "Returns the lower-case version of the string."),
+        "str::to_uppercase" => (&["string"], "Returns the upper-case version of the string."),
+        "str::trim" => (
+            &["string"],
+            "Returns the string with leading and trailing whitespace removed.",
+        ),
+        "str::from" => (&[], "Returns the arguments converted to a string."),
+        #[cfg(feature = "decimal_support")]
+        "decimal::from" => (
+            &["value"],
+            "Returns the number, or the number in the string, converted to a decimal.",
+        ),
+        #[cfg(feature = "decimal_support")]
+        "decimal::round" => (
+            &["x", "places", "mode"],
+            "Returns x as decimal rounded to the given amount of decimal places. The mode is one \
+             of half_even, half_up, half_down, up, down, floor or ceiling.",
+        ),
+        #[cfg(feature = "decimal_support")]
+        "decimal::to_int" => (
+            &["x"],
+            "Returns the decimal x converted to an integer. Fails if x has a fractional part.",
+        ),
+        #[cfg(feature = "decimal_support")]
+        "decimal::to_float" => (&["x"], "Returns the number x converted to a float."),
+        "bitand" => (
+            &["a", "b"],
+            "Returns the bitwise and of the integers a and b.",
+        ),
+        "bitor" => (
+            &["a", "b"],
+            "Returns the bitwise or of the integers a and b.",
+        ),
+        "bitxor" => (
+            &["a", "b"],
+            "Returns the bitwise exclusive or of the integers a and b.",
+        ),
+        "bitnot" => (&["a"], "Returns the bitwise not of the integer a."),
+        "shl" => (&["a", "b"], "Returns the integer a shifted left by b bits."),
+        "shr" => (
+            &["a", "b"],
+            "Returns the integer a shifted right by b bits.",
+        ),
+        _ => return None,
+    })
+}
+
+/// Returns the identifiers of all builtin functions, for example to offer them for autocompletion.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// assert!(builtin_function_identifiers().any(|identifier| identifier == "math::sqrt"));
+/// ```
+pub fn builtin_function_identifiers() -> impl Iterator<Item = &'static str> {
+    BUILTIN_FUNCTIONS.iter().copied()
+}
+
+/// Returns the metadata of the builtin function with the given identifier, or `None` if there is no such builtin function.
+///
+/// All builtin functions have a name, a signature and documentation.
+/// Except for the functions that take any amount of arguments, they also have parameter names.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let metadata = builtin_function_metadata("math::log").unwrap();
+/// assert_eq!(metadata.to_string(), "math::log(x: float | int, base: float | int) -> float");
+/// assert_eq!(metadata.documentation(), Some("Returns the logarithm of x with respect to the base."));
+/// assert!(metadata.is_pure());
+/// assert_eq!(builtin_function_metadata("unknown"), None);
+/// ```
+pub fn builtin_function_metadata(identifier: &str) -> Option<FunctionMetadata> {
+    let function = builtin_function(identifier)?;
+    // Cannot fail, as all builtin functions are documented
+    let (parameter_names, documentation) = builtin_documentation(identifier).unwrap();
+    let parameter_names = if parameter_names.is_empty() {
+        None
+    } else {
+        Some(
+            parameter_names
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        )
+    };
+
+    Some(FunctionMetadata {
+        name: Some(identifier.to_string()),
+        parameter_names,
+        signature: builtin_signature(identifier),
+        documentation: Some(documentation.to_string()),
+        pure: function.is_pure(),
+        deterministic: true,
+    })
+}
+
 pub fn builtin_function(identifier: &str) -> Option<Function> {
     match identifier {
         // Log
@@ -78,91 +627,171 @@ pub fn builtin_function(identifier: &str) -> Option<Function> {
         "math::cbrt" => simple_math!(cbrt),
         // Hypotenuse
         "math::hypot" => simple_math!(hypot, 2),
+        // Euclidean division
+        "div_euclid" => euclid_function(
+            overflow::div_euclid,
+            bigint_function!(div_euclid),
+            FloatType::div_euclid,
+            EvalexprError::division_error,
+        ),
+        "rem_euclid" => euclid_function(
+            overflow::rem_euclid,
+            bigint_function!(rem_euclid),
+            FloatType::rem_euclid,
+            EvalexprError::modulation_error,
+        ),
         // Rounding
-        "floor" => simple_math!(floor),
-        "round" => simple_math!(round),
-        "ceil" => simple_math!(ceil),
+        "floor" => rounding_function(FloatType::floor, "floor"),
+        "round" => rounding_function(FloatType::round, "half_up"),
+        "ceil" => rounding_function(FloatType::ceil, "ceiling"),
         // Float special values
         "math::is_nan" => float_is(f64::is_nan),
         "math::is_finite" => float_is(f64::is_finite),
         "math::is_infinite" => float_is(f64::is_infinite),
         "math::is_normal" => float_is(f64::is_normal),
         // Other
-        "typeof" => Some(Function::new(move |argument| {
+        "typeof" => Some(Function::new_pure(move |argument| {
             Ok(match argument {
                 Value::String(_) => "string",
                 Value::Float(_) => "float",
                 Value::Int(_) => "int",
+                #[cfg(feature = "decimal_support")]
+                Value::Decimal(_) => "decimal",
+                #[cfg(feature = "bigint_support")]
+                Value::BigInt(_) => "bigint",
                 Value::Boolean(_) => "boolean",
                 Value::Tuple(_) => "tuple",
+                Value::Map(_) => "map",
+                Value::Function(_) => "function",
                 Value::Empty => "empty",
             }
             .into())
         })),
-        "min" => Some(Function::new(|argument| {
-            let arguments = argument.as_tuple()?;
-            let mut min_int = IntType::max_value();
-            let mut min_float = 1.0f64 / 0.0f64;
-            debug_assert!(min_float.is_infinite());
-
-            for argument in arguments {
-                if let Value::Float(float) = argument {
-                    min_float = min_float.min(float);
-                } else if let Value::Int(int) = argument {
-                    min_int = min_int.min(int);
-                } else {
-                    return Err(EvalexprError::expected_number(argument));
-                }
-            }
-
-            if (min_int as FloatType) < min_float {
-                Ok(Value::Int(min_int))
-            } else {
-                Ok(Value::Float(min_float))
-            }
+        "min" => Some(Function::new_pure(|argument| {
+            extremum(argument, Ordering::Less)
         })),
-        "max" => Some(Function::new(|argument| {
-            let arguments = argument.as_tuple()?;
-            let mut max_int = IntType::min_value();
-            let mut max_float = -1.0f64 / 0.0f64;
-            debug_assert!(max_float.is_infinite());
-
-            for argument in arguments {
-                if let Value::Float(float) = argument {
-                    max_float = max_float.max(float);
-                } else if let Value::Int(int) = argument {
-                    max_int = max_int.max(int);
-                } else {
-                    return Err(EvalexprError::expected_number(argument));
-                }
-            }
-
-            if (max_int as FloatType) > max_float {
-                Ok(Value::Int(max_int))
-            } else {
-                Ok(Value::Float(max_float))
-            }
+        "max" => Some(Function::new_pure(|argument| {
+            extremum(argument, Ordering::Greater)
         })),
-        "if" => Some(Function::new(|argument| {
+        "if" => Some(Function::new_pure(|argument| {
             let mut arguments = argument.as_fixed_len_tuple(3)?;
             let result_index = if arguments[0].as_boolean()? { 1 } else { 2 };
             Ok(arguments.swap_remove(result_index))
         })),
-        "len" => Some(Function::new(|argument| {
+        "len" => Some(Function::new_pure(|argument| {
             if let Ok(subject) = argument.as_string() {
                 Ok(Value::from(subject.len() as i64))
             } else if let Ok(subject) = argument.as_tuple() {
                 Ok(Value::from(subject.len() as i64))
+            } else if let Value::Map(subject) = argument {
+                Ok(Value::from(subject.len() as i64))
             } else {
                 Err(EvalexprError::type_error(
                     argument.clone(),
-                    vec![ValueType::String, ValueType::Tuple],
+                    vec![ValueType::String, ValueType::Tuple, ValueType::Map],
                 ))
             }
         })),
+        // Map functions
+        "keys" => Some(Function::new_pure(|argument| {
+            let map = argument.as_map()?;
+            Ok(Value::Tuple(
+                map.into_iter().map(|(key, _)| Value::String(key)).collect(),
+            ))
+        })),
+        "values" => Some(Function::new_pure(|argument| {
+            let map = argument.as_map()?;
+            Ok(Value::Tuple(
+                map.into_iter().map(|(_, value)| value).collect(),
+            ))
+        })),
+        "contains_key" => Some(Function::new_pure(|argument| {
+            let arguments = argument.as_fixed_len_tuple(2)?;
+            let map = arguments[0].as_map()?;
+            let key = arguments[1].as_string()?;
+            Ok(Value::Boolean(map.contains_key(&key)))
+        })),
+        // Higher-order functions
+        "map" => Some(Function::higher_order(|argument, context| {
+            let arguments = higher_order_arguments(argument, 2)?;
+            let tuple = arguments[0].as_tuple()?;
+            Ok(Value::Tuple(
+                tuple
+                    .into_iter()
+                    .map(|element| call_value(&arguments[1], element, context))
+                    .collect::<EvalexprResult<_>>()?,
+            ))
+        })),
+        "filter" => Some(Function::higher_order(|argument, context| {
+            let arguments = higher_order_arguments(argument, 2)?;
+            let mut result = Vec::new();
+            for element in arguments[0].as_tuple()? {
+                if call_value(&arguments[1], element.clone(), context)?.as_boolean()? {
+                    result.push(element);
+                }
+            }
+            Ok(Value::Tuple(result))
+        })),
+        "reduce" => Some(Function::higher_order(|argument, context| {
+            let arguments = higher_order_arguments(argument, 3)?;
+            let mut accumulator = arguments[1].clone();
+            for element in arguments[0].as_tuple()? {
+                accumulator = call_value(
+                    &arguments[2],
+                    Value::Tuple(vec![accumulator, element]),
+                    context,
+                )?;
+            }
+            Ok(accumulator)
+        })),
+        "any" => Some(Function::higher_order(|argument, context| {
+            let arguments = higher_order_arguments(argument, 2)?;
+            for element in arguments[0].as_tuple()? {
+                if call_value(&arguments[1], element, context)?.as_boolean()? {
+                    return Ok(Value::Boolean(true));
+                }
+            }
+            Ok(Value::Boolean(false))
+        })),
+        "all" => Some(Function::higher_order(|argument, context| {
+            let arguments = higher_order_arguments(argument, 2)?;
+            for element in arguments[0].as_tuple()? {
+                if !call_value(&arguments[1], element, context)?.as_boolean()? {
+                    return Ok(Value::Boolean(false));
+                }
+            }
+            Ok(Value::Boolean(true))
+        })),
+        "sort_by" => Some(Function::higher_order(|argument, context| {
+            let arguments = higher_order_arguments(argument, 2)?;
+            let mut keyed_elements = arguments[0]
+                .as_tuple()?
+                .into_iter()
+                .map(|element| {
+                    Ok((
+                        call_value(&arguments[1], element.clone(), context)?,
+                        element,
+                    ))
+                })
+                .collect::<EvalexprResult<Vec<_>>>()?;
+
+            if let Some((first_key, _)) = keyed_elements.first() {
+                for (key, _) in &keyed_elements {
+                    compare_keys(first_key, key)?;
+                }
+            }
+            // The keys were checked to be comparable above, and the sort is stable, so elements with equal keys keep their order
+            keyed_elements.sort_by(|(a, _), (b, _)| compare_keys(a, b).unwrap_or(Ordering::Equal));
+            Ok(Value::Tuple(
+                keyed_elements
+                    .into_iter()
+                    .map(|(_, element)| element)
+                    .collect(),
+            ))
+        })),
         // String functions
         #[cfg(feature = "regex_support")]
-        "str::regex_matches" => Some(Function::new(|argument| {
+        "str::regex_matches" => Some(Function::new_pure(|argument| {
             let arguments = argument.as_tuple()?;
 
             let subject = arguments[0].as_string()?;
@@ -176,7 +805,7 @@ pub fn builtin_function(identifier: &str) -> Option<Function> {
             }
         })),
         #[cfg(feature = "regex_support")]
-        "str::regex_replace" => Some(Function::new(|argument| {
+        "str::regex_replace" => Some(Function::new_pure(|argument| {
             let arguments = argument.as_tuple()?;
 
             let subject = arguments[0].as_string()?;
@@ -192,28 +821,49 @@ pub fn builtin_function(identifier: &str) -> Option<Function> {
                 )),
             }
         })),
-        "str::to_lowercase" => Some(Function::new(|argument| {
+        "str::to_lowercase" => Some(Function::new_pure(|argument| {
             let subject = argument.as_string()?;
             Ok(Value::from(subject.to_lowercase()))
         })),
-        "str::to_uppercase" => Some(Function::new(|argument| {
+        "str::to_uppercase" => Some(Function::new_pure(|argument| {
             let subject = argument.as_string()?;
             Ok(Value::from(subject.to_uppercase()))
         })),
-        "str::trim" => Some(Function::new(|argument| {
+        "str::trim" => Some(Function::new_pure(|argument| {
             let subject = argument.as_string()?;
             Ok(Value::from(subject.trim()))
         })),
-        "str::from" => Some(Function::new(|argument| {
+        "str::from" => Some(Function::new_pure(|argument| {
             Ok(Value::String(argument.to_string()))
         })),
+        // Decimals
+        #[cfg(feature = "decimal_support")]
+        "decimal::from" => Some(Function::new_pure(|argument| {
+            Ok(Value::Decimal(decimal::to_decimal(argument)?))
+        })),
+        #[cfg(feature = "decimal_support")]
+        "decimal::round" => Some(Function::new_pure(|argument| {
+            let arguments = argument.as_fixed_len_tuple(3)?;
+            let value = decimal::to_decimal(&arguments[0])?;
+            let places = arguments[1].as_int()?;
+            let mode = arguments[2].as_string()?;
+            Ok(Value::Decimal(decimal::round(value, places, &mode)?))
+        })),
+        #[cfg(feature = "decimal_support")]
+        "decimal::to_int" => Some(Function::new_pure(|argument| {
+            Ok(Value::Int(decimal::to_int(argument)?))
+        })),
+        #[cfg(feature = "decimal_support")]
+        "decimal::to_float" => Some(Function::new_pure(|argument| {
+            Ok(Value::Float(argument.as_number()?))
+        })),
         // Bitwise operators
         "bitand" => int_function!(bitand, 2),
         "bitor" => int_function!(bitor, 2),
         "bitxor" => int_function!(bitxor, 2),
         "bitnot" => int_function!(not),
-        "shl" => int_function!(shl, 2),
-        "shr" => int_function!(shr, 2),
+        "shl" => shift_function(overflow::shl),
+        "shr" => shift_function(overflow::shr),
         _ => None,
     }
 }
@@ -0,0 +1,139 @@
+use std::{borrow::Cow, collections::HashMap, fmt};
+
+use crate::{
+    error::{EvalexprError, EvalexprResult},
+    function::FunctionMetadata,
+    limits,
+    operator::Operator,
+    value::Value,
+    Context, ContextWithMutableFunctions, ContextWithMutableVariables, Node,
+};
+
+/// A function defined within an expression with the syntax `fn(parameters) body`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FunctionDefinition {
+    parameters: Vec<String>,
+    /// The node with the operator `Operator::FunctionDefinition`, whose only child is the body of the function.
+    node: Node,
+    /// The variables the body reads from the context the function value was created in, as they were when it was created.
+    captured: HashMap<String, Value>,
+}
+
+impl FunctionDefinition {
+    /// Creates a function definition from a node with the operator `Operator::FunctionDefinition`.
+    pub(crate) fn from_node(node: &Node) -> EvalexprResult<Self> {
+        match (node.operator(), node.children()) {
+            (Operator::FunctionDefinition { parameters }, [_]) => Ok(Self {
+                parameters: parameters.clone(),
+                node: node.clone(),
+                captured: HashMap::new(),
+            }),
+            (Operator::FunctionDefinition { .. }, children) => Err(
+                EvalexprError::wrong_operator_argument_amount(children.len(), 1),
+            ),
+            _ => Err(EvalexprError::ExpectedFunctionDefinition),
+        }
+    }
+
+    /// Returns the names of the parameters of the function.
+    pub(crate) fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    /// Returns the variables that the body reads without assigning them first, other than the parameters.
+    pub(crate) fn free_variables(&self) -> Vec<String> {
+        self.node
+            .dependencies()
+            .free_variables()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Binds the given free variables to the given values for all calls of the function.
+    /// This is used to capture the variables of the context a function value is created in.
+    pub(crate) fn capture<I: IntoIterator<Item = (String, Value)>>(&mut self, variables: I) {
+        self.captured.extend(variables);
+    }
+
+    /// Calls the function with the given argument.
+    ///
+    /// The body is evaluated in a new scope that binds the captured variables to their values and the parameters to the arguments.
+    /// All other identifiers are looked up in the given context.
+    pub(crate) fn call(&self, argument: &Value, context: &dyn Context) -> EvalexprResult<Value> {
+        let arguments = match (self.parameters.len(), argument) {
+            (1, argument) => vec![argument.clone()],
+            (0, Value::Empty) => Vec::new(),
+            (len, Value::Tuple(tuple)) if tuple.len() == len => tuple.clone(),
+            (len, Value::Tuple(tuple)) => {
+                return Err(EvalexprError::wrong_function_argument_amount(
+                    tuple.len(),
+                    len,
+                ))
+            },
+            (len, Value::Empty) => {
+                return Err(EvalexprError::wrong_function_argument_amount(0, len))
+            },
+            (len, _) => return Err(EvalexprError::wrong_function_argument_amount(1, len)),
+        };
+
+        let _call = limits::enter_call()?;
+        let mut variables = self.captured.clone();
+        variables.extend(self.parameters.iter().cloned().zip(arguments));
+        let mut scope = FunctionScope {
+            parent: context,
+            variables,
+        };
+        // The node has exactly one child, as checked when creating the definition
+        self.node.children()[0].eval_with_context_mut(&mut scope)
+    }
+}
+
+impl fmt::Display for FunctionDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.node.write_source(f)
+    }
+}
+
+/// The context the body of a function defined in an expression is evaluated with.
+///
+/// Parameters and variables assigned within the body are local to the call.
+/// All other variables and all functions are looked up in the context the function was called with.
+struct FunctionScope<'a> {
+    parent: &'a dyn Context,
+    variables: HashMap<String, Value>,
+}
+
+impl<'a> Context for FunctionScope<'a> {
+    fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+        self.variables
+            .get(identifier)
+            .map(Cow::Borrowed)
+            .or_else(|| self.parent.get_value(identifier))
+    }
+
+    fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+        self.parent.call_function(identifier, argument)
+    }
+
+    fn is_function_pure(&self, identifier: &str) -> Option<bool> {
+        self.parent.is_function_pure(identifier)
+    }
+
+    fn function_metadata(&self, identifier: &str) -> Option<FunctionMetadata> {
+        self.parent.function_metadata(identifier)
+    }
+}
+
+impl<'a> ContextWithMutableVariables for FunctionScope<'a> {
+    fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+        self.variables.insert(identifier, value);
+        Ok(())
+    }
+
+    fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+        Ok(self.variables.remove(identifier))
+    }
+}
+
+impl<'a> ContextWithMutableFunctions for FunctionScope<'a> {}
@@ -0,0 +1,116 @@
+use std::fmt;
+
+use crate::typecheck::FunctionSignature;
+
+/// Descriptive information about a function, as used for generating documentation and autocompletion.
+///
+/// All information is optional, as functions implemented in Rust are opaque closures.
+/// The metadata of a function is returned by `Function::metadata`, and set with methods like `Function::with_documentation`.
+/// The metadata of builtin functions is returned by `builtin_function_metadata`.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let function = Function::typed_pure(|x: FloatType| x * x)
+///     .with_name("square".into())
+///     .with_parameter_names(vec!["x".into()])
+///     .with_documentation("Returns the square of x.".into());
+/// let metadata = function.metadata();
+/// assert_eq!(metadata.name(), Some("square"));
+/// assert_eq!(metadata.arity(), Some(1));
+/// assert_eq!(metadata.documentation(), Some("Returns the square of x."));
+/// assert_eq!(metadata.to_string(), "square(x: float | int) -> float");
+/// assert!(metadata.is_pure());
+/// assert!(metadata.is_deterministic());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionMetadata {
+    pub(crate) name: Option<String>,
+    pub(crate) parameter_names: Option<Vec<String>>,
+    pub(crate) signature: Option<FunctionSignature>,
+    pub(crate) documentation: Option<String>,
+    pub(crate) pure: bool,
+    pub(crate) deterministic: bool,
+}
+
+impl FunctionMetadata {
+    /// Returns the name of the function.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the names of the parameters of the function.
+    pub fn parameter_names(&self) -> Option<&[String]> {
+        self.parameter_names.as_deref()
+    }
+
+    /// Returns the amount of parameters of the function, as given by the parameter names or the signature.
+    /// Returns `None` if neither is known, or if the function takes any amount of arguments.
+    pub fn arity(&self) -> Option<usize> {
+        self.parameter_names().map(<[String]>::len).or_else(|| {
+            self.signature()
+                .and_then(FunctionSignature::parameters)
+                .map(<[_]>::len)
+        })
+    }
+
+    /// Returns the types of the parameters and the result of the function.
+    pub fn signature(&self) -> Option<&FunctionSignature> {
+        self.signature.as_ref()
+    }
+
+    /// Returns the documentation of the function.
+    pub fn documentation(&self) -> Option<&str> {
+        self.documentation.as_deref()
+    }
+
+    /// Returns true if the function is pure, as described by `Function::new_pure`.
+    pub fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+    /// Returns true if the function always returns the same result when called with the same argument.
+    /// Unlike pure functions, deterministic functions may have side effects.
+    /// Pure functions are always deterministic.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic || self.pure
+    }
+}
+
+/// Metadata is displayed as a synopsis like `name(a: int, b) -> float`, using the information that is known.
+/// Unknown names are displayed as `fn`, and unknown parameters as `...`.
+impl fmt::Display for FunctionMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}(", self.name().unwrap_or("fn"))?;
+        let parameter_types = self.signature().and_then(FunctionSignature::parameters);
+        match (self.parameter_names(), parameter_types) {
+            (Some(names), types) => {
+                for (index, name) in names.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", name)?;
+                    if let Some(parameter_type) = types.and_then(|types| types.get(index)) {
+                        write!(f, ": {}", parameter_type)?;
+                    }
+                }
+            },
+            (None, Some(types)) => {
+                for (index, parameter_type) in types.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", parameter_type)?;
+                }
+            },
+            (None, None) => write!(f, "...")?,
+        }
+        write!(f, ")")?;
+        if let Some(signature) = self.signature() {
+            write!(f, " -> {}", signature.result())?;
+        }
+        Ok(())
+    }
+}
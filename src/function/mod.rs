@@ -1,8 +1,23 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
 
-use crate::{error::EvalexprResult, value::Value};
+use crate::{
+    build_operator_tree,
+    error::{EvalexprError, EvalexprResult},
+    typecheck::FunctionSignature,
+    value::Value,
+    Context,
+};
+
+pub(crate) use self::definition::FunctionDefinition;
+pub use self::{
+    metadata::FunctionMetadata,
+    typed::{FromValue, IntoFunction, IntoValue},
+};
 
 pub(crate) mod builtin;
+mod definition;
+mod metadata;
+mod typed;
 
 /// A helper trait to enable cloning through `Fn` trait objects.
 trait ClonableFn
@@ -38,14 +53,39 @@ where
 /// })).unwrap(); // Do proper error handling here
 /// assert_eq!(eval_with_context("id(4)", &context), Ok(Value::from(4)));
 /// ```
+///
+/// Functions can also be defined within an expression with the syntax `name = fn(parameters) body`.
+/// Such functions are stored in the context, and their `Display` implementation prints their definition.
+/// Function definitions and lambdas `(parameters) -> body` that are not assigned to an identifier evaluate to a `Value::Function`,
+/// which captures the variables its body reads from the context it is created in.
 pub struct Function {
-    function: Box<dyn ClonableFn>,
+    kind: FunctionKind,
+    pure: bool,
+    /// The metadata is shared between clones to keep values small, and its purity is not used.
+    metadata: Option<Arc<FunctionMetadata>>,
+}
+
+/// The implementation of a function.
+enum FunctionKind {
+    /// A function implemented in Rust.
+    Native(Box<dyn ClonableFn>),
+    /// A function defined within an expression.
+    /// The definition is shared between clones, as function values are cloned whenever they are looked up.
+    Defined(Arc<FunctionDefinition>),
+    /// A builtin function that takes function values as arguments, and needs the context to call them.
+    HigherOrder(fn(&Value, &dyn Context) -> EvalexprResult<Value>),
 }
 
 impl Clone for Function {
     fn clone(&self) -> Self {
         Self {
-            function: self.function.dyn_clone(),
+            kind: match &self.kind {
+                FunctionKind::Native(function) => FunctionKind::Native((**function).dyn_clone()),
+                FunctionKind::Defined(definition) => FunctionKind::Defined(definition.clone()),
+                FunctionKind::HigherOrder(function) => FunctionKind::HigherOrder(*function),
+            },
+            pure: self.pure,
+            metadata: self.metadata.clone(),
         }
     }
 }
@@ -61,23 +101,244 @@ impl Function {
         F: Clone,
     {
         Self {
-            function: Box::new(function) as _,
+            kind: FunctionKind::Native(Box::new(function) as _),
+            pure: false,
+            metadata: None,
         }
     }
 
-    pub(crate) fn call(&self, argument: &Value) -> EvalexprResult<Value> {
-        (self.function)(argument)
+    /// Creates a user-defined pure function.
+    ///
+    /// A pure function returns the same result whenever it is called with the same argument, and has no side effects.
+    /// This allows `Node::optimize` to evaluate calls to the function with a constant argument ahead of time.
+    /// All builtin functions are pure, except for the higher-order functions like `map`, whose purity depends on their function argument.
+    ///
+    /// The `function` is boxed for storage.
+    pub fn new_pure<F>(function: F) -> Self
+    where
+        F: Fn(&Value) -> EvalexprResult<Value>,
+        F: Send + Sync + 'static,
+        F: Clone,
+    {
+        Self {
+            pure: true,
+            ..Self::new(function)
+        }
+    }
+
+    /// Creates a user-defined function from a Rust closure with typed parameters, like `|a: FloatType, b: IntType| a * b as FloatType`.
+    ///
+    /// The function checks the amount and types of its arguments before calling the closure,
+    /// and returns `EvalexprError::WrongFunctionArgumentAmount` or an error like `EvalexprError::ExpectedInt` if they do not match.
+    /// Parameters of type `FloatType` accept integers as well.
+    /// The closure may return any type implementing `IntoValue`, including `EvalexprResult<T>`.
+    /// See `IntoFunction` for details.
+    ///
+    /// The signature of the closure is available through `Function::signature`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let repeat = Function::typed(|string: String, times: IntType| string.repeat(times as usize));
+    /// assert_eq!(
+    ///     repeat.signature(),
+    ///     Some(&FunctionSignature::new(vec![ValueType::String.into(), ValueType::Int.into()], ValueType::String.into()))
+    /// );
+    ///
+    /// let mut context = HashMapContext::new();
+    /// context.set_function("repeat".into(), repeat).unwrap(); // Do proper error handling here
+    /// assert_eq!(eval_with_context("repeat(\"ab\", 2)", &context), Ok(Value::from("abab")));
+    /// assert_eq!(
+    ///     eval_with_context("repeat(\"ab\")", &context),
+    ///     Err(EvalexprError::WrongFunctionArgumentAmount { expected: 2, actual: 1 })
+    /// );
+    /// assert_eq!(
+    ///     eval_with_context("repeat(\"ab\", 1.5)", &context),
+    ///     Err(EvalexprError::ExpectedInt { actual: Value::from(1.5) })
+    /// );
+    /// ```
+    pub fn typed<Parameters, F: IntoFunction<Parameters>>(function: F) -> Self {
+        function.into_function()
+    }
+
+    /// Creates a user-defined pure function from a Rust closure with typed parameters.
+    ///
+    /// See `Function::new_pure` for what makes a function pure, and `Function::typed` for the typed parameters.
+    pub fn typed_pure<Parameters, F: IntoFunction<Parameters>>(function: F) -> Self {
+        Self {
+            pure: true,
+            ..Self::typed(function)
+        }
+    }
+
+    /// Returns true if this function was created as pure function with `Function::new_pure` or `Function::typed_pure`.
+    pub fn is_pure(&self) -> bool {
+        self.pure
+    }
+
+    /// Returns the signature of this function if it was created from a typed closure with `Function::typed`, or set with `Function::with_signature`.
+    ///
+    /// The signature can be declared in a `TypeSchema` to check expressions calling this function.
+    pub fn signature(&self) -> Option<&FunctionSignature> {
+        self.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.signature())
+    }
+
+    /// Returns the metadata of this function.
+    ///
+    /// Functions defined within an expression know the names of their parameters, and are named after the identifier they are assigned to.
+    /// Functions created from typed closures know their signature.
+    /// Everything else can be set with methods like `Function::with_documentation`.
+    pub fn metadata(&self) -> FunctionMetadata {
+        let mut metadata = self.metadata.as_deref().cloned().unwrap_or_default();
+        metadata.pure = self.pure;
+        if let (None, FunctionKind::Defined(definition)) = (&metadata.parameter_names, &self.kind) {
+            metadata.parameter_names = Some(definition.parameters().to_vec());
+        }
+        metadata
+    }
+
+    /// Sets the name of this function in its metadata.
+    pub fn with_name(mut self, name: String) -> Self {
+        self.metadata_mut().name = Some(name);
+        self
+    }
+
+    /// Sets the names of the parameters of this function in its metadata.
+    pub fn with_parameter_names(mut self, parameter_names: Vec<String>) -> Self {
+        self.metadata_mut().parameter_names = Some(parameter_names);
+        self
+    }
+
+    /// Sets the signature of this function in its metadata, which overrides the signature of typed closures.
+    pub fn with_signature(mut self, signature: FunctionSignature) -> Self {
+        self.metadata_mut().signature = Some(signature);
+        self
+    }
+
+    /// Sets the documentation of this function in its metadata.
+    pub fn with_documentation(mut self, documentation: String) -> Self {
+        self.metadata_mut().documentation = Some(documentation);
+        self
+    }
+
+    /// Marks this function as deterministic in its metadata, meaning that it always returns the same result when called with the same argument.
+    /// Pure functions are deterministic anyway.
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.metadata_mut().deterministic = deterministic;
+        self
+    }
+
+    /// Returns the metadata of this function for modification, which is cloned if it is shared.
+    fn metadata_mut(&mut self) -> &mut FunctionMetadata {
+        Arc::make_mut(self.metadata.get_or_insert_with(Default::default))
+    }
+
+    /// Creates a function from its definition `fn(parameters) body` written in the expression language.
+    ///
+    /// The body can call all functions of the context the function is called with, including the function itself.
+    /// Other than the parameters, variables are looked up in that context as well, and assignments within the body are local to the call.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let function = Function::from_definition("fn(a, b) a * b + 1").unwrap(); // Do proper error handling here
+    /// assert_eq!(function.to_string(), "fn(a, b) a * b + 1");
+    ///
+    /// let mut context = HashMapContext::new();
+    /// context.set_function("f".into(), function).unwrap(); // Do proper error handling here
+    /// assert_eq!(eval_with_context("f(2, 3)", &context), Ok(Value::from(7)));
+    /// ```
+    pub fn from_definition(definition: &str) -> EvalexprResult<Self> {
+        match build_operator_tree(definition)?.children() {
+            [node] => Ok(Self::defined(FunctionDefinition::from_node(node)?)),
+            _ => Err(EvalexprError::ExpectedFunctionDefinition),
+        }
+    }
+
+    /// Creates a function from a definition within an expression.
+    pub(crate) fn defined(definition: FunctionDefinition) -> Self {
+        Self {
+            kind: FunctionKind::Defined(Arc::new(definition)),
+            pure: false,
+            metadata: None,
+        }
+    }
+
+    /// Creates a builtin function that takes function values as arguments.
+    pub(crate) fn higher_order(
+        function: fn(&Value, &dyn Context) -> EvalexprResult<Value>,
+    ) -> Self {
+        Self {
+            kind: FunctionKind::HigherOrder(function),
+            pure: false,
+            metadata: None,
+        }
+    }
+
+    /// Calls this function with the given argument.
+    /// Functions defined within an expression can call other functions of the given context, including themselves.
+    pub(crate) fn call(&self, argument: &Value, context: &dyn Context) -> EvalexprResult<Value> {
+        match &self.kind {
+            FunctionKind::Native(function) => function(argument),
+            FunctionKind::Defined(definition) => definition.call(argument, context),
+            FunctionKind::HigherOrder(function) => function(argument, context),
+        }
     }
 }
 
 impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "Function {{ [...] }}")
+        match (
+            &self.kind,
+            self.metadata.as_ref().and_then(|metadata| metadata.name()),
+        ) {
+            (FunctionKind::Native(_), Some(name)) | (FunctionKind::HigherOrder(_), Some(name)) => {
+                write!(f, "Function {{ {} [...] }}", name)
+            },
+            (FunctionKind::Native(_), None) | (FunctionKind::HigherOrder(_), None) => {
+                write!(f, "Function {{ [...] }}")
+            },
+            (FunctionKind::Defined(definition), _) => {
+                write!(f, "Function {{ {} }}", definition)
+            },
+        }
+    }
+}
+
+/// Functions defined within an expression are displayed as their definition `fn(parameters) body`,
+/// which can be parsed again.
+/// Functions implemented in Rust are displayed as `fn(...) [native]`.
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match &self.kind {
+            FunctionKind::Native(_) | FunctionKind::HigherOrder(_) => write!(f, "fn(...) [native]"),
+            FunctionKind::Defined(definition) => definition.fmt(f),
+        }
+    }
+}
+
+/// Functions defined within an expression are equal if their definitions are equal.
+/// Functions implemented in Rust cannot be compared, and are never equal.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.kind, &other.kind) {
+            (FunctionKind::Defined(definition), FunctionKind::Defined(other_definition)) => {
+                definition == other_definition
+            },
+            _ => false,
+        }
     }
 }
 
 /// A trait to ensure a type is `Send` and `Sync`.
 /// If implemented for a type, the crate will not compile if the type is not `Send` and `Sync`.
+#[allow(dead_code)]
 trait IsSendAndSync: Send + Sync {}
 
 impl IsSendAndSync for Function {}
@@ -0,0 +1,183 @@
+use std::slice;
+
+use crate::{
+    error::{EvalexprError, EvalexprResult},
+    function::Function,
+    typecheck::{FunctionSignature, TypeSet},
+    value::{value_type::ValueType, EmptyType, FloatType, IntType, MapType, TupleType, Value},
+};
+
+#[cfg(feature = "bigint_support")]
+use crate::value::BigIntType;
+#[cfg(feature = "decimal_support")]
+use crate::value::DecimalType;
+
+/// A type that can be extracted from a `Value` passed as argument to a typed function.
+///
+/// `FloatType` accepts integers as well, converting them like `Value::as_number`, and `Value` accepts any value.
+pub trait FromValue: Sized {
+    /// Extracts an instance of this type from the given value, or returns `Err` if the value has the wrong type.
+    fn from_value(value: &Value) -> EvalexprResult<Self>;
+
+    /// Returns the types of the values accepted by `FromValue::from_value`.
+    fn value_types() -> TypeSet;
+}
+
+/// A type that can be returned by a typed function.
+///
+/// Besides the types convertible into `Value`, this is implemented for `EvalexprResult<T>`, which allows typed functions to fail.
+pub trait IntoValue {
+    /// Converts this into the value returned by a typed function.
+    fn into_value(self) -> EvalexprResult<Value>;
+
+    /// Returns the types of the values returned by `IntoValue::into_value`.
+    fn value_types() -> TypeSet;
+}
+
+/// A Rust closure that can be converted into a `Function` that checks the amount and types of its arguments.
+///
+/// This is implemented for closures with up to six parameters whose types implement `FromValue` and whose result implements `IntoValue`.
+/// The type parameter `Parameters` is the tuple of the parameter types, and only exists to distinguish the implementations.
+///
+/// Like other functions, a closure with multiple parameters is called with a tuple of the same length,
+/// while a closure with a single parameter receives the whole argument, and a closure without parameters is called with an empty argument.
+/// A tuple or empty argument only counts as one argument if the type of the single parameter accepts it.
+/// Calling the function with a different amount of arguments returns `EvalexprError::WrongFunctionArgumentAmount`.
+pub trait IntoFunction<Parameters> {
+    /// Converts this closure into a function, which exposes its signature through `Function::signature`.
+    fn into_function(self) -> Function;
+}
+
+/// Returns the arguments contained in the argument of a call to a function with parameters of the given types,
+/// or `Err` if the amount of arguments does not match.
+///
+/// A single parameter receives the whole argument, unless it is a tuple or empty and the parameter does not accept this type.
+/// Then the elements of the tuple or the absence of arguments are reported as the wrong amount of arguments.
+fn arguments<'a>(argument: &'a Value, parameter_types: &[TypeSet]) -> EvalexprResult<&'a [Value]> {
+    let len = parameter_types.len();
+    if let [parameter_type] = parameter_types {
+        match argument {
+            Value::Tuple(tuple) if !parameter_type.contains(ValueType::Tuple) => {
+                return Err(EvalexprError::wrong_function_argument_amount(
+                    tuple.len(),
+                    1,
+                ))
+            },
+            Value::Empty if !parameter_type.contains(ValueType::Empty) => {
+                return Err(EvalexprError::wrong_function_argument_amount(0, 1))
+            },
+            argument => return Ok(slice::from_ref(argument)),
+        }
+    }
+
+    let arguments = match argument {
+        Value::Tuple(tuple) => tuple.as_slice(),
+        Value::Empty => &[],
+        argument => slice::from_ref(argument),
+    };
+    if arguments.len() == len {
+        Ok(arguments)
+    } else {
+        Err(EvalexprError::wrong_function_argument_amount(
+            arguments.len(),
+            len,
+        ))
+    }
+}
+
+macro_rules! impl_into_function {
+    ($len:expr; $($parameter:ident),*) => {
+        impl<F, R, $($parameter),*> IntoFunction<($($parameter,)*)> for F
+        where
+            F: Fn($($parameter),*) -> R,
+            F: Send + Sync + 'static,
+            F: Clone,
+            R: IntoValue,
+            $($parameter: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn into_function(self) -> Function {
+                let signature =
+                    FunctionSignature::new(vec![$($parameter::value_types()),*], R::value_types());
+                let function = move |argument: &Value| {
+                    let parameter_types: [TypeSet; $len] = [$($parameter::value_types()),*];
+                    let mut arguments = arguments(argument, &parameter_types)?.iter();
+                    // Cannot fail, as the amount of arguments was checked
+                    $(let $parameter = $parameter::from_value(arguments.next().unwrap())?;)*
+                    self($($parameter),*).into_value()
+                };
+
+                Function::new(function).with_signature(signature)
+            }
+        }
+    };
+}
+
+impl_into_function!(0;);
+impl_into_function!(1; A);
+impl_into_function!(2; A, B);
+impl_into_function!(3; A, B, C);
+impl_into_function!(4; A, B, C, D);
+impl_into_function!(5; A, B, C, D, E);
+impl_into_function!(6; A, B, C, D, E, G);
+
+macro_rules! impl_value_conversions {
+    ($type:ty, $value_type:expr, $from_value:expr) => {
+        impl_value_conversions!($type, $value_type.into(), $value_type.into(), $from_value);
+    };
+    ($type:ty, $accepted_types:expr, $returned_types:expr, $from_value:expr) => {
+        impl FromValue for $type {
+            fn from_value(value: &Value) -> EvalexprResult<Self> {
+                $from_value(value)
+            }
+
+            fn value_types() -> TypeSet {
+                $accepted_types
+            }
+        }
+
+        impl IntoValue for $type {
+            fn into_value(self) -> EvalexprResult<Value> {
+                Ok(Value::from(self))
+            }
+
+            fn value_types() -> TypeSet {
+                $returned_types
+            }
+        }
+    };
+}
+
+impl_value_conversions!(String, ValueType::String, Value::as_string);
+impl_value_conversions!(IntType, ValueType::Int, Value::as_int);
+impl_value_conversions!(
+    FloatType,
+    TypeSet::number(),
+    ValueType::Float.into(),
+    Value::as_number
+);
+#[cfg(feature = "decimal_support")]
+impl_value_conversions!(DecimalType, ValueType::Decimal, Value::as_decimal);
+#[cfg(feature = "bigint_support")]
+impl_value_conversions!(BigIntType, ValueType::BigInt, Value::as_bigint);
+impl_value_conversions!(bool, ValueType::Boolean, Value::as_boolean);
+impl_value_conversions!(TupleType, ValueType::Tuple, Value::as_tuple);
+impl_value_conversions!(MapType, ValueType::Map, Value::as_map);
+impl_value_conversions!(Function, ValueType::Function, Value::as_function);
+impl_value_conversions!(EmptyType, ValueType::Empty, Value::as_empty);
+impl_value_conversions!(
+    Value,
+    TypeSet::any(),
+    TypeSet::any(),
+    |value: &Value| -> EvalexprResult<Value> { Ok(value.clone()) }
+);
+
+impl<T: IntoValue> IntoValue for EvalexprResult<T> {
+    fn into_value(self) -> EvalexprResult<Value> {
+        self?.into_value()
+    }
+
+    fn value_types() -> TypeSet {
+        T::value_types()
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
-    token, tree, value::TupleType, Context, ContextWithMutableVariables, EmptyType, EvalexprError,
-    EvalexprResult, FloatType, HashMapContext, IntType, Node, Value, EMPTY_VALUE,
+    token, tree, value::TupleType, Context, ContextWithMutableFunctions,
+    ContextWithMutableVariables, EmptyType, EvalexprError, EvalexprResult, FloatType,
+    HashMapContext, IntType, Node, Value, VariableChange, EMPTY_VALUE,
 };
 
 /// Evaluate the given expression string.
@@ -34,7 +35,7 @@ pub fn eval(string: &str) -> EvalexprResult<Value> {
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
 pub fn eval_with_context<C: Context>(string: &str, context: &C) -> EvalexprResult<Value> {
-    tree::tokens_to_operator_tree(token::tokenize(string)?)?.eval_with_context(context)
+    build_operator_tree(string)?.eval_with_context(context)
 }
 
 /// Evaluate the given expression string with the given mutable context.
@@ -52,11 +53,69 @@ pub fn eval_with_context<C: Context>(string: &str, context: &C) -> EvalexprResul
 /// ```
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<Value> {
-    tree::tokens_to_operator_tree(token::tokenize(string)?)?.eval_with_context_mut(context)
+    build_operator_tree(string)?.eval_with_context_mut(context)
+}
+
+/// Evaluate the given expression string with the given mutable context, as a transaction.
+///
+/// Assignments and function definitions are only written to the context if the whole expression evaluates successfully.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let mut context = HashMapContext::new();
+/// assert!(eval_with_context_transactional("a = 1; b = a / 0", &mut context).is_err());
+/// assert_eq!(context.get_value("a"), None);
+///
+/// assert_eq!(eval_with_context_transactional("a = 1; b = a + 1", &mut context), Ok(Value::Empty));
+/// assert_eq!(context.get_value("b").as_deref(), Some(&Value::from(2)));
+/// ```
+///
+/// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
+pub fn eval_with_context_transactional<
+    C: ContextWithMutableVariables + ContextWithMutableFunctions,
+>(
+    string: &str,
+    context: &mut C,
+) -> EvalexprResult<Value> {
+    build_operator_tree(string)?.eval_with_context_transactional(context)
+}
+
+/// Evaluate the given expression string with the given mutable context, and return the writes to variables alongside the result.
+///
+/// The writes are returned even if the evaluation fails, so that the writes made before the failure are known.
+/// If the expression cannot be parsed, nothing is written.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let mut context = context_map! { "a" => 1 }.unwrap(); // Do proper error handling here
+/// let (value, changes) = eval_with_context_recorded("a *= 3; a + 1", &mut context);
+/// assert_eq!(value, Ok(Value::from(4)));
+/// assert_eq!(changes, vec![VariableChange {
+///     identifier: "a".into(),
+///     old_value: Some(Value::from(1)),
+///     new_value: Value::from(3),
+/// }]);
+/// ```
+///
+/// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
+pub fn eval_with_context_recorded<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
+    string: &str,
+    context: &mut C,
+) -> (EvalexprResult<Value>, Vec<VariableChange>) {
+    match build_operator_tree(string) {
+        Ok(tree) => tree.eval_with_context_recorded(context),
+        Err(error) => (Err(error), Vec::new()),
+    }
 }
 
 /// Build the operator tree for the given expression string.
@@ -84,6 +143,25 @@ pub fn eval_with_context_mut<C: ContextWithMutableVariables>(
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
 pub fn build_operator_tree(string: &str) -> EvalexprResult<Node> {
+    build_operator_tree_spanned(string).map_err(EvalexprError::without_span)
+}
+
+/// Build the operator tree for the given expression string, locating errors in the string.
+///
+/// This behaves like `build_operator_tree`, but if possible, errors are wrapped into `EvalexprError::Spanned` with the span of the input string that caused them.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let error = build_operator_tree_spanned("1 + (2 * 3").unwrap_err();
+/// assert_eq!(error.span().map(|span| span.start.column), Some(5));
+/// assert_eq!(error.without_span(), EvalexprError::UnmatchedLBrace);
+/// ```
+///
+/// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
+pub fn build_operator_tree_spanned(string: &str) -> EvalexprResult<Node> {
     tree::tokens_to_operator_tree(token::tokenize(string)?)
 }
 
@@ -222,7 +300,9 @@ pub fn eval_empty_with_context<C: Context>(string: &str, context: &C) -> Evalexp
 /// Evaluate the given expression string into a string with the given mutable context.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_string_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_string_with_context_mut<
+    C: ContextWithMutableVariables + ContextWithMutableFunctions,
+>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<String> {
@@ -236,7 +316,7 @@ pub fn eval_string_with_context_mut<C: ContextWithMutableVariables>(
 /// Evaluate the given expression string into an integer with the given mutable context.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_int_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_int_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<IntType> {
@@ -250,7 +330,7 @@ pub fn eval_int_with_context_mut<C: ContextWithMutableVariables>(
 /// Evaluate the given expression string into a float with the given mutable context.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_float_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_float_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<FloatType> {
@@ -265,7 +345,9 @@ pub fn eval_float_with_context_mut<C: ContextWithMutableVariables>(
 /// If the result of the expression is an integer, it is silently converted into a float.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_number_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_number_with_context_mut<
+    C: ContextWithMutableVariables + ContextWithMutableFunctions,
+>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<FloatType> {
@@ -280,7 +362,9 @@ pub fn eval_number_with_context_mut<C: ContextWithMutableVariables>(
 /// Evaluate the given expression string into a boolean with the given mutable context.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_boolean_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_boolean_with_context_mut<
+    C: ContextWithMutableVariables + ContextWithMutableFunctions,
+>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<bool> {
@@ -294,7 +378,7 @@ pub fn eval_boolean_with_context_mut<C: ContextWithMutableVariables>(
 /// Evaluate the given expression string into a tuple with the given mutable context.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_tuple_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_tuple_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<TupleType> {
@@ -308,7 +392,7 @@ pub fn eval_tuple_with_context_mut<C: ContextWithMutableVariables>(
 /// Evaluate the given expression string into an empty value with the given mutable context.
 ///
 /// *See the [crate doc](index.html) for more examples and explanations of the expression format.*
-pub fn eval_empty_with_context_mut<C: ContextWithMutableVariables>(
+pub fn eval_empty_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
     string: &str,
     context: &mut C,
 ) -> EvalexprResult<EmptyType> {
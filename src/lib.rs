@@ -35,7 +35,7 @@
 //! assert_eq!(eval_empty_with_context_mut("a = 5.0", &mut context),
 //!            Err(EvalexprError::expected_int(Value::from(5.0))));
 //! // We can check which value the context stores for a like this
-//! assert_eq!(context.get_value("a"), Some(&Value::from(5)));
+//! assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(5)));
 //! // And use the value in another expression like this
 //! assert_eq!(eval_int_with_context_mut("a = a + 2; a", &mut context), Ok(7));
 //! // It is also possible to save a bit of typing by using an operator-assignment operator
@@ -100,6 +100,28 @@
 //! assert_eq!(precompiled.eval_boolean_with_context(&context), Ok(false));
 //! ```
 //!
+//! If the same expression is evaluated very often, it can additionally be **compiled** into a flat program for a stack machine.
+//! Compiled expressions resolve their variables into slots, so the values can also be passed by position instead of by identifier:
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let compiled = build_operator_tree("a * b - c > 5").unwrap().compile(); // Do proper error handling here
+//! assert_eq!(compiled.variable_identifiers(), &["a".to_string(), "b".to_string(), "c".to_string()]);
+//! assert_eq!(compiled.eval_with_slots(&[6.into(), 2.into(), 3.into()], &EmptyContext), Ok(Value::from(true)));
+//! ```
+//!
+//! Before evaluating or compiling, an operator tree can be **optimized**.
+//! This evaluates subtrees that do not depend on variables or impure functions ahead of time, and removes operations that have no effect:
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut tree = build_operator_tree("a * (60 * 60) + 0 * 1").unwrap(); // Do proper error handling here
+//! tree.optimize();
+//! assert_eq!(tree.eval_with_context(&context_map! { "a" => 2 }.unwrap()), Ok(Value::from(7200)));
+//! ```
+//!
 //! ## Features
 //!
 //! ### Operators
@@ -113,9 +135,10 @@
 //!
 //! | Operator | Precedence | Description |
 //! |----------|------------|-------------|
-//! | ^ | 120 | Exponentiation |
+//! | ^ | 120 | Exponentiation (integer if the base is an integer and the exponent a non-negative integer, otherwise float) |
 //! | * | 100 | Product |
 //! | / | 100 | Division (integer if both arguments are integers, otherwise float) |
+//! | // | 100 | Floor division, rounding the quotient towards negative infinity (integer if both arguments are integers, otherwise float) |
 //! | % | 100 | Modulo (integer if both arguments are integers, otherwise float) |
 //! | + | 95 | Sum or String Concatenation |
 //! | - | 95 | Difference |
@@ -125,13 +148,14 @@
 //! | \>= | 80 | Greater than or equal |
 //! | == | 80 | Equal |
 //! | != | 80 | Not equal |
-//! | && | 75 | Logical and |
-//! | &#124;&#124; | 70 | Logical or |
+//! | && | 75 | Logical and (short-circuiting) |
+//! | &#124;&#124; | 70 | Logical or (short-circuiting) |
 //! | = | 50 | Assignment |
 //! | += | 50 | Sum-Assignment or String-Concatenation-Assignment |
 //! | -= | 50 | Difference-Assignment |
 //! | *= | 50 | Product-Assignment |
 //! | /= | 50 | Division-Assignment |
+//! | //= | 50 | Floor-Division-Assignment |
 //! | %= | 50 | Modulo-Assignment |
 //! | ^= | 50 | Exponentiation-Assignment |
 //! | &&= | 50 | Logical-And-Assignment |
@@ -146,10 +170,13 @@
 //! | - | 110 | Negation |
 //! | ! | 110 | Logical not |
 //!
+//! Additionally, the indexing operator `value[index]` and the slicing operator `value[start..end]` have a precedence of 150.
+//!
 //! Operators that take numbers as arguments can either take integers or floating point numbers.
 //! If one of the arguments is a floating point number, all others are converted to floating point numbers as well, and the resulting value is a floating point number as well.
 //! Otherwise, the result is an integer.
-//! An exception to this is the exponentiation operator that always returns a floating point number.
+//! An exception to this is the exponentiation operator, which returns a floating point number if the exponent is a negative integer.
+//! Integer division with `/` rounds towards zero, while `//` rounds towards negative infinity.
 //! Example:
 //!
 //! ```rust
@@ -157,7 +184,22 @@
 //!
 //! assert_eq!(eval("1 / 2"), Ok(Value::from(0)));
 //! assert_eq!(eval("1.0 / 2"), Ok(Value::from(0.5)));
-//! assert_eq!(eval("2^2"), Ok(Value::from(4.0)));
+//! assert_eq!(eval("2^2"), Ok(Value::from(4)));
+//! assert_eq!(eval("2^(-1)"), Ok(Value::from(0.5)));
+//! assert_eq!(eval("-7 / 2"), Ok(Value::from(-3)));
+//! assert_eq!(eval("-7 // 2"), Ok(Value::from(-4)));
+//! ```
+//!
+//! The logical operators `&&` and `||` short-circuit, meaning that their right argument is only evaluated if the left argument does not already determine the result.
+//! Likewise, the builtin function `if` only evaluates the branch that it returns.
+//! This allows to guard expressions that would fail otherwise:
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let context = context_map! { "x" => 0 }.unwrap(); // Do proper error handling here
+//! assert_eq!(eval_boolean_with_context("x != 0 && 10 / x > 2", &context), Ok(false));
+//! assert_eq!(eval_int_with_context("if(x == 0, 0, 10 / x)", &context), Ok(0));
 //! ```
 //!
 //! #### The Aggregation Operator
@@ -206,7 +248,7 @@
 //! assert_eq!(eval_empty_with_context_mut("a = 5.0", &mut context),
 //!            Err(EvalexprError::expected_int(5.0.into())));
 //! assert_eq!(eval_int_with_context("a", &context), Ok(5));
-//! assert_eq!(context.get_value("a"), Some(5.into()).as_ref());
+//! assert_eq!(context.get_value("a").as_deref(), Some(5.into()).as_ref());
 //! ```
 //!
 //! For each binary operator, there exists an equivalent operator-assignment operator.
@@ -245,6 +287,40 @@
 //! assert_eq!(healing_script.eval_int_with_context_mut(&mut context), Ok(5));
 //! ```
 //!
+//! #### Indexing and Slicing
+//!
+//! The elements of tuples and the characters of strings are accessed by their index with `value[index]`, starting at zero.
+//! Negative indices count from the end, such that `value[-1]` is the last element.
+//! A range `value[start..end]` returns the tuple or string of the elements from `start` up to but excluding `end`.
+//! Both bounds may be negative or omitted, where an omitted start is the beginning and an omitted end is the end of the value.
+//! Accessing an index or a range outside of the value fails with `EvalexprError::IndexOutOfBounds` or `EvalexprError::SliceOutOfBounds`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! assert_eq!(eval("(1, 2, 3)[0] + (1, 2, 3)[-1]"), Ok(Value::from(4)));
+//! assert_eq!(eval("(1, 2, 3)[1..]"), Ok(Value::from(vec![Value::from(2), Value::from(3)])));
+//! assert_eq!(eval("\"hello\"[..-1]"), Ok(Value::from("hell")));
+//! assert_eq!(eval("(1, 2, 3)[3]"), Err(EvalexprError::IndexOutOfBounds { index: 3, len: 3 }));
+//! ```
+//!
+//! #### Maps
+//!
+//! Maps are created with curly braces containing comma-separated key-value pairs `key: value`, where keys are strings.
+//! The values of a map are accessed either with the indexing operator `map["key"]`, or with a dot as in `map.key` if the map is stored in a variable.
+//! If a variable identifier containing dots is not bound by the context, it is resolved by looking up the longest prefix ending before a dot that is bound, and accessing the remaining dot-separated parts as keys of nested maps.
+//! Variables with dots in their identifier that are bound by the context take precedence.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = HashMapContext::new();
+//! eval_with_context_mut("user = {\"name\": \"Alice\", \"address\": {\"city\": \"Berlin\"}}", &mut context).unwrap(); // Do proper error handling here
+//! assert_eq!(eval_with_context("user.address.city", &context), Ok(Value::from("Berlin")));
+//! assert_eq!(eval_with_context("user[\"name\"] + \"!\"", &context), Ok(Value::from("Alice!")));
+//! assert_eq!(eval_with_context("keys(user)", &context), Ok(Value::from(vec!["address".into(), "name".into()])));
+//! ```
+//!
 //! ### Contexts
 //!
 //! An expression evaluator that just evaluates expressions would be useful already, but this crate can do more.
@@ -297,8 +373,26 @@
 //! // We can write or overwrite variables in expressions...
 //! assert_eq!(eval_with_context_mut("a = 10; b = 1.0;", &mut context), Ok(().into()));
 //! // ...and read the value in code like this
-//! assert_eq!(context.get_value("a"), Some(&Value::from(10)));
-//! assert_eq!(context.get_value("b"), Some(&Value::from(1.0)));
+//! assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(10)));
+//! assert_eq!(context.get_value("b").as_deref(), Some(&Value::from(1.0)));
+//! ```
+//!
+//! The variables and functions of a context can be listed with `IterateVariablesContext` and `GetFunctionContext`, and removed again:
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = HashMapContext::new();
+//! eval_with_context_mut("a = 1; b = 2; f = fn(x) x + a", &mut context).unwrap(); // Do proper error handling here
+//! let mut names: Vec<_> = context.iter_variable_names().collect();
+//! names.sort();
+//! assert_eq!(names, vec!["a", "b"]);
+//! assert!(context.get_function("f").is_some());
+//!
+//! assert_eq!(context.remove_value("a"), Ok(Some(Value::from(1))));
+//! assert_eq!(context.remove_function("f").map(|function| function.is_some()), Ok(true));
+//! assert_eq!(context.iter_variables().collect::<Vec<_>>(), vec![("b", &Value::from(2))]);
+//! assert_eq!(context.iter_functions().count(), 0);
 //! ```
 //!
 //! Contexts are also required for user-defined functions.
@@ -315,6 +409,74 @@
 //!
 //! For more information about user-defined functions, refer to the respective [section](#user-defined-functions).
 //!
+//! To share a context between many evaluations without cloning it, a `ScopedContext` can be layered on top of it.
+//! Lookups fall through to the shared context, while assignments land in the innermost layer of the scoped context.
+//! Layers for nested scopes are added with `ScopedContext::push_scope` and discarded with `ScopedContext::pop_scope`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let shared = context_map! { "limit" => 100 }.unwrap(); // Do proper error handling here
+//! let mut request = ScopedContext::new(&shared);
+//! assert_eq!(eval_with_context_mut("value = 120; value > limit", &mut request), Ok(Value::from(true)));
+//! assert_eq!(shared.get_value("value"), None);
+//! ```
+//!
+//! Contexts return the values of variables as `Cow<Value>`, so they can either borrow them from their own data structures or compute them on demand.
+//! The `LazyContext` resolves variables only when an expression uses them, by calling a function with their identifier:
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let context = LazyContext::cached(|identifier| match identifier {
+//!     "answer" => Some(Value::from(42)),
+//!     _ => None,
+//! });
+//! assert_eq!(eval_with_context("answer + 1", &context), Ok(Value::from(43)));
+//! ```
+//!
+//! To evaluate expressions in parallel against one set of variables, a `ConcurrentContext` can be shared between threads.
+//! It protects its variables with locks, so assignments only need a shared reference to it, passed as `&mut &context`.
+//! Compound assignments like `a += 1` update the variable atomically.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let context = ConcurrentContext::new();
+//! context.set_value("hits".into(), Value::from(0)).unwrap(); // Do proper error handling here
+//! eval_with_context_mut("hits += 1", &mut &context).unwrap(); // Do proper error handling here
+//! assert_eq!(eval_with_context("hits", &context), Ok(Value::from(1)));
+//! ```
+//!
+//! If an expression fails halfway, the assignments made before the failure remain in the context.
+//! To avoid this, `eval_with_context_transactional` buffers all assignments and function definitions of an expression, and only writes them to the context if the expression evaluates successfully.
+//! A `HashMapContext` can also be saved explicitly with `HashMapContext::snapshot`, and reset to the saved state with `HashMapContext::restore`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = HashMapContext::new();
+//! assert!(eval_with_context_mut("a = 1; b = a / 0", &mut context).is_err());
+//! assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(1)));
+//!
+//! context.clear();
+//! assert!(eval_with_context_transactional("a = 1; b = a / 0", &mut context).is_err());
+//! assert_eq!(context.get_value("a"), None);
+//! ```
+//!
+//! To find out which variables an expression wrote, for example to update values that depend on them, a context can be wrapped into a `RecordingContext`.
+//! It records each assignment, including compound assignments like `a += 1`, as a `VariableChange` with the old and the new value of the variable.
+//! `eval_with_context_recorded` returns the recorded changes alongside the result, also if the evaluation fails.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = context_map! { "a" => 1 }.unwrap(); // Do proper error handling here
+//! let (_, changes) = eval_with_context_recorded("a += 1; b = a", &mut context);
+//! let written: Vec<_> = changes.iter().map(|change| change.identifier.as_str()).collect();
+//! assert_eq!(written, vec!["a", "b"]);
+//! ```
+//!
 //! ### Builtin Functions
 //!
 //! This crate offers a set of builtin functions.
@@ -323,12 +485,27 @@
 //! |----------------------|-----------------|------------------------|-------------|
 //! | `min`                | >= 1            | Numeric                | Returns the minimum of the arguments |
 //! | `max`                | >= 1            | Numeric                | Returns the maximum of the arguments |
-//! | `len`                | 1               | String/Tuple           | Returns the character length of a string, or the amount of elements in a tuple (not recursively) |
+//! | `len`                | 1               | String/Tuple/Map       | Returns the character length of a string, or the amount of elements in a tuple or map (not recursively) |
+//! | `div_euclid`         | 2               | Numeric, Numeric       | Returns the quotient of the euclidean division of the first by the second argument (integer if both arguments are integers, otherwise float) |
+//! | `rem_euclid`         | 2               | Numeric, Numeric       | Returns the non-negative remainder of the euclidean division of the first by the second argument (integer if both arguments are integers, otherwise float) |
 //! | `floor`              | 1               | Numeric                | Returns the largest integer less than or equal to a number |
 //! | `round`              | 1               | Numeric                | Returns the nearest integer to a number. Rounds half-way cases away from 0.0 |
 //! | `ceil`               | 1               | Numeric                | Returns the smallest integer greater than or equal to a number |
-//! | `if`                 | 3               | Boolean, Any, Any      | If the first argument is true, returns the second argument, otherwise, returns the third  |
-//! | `typeof`             | 1               | Any                    | returns "string", "float", "int", "boolean", "tuple", or "empty" depending on the type of the argument  |
+//! | `decimal::from`      | 1               | Numeric, String        | Returns the number, or the number in the string, converted to a decimal (Requires `decimal_support` feature flag) |
+//! | `decimal::round`     | 3               | Numeric, Int, String   | Rounds a number to a decimal with the given amount of decimal places, using the rounding mode of the given name (Requires `decimal_support` feature flag) |
+//! | `decimal::to_int`    | 1               | Int or Decimal         | Converts a decimal without fractional part to an integer (Requires `decimal_support` feature flag) |
+//! | `decimal::to_float`  | 1               | Numeric                | Converts a number to the nearest float (Requires `decimal_support` feature flag) |
+//! | `if`                 | 3               | Boolean, Any, Any      | If the first argument is true, returns the second argument, otherwise, returns the third. Only the returned argument is evaluated  |
+//! | `typeof`             | 1               | Any                    | returns "string", "float", "int", "boolean", "tuple", "map", "function", or "empty" depending on the type of the argument  |
+//! | `keys`               | 1               | Map                    | Returns the keys of a map as a tuple of strings, in ascending order |
+//! | `values`             | 1               | Map                    | Returns the values of a map as a tuple, in ascending order of their keys |
+//! | `contains_key`       | 2               | Map, String            | Returns true if the map contains the given key |
+//! | `map`                | 2               | Tuple, Function        | Returns a tuple of the results of calling the function with each element |
+//! | `filter`             | 2               | Tuple, Function        | Returns a tuple of the elements for which the function returns true |
+//! | `reduce`             | 3               | Tuple, Any, Function   | Calls the function with the accumulator and each element, starting with the second argument as accumulator, and returns the final accumulator |
+//! | `any`                | 2               | Tuple, Function        | Returns true if the function returns true for any element |
+//! | `all`                | 2               | Tuple, Function        | Returns true if the function returns true for all elements |
+//! | `sort_by`            | 2               | Tuple, Function        | Returns the elements sorted by the keys the function returns for them, which must all be numbers or all be strings. Elements with equal keys keep their order |
 //! | `math::is_nan`       | 1               | Numeric                | Returns true if the argument is the floating-point value NaN, false if it is another floating-point value, and throws an error if it is not a number  |
 //! | `math::is_finite`    | 1               | Numeric                | Returns true if the argument is a finite floating-point number, false otherwise  |
 //! | `math::is_infinite`  | 1               | Numeric                | Returns true if the argument is an infinite floating-point number, false otherwise  |
@@ -373,12 +550,16 @@
 //! If the maximum or minimum is an integer, then an integer is returned.
 //! Otherwise, a float is returned.
 //!
-//! The regex functions require the feature flag `regex_support`.
+//! The regex functions require the feature flag `regex_support`, and the `decimal` functions require the feature flag `decimal_support`.
+//! With the feature flag `decimal_support`, the functions `floor`, `round` and `ceil` return decimals when given a decimal.
+//!
+//! The functions `map`, `filter`, `reduce`, `any`, `all` and `sort_by` take a [function value](#lambdas-and-function-values) as their last argument.
 //!
 //! ### Values
 //!
 //! Operators take values as arguments and produce values as results.
-//! Values can be booleans, integer or floating point numbers, strings, tuples or the empty type.
+//! Values can be booleans, integer or floating point numbers, strings, tuples, maps, functions or the empty type,
+//! as well as [decimal numbers](#decimal-numbers) with the `decimal_support` feature flag and [big integers](#big-integers) with the `bigint_support` feature flag.
 //! Values are denoted as displayed in the following table.
 //!
 //! | Value type | Example |
@@ -387,14 +568,18 @@
 //! | `Value::Boolean` | `true`, `false` |
 //! | `Value::Int` | `3`, `-9`, `0`, `135412` |
 //! | `Value::Float` | `3.`, `.35`, `1.00`, `0.5`, `123.554`, `23e4`, `-2e-3`, `3.54e+2` |
+//! | `Value::Decimal` | `0.1d`, `25d`, `1e-3d` |
+//! | `Value::BigInt` | `18446744073709551616` |
 //! | `Value::Tuple` | `(3, 55.0, false, ())`, `(1, 2)` |
+//! | `Value::Map` | `{"a": 1, "b": (2, 3)}`, `{}` |
+//! | `Value::Function` | `x -> x + 1`, `fn(a, b) a * b` |
 //! | `Value::Empty` | `()` |
 //!
 //! Integers are internally represented as `i64`, and floating point numbers are represented as `f64`.
-//! Tuples are represented as `Vec<Value>` and empty values are not stored, but represented by Rust's unit type `()` where necessary.
+//! Tuples are represented as `Vec<Value>`, maps are represented as `BTreeMap<String, Value>` and empty values are not stored, but represented by Rust's unit type `()` where necessary.
 //!
 //! There exist type aliases for some of the types.
-//! They include `IntType`, `FloatType`, `TupleType` and `EmptyType`.
+//! They include `IntType`, `FloatType`, `TupleType`, `MapType` and `EmptyType`.
 //!
 //! Values can be constructed either directly or using the `From` trait.
 //! They can be decomposed using the `Value::as_[type]` methods.
@@ -449,7 +634,7 @@
 //! This crate allows to define arbitrary functions to be used in parsed expressions.
 //! A function is defined as a `Function` instance, wrapping an `fn(&Value) -> EvalexprResult<Value>`.
 //! The definition needs to be included in the [`Context`](#contexts) that is used for evaluation.
-//! As of now, functions cannot be defined within the expression, but that might change in the future.
+//! Functions can also be defined within the expression, as described [below](#functions-defined-in-expressions).
 //!
 //! The function gets passed what ever value is directly behind it, be it a tuple or a single values.
 //! If there is no value behind a function, it is interpreted as a variable instead.
@@ -460,6 +645,18 @@
 //! The `error` module contains some shortcuts for verification, and error types for passing a wrong value type.
 //! Also, most numeric functions need to distinguish between being called with integers or floating point numbers, and act accordingly.
 //!
+//! Alternatively, functions can be created from Rust closures with typed parameters using `Function::typed`.
+//! These check the amount and types of their arguments, and expose their `FunctionSignature` for tooling like the [type checker](#type-checking).
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = HashMapContext::new();
+//! context.set_function("area".into(), Function::typed(|width: FloatType, height: FloatType| width * height)).unwrap(); // Do proper error handling here
+//! assert_eq!(eval_with_context("area(2, 1.5)", &context), Ok(Value::from(3.0)));
+//! assert_eq!(eval_with_context("area(2)", &context), Err(EvalexprError::WrongFunctionArgumentAmount { expected: 2, actual: 1 }));
+//! ```
+//!
 //! Here are some examples and counter-examples on expressions that are interpreted as function calls:
 //!
 //! | Expression | Function? | Explanation |
@@ -474,6 +671,258 @@
 //!
 //! Functions have a precedence of 190.
 //!
+//! Functions can carry metadata like their name, parameter names, signature and documentation, for example to generate help pages or autocompletion.
+//! It is set with methods like `Function::with_documentation`, and returned by `Function::metadata` and `Context::function_metadata`.
+//! The identifiers and metadata of the builtin functions are returned by `builtin_function_identifiers` and `builtin_function_metadata`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = HashMapContext::new();
+//! context.set_function("double".into(), Function::typed_pure(|x: IntType| x * 2)
+//!     .with_parameter_names(vec!["x".into()])
+//!     .with_documentation("Doubles x.".into())).unwrap(); // Do proper error handling here
+//! let metadata = context.function_metadata("double").unwrap();
+//! assert_eq!(metadata.to_string(), "fn(x: int) -> int");
+//! assert_eq!(metadata.documentation(), Some("Doubles x."));
+//! assert_eq!(builtin_function_metadata("math::sqrt").unwrap().to_string(), "math::sqrt(x: float | int) -> float");
+//! ```
+//!
+//! #### Functions Defined in Expressions
+//!
+//! Functions can be defined within an expression with the keyword `fn`, followed by a parenthesized list of parameters and the body of the function.
+//! Assigning a definition directly to an identifier stores the function in the context like `ContextWithMutableFunctions::set_function`.
+//! Otherwise, the definition evaluates to a [function value](#lambdas-and-function-values).
+//! Hence, `fn` cannot be used as an identifier.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = HashMapContext::new();
+//! assert_eq!(eval_with_context_mut("square = fn(x) x * x; square(4)", &mut context), Ok(Value::from(16)));
+//! assert_eq!(eval_with_context_mut("fact = fn(n) if(n <= 1, 1, n * fact(n - 1)); fact(5)", &mut context), Ok(Value::from(120)));
+//! ```
+//!
+//! When the function is called, its parameters are bound to the arguments in a new scope, and variables assigned within the body are local to the call.
+//! All other identifiers are looked up in the context the function is called with, so functions can call themselves and each other.
+//! Calls to functions defined in expressions can be nested at most 100 levels deep, otherwise `EvalexprError::RecursionLimitExceeded` is returned.
+//! This limit can be changed with `EvalLimits::max_recursion`.
+//!
+//! The body of a function extends as far as an assignment would, so it ends before the next `,` or `;` that is not within parentheses.
+//! Functions can also be created from Rust with `Function::from_definition`, and functions defined in expressions are displayed as their definition, such that the output can be parsed again.
+//!
+//! #### Lambdas and Function Values
+//!
+//! A function definition that is not assigned directly to an identifier evaluates to a `Value::Function`.
+//! Lambdas are a shorter syntax for function definitions, where a single parameter or a parenthesized list of parameters is followed by `->` and the body, like `x -> x * 2` or `(a, b) -> a + b`.
+//! Function values can be passed as arguments, stored in variables, tuples and maps, and called like other functions.
+//! When calling an identifier, functions of the context are preferred over variables holding function values, and those are preferred over builtin functions.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! assert_eq!(eval("map((1, 2, 3), x -> x * 2)"), Ok(Value::from(vec![Value::from(2), Value::from(4), Value::from(6)])));
+//! assert_eq!(eval("reduce((1, 2, 3), 0, (sum, x) -> sum + x)"), Ok(Value::from(6)));
+//! assert_eq!(eval("apply = (f, x) -> f(x); apply(x -> x + 1, 2)"), Ok(Value::from(3)));
+//! ```
+//!
+//! When a function value is created, it captures the current values of the variables its body reads, if they are defined at that point.
+//! So a lambda passed to a builtin function like `map` or returned from a function can use the variables and parameters of the expression or function it is written in.
+//! Other identifiers, including all functions, are looked up when the function value is called.
+//! Function values can only be called through an identifier, so a lambda cannot be called directly, like `(x -> x)(5)`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! assert_eq!(eval("make_adder = fn(k) x -> x + k; add3 = make_adder(3); add3(1)"), Ok(Value::from(4)));
+//! ```
+//!
+//! Function values cannot be serialized.
+//!
+//! ### Resource Limits
+//!
+//! When evaluating expressions from untrusted sources, the resources used for parsing and evaluating them can be limited with `EvalLimits`.
+//! The limits apply to everything that is parsed or evaluated within a call to `EvalLimits::enforce` on the same thread.
+//! They restrict the amount of evaluated operations, the nesting depth of the evaluation, the size of computed values and the nesting of the expression while parsing.
+//! If a limit is exceeded, `EvalexprError::LimitExceeded` is returned.
+//! Even without `EvalLimits`, expressions can only be nested 256 levels deep while parsing, as deeper operator trees could overflow the stack when they are evaluated.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let limits = EvalLimits::new().max_operations(1000).max_depth(100).max_value_size(1000).max_nesting(50);
+//! assert_eq!(limits.enforce(|| eval("loop = fn(n) loop(n + 1); loop(0)")),
+//!     Err(EvalexprError::LimitExceeded { limit: Limit::Depth, maximum: 100 }));
+//! assert_eq!(limits.enforce(|| eval("s = \"ab\"; s = s + s; s = s + s; len(s)")), Ok(Value::from(8)));
+//! ```
+//!
+//! Evaluations can also be interrupted with a deadline or timeout, and with a `CancellationToken` that can be cancelled from another thread.
+//! These are checked before each operation, and the evaluation stops with `EvalexprError::DeadlineExceeded` or `EvalexprError::Cancelled`, respectively.
+//!
+//! ```rust
+//! use evalexpr::*;
+//! use std::time::Duration;
+//!
+//! let token = CancellationToken::new();
+//! let limits = EvalLimits::new().timeout(Duration::from_millis(100)).cancellation_token(token.clone());
+//! assert_eq!(limits.enforce(|| eval("1 + 2")), Ok(Value::from(3)));
+//! token.cancel();
+//! assert_eq!(limits.enforce(|| eval("1 + 2")), Err(EvalexprError::Cancelled));
+//! ```
+//!
+//! ### Integer Overflow
+//!
+//! By default, integer operations whose result does not fit into an `IntType` fail with an error like `EvalexprError::AdditionError`.
+//! Within a call to `OverflowPolicy::enforce`, the given `OverflowPolicy` decides the result instead:
+//! it can wrap around, saturate at the bounds of `IntType`, or be computed with floats,
+//! or with [big integers](#big-integers) if the `bigint_support` feature flag is set.
+//! The policy applies to the arithmetic operators, compound assignments and the integer builtin functions `div_euclid`, `rem_euclid`, `shl` and `shr`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut context = context_map! { "a" => IntType::MAX }.unwrap(); // Do proper error handling here
+//! assert!(eval_with_context("a * 2", &context).is_err());
+//! assert_eq!(OverflowPolicy::Saturating.enforce(|| eval_with_context("a * 2", &context)), Ok(Value::from(IntType::MAX)));
+//! assert_eq!(OverflowPolicy::Wrapping.enforce(|| eval_with_context_mut("a += 1; a", &mut context)), Ok(Value::from(IntType::MIN)));
+//! ```
+//!
+//! ### Type Checking
+//!
+//! Operator trees can be checked for type errors before evaluating them with `Node::type_check`.
+//! The types of variables and the signatures of functions are declared in a `TypeSchema`, and types are described by a `TypeSet` of possible `ValueType`s.
+//! Builtin functions are known to the type checker, and assignments and functions defined in the expression are taken into account.
+//! All errors found are returned at once, located by spans if the tree was built with spans.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut schema = TypeSchema::new();
+//! schema.set_variable_type("name".into(), ValueType::String);
+//! schema.set_variable_type("age".into(), ValueType::Int);
+//!
+//! let tree = build_operator_tree("if(age >= 18, name, \"minor\")").unwrap(); // Do proper error handling here
+//! assert_eq!(tree.type_check(&schema), Ok(ValueType::String.into()));
+//!
+//! let tree = build_operator_tree("age + name").unwrap(); // Do proper error handling here
+//! let errors = tree.type_check(&schema).unwrap_err();
+//! assert_eq!(errors[0].to_string(), "The operator Add was called with a wrong combination of types: [Int, String] (at 1:1-1:11)");
+//! ```
+//!
+//! ### Dependency Analysis
+//!
+//! `Node::dependencies` finds out which variables an operator tree reads and writes, and which functions it calls, without evaluating it.
+//! Variables that are read before they are assigned to are free, meaning that they have to be provided by the context.
+//! This allows to order expressions that depend on each other and to detect cycles between them.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let tree = build_operator_tree("a = b + 1; c = a * 2; max(a, c)").unwrap(); // Do proper error handling here
+//! let dependencies = tree.dependencies();
+//! assert!(dependencies.read_variables().contains("a"));
+//! assert!(dependencies.written_variables().contains("c"));
+//! assert_eq!(dependencies.free_variables().iter().collect::<Vec<_>>(), vec!["b"]);
+//! assert!(dependencies.called_functions().contains("max"));
+//! ```
+//!
+//! ### Formula Sets
+//!
+//! A `FormulaSet` holds named formulas that reference each other and a set of inputs by their names, like the cells of a spreadsheet.
+//! It derives the dependencies of each formula, rejects formulas that would depend on themselves with `EvalexprError::DependencyCycle`,
+//! and evaluates the formulas in an order where each formula comes after the formulas it depends on.
+//! When an input changes, `FormulaSet::recompute` only evaluates the formulas that depend on it.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let mut formulas = FormulaSet::new();
+//! formulas.set_input("width".into(), 3.into()).unwrap(); // Do proper error handling here
+//! formulas.set_input("height".into(), 4.into()).unwrap(); // Do proper error handling here
+//! formulas.set_formula("area".into(), "width * height").unwrap(); // Do proper error handling here
+//! formulas.set_formula("double_area".into(), "2 * area").unwrap(); // Do proper error handling here
+//! formulas.set_formula("label".into(), "\"rectangle\"").unwrap(); // Do proper error handling here
+//! formulas.recompute().unwrap(); // Do proper error handling here
+//! assert_eq!(formulas.get_value("double_area").as_deref(), Some(&Value::from(24)));
+//!
+//! formulas.set_input("width".into(), 5.into()).unwrap(); // Do proper error handling here
+//! assert_eq!(formulas.recompute(), Ok(vec!["area".to_string(), "double_area".to_string()]));
+//! assert_eq!(formulas.get_value("double_area").as_deref(), Some(&Value::from(40)));
+//! ```
+//!
+//! ### Spans
+//!
+//! Each node of an operator tree remembers the part of the input string it was parsed from, as a `Span` of two `Position`s.
+//! A position consists of a byte offset as well as a line and a column, both counted from one.
+//! To find out where in the input string an error happened, use `build_operator_tree_spanned` and `Node::eval_spanned_with_context[_mut]`.
+//! These wrap their errors into `EvalexprError::Spanned`, from which the span can be retrieved with `EvalexprError::span`.
+//!
+//! ```rust
+//! use evalexpr::*;
+//!
+//! let expression = "x = 5;\ny = x / (x - 5)";
+//! let tree = build_operator_tree_spanned(expression).unwrap(); // Do proper error handling here
+//! let error = tree.eval_spanned_with_context_mut(&mut HashMapContext::new()).unwrap_err();
+//! let span = error.span().unwrap();
+//! assert_eq!(&expression[span.start.byte..span.end.byte], "x / (x - 5)");
+//! assert_eq!((span.start.line, span.start.column), (2, 5));
+//! ```
+//!
+//! ### Decimal Numbers
+//!
+//! With the `decimal_support` feature flag, values can be arbitrary-precision decimals of the type `Value::Decimal`,
+//! which represent decimal fractions like `0.1` exactly and are suited for calculations with money.
+//! Decimal literals are numbers followed by the suffix `d`, like `0.1d`, `25d` or `1e-3d`.
+//!
+//! Arithmetic and comparison operators accept decimals.
+//! Combining a decimal with a decimal or an integer results in a decimal, while combining it with a float results in a float.
+//! Decimals can be raised to integer powers, and other powers are computed with floats.
+//! Results that do not fit into a decimal fail with errors like `EvalexprError::MultiplicationError`, regardless of the `OverflowPolicy`.
+//!
+//! The builtin function `decimal::round` rounds to a given amount of decimal places with an explicit rounding mode,
+//! which is one of `half_even`, `half_up`, `half_down`, `up`, `down`, `floor` or `ceiling`.
+//! Numbers are converted with `decimal::from`, `decimal::to_int` and `decimal::to_float`,
+//! where conversions that would lose information fail with `EvalexprError::ConversionError`.
+//!
+//! ```rust
+//! # #[cfg(feature = "decimal_support")] {
+//! use evalexpr::*;
+//!
+//! assert_eq!(eval("0.1d + 0.2d == 0.3d"), Ok(Value::from(true)));
+//! assert_eq!(eval("str::from(1.10d * 3)"), Ok(Value::from("3.30")));
+//! assert_eq!(eval("str::from(decimal::round(2.345d, 2, \"half_even\"))"), Ok(Value::from("2.34")));
+//! assert_eq!(eval("decimal::to_int(12.00d)"), Ok(Value::from(12)));
+//! assert!(eval("decimal::to_int(12.5d)").is_err());
+//! # }
+//! ```
+//!
+//! ### Big Integers
+//!
+//! With the `bigint_support` feature flag, integers that do not fit into an `IntType` are represented as arbitrarily large integers of the type `Value::BigInt`.
+//! Integer literals that are too large for an `IntType` are parsed as big integers instead of floats.
+//!
+//! Arithmetic and comparison operators, as well as `min`, `max`, `div_euclid` and `rem_euclid`, accept big integers.
+//! Combining a big integer with an integer or a big integer results in an integer if the result fits into an `IntType`, and in a big integer otherwise,
+//! while combining it with a float or a decimal results in a float.
+//! Within `OverflowPolicy::PromoteToBigInt`, operations on integers whose result does not fit into an `IntType` return big integers instead of failing.
+//! Powers of big integers that would need more than a mebibyte of memory fail with `EvalexprError::ExponentiationError` before they are computed,
+//! and powers exceeding `EvalLimits::max_value_size` fail with `EvalexprError::LimitExceeded`.
+//!
+//! ```rust
+//! # #[cfg(feature = "bigint_support")] {
+//! use evalexpr::*;
+//!
+//! assert_eq!(eval("typeof(18446744073709551616)"), Ok(Value::from("bigint")));
+//! assert_eq!(eval("str::from(18446744073709551616 * 2)"), Ok(Value::from("36893488147419103232")));
+//! assert_eq!(eval("18446744073709551616 - 18446744073709551615"), Ok(Value::from(1)));
+//! assert!(eval("9223372036854775807 + 1").is_err());
+//! assert_eq!(
+//!     OverflowPolicy::PromoteToBigInt.enforce(|| eval("str::from(2 ^ 100)")),
+//!     Ok(Value::from("1267650600228229401496703205376"))
+//! );
+//! # }
+//! ```
+//!
 //! ### [Serde](https://serde.rs)
 //!
 //! To use this crate with serde, the `serde_support` feature flag has to be set.
@@ -512,6 +961,7 @@
 //!
 //! The crate also implements `Serialize` and `Deserialize` for the `HashMapContext`,
 //! but note that only the variables get (de)serialized, not the functions.
+//! Big integers are (de)serialized as strings of decimal digits.
 //!
 //! ## License
 //!
@@ -522,10 +972,16 @@
 #![deny(missing_docs)]
 #![forbid(unsafe_code)]
 
+#[cfg(feature = "bigint_support")]
+extern crate num_bigint;
+#[cfg(feature = "bigint_support")]
+extern crate num_traits;
 #[cfg(feature = "regex_support")]
 extern crate regex;
 #[cfg(test)]
 extern crate ron;
+#[cfg(feature = "decimal_support")]
+extern crate rust_decimal;
 #[cfg(feature = "serde_support")]
 extern crate serde;
 #[cfg(feature = "serde_support")]
@@ -533,28 +989,56 @@ extern crate serde;
 extern crate serde_derive;
 
 pub use crate::{
+    compiled::CompiledExpression,
     context::{
-        Context, ContextWithMutableFunctions, ContextWithMutableVariables, EmptyContext,
-        HashMapContext,
+        ConcurrentContext, Context, ContextSnapshot, ContextWithMutableFunctions,
+        ContextWithMutableVariables, EmptyContext, GetFunctionContext, HashMapContext,
+        IterateVariablesContext, LazyContext, RecordingContext, ScopedContext, VariableChange,
     },
     error::{EvalexprError, EvalexprResult},
-    function::Function,
+    formula::FormulaSet,
+    function::{
+        builtin::{builtin_function_identifiers, builtin_function_metadata},
+        FromValue, Function, FunctionMetadata, IntoFunction, IntoValue,
+    },
     interface::*,
+    limits::{CancellationToken, EvalLimits, Limit},
     operator::Operator,
+    overflow::OverflowPolicy,
+    span::{Position, Span},
     token::PartialToken,
-    tree::Node,
-    value::{value_type::ValueType, EmptyType, FloatType, IntType, TupleType, Value, EMPTY_VALUE},
+    tree::{Dependencies, Node},
+    typecheck::{FunctionSignature, TypeSchema, TypeSet},
+    value::{
+        value_type::ValueType, EmptyType, FloatType, IntType, MapType, TupleType, Value,
+        EMPTY_VALUE,
+    },
 };
 
+#[cfg(feature = "bigint_support")]
+pub use crate::value::BigIntType;
+#[cfg(feature = "decimal_support")]
+pub use crate::value::DecimalType;
+
+#[cfg(feature = "bigint_support")]
+mod bigint;
+mod compiled;
 mod context;
+#[cfg(feature = "decimal_support")]
+mod decimal;
 pub mod error;
 #[cfg(feature = "serde_support")]
 mod feature_serde;
+mod formula;
 mod function;
 mod interface;
+mod limits;
 mod operator;
+mod overflow;
+mod span;
 mod token;
 mod tree;
+mod typecheck;
 mod value;
 
 // Exports
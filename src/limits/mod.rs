@@ -0,0 +1,402 @@
+//! The `limits` module contains the configuration of resource limits for parsing and evaluating expressions,
+//! as well as means to interrupt an evaluation with a deadline or a `CancellationToken`.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::{EvalexprError, EvalexprResult},
+    value::Value,
+};
+
+/// The maximum nesting depth of calls to functions defined in expressions, unless configured with `EvalLimits::max_recursion`.
+pub(crate) const DEFAULT_RECURSION_LIMIT: usize = 100;
+/// The maximum nesting depth of expressions while parsing, unless configured with `EvalLimits::max_nesting`.
+/// Operator trees are parsed, evaluated and dropped recursively, so this protects the stack even in debug builds.
+pub(crate) const DEFAULT_MAX_NESTING: usize = 256;
+
+thread_local! {
+    /// The limits that are currently enforced on this thread, together with the resources used so far.
+    static STATE: RefCell<Option<LimitState>> = RefCell::new(None);
+    /// True if `STATE` contains limits, which allows to skip all checks quickly if no limits are enforced.
+    static ACTIVE: Cell<bool> = Cell::new(false);
+    /// The nesting depth of the calls to functions defined in expressions that are currently evaluated on this thread.
+    static CALL_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+/// Resource limits for parsing and evaluating expressions from untrusted sources.
+///
+/// Limits are enforced for everything that is parsed or evaluated within `EvalLimits::enforce` on the same thread,
+/// including operator trees, compiled expressions and functions defined in expressions.
+/// If a limit is exceeded, `EvalexprError::LimitExceeded` is returned.
+/// By default, only the nesting of expressions while parsing and the recursion of functions defined in expressions are limited, to protect the stack.
+///
+/// The limits can also contain a deadline and a `CancellationToken`, which are checked before each operation.
+/// Functions implemented in Rust are not interrupted, but the evaluation stops with `EvalexprError::DeadlineExceeded` or `EvalexprError::Cancelled` after they return.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let limits = EvalLimits::new().max_operations(100).max_nesting(10);
+/// assert_eq!(limits.enforce(|| eval("1 + 2 * 3")), Ok(Value::from(7)));
+/// assert_eq!(
+///     limits.enforce(|| eval("((((((((((1))))))))))")),
+///     Err(EvalexprError::LimitExceeded { limit: Limit::Nesting, maximum: 10 })
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EvalLimits {
+    max_operations: Option<usize>,
+    max_depth: Option<usize>,
+    max_value_size: Option<usize>,
+    max_nesting: Option<usize>,
+    max_recursion: Option<usize>,
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+/// A kind of resource limited by `EvalLimits`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Limit {
+    /// The amount of evaluated operator tree nodes and executed instructions of compiled expressions.
+    Operations,
+    /// The nesting depth of evaluated operator tree nodes, including the nodes of called functions.
+    Depth,
+    /// The size of a single value.
+    ValueSize,
+    /// The nesting depth of an expression while it is parsed.
+    Nesting,
+}
+
+impl EvalLimits {
+    /// Creates limits that only restrict the nesting while parsing and the recursion of functions defined in expressions to their defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the amount of operator tree nodes that are evaluated, and of instructions that are executed by compiled expressions.
+    pub fn max_operations(mut self, max_operations: usize) -> Self {
+        self.max_operations = Some(max_operations);
+        self
+    }
+
+    /// Limits the nesting depth of operator tree nodes that are evaluated.
+    /// The nodes of functions defined in expressions count towards the depth of the node calling them.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Limits the size of each value that is computed.
+    ///
+    /// The size of a string is its length in bytes.
+    /// The size of a tuple or map is the amount of its elements plus the sizes of its elements, where the keys of maps count as strings.
+    /// The size of a big integer is the amount of bytes of its magnitude, and powers of big integers are rejected before they are computed if they would be too large.
+    /// Other values have a size of zero.
+    pub fn max_value_size(mut self, max_value_size: usize) -> Self {
+        self.max_value_size = Some(max_value_size);
+        self
+    }
+
+    /// Limits how deeply parentheses, brackets, braces and operators that are still missing operands can be nested while parsing.
+    /// The whole expression counts as one level, and for example each pair of parentheses adds another one.
+    /// Without this limit, expressions can be nested 256 levels deep, also outside of `EvalLimits::enforce`.
+    pub fn max_nesting(mut self, max_nesting: usize) -> Self {
+        self.max_nesting = Some(max_nesting);
+        self
+    }
+
+    /// Limits how deeply calls to functions defined in expressions can be nested, otherwise `EvalexprError::RecursionLimitExceeded` is returned.
+    /// Without this limit, calls can be nested 100 levels deep.
+    pub fn max_recursion(mut self, max_recursion: usize) -> Self {
+        self.max_recursion = Some(max_recursion);
+        self
+    }
+
+    /// Stops the evaluation if it is still running at the given point in time.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Stops the evaluation if it is still running after the given duration, measured from the start of each call to `EvalLimits::enforce`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Stops the evaluation once the given token is cancelled, for example from another thread.
+    pub fn cancellation_token(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    /// Calls `f`, enforcing these limits for everything that is parsed or evaluated during the call on the current thread.
+    ///
+    /// If limits are enforced already, these limits replace them until `f` returns, and resources used within `f` are not counted towards the outer limits.
+    pub fn enforce<T, F: FnOnce() -> EvalexprResult<T>>(&self, f: F) -> EvalexprResult<T> {
+        let _scope = LimitScope::enter(self.clone());
+        f()
+    }
+}
+
+/// A token to cancel evaluations from another thread.
+///
+/// Clones of a token share their state, so cancelling one clone cancels all evaluations that enforce limits with any of them.
+/// Functions implemented in Rust can check a clone of the token to stop long computations early.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let token = CancellationToken::new();
+/// let limits = EvalLimits::new().cancellation_token(token.clone());
+/// assert_eq!(limits.enforce(|| eval("1 + 2")), Ok(Value::from(3)));
+///
+/// token.cancel();
+/// assert_eq!(limits.enforce(|| eval("1 + 2")), Err(EvalexprError::Cancelled));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels all evaluations that enforce limits with this token or one of its clones.
+    /// Cancelling cannot be undone.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if this token or one of its clones was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Limit::Operations => write!(f, "operation"),
+            Limit::Depth => write!(f, "evaluation depth"),
+            Limit::ValueSize => write!(f, "value size"),
+            Limit::Nesting => write!(f, "nesting"),
+        }
+    }
+}
+
+/// The limits enforced on the current thread, and the resources used so far.
+struct LimitState {
+    limits: EvalLimits,
+    /// The earlier one of the deadline and the end of the timeout.
+    deadline: Option<Instant>,
+    operations: usize,
+    depth: usize,
+}
+
+/// A guard that enforces limits on the current thread as long as it is alive, and restores the previously enforced limits afterwards.
+struct LimitScope {
+    previous: Option<LimitState>,
+}
+
+impl LimitScope {
+    fn enter(limits: EvalLimits) -> Self {
+        let timeout_end = limits.timeout.map(|timeout| Instant::now() + timeout);
+        let deadline = match (limits.deadline, timeout_end) {
+            (Some(deadline), Some(timeout_end)) => Some(deadline.min(timeout_end)),
+            (deadline, timeout_end) => deadline.or(timeout_end),
+        };
+        let state = LimitState {
+            limits,
+            deadline,
+            operations: 0,
+            depth: 0,
+        };
+        ACTIVE.with(|active| active.set(true));
+        Self {
+            previous: STATE.with(|current| current.replace(Some(state))),
+        }
+    }
+}
+
+impl Drop for LimitScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|active| active.set(previous.is_some()));
+        STATE.with(|current| current.replace(previous));
+    }
+}
+
+/// A guard that counts the evaluation of an operator tree node towards the evaluation depth as long as it is alive.
+pub(crate) struct DepthGuard {
+    counted: bool,
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        if self.counted {
+            leave_limited_node();
+        }
+    }
+}
+
+#[inline(never)]
+fn leave_limited_node() {
+    with_state(|state| state.depth = state.depth.saturating_sub(1));
+}
+
+/// Calls `f` with the state of the limits enforced on the current thread, if there are any.
+fn with_state<T, F: FnOnce(&mut LimitState) -> T>(f: F) -> Option<T> {
+    if !ACTIVE.with(Cell::get) {
+        return None;
+    }
+    STATE.with(|state| state.borrow_mut().as_mut().map(f))
+}
+
+/// Returns `Err` if `value` exceeds `maximum`.
+fn check(limit: Limit, maximum: Option<usize>, value: usize) -> EvalexprResult<()> {
+    match maximum {
+        Some(maximum) if value > maximum => Err(EvalexprError::LimitExceeded { limit, maximum }),
+        _ => Ok(()),
+    }
+}
+
+/// Counts an operation, or returns `Err` if this exceeds the operation limit, or if the evaluation was cancelled or exceeded its deadline.
+pub(crate) fn count_operation() -> EvalexprResult<()> {
+    with_state(|state| {
+        if let Some(cancellation_token) = &state.limits.cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(EvalexprError::Cancelled);
+            }
+        }
+        if let Some(deadline) = state.deadline {
+            if Instant::now() >= deadline {
+                return Err(EvalexprError::DeadlineExceeded);
+            }
+        }
+
+        state.operations += 1;
+        check(
+            Limit::Operations,
+            state.limits.max_operations,
+            state.operations,
+        )
+    })
+    .unwrap_or(Ok(()))
+}
+
+/// Counts the evaluation of an operator tree node as an operation and towards the evaluation depth,
+/// or returns `Err` if this exceeds one of the limits.
+///
+/// This is called for each node, so the case without limits is kept small enough to be inlined into the recursive evaluation,
+/// while the checks are kept out of its stack frame.
+#[inline]
+pub(crate) fn enter_node() -> EvalexprResult<DepthGuard> {
+    if ACTIVE.with(Cell::get) {
+        enter_limited_node()
+    } else {
+        Ok(DepthGuard { counted: false })
+    }
+}
+
+#[inline(never)]
+fn enter_limited_node() -> EvalexprResult<DepthGuard> {
+    count_operation()?;
+    with_state(|state| {
+        check(Limit::Depth, state.limits.max_depth, state.depth + 1)?;
+        state.depth += 1;
+        Ok(DepthGuard { counted: true })
+    })
+    .unwrap_or(Ok(DepthGuard { counted: false }))
+}
+
+/// A guard that counts a call to a function defined in an expression as long as it is alive.
+pub(crate) struct CallGuard;
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        CALL_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Counts a call to a function defined in an expression, or returns `Err` if this exceeds the recursion limit.
+/// The recursion limit also applies if no limits are enforced.
+pub(crate) fn enter_call() -> EvalexprResult<CallGuard> {
+    let limit = with_state(|state| state.limits.max_recursion)
+        .flatten()
+        .unwrap_or(DEFAULT_RECURSION_LIMIT);
+    CALL_DEPTH.with(|depth| {
+        if depth.get() >= limit {
+            Err(EvalexprError::RecursionLimitExceeded { limit })
+        } else {
+            depth.set(depth.get() + 1);
+            Ok(CallGuard)
+        }
+    })
+}
+
+/// Returns `Err` if the size of the given value exceeds the value size limit.
+#[inline]
+pub(crate) fn check_value_size(value: &Value) -> EvalexprResult<()> {
+    if ACTIVE.with(Cell::get) {
+        check_limited_value_size(value)
+    } else {
+        Ok(())
+    }
+}
+
+#[inline(never)]
+fn check_limited_value_size(value: &Value) -> EvalexprResult<()> {
+    check_size(value_size(value))
+}
+
+/// Returns `Err` if a value of the given size would exceed the value size limit, which allows to reject results before computing them.
+pub(crate) fn check_size(size: usize) -> EvalexprResult<()> {
+    with_state(|state| check(Limit::ValueSize, state.limits.max_value_size, size)).unwrap_or(Ok(()))
+}
+
+/// Returns the nesting limit for parsing, which is the default nesting limit if no limits are enforced.
+pub(crate) fn max_nesting() -> usize {
+    with_state(|state| state.limits.max_nesting)
+        .flatten()
+        .unwrap_or(DEFAULT_MAX_NESTING)
+}
+
+/// Returns `Err` if the given nesting depth exceeds the nesting limit returned by `max_nesting`.
+pub(crate) fn check_nesting(nesting: usize, maximum: usize) -> EvalexprResult<()> {
+    check(Limit::Nesting, Some(maximum), nesting)
+}
+
+/// Returns the size of a value as described by `EvalLimits::max_value_size`.
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::String(string) => string.len(),
+        Value::Tuple(tuple) => tuple.len() + tuple.iter().map(value_size).sum::<usize>(),
+        Value::Map(map) => {
+            map.len()
+                + map
+                    .iter()
+                    .map(|(key, value)| key.len() + value_size(value))
+                    .sum::<usize>()
+        },
+        #[cfg(feature = "bigint_support")]
+        Value::BigInt(bigint) => crate::bigint::size(bigint),
+        _ => 0,
+    }
+}
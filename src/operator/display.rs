@@ -14,6 +14,7 @@ impl Display for Operator {
             Neg => write!(f, "-"),
             Mul => write!(f, "*"),
             Div => write!(f, "/"),
+            FloorDiv => write!(f, "//"),
             Mod => write!(f, "%"),
             Exp => write!(f, "^"),
 
@@ -32,6 +33,7 @@ impl Display for Operator {
             SubAssign => write!(f, " -= "),
             MulAssign => write!(f, " *= "),
             DivAssign => write!(f, " /= "),
+            FloorDivAssign => write!(f, " //= "),
             ModAssign => write!(f, " %= "),
             ExpAssign => write!(f, " ^= "),
             AndAssign => write!(f, " &&= "),
@@ -40,6 +42,13 @@ impl Display for Operator {
             Tuple => write!(f, ", "),
             Chain => write!(f, "; "),
 
+            Map => write!(f, "{{}}"),
+            KeyValue => write!(f, ": "),
+            Index => write!(f, "[]"),
+            Slice => write!(f, "[..]"),
+            Range => write!(f, ".."),
+            FunctionDefinition { parameters } => write!(f, "fn({})", parameters.join(", ")),
+
             Const { value } => write!(f, "{}", value),
             VariableIdentifier { identifier } => write!(f, "{}", identifier),
             FunctionIdentifier { identifier } => write!(f, "{}", identifier),
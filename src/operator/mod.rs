@@ -1,7 +1,17 @@
+#[cfg(feature = "decimal_support")]
+use crate::decimal;
 use crate::function::builtin::builtin_function;
-
-use crate::{context::Context, error::*, value::Value, ContextWithMutableVariables};
-use std::borrow::Borrow;
+#[cfg(feature = "bigint_support")]
+use crate::{bigint, limits};
+
+use crate::{
+    context::{Context, EmptyContext},
+    error::*,
+    overflow,
+    value::{value_type::ValueType, IntType, MapType, Value},
+    ContextWithMutableVariables,
+};
+use std::{borrow::Borrow, ops::Range};
 
 mod display;
 
@@ -22,6 +32,8 @@ pub enum Operator {
     Mul,
     /// A binary division operator.
     Div,
+    /// A binary floor division operator, that rounds the quotient towards negative infinity.
+    FloorDiv,
     /// A binary modulo operator.
     Mod,
     /// A binary exponentiation operator.
@@ -56,6 +68,8 @@ pub enum Operator {
     MulAssign,
     /// A binary divide-assign operator.
     DivAssign,
+    /// A binary floor-divide-assign operator.
+    FloorDivAssign,
     /// A binary modulo-assign operator.
     ModAssign,
     /// A binary exponentiate-assign operator.
@@ -70,6 +84,26 @@ pub enum Operator {
     /// An n-ary subexpression chain.
     Chain,
 
+    /// A map constructor.
+    /// Its argument is a tuple of key-value pairs, each of which is a tuple of a string key and a value.
+    Map,
+    /// A binary key-value pair within a map literal.
+    KeyValue,
+    /// A binary indexing operator, that accesses the element of its first argument at the key or index given by its second argument.
+    Index,
+    /// A binary slicing operator, that takes the elements of its first argument within the range given by its second argument.
+    Slice,
+    /// A binary range within a slicing operator.
+    /// Omitted bounds are represented by empty values.
+    Range,
+
+    /// A function definition `fn(parameters) body`, whose only argument is the body of the function.
+    /// It can only be evaluated as the right side of an assignment, which stores the function in the context.
+    FunctionDefinition {
+        /// The identifiers of the parameters of the function.
+        parameters: Vec<String>,
+    },
+
     /// A constant value.
     Const {
         /** The value of the constant. */
@@ -100,6 +134,10 @@ impl Operator {
         Operator::FunctionIdentifier { identifier }
     }
 
+    pub(crate) fn function_definition(parameters: Vec<String>) -> Self {
+        Operator::FunctionDefinition { parameters }
+    }
+
     /// Returns the precedence of the operator.
     /// A high precedence means that the operator has priority to be deeper in the tree.
     pub(crate) const fn precedence(&self) -> i32 {
@@ -109,7 +147,7 @@ impl Operator {
 
             Add | Sub => 95,
             Neg => 110,
-            Mul | Div | Mod => 100,
+            Mul | Div | FloorDiv | Mod => 100,
             Exp => 120,
 
             Eq | Neq | Gt | Lt | Geq | Leq => 80,
@@ -117,12 +155,18 @@ impl Operator {
             Or => 70,
             Not => 110,
 
-            Assign | AddAssign | SubAssign | MulAssign | DivAssign | ModAssign | ExpAssign
-            | AndAssign | OrAssign => 50,
+            Assign | AddAssign | SubAssign | MulAssign | DivAssign | FloorDivAssign | ModAssign
+            | ExpAssign | AndAssign | OrAssign => 50,
 
             Tuple => 40,
             Chain => 0,
 
+            Map => 190,
+            KeyValue => 45,
+            Index | Slice => 150,
+            Range => 60,
+            FunctionDefinition { parameters: _ } => 50,
+
             Const { value: _ } => 200,
             VariableIdentifier { identifier: _ } => 200,
             FunctionIdentifier { identifier: _ } => 190,
@@ -134,7 +178,13 @@ impl Operator {
     /// Left-to-right chaining has priority if operators with different order but same precedence are chained.
     pub(crate) const fn is_left_to_right(&self) -> bool {
         use crate::operator::Operator::*;
-        !matches!(self, Assign | FunctionIdentifier { identifier: _ })
+        !matches!(
+            self,
+            Assign
+                | Map
+                | FunctionIdentifier { identifier: _ }
+                | FunctionDefinition { parameters: _ }
+        )
     }
 
     /// Returns true if chains of this operator should be flattened into one operator with many arguments.
@@ -153,11 +203,13 @@ impl Operator {
     pub(crate) const fn max_argument_amount(&self) -> Option<usize> {
         use crate::operator::Operator::*;
         match self {
-            Add | Sub | Mul | Div | Mod | Exp | Eq | Neq | Gt | Lt | Geq | Leq | And | Or
-            | Assign | AddAssign | SubAssign | MulAssign | DivAssign | ModAssign | ExpAssign
-            | AndAssign | OrAssign => Some(2),
+            Add | Sub | Mul | Div | FloorDiv | Mod | Exp | Eq | Neq | Gt | Lt | Geq | Leq | And
+            | Or | Assign | AddAssign | SubAssign | MulAssign | DivAssign | FloorDivAssign
+            | ModAssign | ExpAssign | AndAssign | OrAssign | KeyValue | Index | Slice | Range => {
+                Some(2)
+            },
             Tuple | Chain => None,
-            Not | Neg | RootNode => Some(1),
+            Not | Neg | RootNode | Map | FunctionDefinition { parameters: _ } => Some(1),
             Const { value: _ } => Some(0),
             VariableIdentifier { identifier: _ } => Some(0),
             FunctionIdentifier { identifier: _ } => Some(1),
@@ -170,6 +222,18 @@ impl Operator {
         arguments: &[Value],
         context: &C,
     ) -> EvalexprResult<Value> {
+        match self {
+            // Function calls are evaluated outside of `eval_operator`, as its large stack frame would otherwise be part of each level of recursive calls
+            Operator::FunctionIdentifier { identifier } => {
+                expect_operator_argument_amount(arguments.len(), 1)?;
+                call_function(context, identifier, &arguments[0])
+            },
+            _ => self.eval_operator(arguments, context),
+        }
+    }
+
+    /// Evaluates the operator, which is not a function call, with the given arguments and context.
+    fn eval_operator<C: Context>(&self, arguments: &[Value], context: &C) -> EvalexprResult<Value> {
         use crate::operator::Operator::*;
         match self {
             RootNode => {
@@ -184,15 +248,26 @@ impl Operator {
                 expect_number_or_string(&arguments[0])?;
                 expect_number_or_string(&arguments[1])?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return a.checked_add(b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::addition_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::arithmetic_operands(&arguments[0], &arguments[1]) {
+                    return Ok(bigint::normalize(a + b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_string(), arguments[1].as_string()) {
                     let mut result = String::with_capacity(a.len() + b.len());
                     result.push_str(&a);
                     result.push_str(&b);
                     Ok(Value::String(result))
                 } else if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
-                    let result = a.checked_add(b);
-                    if let Some(result) = result {
-                        Ok(Value::Int(result))
+                    if let Some(result) = overflow::add(a, b) {
+                        Ok(result)
                     } else {
                         Err(EvalexprError::addition_error(
                             arguments[0].clone(),
@@ -214,10 +289,21 @@ impl Operator {
                 arguments[0].as_number()?;
                 arguments[1].as_number()?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return a.checked_sub(b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::subtraction_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::arithmetic_operands(&arguments[0], &arguments[1]) {
+                    return Ok(bigint::normalize(a - b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
-                    let result = a.checked_sub(b);
-                    if let Some(result) = result {
-                        Ok(Value::Int(result))
+                    if let Some(result) = overflow::sub(a, b) {
+                        Ok(result)
                     } else {
                         Err(EvalexprError::subtraction_error(
                             arguments[0].clone(),
@@ -234,10 +320,19 @@ impl Operator {
                 expect_operator_argument_amount(arguments.len(), 1)?;
                 arguments[0].as_number()?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Value::Decimal(a) = arguments[0] {
+                    return Ok(Value::Decimal(-a));
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some(a) = bigint::arithmetic_operand(&arguments[0]) {
+                    return Ok(bigint::normalize(-a));
+                }
+
                 if let Ok(a) = arguments[0].as_int() {
-                    let result = a.checked_neg();
-                    if let Some(result) = result {
-                        Ok(Value::Int(result))
+                    if let Some(result) = overflow::neg(a) {
+                        Ok(result)
                     } else {
                         Err(EvalexprError::negation_error(arguments[0].clone()))
                     }
@@ -250,10 +345,24 @@ impl Operator {
                 arguments[0].as_number()?;
                 arguments[1].as_number()?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return a.checked_mul(b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::multiplication_error(
+                            arguments[0].clone(),
+                            arguments[1].clone(),
+                        )
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::arithmetic_operands(&arguments[0], &arguments[1]) {
+                    return Ok(bigint::normalize(a * b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
-                    let result = a.checked_mul(b);
-                    if let Some(result) = result {
-                        Ok(Value::Int(result))
+                    if let Some(result) = overflow::mul(a, b) {
+                        Ok(result)
                     } else {
                         Err(EvalexprError::multiplication_error(
                             arguments[0].clone(),
@@ -271,10 +380,23 @@ impl Operator {
                 arguments[0].as_number()?;
                 arguments[1].as_number()?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return a.checked_div(b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::division_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::arithmetic_operands(&arguments[0], &arguments[1]) {
+                    return bigint::div(a, b).ok_or_else(|| {
+                        EvalexprError::division_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
-                    let result = a.checked_div(b);
-                    if let Some(result) = result {
-                        Ok(Value::Int(result))
+                    if let Some(result) = overflow::div(a, b) {
+                        Ok(result)
                     } else {
                         Err(EvalexprError::division_error(
                             arguments[0].clone(),
@@ -287,15 +409,62 @@ impl Operator {
                     ))
                 }
             },
+            FloorDiv => {
+                expect_operator_argument_amount(arguments.len(), 2)?;
+                arguments[0].as_number()?;
+                arguments[1].as_number()?;
+
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return decimal::floor_div(a, b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::division_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::arithmetic_operands(&arguments[0], &arguments[1]) {
+                    return bigint::floor_div(a, b).ok_or_else(|| {
+                        EvalexprError::division_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
+                if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
+                    if let Some(result) = overflow::floor_div(a, b) {
+                        Ok(result)
+                    } else {
+                        Err(EvalexprError::division_error(
+                            arguments[0].clone(),
+                            arguments[1].clone(),
+                        ))
+                    }
+                } else {
+                    Ok(Value::Float(
+                        (arguments[0].as_number()? / arguments[1].as_number()?).floor(),
+                    ))
+                }
+            },
             Mod => {
                 expect_operator_argument_amount(arguments.len(), 2)?;
                 arguments[0].as_number()?;
                 arguments[1].as_number()?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return a.checked_rem(b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::modulation_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::arithmetic_operands(&arguments[0], &arguments[1]) {
+                    return bigint::rem(a, b).ok_or_else(|| {
+                        EvalexprError::modulation_error(arguments[0].clone(), arguments[1].clone())
+                    });
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
-                    let result = a.checked_rem(b);
-                    if let Some(result) = result {
-                        Ok(Value::Int(result))
+                    if let Some(result) = overflow::rem(a, b) {
+                        Ok(result)
                     } else {
                         Err(EvalexprError::modulation_error(
                             arguments[0].clone(),
@@ -313,9 +482,42 @@ impl Operator {
                 arguments[0].as_number()?;
                 arguments[1].as_number()?;
 
-                Ok(Value::Float(
-                    arguments[0].as_number()?.powf(arguments[1].as_number()?),
-                ))
+                #[cfg(feature = "decimal_support")]
+                if let (Value::Decimal(a), Value::Int(b)) = (&arguments[0], &arguments[1]) {
+                    return decimal::pow(*a, *b).map(Value::Decimal).ok_or_else(|| {
+                        EvalexprError::exponentiation_error(
+                            arguments[0].clone(),
+                            arguments[1].clone(),
+                        )
+                    });
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let (Some(a), Value::Int(b)) =
+                    (bigint::arithmetic_operand(&arguments[0]), &arguments[1])
+                {
+                    if *b >= 0 {
+                        limits::check_size(bigint::pow_size(&a, *b))?;
+                        return bigint::pow(a, *b).ok_or_else(|| {
+                            EvalexprError::exponentiation_error(
+                                arguments[0].clone(),
+                                arguments[1].clone(),
+                            )
+                        });
+                    }
+                }
+
+                match (arguments[0].as_int(), arguments[1].as_int()) {
+                    (Ok(a), Ok(b)) if b >= 0 => overflow::pow(a, b).ok_or_else(|| {
+                        EvalexprError::exponentiation_error(
+                            arguments[0].clone(),
+                            arguments[1].clone(),
+                        )
+                    }),
+                    _ => Ok(Value::Float(
+                        arguments[0].as_number()?.powf(arguments[1].as_number()?),
+                    )),
+                }
             },
             Eq => {
                 expect_operator_argument_amount(arguments.len(), 2)?;
@@ -332,6 +534,16 @@ impl Operator {
                 expect_number_or_string(&arguments[0])?;
                 expect_number_or_string(&arguments[1])?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a > b));
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a > b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_string(), arguments[1].as_string()) {
                     Ok(Value::Boolean(a > b))
                 } else if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
@@ -347,6 +559,16 @@ impl Operator {
                 expect_number_or_string(&arguments[0])?;
                 expect_number_or_string(&arguments[1])?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a < b));
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a < b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_string(), arguments[1].as_string()) {
                     Ok(Value::Boolean(a < b))
                 } else if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
@@ -362,6 +584,16 @@ impl Operator {
                 expect_number_or_string(&arguments[0])?;
                 expect_number_or_string(&arguments[1])?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a >= b));
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a >= b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_string(), arguments[1].as_string()) {
                     Ok(Value::Boolean(a >= b))
                 } else if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
@@ -377,6 +609,16 @@ impl Operator {
                 expect_number_or_string(&arguments[0])?;
                 expect_number_or_string(&arguments[1])?;
 
+                #[cfg(feature = "decimal_support")]
+                if let Some((a, b)) = decimal::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a <= b));
+                }
+
+                #[cfg(feature = "bigint_support")]
+                if let Some((a, b)) = bigint::operands(&arguments[0], &arguments[1]) {
+                    return Ok(Value::Boolean(a <= b));
+                }
+
                 if let (Ok(a), Ok(b)) = (arguments[0].as_string(), arguments[1].as_string()) {
                     Ok(Value::Boolean(a <= b))
                 } else if let (Ok(a), Ok(b)) = (arguments[0].as_int(), arguments[1].as_int()) {
@@ -407,8 +649,8 @@ impl Operator {
 
                 Ok(Value::Boolean(!a))
             },
-            Assign | AddAssign | SubAssign | MulAssign | DivAssign | ModAssign | ExpAssign
-            | AndAssign | OrAssign => Err(EvalexprError::ContextNotMutable),
+            Assign | AddAssign | SubAssign | MulAssign | DivAssign | FloorDivAssign | ModAssign
+            | ExpAssign | AndAssign | OrAssign => Err(EvalexprError::ContextNotMutable),
             Tuple => Ok(Value::Tuple(arguments.into())),
             Chain => {
                 if arguments.is_empty() {
@@ -417,6 +659,87 @@ impl Operator {
 
                 Ok(arguments.last().cloned().unwrap_or(Value::Empty))
             },
+            Map => {
+                expect_operator_argument_amount(arguments.len(), 1)?;
+
+                let mut map = MapType::new();
+                for entry in arguments[0].as_tuple()? {
+                    let mut entry = entry.as_fixed_len_tuple(2)?;
+                    // Cannot fail, as the entry has length two
+                    let value = entry.pop().unwrap();
+                    let key = entry.pop().unwrap().as_string()?;
+                    map.insert(key, value);
+                }
+                Ok(Value::Map(map))
+            },
+            KeyValue => {
+                expect_operator_argument_amount(arguments.len(), 2)?;
+                arguments[0].as_string()?;
+
+                Ok(Value::Tuple(arguments.into()))
+            },
+            Index => {
+                expect_operator_argument_amount(arguments.len(), 2)?;
+
+                match &arguments[0] {
+                    Value::Map(_) => {
+                        let key = arguments[1].as_string()?;
+                        Ok(map_field(&arguments[0], &key)?.clone())
+                    },
+                    Value::Tuple(tuple) => {
+                        let index = sequence_index(arguments[1].as_int()?, tuple.len())?;
+                        Ok(tuple[index].clone())
+                    },
+                    Value::String(string) => {
+                        let index = sequence_index(arguments[1].as_int()?, string.chars().count())?;
+                        // Cannot fail, as the index is within the character count
+                        Ok(Value::from(string.chars().nth(index).unwrap().to_string()))
+                    },
+                    value => Err(EvalexprError::type_error(
+                        value.clone(),
+                        vec![ValueType::String, ValueType::Tuple, ValueType::Map],
+                    )),
+                }
+            },
+            Slice => {
+                expect_operator_argument_amount(arguments.len(), 2)?;
+                let bounds = arguments[1].as_fixed_len_tuple(2)?;
+                let start = optional_int(&bounds[0])?;
+                let end = optional_int(&bounds[1])?;
+
+                match &arguments[0] {
+                    Value::Tuple(tuple) => {
+                        let range = sequence_range(start, end, tuple.len())?;
+                        Ok(Value::Tuple(tuple[range].to_vec()))
+                    },
+                    Value::String(string) => {
+                        let range = sequence_range(start, end, string.chars().count())?;
+                        Ok(Value::String(
+                            string
+                                .chars()
+                                .skip(range.start)
+                                .take(range.end - range.start)
+                                .collect(),
+                        ))
+                    },
+                    value => Err(EvalexprError::type_error(
+                        value.clone(),
+                        vec![ValueType::String, ValueType::Tuple],
+                    )),
+                }
+            },
+            Range => {
+                expect_operator_argument_amount(arguments.len(), 2)?;
+                optional_int(&arguments[0])?;
+                optional_int(&arguments[1])?;
+
+                Ok(Value::Tuple(arguments.into()))
+            },
+            // A function value can only be created from the unevaluated body, which is done by `Node`
+            FunctionDefinition { parameters: _ } => {
+                expect_operator_argument_amount(arguments.len(), 1)?;
+                Err(EvalexprError::ExpectedFunctionDefinition)
+            },
             Const { value } => {
                 expect_operator_argument_amount(arguments.len(), 0)?;
 
@@ -425,30 +748,10 @@ impl Operator {
             VariableIdentifier { identifier } => {
                 expect_operator_argument_amount(arguments.len(), 0)?;
 
-                if let Some(value) = context.get_value(identifier).cloned() {
-                    Ok(value)
-                } else {
-                    Err(EvalexprError::VariableIdentifierNotFound(
-                        identifier.clone(),
-                    ))
-                }
+                variable_value(context, identifier)
             },
-            FunctionIdentifier { identifier } => {
-                expect_operator_argument_amount(arguments.len(), 1)?;
-                let arguments = &arguments[0];
-
-                match context.call_function(identifier, arguments) {
-                    Err(EvalexprError::FunctionIdentifierNotFound(_)) => {
-                        if let Some(builtin_function) = builtin_function(identifier) {
-                            builtin_function.call(arguments)
-                        } else {
-                            Err(EvalexprError::FunctionIdentifierNotFound(
-                                identifier.clone(),
-                            ))
-                        }
-                    },
-                    result => result,
-                }
+            FunctionIdentifier { identifier: _ } => {
+                unreachable!("Function calls are evaluated by Operator::eval")
             },
         }
     }
@@ -468,32 +771,30 @@ impl Operator {
 
                 Ok(Value::Empty)
             },
-            AddAssign | SubAssign | MulAssign | DivAssign | ModAssign | ExpAssign | AndAssign
-            | OrAssign => {
+            AddAssign | SubAssign | MulAssign | DivAssign | FloorDivAssign | ModAssign
+            | ExpAssign | AndAssign | OrAssign => {
                 expect_operator_argument_amount(arguments.len(), 2)?;
 
                 let target = arguments[0].as_string()?;
-                let left_value = Operator::VariableIdentifier {
-                    identifier: target.clone(),
-                }
-                .eval(&Vec::new(), context)?;
-                let arguments = vec![left_value, arguments[1].clone()];
-
-                let result = match self {
-                    AddAssign => Operator::Add.eval(&arguments, context),
-                    SubAssign => Operator::Sub.eval(&arguments, context),
-                    MulAssign => Operator::Mul.eval(&arguments, context),
-                    DivAssign => Operator::Div.eval(&arguments, context),
-                    ModAssign => Operator::Mod.eval(&arguments, context),
-                    ExpAssign => Operator::Exp.eval(&arguments, context),
-                    AndAssign => Operator::And.eval(&arguments, context),
-                    OrAssign => Operator::Or.eval(&arguments, context),
+                let operator = match self {
+                    AddAssign => Operator::Add,
+                    SubAssign => Operator::Sub,
+                    MulAssign => Operator::Mul,
+                    DivAssign => Operator::Div,
+                    FloorDivAssign => Operator::FloorDiv,
+                    ModAssign => Operator::Mod,
+                    ExpAssign => Operator::Exp,
+                    AndAssign => Operator::And,
+                    OrAssign => Operator::Or,
                     _ => unreachable!(
                         "Forgot to add a match arm for an assign operation: {}",
                         self
                     ),
-                }?;
-                context.set_value(target, result)?;
+                };
+                // The arithmetic and logic operators do not access the context
+                context.update_value(target, &mut |left_value| {
+                    operator.eval(&[left_value, arguments[1].clone()], &EmptyContext)
+                })?;
 
                 Ok(Value::Empty)
             },
@@ -501,3 +802,118 @@ impl Operator {
         }
     }
 }
+
+/// Calls the function with the given identifier from the given context.
+///
+/// If the context does not define such a function, a variable with the identifier that holds a `Value::Function` is called instead.
+/// Otherwise, the builtin function with the identifier is called.
+fn call_function<C: Context>(
+    context: &C,
+    identifier: &str,
+    argument: &Value,
+) -> EvalexprResult<Value> {
+    match context.call_function(identifier, argument) {
+        Err(EvalexprError::FunctionIdentifierNotFound(_)) => {
+            if let Ok(Value::Function(function)) = variable_value(context, identifier) {
+                function.call(argument, context)
+            } else if let Some(builtin_function) = builtin_function(identifier) {
+                builtin_function.call(argument, context)
+            } else {
+                Err(EvalexprError::FunctionIdentifierNotFound(
+                    identifier.to_string(),
+                ))
+            }
+        },
+        result => result,
+    }
+}
+
+/// Returns the value of the variable with the given identifier from the given context.
+///
+/// If the context does not contain the identifier, it is interpreted as a field access into a map, like `user.address.city`.
+/// Then the longest prefix of the identifier that ends before a dot and is contained in the context is looked up,
+/// and the remaining dot-separated parts are used as keys into nested maps.
+pub(crate) fn variable_value<C: Context>(context: &C, identifier: &str) -> EvalexprResult<Value> {
+    if let Some(value) = context.get_value(identifier) {
+        return Ok(value.into_owned());
+    }
+
+    let mut prefix = identifier;
+    while let Some(dot) = prefix.rfind('.') {
+        prefix = &identifier[..dot];
+        if let Some(map) = context.get_value(prefix) {
+            let mut value = map.as_ref();
+            for key in identifier[dot + 1..].split('.') {
+                value = map_field(value, key)?;
+            }
+            return Ok(value.clone());
+        }
+    }
+
+    Err(EvalexprError::VariableIdentifierNotFound(
+        identifier.to_string(),
+    ))
+}
+
+/// Returns the value stored under the given key, or `Err` if `map` is not a `Value::Map` or does not contain the key.
+fn map_field<'a>(map: &'a Value, key: &str) -> EvalexprResult<&'a Value> {
+    match map {
+        Value::Map(map) => map
+            .get(key)
+            .ok_or_else(|| EvalexprError::KeyNotFound(key.to_string())),
+        value => Err(EvalexprError::expected_map(value.clone())),
+    }
+}
+
+/// Returns the integer stored in `value`, `None` if `value` is empty, or `Err` otherwise.
+fn optional_int(value: &Value) -> EvalexprResult<Option<IntType>> {
+    match value {
+        Value::Int(int) => Ok(Some(*int)),
+        Value::Empty => Ok(None),
+        value => Err(EvalexprError::type_error(
+            value.clone(),
+            vec![ValueType::Int, ValueType::Empty],
+        )),
+    }
+}
+
+/// Converts a possibly negative index into a position in a sequence of the given length.
+/// Negative indices count from the end of the sequence.
+fn sequence_index(index: IntType, len: usize) -> EvalexprResult<usize> {
+    let position = if index < 0 {
+        index + len as IntType
+    } else {
+        index
+    };
+
+    if position >= 0 && (position as usize) < len {
+        Ok(position as usize)
+    } else {
+        Err(EvalexprError::IndexOutOfBounds { index, len })
+    }
+}
+
+/// Converts possibly negative or omitted bounds into a range of positions in a sequence of the given length.
+/// Negative bounds count from the end of the sequence, an omitted start is the start and an omitted end is the end of the sequence.
+fn sequence_range(
+    start: Option<IntType>,
+    end: Option<IntType>,
+    len: usize,
+) -> EvalexprResult<Range<usize>> {
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(len as IntType);
+    let position = |bound: IntType| {
+        if bound < 0 {
+            bound + len as IntType
+        } else {
+            bound
+        }
+    };
+    let (start_position, end_position) = (position(start), position(end));
+
+    if 0 <= start_position && start_position <= end_position && end_position <= len as IntType {
+        Ok(start_position as usize..end_position as usize)
+    } else {
+        Err(EvalexprError::SliceOutOfBounds { start, end, len })
+    }
+}
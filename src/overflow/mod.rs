@@ -0,0 +1,285 @@
+//! The `overflow` module contains the configuration of what happens if the result of an integer operation does not fit into an `IntType`.
+
+use std::{cell::Cell, convert::TryFrom, mem};
+
+use crate::{
+    error::EvalexprResult,
+    value::{FloatType, IntType, Value},
+};
+
+thread_local! {
+    /// The overflow policy that is currently in effect on this thread.
+    static POLICY: Cell<OverflowPolicy> = Cell::new(OverflowPolicy::Checked);
+}
+
+/// Determines the result of integer operations whose result does not fit into an `IntType`.
+///
+/// The policy applies to the operators `+`, `-`, `*`, `/`, `//`, `%` and `^` as well as the unary `-`, including compound assignments like `+=`,
+/// and to the integer builtin functions `div_euclid`, `rem_euclid`, `shl` and `shr`.
+/// It is in effect for everything that is evaluated within `OverflowPolicy::enforce` on the same thread,
+/// including operator trees, compiled expressions and functions defined in expressions.
+/// Outside of `OverflowPolicy::enforce`, the policy `OverflowPolicy::Checked` is in effect.
+///
+/// Shifting by an amount outside of `0..64` bits counts as an overflow.
+/// Division and modulo by zero always fail, independent of the policy.
+///
+/// Promoting policies change the type of the result, so a compound assignment like `a += 1` that overflows under them
+/// fails with an error like `EvalexprError::ExpectedInt` in contexts whose variables cannot change their type, like `HashMapContext`.
+/// The variable keeps its previous value in this case.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let expression = "9223372036854775807 + 1";
+/// assert!(eval(expression).is_err());
+/// assert_eq!(OverflowPolicy::Wrapping.enforce(|| eval(expression)), Ok(Value::from(IntType::MIN)));
+/// assert_eq!(OverflowPolicy::Saturating.enforce(|| eval(expression)), Ok(Value::from(IntType::MAX)));
+/// assert_eq!(OverflowPolicy::PromoteToFloat.enforce(|| eval(expression)), Ok(Value::from(9223372036854775808.0)));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// The operation fails with an error like `EvalexprError::AdditionError`.
+    Checked,
+    /// The result wraps around at the boundaries of `IntType`.
+    /// Shifts use the amount modulo 64.
+    Wrapping,
+    /// The result is clamped to `IntType::MIN` or `IntType::MAX`.
+    /// Shifts by too many bits, or by a negative amount, shift out all bits.
+    Saturating,
+    /// The operation is performed on `FloatType`s instead, and returns a float.
+    /// Shifts behave like with `OverflowPolicy::Saturating`, and always return integers.
+    PromoteToFloat,
+    /// The operation is performed on big integers instead, and returns a `Value::BigInt` if the result does not fit into an `IntType`.
+    /// Shifts behave like with `OverflowPolicy::Saturating`, and always return integers.
+    /// Requires the `bigint_support` feature flag.
+    #[cfg(feature = "bigint_support")]
+    PromoteToBigInt,
+}
+
+impl OverflowPolicy {
+    /// Returns the overflow policy that is in effect on the current thread.
+    pub fn current() -> Self {
+        POLICY.with(Cell::get)
+    }
+
+    /// Calls `f`, applying this overflow policy to everything that is evaluated during the call on the current thread.
+    ///
+    /// If another policy is in effect already, this policy replaces it until `f` returns.
+    pub fn enforce<T, F: FnOnce() -> EvalexprResult<T>>(self, f: F) -> EvalexprResult<T> {
+        let _scope = PolicyScope {
+            previous: POLICY.with(|policy| policy.replace(self)),
+        };
+        f()
+    }
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        OverflowPolicy::Checked
+    }
+}
+
+/// A guard that restores the previously enforced overflow policy when it is dropped.
+struct PolicyScope {
+    previous: OverflowPolicy,
+}
+
+impl Drop for PolicyScope {
+    fn drop(&mut self) {
+        POLICY.with(|policy| policy.set(self.previous));
+    }
+}
+
+/// Returns the result of an integer operation according to the current policy, or `None` if it overflows and the policy is `OverflowPolicy::Checked`.
+fn resolve<W: FnOnce() -> IntType, S: FnOnce() -> IntType, F: FnOnce() -> FloatType>(
+    checked: Option<IntType>,
+    wrapping: W,
+    saturating: S,
+    float: F,
+) -> Option<Value> {
+    if let Some(result) = checked {
+        return Some(Value::Int(result));
+    }
+
+    match OverflowPolicy::current() {
+        OverflowPolicy::Checked => None,
+        OverflowPolicy::Wrapping => Some(Value::Int(wrapping())),
+        OverflowPolicy::Saturating => Some(Value::Int(saturating())),
+        OverflowPolicy::PromoteToFloat => Some(Value::Float(float())),
+        // Callers perform integer operations on big integers under this policy, so they do not overflow
+        #[cfg(feature = "bigint_support")]
+        OverflowPolicy::PromoteToBigInt => None,
+    }
+}
+
+pub(crate) fn add(a: IntType, b: IntType) -> Option<Value> {
+    resolve(
+        a.checked_add(b),
+        || a.wrapping_add(b),
+        || a.saturating_add(b),
+        || a as FloatType + b as FloatType,
+    )
+}
+
+pub(crate) fn sub(a: IntType, b: IntType) -> Option<Value> {
+    resolve(
+        a.checked_sub(b),
+        || a.wrapping_sub(b),
+        || a.saturating_sub(b),
+        || a as FloatType - b as FloatType,
+    )
+}
+
+pub(crate) fn mul(a: IntType, b: IntType) -> Option<Value> {
+    resolve(
+        a.checked_mul(b),
+        || a.wrapping_mul(b),
+        || a.saturating_mul(b),
+        || a as FloatType * b as FloatType,
+    )
+}
+
+pub(crate) fn neg(a: IntType) -> Option<Value> {
+    resolve(
+        a.checked_neg(),
+        || a.wrapping_neg(),
+        || a.saturating_neg(),
+        || -(a as FloatType),
+    )
+}
+
+/// Returns `None` if `b` is zero, independent of the policy.
+pub(crate) fn div(a: IntType, b: IntType) -> Option<Value> {
+    if b == 0 {
+        return None;
+    }
+    // The only overflowing division is `IntType::MIN / -1`, whose result is one more than `IntType::MAX`
+    resolve(
+        a.checked_div(b),
+        || a.wrapping_div(b),
+        || IntType::MAX,
+        || a as FloatType / b as FloatType,
+    )
+}
+
+/// Returns `None` if `b` is zero, independent of the policy.
+pub(crate) fn rem(a: IntType, b: IntType) -> Option<Value> {
+    if b == 0 {
+        return None;
+    }
+    // The only overflowing remainder is `IntType::MIN % -1`, whose result zero fits into an `IntType` under every policy
+    Some(Value::Int(a.wrapping_rem(b)))
+}
+
+/// Returns the quotient of `a` and `b` rounded towards negative infinity, or `None` if `b` is zero, independent of the policy.
+pub(crate) fn floor_div(a: IntType, b: IntType) -> Option<Value> {
+    let quotient = div(a, b)?;
+    match quotient {
+        Value::Int(quotient) if a.wrapping_rem(b) != 0 && (a < 0) != (b < 0) => {
+            Some(Value::Int(quotient - 1))
+        },
+        Value::Float(quotient) => Some(Value::Float(quotient.floor())),
+        quotient => Some(quotient),
+    }
+}
+
+/// Returns `None` if `b` is zero, independent of the policy.
+pub(crate) fn div_euclid(a: IntType, b: IntType) -> Option<Value> {
+    if b == 0 {
+        return None;
+    }
+    resolve(
+        a.checked_div_euclid(b),
+        || a.wrapping_div_euclid(b),
+        || IntType::MAX,
+        || (a as FloatType).div_euclid(b as FloatType),
+    )
+}
+
+/// Returns `None` if `b` is zero, independent of the policy.
+pub(crate) fn rem_euclid(a: IntType, b: IntType) -> Option<Value> {
+    if b == 0 {
+        return None;
+    }
+    Some(Value::Int(a.wrapping_rem_euclid(b)))
+}
+
+/// Returns `a` raised to the power of the non-negative integer `b`.
+pub(crate) fn pow(a: IntType, b: IntType) -> Option<Value> {
+    debug_assert!(b >= 0);
+    let checked = match (u32::try_from(b), a) {
+        (Ok(b), a) => a.checked_pow(b),
+        // Only powers of zero, one and minus one fit into an integer for such large exponents
+        (Err(_), 0) | (Err(_), 1) => Some(a),
+        (Err(_), -1) => Some(if b % 2 == 0 { 1 } else { -1 }),
+        (Err(_), _) => None,
+    };
+    resolve(
+        checked,
+        || wrapping_pow(a, b as u64),
+        || {
+            if a < 0 && b % 2 == 1 {
+                IntType::MIN
+            } else {
+                IntType::MAX
+            }
+        },
+        || (a as FloatType).powf(b as FloatType),
+    )
+}
+
+/// Computes `a` raised to the power of `b` by repeated squaring, wrapping around at the boundaries of `IntType`.
+fn wrapping_pow(mut a: IntType, mut b: u64) -> IntType {
+    let mut result: IntType = 1;
+    while b > 0 {
+        if b % 2 == 1 {
+            result = result.wrapping_mul(a);
+        }
+        a = a.wrapping_mul(a);
+        b /= 2;
+    }
+    result
+}
+
+pub(crate) fn shl(a: IntType, b: IntType) -> Option<Value> {
+    resolve_shift(
+        shift_amount(b).map(|b| a << b),
+        || a.wrapping_shl(b as u32),
+        0,
+    )
+}
+
+pub(crate) fn shr(a: IntType, b: IntType) -> Option<Value> {
+    let shifted_out = if a < 0 { -1 } else { 0 };
+    resolve_shift(
+        shift_amount(b).map(|b| a >> b),
+        || a.wrapping_shr(b as u32),
+        shifted_out,
+    )
+}
+
+/// Like `resolve`, but for shifts, whose result is the given value with all bits shifted out unless the policy is `OverflowPolicy::Checked` or `OverflowPolicy::Wrapping`.
+fn resolve_shift<W: FnOnce() -> IntType>(
+    checked: Option<IntType>,
+    wrapping: W,
+    shifted_out: IntType,
+) -> Option<Value> {
+    match (checked, OverflowPolicy::current()) {
+        (Some(result), _) => Some(Value::Int(result)),
+        (None, OverflowPolicy::Checked) => None,
+        (None, OverflowPolicy::Wrapping) => Some(Value::Int(wrapping())),
+        (None, _) => Some(Value::Int(shifted_out)),
+    }
+}
+
+/// Returns the given amount of bits to shift by, or `None` if it is not smaller than the amount of bits of an `IntType` or negative.
+fn shift_amount(amount: IntType) -> Option<u32> {
+    let bits = (mem::size_of::<IntType>() * 8) as IntType;
+    if (0..bits).contains(&amount) {
+        Some(amount as u32)
+    } else {
+        None
+    }
+}
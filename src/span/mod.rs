@@ -0,0 +1,136 @@
+//! The `span` module contains the types used to locate tokens, operator tree nodes and errors within the input string.
+
+use std::fmt;
+
+/// A position within an expression string.
+///
+/// Lines and columns are counted from one, while the byte offset is counted from zero.
+/// Columns count characters, not bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Position {
+    /// The byte offset of the position from the start of the string.
+    pub byte: usize,
+    /// The line of the position.
+    pub line: usize,
+    /// The column of the position within its line.
+    pub column: usize,
+}
+
+impl Position {
+    /// Returns the position of the first character of a string.
+    pub const fn start() -> Self {
+        Self {
+            byte: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Returns the position directly behind the given character, assuming the character is located at `self`.
+    pub(crate) fn advance(self, c: char) -> Self {
+        if c == '\n' {
+            Self {
+                byte: self.byte + c.len_utf8(),
+                line: self.line + 1,
+                column: 1,
+            }
+        } else {
+            Self {
+                byte: self.byte + c.len_utf8(),
+                line: self.line,
+                column: self.column + 1,
+            }
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+/// A range within an expression string.
+///
+/// The `start` position is inclusive, the `end` position is exclusive.
+/// Hence `&string[span.start.byte..span.end.byte]` is the part of the expression string covered by the span.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Span {
+    /// The position of the first character covered by the span.
+    pub start: Position,
+    /// The position directly behind the last character covered by the span.
+    pub end: Position,
+}
+
+impl Span {
+    /// Constructs a span from `start` to `end`.
+    pub const fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the smallest span that covers both `self` and `other`.
+    pub fn merge(self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Returns the length of the span in bytes.
+    pub fn len(&self) -> usize {
+        self.end.byte - self.start.byte
+    }
+
+    /// Returns true if the span covers no characters.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::span::{Position, Span};
+
+    #[test]
+    fn test_position_advance() {
+        let position = Position::start().advance('a').advance('ä');
+        assert_eq!(
+            position,
+            Position {
+                byte: 3,
+                line: 1,
+                column: 3
+            }
+        );
+        assert_eq!(
+            position.advance('\n'),
+            Position {
+                byte: 4,
+                line: 2,
+                column: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_span_merge() {
+        let a = Position::start();
+        let b = a.advance('a');
+        let c = b.advance('b');
+        assert_eq!(Span::new(b, c).merge(Span::new(a, b)), Span::new(a, c));
+        assert_eq!(Span::new(a, c).len(), 2);
+        assert!(Span::new(b, b).is_empty());
+    }
+}
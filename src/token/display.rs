@@ -10,6 +10,7 @@ impl fmt::Display for Token {
             Minus => write!(f, "-"),
             Star => write!(f, "*"),
             Slash => write!(f, "/"),
+            DoubleSlash => write!(f, "//"),
             Percent => write!(f, "%"),
             Hat => write!(f, "^"),
 
@@ -28,12 +29,21 @@ impl fmt::Display for Token {
             LBrace => write!(f, "("),
             RBrace => write!(f, ")"),
 
+            // Maps and indexing
+            LBracket => write!(f, "["),
+            RBracket => write!(f, "]"),
+            LCurlyBrace => write!(f, "{{"),
+            RCurlyBrace => write!(f, "}}"),
+            Colon => write!(f, ":"),
+            DotDot => write!(f, ".."),
+
             // Assignment
             Assign => write!(f, "="),
             PlusAssign => write!(f, "+="),
             MinusAssign => write!(f, "-="),
             StarAssign => write!(f, "*="),
             SlashAssign => write!(f, "/="),
+            DoubleSlashAssign => write!(f, "//="),
             PercentAssign => write!(f, "%="),
             HatAssign => write!(f, "^="),
             AndAssign => write!(f, "&&="),
@@ -43,10 +53,18 @@ impl fmt::Display for Token {
             Comma => write!(f, ","),
             Semicolon => write!(f, ";"),
 
+            // Function definitions
+            Fn => write!(f, "fn"),
+            Arrow => write!(f, "->"),
+
             // Values => write!(f, ""), Variables and Functions
             Identifier(identifier) => identifier.fmt(f),
             Float(float) => float.fmt(f),
             Int(int) => int.fmt(f),
+            #[cfg(feature = "decimal_support")]
+            Decimal(decimal) => write!(f, "{}d", decimal),
+            #[cfg(feature = "bigint_support")]
+            BigInt(bigint) => write!(f, "{}", bigint),
             Boolean(boolean) => boolean.fmt(f),
             String(string) => fmt::Debug::fmt(string, f),
         }
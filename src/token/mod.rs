@@ -1,8 +1,14 @@
 use crate::{
     error::{EvalexprError, EvalexprResult},
+    span::{Position, Span},
     value::{FloatType, IntType},
 };
 
+#[cfg(feature = "bigint_support")]
+use crate::{bigint, value::BigIntType};
+#[cfg(feature = "decimal_support")]
+use crate::{decimal, value::DecimalType};
+
 mod display;
 
 #[derive(Clone, PartialEq, Debug)]
@@ -12,6 +18,7 @@ pub enum Token {
     Minus,
     Star,
     Slash,
+    DoubleSlash,
     Percent,
     Hat,
 
@@ -30,12 +37,21 @@ pub enum Token {
     LBrace,
     RBrace,
 
+    // Maps and indexing
+    LBracket,
+    RBracket,
+    LCurlyBrace,
+    RCurlyBrace,
+    Colon,
+    DotDot,
+
     // Assignment
     Assign,
     PlusAssign,
     MinusAssign,
     StarAssign,
     SlashAssign,
+    DoubleSlashAssign,
     PercentAssign,
     HatAssign,
     AndAssign,
@@ -45,14 +61,29 @@ pub enum Token {
     Comma,
     Semicolon,
 
+    // Function definitions
+    Fn,
+    Arrow,
+
     // Values, Variables and Functions
     Identifier(String),
     Float(FloatType),
     Int(IntType),
+    #[cfg(feature = "decimal_support")]
+    Decimal(DecimalType),
+    #[cfg(feature = "bigint_support")]
+    BigInt(BigIntType),
     Boolean(bool),
     String(String),
 }
 
+/// A token together with the span of the input string it was parsed from.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 /// A partial token is an input character whose meaning depends on the characters around it.
 #[derive(Clone, Debug, PartialEq)]
 pub enum PartialToken {
@@ -101,6 +132,12 @@ fn char_to_partial_token(c: char) -> PartialToken {
         '(' => PartialToken::Token(Token::LBrace),
         ')' => PartialToken::Token(Token::RBrace),
 
+        '[' => PartialToken::Token(Token::LBracket),
+        ']' => PartialToken::Token(Token::RBracket),
+        '{' => PartialToken::Token(Token::LCurlyBrace),
+        '}' => PartialToken::Token(Token::RCurlyBrace),
+        ':' => PartialToken::Token(Token::Colon),
+
         ',' => PartialToken::Token(Token::Comma),
         ';' => PartialToken::Token(Token::Semicolon),
 
@@ -129,6 +166,7 @@ impl Token {
             Token::Minus => false,
             Token::Star => false,
             Token::Slash => false,
+            Token::DoubleSlash => false,
             Token::Percent => false,
             Token::Hat => false,
 
@@ -145,14 +183,25 @@ impl Token {
             Token::LBrace => true,
             Token::RBrace => false,
 
+            Token::LBracket => false,
+            Token::RBracket => false,
+            Token::LCurlyBrace => true,
+            Token::RCurlyBrace => false,
+            Token::Colon => false,
+            Token::DotDot => false,
+
             Token::Comma => false,
             Token::Semicolon => false,
 
+            Token::Fn => false,
+            Token::Arrow => false,
+
             Token::Assign => false,
             Token::PlusAssign => false,
             Token::MinusAssign => false,
             Token::StarAssign => false,
             Token::SlashAssign => false,
+            Token::DoubleSlashAssign => false,
             Token::PercentAssign => false,
             Token::HatAssign => false,
             Token::AndAssign => false,
@@ -161,6 +210,10 @@ impl Token {
             Token::Identifier(_) => true,
             Token::Float(_) => true,
             Token::Int(_) => true,
+            #[cfg(feature = "decimal_support")]
+            Token::Decimal(_) => true,
+            #[cfg(feature = "bigint_support")]
+            Token::BigInt(_) => true,
             Token::Boolean(_) => true,
             Token::String(_) => true,
         }
@@ -173,6 +226,7 @@ impl Token {
             Token::Minus => false,
             Token::Star => false,
             Token::Slash => false,
+            Token::DoubleSlash => false,
             Token::Percent => false,
             Token::Hat => false,
 
@@ -189,14 +243,25 @@ impl Token {
             Token::LBrace => false,
             Token::RBrace => true,
 
+            Token::LBracket => false,
+            Token::RBracket => true,
+            Token::LCurlyBrace => false,
+            Token::RCurlyBrace => true,
+            Token::Colon => false,
+            Token::DotDot => false,
+
             Token::Comma => false,
             Token::Semicolon => false,
 
+            Token::Fn => false,
+            Token::Arrow => false,
+
             Token::Assign => false,
             Token::PlusAssign => false,
             Token::MinusAssign => false,
             Token::StarAssign => false,
             Token::SlashAssign => false,
+            Token::DoubleSlashAssign => false,
             Token::PercentAssign => false,
             Token::HatAssign => false,
             Token::AndAssign => false,
@@ -205,6 +270,10 @@ impl Token {
             Token::Identifier(_) => true,
             Token::Float(_) => true,
             Token::Int(_) => true,
+            #[cfg(feature = "decimal_support")]
+            Token::Decimal(_) => true,
+            #[cfg(feature = "bigint_support")]
+            Token::BigInt(_) => true,
             Token::Boolean(_) => true,
             Token::String(_) => true,
         }
@@ -220,6 +289,7 @@ impl Token {
                 | MinusAssign
                 | StarAssign
                 | SlashAssign
+                | DoubleSlashAssign
                 | PercentAssign
                 | HatAssign
                 | AndAssign
@@ -228,31 +298,93 @@ impl Token {
     }
 }
 
+/// An iterator over the characters of a string that keeps track of their positions.
+struct PositionedChars<'a> {
+    chars: std::str::Chars<'a>,
+    position: Position,
+}
+
+impl<'a> PositionedChars<'a> {
+    fn new(string: &'a str) -> Self {
+        Self {
+            chars: string.chars(),
+            position: Position::start(),
+        }
+    }
+
+    /// Returns the position of the next character, or the end of the string if there is none.
+    fn current_position(&self) -> Position {
+        self.position
+    }
+
+    /// Returns the next character without consuming it.
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+}
+
+impl<'a> Iterator for PositionedChars<'a> {
+    type Item = (char, Position);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let c = self.chars.next()?;
+        let position = self.position;
+        self.position = position.advance(c);
+        Some((c, position))
+    }
+}
+
 /// Parses an escape sequence within a string literal.
-fn parse_escape_sequence<Iter: Iterator<Item = char>>(iter: &mut Iter) -> EvalexprResult<char> {
+/// The `start` is the position of the backslash that introduces the escape sequence.
+fn parse_escape_sequence(iter: &mut PositionedChars, start: Position) -> EvalexprResult<char> {
     match iter.next() {
-        Some('"') => Ok('"'),
-        Some('\\') => Ok('\\'),
-        Some(c) => Err(EvalexprError::IllegalEscapeSequence(format!("\\{}", c))),
-        None => Err(EvalexprError::IllegalEscapeSequence("\\".to_string())),
+        Some(('"', _)) => Ok('"'),
+        Some(('\\', _)) => Ok('\\'),
+        Some((c, _)) => Err(EvalexprError::IllegalEscapeSequence(format!("\\{}", c))
+            .with_span(Span::new(start, iter.current_position()))),
+        None => Err(EvalexprError::IllegalEscapeSequence("\\".to_string())
+            .with_span(Span::new(start, iter.current_position()))),
     }
 }
 
+/// Parses a decimal literal like `1.25d` into a token, or returns `None` if the literal is not a decimal literal.
+/// Returns `Err` if the literal is out of the range of decimals.
+#[cfg(feature = "decimal_support")]
+fn parse_decimal_literal(literal: &str) -> Option<EvalexprResult<Token>> {
+    decimal::parse_literal(literal).map(|result| result.map(Token::Decimal))
+}
+
+/// Decimal literals are only recognised with the `decimal_support` feature flag.
+#[cfg(not(feature = "decimal_support"))]
+fn parse_decimal_literal(_literal: &str) -> Option<EvalexprResult<Token>> {
+    None
+}
+
+/// Parses an integer literal that is too large for an `IntType` into a token, or returns `None` if the literal is not an integer literal.
+#[cfg(feature = "bigint_support")]
+fn parse_bigint_literal(literal: &str) -> Option<Token> {
+    bigint::parse_literal(literal).map(Token::BigInt)
+}
+
+/// Without the `bigint_support` feature flag, integer literals that are too large for an `IntType` are parsed as floats.
+#[cfg(not(feature = "bigint_support"))]
+fn parse_bigint_literal(_literal: &str) -> Option<Token> {
+    None
+}
+
 /// Parses a string value from the given character iterator.
 ///
 /// The first character from the iterator is interpreted as first character of the string.
 /// The string is terminated by a double quote `"`.
 /// Occurrences of `"` within the string can be escaped with `\`.
 /// The backslash needs to be escaped with another backslash `\`.
-fn parse_string_literal<Iter: Iterator<Item = char>>(
-    mut iter: &mut Iter,
-) -> EvalexprResult<PartialToken> {
+fn parse_string_literal(iter: &mut PositionedChars) -> EvalexprResult<PartialToken> {
     let mut result = String::new();
 
-    while let Some(c) = iter.next() {
+    while let Some((c, position)) = iter.next() {
         match c {
             '"' => break,
-            '\\' => result.push(parse_escape_sequence(&mut iter)?),
+            '\\' => result.push(parse_escape_sequence(iter, position)?),
             c => result.push(c),
         }
     }
@@ -260,29 +392,42 @@ fn parse_string_literal<Iter: Iterator<Item = char>>(
     Ok(PartialToken::Token(Token::String(result)))
 }
 
-/// Converts a string to a vector of partial tokens.
-fn str_to_partial_tokens(string: &str) -> EvalexprResult<Vec<PartialToken>> {
-    let mut result = Vec::new();
-    let mut iter = string.chars().peekable();
+/// Converts a string to a vector of partial tokens, each paired with the span it was parsed from.
+fn str_to_partial_tokens(string: &str) -> EvalexprResult<Vec<(PartialToken, Span)>> {
+    let mut result: Vec<(PartialToken, Span)> = Vec::new();
+    let mut iter = PositionedChars::new(string);
 
-    while let Some(c) = iter.next() {
+    while let Some((c, start)) = iter.next() {
         if c == '"' {
-            result.push(parse_string_literal(&mut iter)?);
+            let partial_token = parse_string_literal(&mut iter)?;
+            result.push((partial_token, Span::new(start, iter.current_position())));
         } else {
-            let partial_token = char_to_partial_token(c);
-
-            let if_let_successful =
-                if let (Some(PartialToken::Literal(last)), PartialToken::Literal(literal)) =
-                    (result.last_mut(), &partial_token)
-                {
-                    last.push_str(literal);
-                    true
-                } else {
-                    false
-                };
+            let partial_token = if c == ':' && iter.peek() == Some(':') {
+                // A double colon separates the parts of an identifier like `math::sqrt`
+                iter.next();
+                PartialToken::Literal("::".to_string())
+            } else if c == '.' && iter.peek() == Some('.') {
+                iter.next();
+                PartialToken::Token(Token::DotDot)
+            } else {
+                char_to_partial_token(c)
+            };
+            let span = Span::new(start, iter.current_position());
+
+            let if_let_successful = if let (
+                Some((PartialToken::Literal(last), last_span)),
+                PartialToken::Literal(literal),
+            ) = (result.last_mut(), &partial_token)
+            {
+                last.push_str(literal);
+                *last_span = last_span.merge(span);
+                true
+            } else {
+                false
+            };
 
             if !if_let_successful {
-                result.push(partial_token);
+                result.push((partial_token, span));
             }
         }
     }
@@ -290,155 +435,184 @@ fn str_to_partial_tokens(string: &str) -> EvalexprResult<Vec<PartialToken>> {
 }
 
 /// Resolves all partial tokens by converting them to complex tokens.
-fn partial_tokens_to_tokens(mut tokens: &[PartialToken]) -> EvalexprResult<Vec<Token>> {
+fn partial_tokens_to_tokens(
+    mut tokens: &[(PartialToken, Span)],
+) -> EvalexprResult<Vec<SpannedToken>> {
     let mut result = Vec::new();
     while !tokens.is_empty() {
-        let first = tokens[0].clone();
-        let second = tokens.get(1).cloned();
-        let third = tokens.get(2).cloned();
+        let (first, first_span) = tokens[0].clone();
+        let second = tokens.get(1).map(|(token, _)| token.clone());
+        let third = tokens.get(2).map(|(token, _)| token.clone());
         let mut cutoff = 2;
 
-        result.extend(
-            match first {
-                PartialToken::Token(token) => {
+        let token = match first {
+            PartialToken::Token(token) => {
+                cutoff = 1;
+                Some(token)
+            },
+            PartialToken::Plus => match second {
+                Some(PartialToken::Eq) => Some(Token::PlusAssign),
+                _ => {
                     cutoff = 1;
-                    Some(token)
-                },
-                PartialToken::Plus => match second {
-                    Some(PartialToken::Eq) => Some(Token::PlusAssign),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Plus)
-                    },
+                    Some(Token::Plus)
                 },
-                PartialToken::Minus => match second {
-                    Some(PartialToken::Eq) => Some(Token::MinusAssign),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Minus)
-                    },
+            },
+            PartialToken::Minus => match second {
+                Some(PartialToken::Eq) => Some(Token::MinusAssign),
+                Some(PartialToken::Gt) => Some(Token::Arrow),
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Minus)
                 },
-                PartialToken::Star => match second {
-                    Some(PartialToken::Eq) => Some(Token::StarAssign),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Star)
-                    },
+            },
+            PartialToken::Star => match second {
+                Some(PartialToken::Eq) => Some(Token::StarAssign),
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Star)
                 },
-                PartialToken::Slash => match second {
-                    Some(PartialToken::Eq) => Some(Token::SlashAssign),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Slash)
+            },
+            PartialToken::Slash => match second {
+                Some(PartialToken::Eq) => Some(Token::SlashAssign),
+                Some(PartialToken::Slash) => match third {
+                    Some(PartialToken::Eq) => {
+                        cutoff = 3;
+                        Some(Token::DoubleSlashAssign)
                     },
+                    _ => Some(Token::DoubleSlash),
                 },
-                PartialToken::Percent => match second {
-                    Some(PartialToken::Eq) => Some(Token::PercentAssign),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Percent)
-                    },
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Slash)
                 },
-                PartialToken::Hat => match second {
-                    Some(PartialToken::Eq) => Some(Token::HatAssign),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Hat)
-                    },
+            },
+            PartialToken::Percent => match second {
+                Some(PartialToken::Eq) => Some(Token::PercentAssign),
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Percent)
                 },
-                PartialToken::Literal(literal) => {
+            },
+            PartialToken::Hat => match second {
+                Some(PartialToken::Eq) => Some(Token::HatAssign),
+                _ => {
                     cutoff = 1;
-                    if let Ok(number) = literal.parse::<IntType>() {
-                        Some(Token::Int(number))
-                    } else if let Ok(number) = literal.parse::<FloatType>() {
-                        Some(Token::Float(number))
-                    } else if let Ok(boolean) = literal.parse::<bool>() {
-                        Some(Token::Boolean(boolean))
-                    } else {
-                        // If there are two tokens following this one, check if the next one is
-                        // a plus or a minus. If so, then attempt to parse all three tokens as a
-                        // scientific notation number of the form `<coefficient>e{+,-}<exponent>`,
-                        // for example [Literal("10e"), Minus, Literal("3")] => "1e-3".parse().
-                        match (second, third) {
-                            (Some(second), Some(third))
-                                if second == PartialToken::Minus
-                                    || second == PartialToken::Plus =>
-                            {
-                                if let Ok(number) =
-                                    format!("{}{}{}", literal, second, third).parse::<FloatType>()
-                                {
-                                    cutoff = 3;
-                                    Some(Token::Float(number))
-                                } else {
-                                    Some(Token::Identifier(literal.to_string()))
-                                }
-                            },
-                            _ => Some(Token::Identifier(literal.to_string())),
-                        }
+                    Some(Token::Hat)
+                },
+            },
+            PartialToken::Literal(literal) => {
+                cutoff = 1;
+                if let Ok(number) = literal.parse::<IntType>() {
+                    Some(Token::Int(number))
+                } else if let Some(token) = parse_bigint_literal(&literal) {
+                    Some(token)
+                } else if let Ok(number) = literal.parse::<FloatType>() {
+                    Some(Token::Float(number))
+                } else if let Some(token) = parse_decimal_literal(&literal) {
+                    Some(token.map_err(|error| error.with_span(first_span))?)
+                } else if let Ok(boolean) = literal.parse::<bool>() {
+                    Some(Token::Boolean(boolean))
+                } else if literal == "fn" {
+                    Some(Token::Fn)
+                } else {
+                    // If there are two tokens following this one, check if the next one is
+                    // a plus or a minus. If so, then attempt to parse all three tokens as a
+                    // scientific notation number of the form `<coefficient>e{+,-}<exponent>`,
+                    // for example [Literal("10e"), Minus, Literal("3")] => "1e-3".parse().
+                    match (second, third) {
+                        (Some(second), Some(third))
+                            if second == PartialToken::Minus || second == PartialToken::Plus =>
+                        {
+                            let number = format!("{}{}{}", literal, second, third);
+                            if let Ok(float) = number.parse::<FloatType>() {
+                                cutoff = 3;
+                                Some(Token::Float(float))
+                            } else if let Some(token) = parse_decimal_literal(&number) {
+                                cutoff = 3;
+                                Some(token.map_err(|error| {
+                                    error.with_span(first_span.merge(tokens[2].1))
+                                })?)
+                            } else {
+                                Some(Token::Identifier(literal.to_string()))
+                            }
+                        },
+                        _ => Some(Token::Identifier(literal.to_string())),
                     }
+                }
+            },
+            PartialToken::Whitespace => {
+                cutoff = 1;
+                None
+            },
+            PartialToken::Eq => match second {
+                Some(PartialToken::Eq) => Some(Token::Eq),
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Assign)
                 },
-                PartialToken::Whitespace => {
+            },
+            PartialToken::ExclamationMark => match second {
+                Some(PartialToken::Eq) => Some(Token::Neq),
+                _ => {
                     cutoff = 1;
-                    None
+                    Some(Token::Not)
                 },
-                PartialToken::Eq => match second {
-                    Some(PartialToken::Eq) => Some(Token::Eq),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Assign)
-                    },
+            },
+            PartialToken::Gt => match second {
+                Some(PartialToken::Eq) => Some(Token::Geq),
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Gt)
                 },
-                PartialToken::ExclamationMark => match second {
-                    Some(PartialToken::Eq) => Some(Token::Neq),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Not)
-                    },
+            },
+            PartialToken::Lt => match second {
+                Some(PartialToken::Eq) => Some(Token::Leq),
+                _ => {
+                    cutoff = 1;
+                    Some(Token::Lt)
                 },
-                PartialToken::Gt => match second {
-                    Some(PartialToken::Eq) => Some(Token::Geq),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Gt)
+            },
+            PartialToken::Ampersand => match second {
+                Some(PartialToken::Ampersand) => match third {
+                    Some(PartialToken::Eq) => {
+                        cutoff = 3;
+                        Some(Token::AndAssign)
                     },
+                    _ => Some(Token::And),
                 },
-                PartialToken::Lt => match second {
-                    Some(PartialToken::Eq) => Some(Token::Leq),
-                    _ => {
-                        cutoff = 1;
-                        Some(Token::Lt)
-                    },
+                _ => {
+                    return Err(
+                        EvalexprError::unmatched_partial_token(first, second).with_span(first_span)
+                    )
                 },
-                PartialToken::Ampersand => match second {
-                    Some(PartialToken::Ampersand) => match third {
-                        Some(PartialToken::Eq) => {
-                            cutoff = 3;
-                            Some(Token::AndAssign)
-                        },
-                        _ => Some(Token::And),
+            },
+            PartialToken::VerticalBar => match second {
+                Some(PartialToken::VerticalBar) => match third {
+                    Some(PartialToken::Eq) => {
+                        cutoff = 3;
+                        Some(Token::OrAssign)
                     },
-                    _ => return Err(EvalexprError::unmatched_partial_token(first, second)),
+                    _ => Some(Token::Or),
                 },
-                PartialToken::VerticalBar => match second {
-                    Some(PartialToken::VerticalBar) => match third {
-                        Some(PartialToken::Eq) => {
-                            cutoff = 3;
-                            Some(Token::OrAssign)
-                        },
-                        _ => Some(Token::Or),
-                    },
-                    _ => return Err(EvalexprError::unmatched_partial_token(first, second)),
+                _ => {
+                    return Err(
+                        EvalexprError::unmatched_partial_token(first, second).with_span(first_span)
+                    )
                 },
-            }
-            .into_iter(),
-        );
+            },
+        };
+
+        if let Some(token) = token {
+            let span = first_span.merge(tokens[cutoff - 1].1);
+            result.push(SpannedToken { token, span });
+        }
 
         tokens = &tokens[cutoff..];
     }
     Ok(result)
 }
 
-pub(crate) fn tokenize(string: &str) -> EvalexprResult<Vec<Token>> {
+pub(crate) fn tokenize(string: &str) -> EvalexprResult<Vec<SpannedToken>> {
     partial_tokens_to_tokens(&str_to_partial_tokens(string)?)
 }
 
@@ -449,7 +623,8 @@ mod tests {
     #[test]
     fn test_partial_token_display() {
         let chars = vec![
-            '+', '-', '*', '/', '%', '^', '(', ')', ',', ';', '=', '!', '>', '<', '&', '|', ' ',
+            '+', '-', '*', '/', '%', '^', '(', ')', '[', ']', '{', '}', ':', ',', ';', '=', '!',
+            '>', '<', '&', '|', ' ',
         ];
 
         for char in chars {
@@ -462,13 +637,13 @@ mod tests {
 
     #[test]
     fn test_token_display() {
-        let token_string =
-            "+ - * / % ^ == != > < >= <= && || ! ( ) = += -= *= /= %= ^= &&= ||= , ; ";
+        let token_string = "+ - * / % ^ == != > < >= <= && || ! ( ) [ ] { } : .. = += -= *= /= %= \
+                            ^= &&= ||= , ; fn -> ";
         let tokens = tokenize(token_string).unwrap();
         let mut result_string = String::new();
 
         for token in tokens {
-            result_string += &format!("{} ", token);
+            result_string += &format!("{} ", token.token);
         }
 
         assert_eq!(token_string, result_string);
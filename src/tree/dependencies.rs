@@ -0,0 +1,220 @@
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{operator::Operator, Node};
+
+use super::{typecheck::assignment_target, LazyEvaluation};
+
+/// The variables and functions an operator tree reads, writes and calls, as returned by `Node::dependencies`.
+///
+/// Identifiers are reported as they appear in the expression, so reading the field `m.x` of a map is reported as a read of `m.x`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    read_variables: BTreeSet<String>,
+    written_variables: BTreeSet<String>,
+    free_variables: BTreeSet<String>,
+    called_functions: BTreeSet<String>,
+    defined_functions: BTreeSet<String>,
+}
+
+impl Dependencies {
+    /// Returns the variables whose value is read, including the targets of compound assignments like `a += 1`.
+    pub fn read_variables(&self) -> &BTreeSet<String> {
+        &self.read_variables
+    }
+
+    /// Returns the variables that are assigned to with `=` or a compound assignment, including the ones that are only assigned to under a condition.
+    pub fn written_variables(&self) -> &BTreeSet<String> {
+        &self.written_variables
+    }
+
+    /// Returns the variables that may be read before they are assigned to, and therefore need to be provided by the context.
+    pub fn free_variables(&self) -> &BTreeSet<String> {
+        &self.free_variables
+    }
+
+    /// Returns the functions that are called, including the ones defined in the expression.
+    pub fn called_functions(&self) -> &BTreeSet<String> {
+        &self.called_functions
+    }
+
+    /// Returns the functions that are defined in the expression with `name = fn(parameters) body`.
+    pub fn defined_functions(&self) -> &BTreeSet<String> {
+        &self.defined_functions
+    }
+}
+
+impl Node {
+    /// Analyses which variables this operator tree reads and writes, and which functions it calls, without evaluating it.
+    ///
+    /// Subexpressions are assumed to be evaluated in order.
+    /// A variable that is read before it is assigned to is free, and must be provided by the context.
+    /// Assignments in the branches of `if` and in the right operand of `&&` and `||` may be skipped, so a variable only assigned to there is still free when it is read afterwards.
+    /// Parameters of function definitions and variables assigned within their bodies are local to the function and are not reported.
+    /// Variables of the context read within the body are reported as read where the function is defined.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let tree = build_operator_tree("total += price * count; count = 0; f = fn(x) x * rate; f(total)").unwrap(); // Do proper error handling here
+    /// let dependencies = tree.dependencies();
+    /// assert_eq!(dependencies.read_variables().iter().collect::<Vec<_>>(), vec!["count", "price", "rate", "total"]);
+    /// assert_eq!(dependencies.written_variables().iter().collect::<Vec<_>>(), vec!["count", "total"]);
+    /// assert_eq!(dependencies.free_variables().iter().collect::<Vec<_>>(), vec!["count", "price", "rate", "total"]);
+    /// assert_eq!(dependencies.called_functions().iter().collect::<Vec<_>>(), vec!["f"]);
+    /// assert_eq!(dependencies.defined_functions().iter().collect::<Vec<_>>(), vec!["f"]);
+    /// ```
+    pub fn dependencies(&self) -> Dependencies {
+        let mut analysis = DependencyAnalysis::default();
+        analysis.visit(self);
+        analysis.dependencies
+    }
+}
+
+#[derive(Default)]
+struct DependencyAnalysis {
+    dependencies: Dependencies,
+    /// The local variables of the bodies of the function definitions that are currently visited, with the innermost one last.
+    scopes: Vec<HashSet<String>>,
+    /// The variables outside of function bodies that are assigned to in all subexpressions that are visited at the moment.
+    assigned_variables: HashSet<String>,
+}
+
+impl DependencyAnalysis {
+    fn visit(&mut self, node: &Node) {
+        if let Some((identifier, definition)) = node.function_assignment() {
+            self.visit(definition);
+            if self.scopes.is_empty() {
+                self.dependencies
+                    .defined_functions
+                    .insert(identifier.to_string());
+            }
+            return;
+        }
+
+        match node.lazy_evaluation() {
+            Some(LazyEvaluation::ShortCircuit { left, right, .. }) => {
+                self.visit(left);
+                self.visit_conditionally(right);
+                return;
+            },
+            Some(LazyEvaluation::Conditional {
+                condition,
+                then,
+                otherwise,
+            }) => {
+                self.visit(condition);
+                self.visit_conditionally(then);
+                self.visit_conditionally(otherwise);
+                self.call("if");
+                return;
+            },
+            _ => {},
+        }
+
+        match (node.operator(), node.children()) {
+            (Operator::VariableIdentifier { identifier }, _) => self.read(identifier),
+            (Operator::FunctionIdentifier { identifier }, arguments) => {
+                for argument in arguments {
+                    self.visit(argument);
+                }
+                self.call(identifier);
+            },
+            (Operator::FunctionDefinition { parameters }, body) => {
+                self.scopes.push(parameters.iter().cloned().collect());
+                for child in body {
+                    self.visit(child);
+                }
+                self.scopes.pop();
+            },
+            (Operator::Assign, [target, value]) => {
+                self.visit(value);
+                match assignment_target(target) {
+                    Some(identifier) => self.write(identifier),
+                    None => self.visit(target),
+                }
+            },
+            (operator, [target, value])
+                if matches!(
+                    operator,
+                    Operator::AddAssign
+                        | Operator::SubAssign
+                        | Operator::MulAssign
+                        | Operator::DivAssign
+                        | Operator::FloorDivAssign
+                        | Operator::ModAssign
+                        | Operator::ExpAssign
+                        | Operator::AndAssign
+                        | Operator::OrAssign
+                ) =>
+            {
+                self.visit(value);
+                match assignment_target(target) {
+                    Some(identifier) => {
+                        self.read(identifier);
+                        self.write(identifier);
+                    },
+                    None => self.visit(target),
+                }
+            },
+            (_, children) => {
+                for child in children {
+                    self.visit(child);
+                }
+            },
+        }
+    }
+
+    /// Visits a subexpression that may be skipped, so the variables it assigns to are forgotten afterwards.
+    fn visit_conditionally(&mut self, node: &Node) {
+        let assigned_variables = self.assigned_variables.clone();
+        let scope = self.scopes.last().cloned();
+        self.visit(node);
+        self.assigned_variables = assigned_variables;
+        if let (Some(scope), Some(current)) = (scope, self.scopes.last_mut()) {
+            *current = scope;
+        }
+    }
+
+    fn is_local(&self, identifier: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.contains(identifier))
+    }
+
+    fn read(&mut self, identifier: &str) {
+        if self.is_local(identifier) {
+            return;
+        }
+        if !self.assigned_variables.contains(identifier) {
+            self.dependencies
+                .free_variables
+                .insert(identifier.to_string());
+        }
+        self.dependencies
+            .read_variables
+            .insert(identifier.to_string());
+    }
+
+    fn write(&mut self, identifier: &str) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                scope.insert(identifier.to_string());
+            },
+            None => {
+                self.assigned_variables.insert(identifier.to_string());
+                self.dependencies
+                    .written_variables
+                    .insert(identifier.to_string());
+            },
+        }
+    }
+
+    /// Variables holding functions, like the parameters of higher-order functions, are called like functions.
+    fn call(&mut self, identifier: &str) {
+        if !self.is_local(identifier) {
+            self.dependencies
+                .called_functions
+                .insert(identifier.to_string());
+        }
+    }
+}
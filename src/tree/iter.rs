@@ -26,7 +26,7 @@ impl<'a> Iterator for NodeIter<'a> {
                     result = Some(next);
                 } else {
                     // Can not fail because we just borrowed last.
-                    self.stack.pop().unwrap();
+                    self.stack.pop();
                 }
             } else {
                 return None;
@@ -1,20 +1,30 @@
 use crate::{
-    token::Token,
+    limits,
+    span::Span,
+    token::{SpannedToken, Token},
     value::{TupleType, EMPTY_VALUE},
-    Context, ContextWithMutableVariables, EmptyType, FloatType, HashMapContext, IntType,
+    Context, ContextWithMutableFunctions, ContextWithMutableVariables, EmptyType, FloatType,
+    HashMapContext, IntType, RecordingContext, ScopedContext, VariableChange,
 };
 
 use crate::{
     error::{EvalexprError, EvalexprResult},
+    function::{Function, FunctionDefinition},
     operator::*,
     value::Value,
 };
 use std::mem;
 
+pub use self::dependencies::Dependencies;
+
+mod dependencies;
 // Exclude display module from coverage, as it prints not well-defined prefix notation.
 #[cfg(not(tarpaulin_include))]
 mod display;
 mod iter;
+mod optimize;
+mod source;
+mod typecheck;
 
 /// A node in the operator tree.
 /// The operator tree is created by the crate-level `build_operator_tree` method.
@@ -33,10 +43,36 @@ mod iter;
 /// assert_eq!(node.eval_with_context(&context), Ok(Value::from(3)));
 /// ```
 ///
-#[derive(Debug, PartialEq, Clone)]
+/// Two nodes are equal if they have the same operator and equal children, regardless of their spans.
+#[derive(Debug, Clone)]
 pub struct Node {
     operator: Operator,
     children: Vec<Node>,
+    span: Option<Span>,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.operator == other.operator && self.children == other.children
+    }
+}
+
+/// A node whose operator does not evaluate all of its children eagerly.
+enum LazyEvaluation<'a> {
+    /// `&&` or `||`, which only evaluate `right` if `left` is not `short_circuit_value`.
+    ShortCircuit {
+        left: &'a Node,
+        right: &'a Node,
+        short_circuit_value: bool,
+    },
+    /// `if(condition, then, otherwise)`, which only evaluates the selected branch.
+    Conditional {
+        condition: &'a Node,
+        then: &'a Node,
+        otherwise: &'a Node,
+    },
+    /// A function definition, whose body is only evaluated when the function is called.
+    FunctionDefinition,
 }
 
 impl Node {
@@ -44,6 +80,7 @@ impl Node {
         Self {
             children: Vec::new(),
             operator,
+            span: None,
         }
     }
 
@@ -121,25 +158,280 @@ impl Node {
     ///
     /// Fails, if one of the operators in the expression tree fails.
     pub fn eval_with_context<C: Context>(&self, context: &C) -> EvalexprResult<Value> {
-        let mut arguments = Vec::new();
-        for child in self.children() {
-            arguments.push(child.eval_with_context(context)?);
+        self.eval_spanned_with_context(context)
+            .map_err(EvalexprError::without_span)
+    }
+
+    /// Evaluates the operator tree rooted at this node with the given mutable context.
+    ///
+    /// Fails, if one of the operators in the expression tree fails.
+    pub fn eval_with_context_mut<C: ContextWithMutableVariables + ContextWithMutableFunctions>(
+        &self,
+        context: &mut C,
+    ) -> EvalexprResult<Value> {
+        self.eval_spanned_with_context_mut(context)
+            .map_err(EvalexprError::without_span)
+    }
+
+    /// Evaluates the operator tree rooted at this node with the given mutable context, as a transaction.
+    ///
+    /// All assignments and function definitions are buffered, and only written to the context if the evaluation succeeds.
+    /// If the evaluation fails, the context is left unchanged.
+    /// If the context rejects one of the buffered variables or functions, the variables and functions written before are reset and the error is returned.
+    /// Functions are only reset if the context supports removing them.
+    pub fn eval_with_context_transactional<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
+        &self,
+        context: &mut C,
+    ) -> EvalexprResult<Value> {
+        let mut transaction = ScopedContext::new(&*context);
+        let value = self.eval_with_context_mut(&mut transaction)?;
+        transaction.into_innermost_scope().commit_into(context)?;
+        Ok(value)
+    }
+
+    /// Evaluates the operator tree rooted at this node with the given mutable context, and returns the writes to variables alongside the result.
+    ///
+    /// The writes are recorded as described by `RecordingContext`.
+    /// The writes are returned on success as well as on failure, in which case they contain the writes made before the failure.
+    pub fn eval_with_context_recorded<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
+        &self,
+        context: &mut C,
+    ) -> (EvalexprResult<Value>, Vec<VariableChange>) {
+        let mut recording = RecordingContext::new(context);
+        let result = self.eval_with_context_mut(&mut recording);
+        (result, recording.into_changes())
+    }
+
+    /// Evaluates the operator tree rooted at this node with the given context.
+    ///
+    /// Fails, if one of the operators in the expression tree fails.
+    /// The error is wrapped into `EvalexprError::Spanned` with the span of the innermost node whose evaluation failed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let tree = build_operator_tree("1 + (2 * true)").unwrap(); // Do proper error handling here
+    /// let error = tree.eval_spanned_with_context(&EmptyContext).unwrap_err();
+    /// let span = error.span().unwrap();
+    /// assert_eq!(&"1 + (2 * true)"[span.start.byte..span.end.byte], "2 * true");
+    /// ```
+    pub fn eval_spanned_with_context<C: Context>(&self, context: &C) -> EvalexprResult<Value> {
+        let _depth = limits::enter_node().map_err(|error| self.attach_span(error))?;
+        if self.function_assignment().is_some() {
+            return Err(self.attach_span(EvalexprError::ContextNotMutable));
+        }
+        match self.lazy_evaluation() {
+            Some(LazyEvaluation::ShortCircuit {
+                left,
+                right,
+                short_circuit_value,
+            }) => {
+                if self.condition(left.eval_spanned_with_context(context)?)? == short_circuit_value
+                {
+                    Ok(Value::Boolean(short_circuit_value))
+                } else {
+                    self.condition(right.eval_spanned_with_context(context)?)
+                        .map(Value::Boolean)
+                }
+            },
+            Some(LazyEvaluation::Conditional {
+                condition,
+                then,
+                otherwise,
+            }) => {
+                if self.condition(condition.eval_spanned_with_context(context)?)? {
+                    then.eval_spanned_with_context(context)
+                } else {
+                    otherwise.eval_spanned_with_context(context)
+                }
+            },
+            Some(LazyEvaluation::FunctionDefinition) => self.function_value(context),
+            None => {
+                let mut arguments = Vec::new();
+                for child in self.children() {
+                    arguments.push(child.eval_spanned_with_context(context)?);
+                }
+                self.apply(|operator| operator.eval(&arguments, context))
+            },
         }
-        self.operator().eval(&arguments, context)
     }
 
     /// Evaluates the operator tree rooted at this node with the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_with_context_mut<C: ContextWithMutableVariables>(
+    /// The error is wrapped into `EvalexprError::Spanned` with the span of the innermost node whose evaluation failed.
+    pub fn eval_spanned_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
+        &self,
+        context: &mut C,
+    ) -> EvalexprResult<Value> {
+        let _depth = limits::enter_node().map_err(|error| self.attach_span(error))?;
+        if let Some((identifier, definition)) = self.function_assignment() {
+            return self.define_function(identifier, definition, context);
+        }
+        match self.lazy_evaluation() {
+            Some(LazyEvaluation::ShortCircuit {
+                left,
+                right,
+                short_circuit_value,
+            }) => {
+                if self.condition(left.eval_spanned_with_context_mut(context)?)?
+                    == short_circuit_value
+                {
+                    Ok(Value::Boolean(short_circuit_value))
+                } else {
+                    self.condition(right.eval_spanned_with_context_mut(context)?)
+                        .map(Value::Boolean)
+                }
+            },
+            Some(LazyEvaluation::Conditional {
+                condition,
+                then,
+                otherwise,
+            }) => {
+                if self.condition(condition.eval_spanned_with_context_mut(context)?)? {
+                    then.eval_spanned_with_context_mut(context)
+                } else {
+                    otherwise.eval_spanned_with_context_mut(context)
+                }
+            },
+            Some(LazyEvaluation::FunctionDefinition) => self.function_value(context),
+            None => {
+                let mut arguments = Vec::new();
+                for child in self.children() {
+                    arguments.push(child.eval_spanned_with_context_mut(context)?);
+                }
+                self.apply(|operator| operator.eval_mut(&arguments, context))
+            },
+        }
+    }
+
+    /// Applies the operator of this node with `eval`, and checks the size of the result against the limits of `EvalLimits::enforce`.
+    ///
+    /// Helpers called by the evaluation are not inlined, such that their stack frames are not part of each level of the recursive evaluation.
+    /// The results of the other branches of the evaluation are booleans, function values and values of child nodes, which need no size check.
+    #[inline(never)]
+    fn apply<F: FnOnce(&Operator) -> EvalexprResult<Value>>(
+        &self,
+        eval: F,
+    ) -> EvalexprResult<Value> {
+        let value = eval(self.operator()).map_err(|error| self.attach_span(error))?;
+        limits::check_value_size(&value).map_err(|error| self.attach_span(error))?;
+        Ok(value)
+    }
+
+    /// Stores the function defined by the given definition node under the given identifier in the context.
+    #[inline(never)]
+    fn define_function<C: ContextWithMutableFunctions>(
         &self,
+        identifier: &str,
+        definition: &Node,
         context: &mut C,
     ) -> EvalexprResult<Value> {
-        let mut arguments = Vec::new();
-        for child in self.children() {
-            arguments.push(child.eval_with_context_mut(context)?);
+        FunctionDefinition::from_node(definition)
+            .and_then(|definition| {
+                let function = Function::defined(definition).with_name(identifier.to_string());
+                context.set_function(identifier.to_string(), function)
+            })
+            .map(|()| Value::Empty)
+            .map_err(|error| self.attach_span(error))
+    }
+
+    /// Returns how this node is evaluated if its operator does not need all of its arguments.
+    ///
+    /// This is the case for the short-circuiting operators `&&` and `||` as well as for the conditional `if(condition, then, else)`.
+    /// Function definitions do not evaluate their body at all.
+    /// Returns `None` if this node needs all of its children to be evaluated.
+    fn lazy_evaluation(&self) -> Option<LazyEvaluation<'_>> {
+        match (self.operator(), self.children()) {
+            (Operator::And, [left, right]) | (Operator::Or, [left, right]) => {
+                Some(LazyEvaluation::ShortCircuit {
+                    left,
+                    right,
+                    short_circuit_value: self.operator() == &Operator::Or,
+                })
+            },
+            (Operator::FunctionIdentifier { identifier }, [argument]) if identifier == "if" => {
+                let (condition, then, otherwise) = argument.conditional_arguments()?;
+                Some(LazyEvaluation::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                })
+            },
+            // The body of a function definition is only evaluated when the function is called
+            (Operator::FunctionDefinition { .. }, _) => Some(LazyEvaluation::FunctionDefinition),
+            _ => None,
+        }
+    }
+
+    /// Interprets the value of an operand of `&&`, `||` or `if` as condition.
+    fn condition(&self, value: Value) -> EvalexprResult<bool> {
+        value.as_boolean().map_err(|error| self.attach_span(error))
+    }
+
+    /// Returns the function defined by this function definition node, capturing the free variables of its body that are defined in the given context.
+    #[inline(never)]
+    fn function_value<C: Context>(&self, context: &C) -> EvalexprResult<Value> {
+        let mut definition =
+            FunctionDefinition::from_node(self).map_err(|error| self.attach_span(error))?;
+        let captured: Vec<_> = definition
+            .free_variables()
+            .into_iter()
+            .filter_map(|identifier| {
+                let value = variable_value(context, &identifier).ok()?;
+                Some((identifier, value))
+            })
+            .collect();
+        definition.capture(captured);
+        Ok(Value::Function(Function::defined(definition)))
+    }
+
+    /// Returns the identifier and the definition node if this node assigns a function definition, like `f = fn(x) x * x`.
+    pub(crate) fn function_assignment(&self) -> Option<(&str, &Node)> {
+        match (self.operator(), self.children()) {
+            (Operator::Assign, [target, definition]) => {
+                match (target.operator(), definition.operator()) {
+                    (
+                        Operator::Const {
+                            value: Value::String(identifier),
+                        },
+                        Operator::FunctionDefinition { .. },
+                    ) => Some((identifier, definition)),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the condition, the then-branch and the else-branch if this node is the argument of a call to `if`.
+    pub(crate) fn conditional_arguments(&self) -> Option<(&Node, &Node, &Node)> {
+        match (self.operator(), self.children()) {
+            (Operator::RootNode, [tuple]) => match (tuple.operator(), tuple.children()) {
+                (Operator::Tuple, [condition, then, otherwise]) => {
+                    Some((condition, then, otherwise))
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Attaches the span of this node to the given error, if this node has a span.
+    fn attach_span(&self, error: EvalexprError) -> EvalexprError {
+        if let Some(span) = self.span() {
+            error.with_span(span)
+        } else {
+            error
         }
-        self.operator().eval_mut(&arguments, context)
     }
 
     /// Evaluates the operator tree rooted at this node.
@@ -231,7 +523,9 @@ impl Node {
     /// Evaluates the operator tree rooted at this node into a string with an the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_string_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_string_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<String> {
@@ -245,7 +539,9 @@ impl Node {
     /// Evaluates the operator tree rooted at this node into a float with an the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_float_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_float_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<FloatType> {
@@ -259,7 +555,9 @@ impl Node {
     /// Evaluates the operator tree rooted at this node into an integer with an the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_int_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_int_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<IntType> {
@@ -274,7 +572,9 @@ impl Node {
     /// If the result of the expression is an integer, it is silently converted into a float.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_number_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_number_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<FloatType> {
@@ -289,7 +589,9 @@ impl Node {
     /// Evaluates the operator tree rooted at this node into a boolean with an the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_boolean_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_boolean_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<bool> {
@@ -303,7 +605,9 @@ impl Node {
     /// Evaluates the operator tree rooted at this node into a tuple with an the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_tuple_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_tuple_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<TupleType> {
@@ -317,7 +621,9 @@ impl Node {
     /// Evaluates the operator tree rooted at this node into an empty value with an the given mutable context.
     ///
     /// Fails, if one of the operators in the expression tree fails.
-    pub fn eval_empty_with_context_mut<C: ContextWithMutableVariables>(
+    pub fn eval_empty_with_context_mut<
+        C: ContextWithMutableVariables + ContextWithMutableFunctions,
+    >(
         &self,
         context: &mut C,
     ) -> EvalexprResult<EmptyType> {
@@ -388,6 +694,35 @@ impl Node {
         &self.operator
     }
 
+    /// Returns the span of the input string covered by this node, including all its children.
+    ///
+    /// Returns `None` if neither this node nor any of its children were parsed from a string.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let tree = build_operator_tree("a + b * c").unwrap(); // Do proper error handling here
+    /// let span = tree.span().unwrap();
+    /// assert_eq!((span.start.column, span.end.column), (1, 10));
+    /// ```
+    pub fn span(&self) -> Option<Span> {
+        self.children
+            .iter()
+            .filter_map(Node::span)
+            .fold(self.span, |result, span| {
+                Some(result.map_or(span, |result| result.merge(span)))
+            })
+    }
+
+    /// Returns the span of the token this node was created from, excluding its children.
+    ///
+    /// Returns `None` if this node was not created from a token, like the implicit root node of an expression.
+    pub fn token_span(&self) -> Option<Span> {
+        self.span
+    }
+
     /// Returns a mutable reference to the vector containing the children of this node.
     ///
     /// WARNING: Writing to this might have unexpected results, as some operators require certain amounts and types of arguments.
@@ -414,7 +749,9 @@ impl Node {
         }
     }
 
-    fn insert_back_prioritized(&mut self, node: Node, is_root_node: bool) -> EvalexprResult<()> {
+    /// Inserts the given node into the last children of this node according to the precedences of the operators.
+    /// Returns the depth below this node of the deepest node that was inserted or moved down by the insertion.
+    fn insert_back_prioritized(&mut self, node: Node, is_root_node: bool) -> EvalexprResult<usize> {
         // println!("Inserting {:?} into {:?}", node.operator, self.operator());
         if self.operator().precedence() < node.operator().precedence() || is_root_node
             // Right-to-left chaining
@@ -438,6 +775,7 @@ impl Node {
                         .last_mut()
                         .unwrap()
                         .insert_back_prioritized(node, false)
+                        .map(|depth| depth + 1)
                 } else {
                     // println!("Rotating");
                     if node.operator().is_leaf() {
@@ -474,12 +812,12 @@ impl Node {
                         return Err(EvalexprError::MissingOperatorOutsideOfBrace);
                     }
                     node.children.push(last_child);
-                    Ok(())
+                    Ok(2)
                 }
             } else {
                 // println!("Inserting as specified");
                 self.children.push(node);
-                Ok(())
+                Ok(1)
             }
         } else {
             Err(EvalexprError::PrecedenceViolation)
@@ -557,13 +895,207 @@ fn collapse_all_sequences(root_stack: &mut Vec<Node>) -> EvalexprResult<()> {
     Ok(())
 }
 
-pub(crate) fn tokens_to_operator_tree(tokens: Vec<Token>) -> EvalexprResult<Node> {
+/// Returns true if the given closing brace token matches the given opening brace token.
+fn closes_brace(closing: &Token, opening: Option<&(Token, Span)>) -> bool {
+    matches!(
+        (opening, closing),
+        (Some((Token::LBrace, _)), Token::RBrace)
+            | (Some((Token::Fn, _)), Token::RBrace)
+            | (Some((Token::LBracket, _)), Token::RBracket)
+            | (Some((Token::LCurlyBrace, _)), Token::RCurlyBrace)
+    )
+}
+
+/// Returns true if the given root node holding the contents of brackets holds a range.
+/// If the end of the range was omitted, it is completed with an empty value.
+fn complete_range(root: &mut Node) -> bool {
+    match root.children.last_mut() {
+        Some(range) if range.operator() == &Operator::Range => {
+            if range.children().len() < 2 {
+                range
+                    .children
+                    .push(Node::new(Operator::value(Value::Empty)));
+            }
+            true
+        },
+        _ => false,
+    }
+}
+
+/// Converts the root node holding the contents of a map literal such that it holds a tuple of key-value pairs.
+/// The `span` is the span of the closing curly brace, used for errors in entries that have no span.
+fn map_literal_entries(mut root: Node, span: Span) -> EvalexprResult<Node> {
+    let entries = match root.children.pop() {
+        Some(child) if child.operator() == &Operator::Tuple => child,
+        child => {
+            let mut entries = Node::new(Operator::Tuple);
+            entries.children.extend(child);
+            entries
+        },
+    };
+
+    for entry in entries.children() {
+        let pair = match (entry.operator(), entry.children()) {
+            (Operator::RootNode, [pair]) => pair,
+            _ => entry,
+        };
+        if pair.operator() != &Operator::KeyValue {
+            return Err(EvalexprError::ExpectedKeyValuePair.with_span(entry.span().unwrap_or(span)));
+        }
+    }
+
+    root.children.push(entries);
+    Ok(root)
+}
+
+/// Returns the identifiers in the root node holding the contents of the parameter list of a function definition.
+/// The `span` is the span of the closing parenthesis, used for errors in parameters that have no span.
+fn parameter_list(mut root: Node, span: Span) -> EvalexprResult<Vec<String>> {
+    let parameters = match root.children.pop() {
+        Some(child) if child.operator() == &Operator::Tuple => child.children,
+        child => child.into_iter().collect(),
+    };
+
+    let mut identifiers: Vec<String> = Vec::new();
+    for parameter in &parameters {
+        let parameter = match (parameter.operator(), parameter.children()) {
+            (Operator::RootNode, [parameter]) => parameter,
+            _ => parameter,
+        };
+        match parameter.operator() {
+            Operator::VariableIdentifier { identifier } if !identifiers.contains(identifier) => {
+                identifiers.push(identifier.clone())
+            },
+            _ => {
+                return Err(EvalexprError::ExpectedParameterList
+                    .with_span(parameter.span().unwrap_or(span)))
+            },
+        }
+    }
+
+    Ok(identifiers)
+}
+
+/// Returns the operand that was inserted last into the operator tree that is being built on the root stack.
+///
+/// Function calls, indexing operators and map literals are returned as a whole, as they are complete operands.
+fn last_operand(root_stack: &mut [Node]) -> Option<&mut Node> {
+    let mut root = root_stack.last_mut()?;
+    if root.operator().is_sequence() {
+        root = root.children.last_mut()?;
+    }
+
+    let mut operand = root.children.last_mut()?;
+    while !operand.operator().is_leaf()
+        && !matches!(
+            operand.operator(),
+            Operator::RootNode
+                | Operator::FunctionIdentifier { .. }
+                | Operator::Index
+                | Operator::Slice
+                | Operator::Map
+        )
+    {
+        operand = operand.children.last_mut()?;
+    }
+    Some(operand)
+}
+
+/// Inserts a node created from a token with the given span into the operator tree that is being built on the root stack.
+///
+/// Returns `Err` if the nesting depth of the inserted node exceeds `max_nesting`.
+/// This is the amount of open braces and sequences plus the depth of the node within the innermost one.
+fn insert_node(
+    root_stack: &mut Vec<Node>,
+    mut node: Node,
+    span: Span,
+    max_nesting: usize,
+) -> EvalexprResult<()> {
+    node.span = Some(node.span.map_or(span, |node_span| node_span.merge(span)));
+    // Errors caused by inserting the node point to everything the node covers
+    let span = node.span().unwrap_or(span);
+    // Sequences only add to the root stack, so their depth within the innermost one is zero
+    let mut depth = 0;
+    // Need to pop and then repush here, because Rust 1.33.0 cannot release the mutable borrow of root_stack before the end of this complete if-statement
+    if let Some(mut root) = root_stack.pop() {
+        if node.operator().is_sequence() {
+            // println!("Found a sequence operator");
+            // println!("Stack before sequence operation: {:?}, {:?}", root_stack, root);
+            // If root.operator() and node.operator() are of the same variant, ...
+            if mem::discriminant(root.operator()) == mem::discriminant(node.operator()) {
+                // ... we create a new root node for the next expression in the sequence
+                root.children.push(Node::root_node());
+                root_stack.push(root);
+            } else if root.operator() == &Operator::RootNode {
+                // If the current root is an actual root node, we start a new sequence
+                // The span of an opening brace stays with the root node that remains on the stack
+                let mut new_root = Node::root_node();
+                new_root.span = root.span.take();
+                node.children.push(root);
+                node.children.push(Node::root_node());
+                root_stack.push(new_root);
+                root_stack.push(node);
+            } else {
+                // Otherwise, we combine the sequences based on their precedences
+                // TODO I'm not sure about this <, as I have no example for different sequence operators with the same precedence
+                if root.operator().precedence() < node.operator().precedence() {
+                    // If the new sequence has a higher precedence, it is part of the last element of the current root sequence
+                    if let Some(last_root_child) = root.children.pop() {
+                        node.children.push(last_root_child);
+                        node.children.push(Node::root_node());
+                        root_stack.push(root);
+                        root_stack.push(node);
+                    } else {
+                        // Once a sequence has been pushed on top of the stack, it also gets a child
+                        unreachable!()
+                    }
+                } else {
+                    // If the new sequence doesn't have a higher precedence, then all sequences with a higher precedence are collapsed below this one
+                    root = collapse_root_stack_to(root_stack, root, &node)
+                        .map_err(|error| error.with_span(span))?;
+                    node.children.push(root);
+                    root_stack.push(node);
+                }
+            }
+        // println!("Stack after sequence operation: {:?}", root_stack);
+        } else if root.operator().is_sequence() {
+            if let Some(mut last_root_child) = root.children.pop() {
+                depth = last_root_child
+                    .insert_back_prioritized(node, true)
+                    .map_err(|error| error.with_span(span))?
+                    + 1;
+                root.children.push(last_root_child);
+                root_stack.push(root);
+            } else {
+                // Once a sequence has been pushed on top of the stack, it also gets a child
+                unreachable!()
+            }
+        } else {
+            depth = root
+                .insert_back_prioritized(node, true)
+                .map_err(|error| error.with_span(span))?;
+            root_stack.push(root);
+        }
+    } else {
+        return Err(EvalexprError::UnmatchedRBrace.with_span(span));
+    }
+
+    limits::check_nesting(root_stack.len() + depth, max_nesting)
+        .map_err(|error| error.with_span(span))
+}
+
+pub(crate) fn tokens_to_operator_tree(tokens: Vec<SpannedToken>) -> EvalexprResult<Node> {
     let mut root_stack = vec![Node::root_node()];
+    // Reading the limit once keeps the nesting checks cheap
+    let max_nesting = limits::max_nesting();
+    let mut open_braces = Vec::new();
     let mut last_token_is_rightsided_value = false;
+    // The span of the keyword `fn` whose parameter list is opened by the next token
+    let mut function_definition_span = None;
     let mut token_iter = tokens.iter().peekable();
 
-    while let Some(token) = token_iter.next().cloned() {
-        let next = token_iter.peek().cloned();
+    while let Some(SpannedToken { token, span }) = token_iter.next().cloned() {
+        let next = token_iter.peek().map(|next| &next.token);
 
         let node = match token.clone() {
             Token::Plus => Some(Node::new(Operator::Add)),
@@ -576,6 +1108,7 @@ pub(crate) fn tokens_to_operator_tree(tokens: Vec<Token>) -> EvalexprResult<Node
             },
             Token::Star => Some(Node::new(Operator::Mul)),
             Token::Slash => Some(Node::new(Operator::Div)),
+            Token::DoubleSlash => Some(Node::new(Operator::FloorDiv)),
             Token::Percent => Some(Node::new(Operator::Mod)),
             Token::Hat => Some(Node::new(Operator::Exp)),
 
@@ -589,17 +1122,68 @@ pub(crate) fn tokens_to_operator_tree(tokens: Vec<Token>) -> EvalexprResult<Node
             Token::Or => Some(Node::new(Operator::Or)),
             Token::Not => Some(Node::new(Operator::Not)),
 
-            Token::LBrace => {
-                root_stack.push(Node::root_node());
-                None
+            // The root nodes for opening braces are pushed after inserting the node
+            Token::LBrace | Token::LBracket => None,
+            Token::LCurlyBrace => Some(Node::new(Operator::Map)),
+            Token::RBrace | Token::RBracket | Token::RCurlyBrace => {
+                let opening = open_braces.pop();
+                if root_stack.len() <= 1 || !closes_brace(&token, opening.as_ref()) {
+                    return Err(EvalexprError::UnmatchedRBrace.with_span(span));
+                } else {
+                    collapse_all_sequences(&mut root_stack)
+                        .map_err(|error| error.with_span(span))?;
+                    match (&token, root_stack.pop(), opening) {
+                        (Token::RCurlyBrace, Some(root), _) => {
+                            Some(map_literal_entries(root, span)?)
+                        },
+                        // The function definition is only inserted now, as its operator holds the parameters
+                        (Token::RBrace, Some(root), Some((Token::Fn, fn_span))) => {
+                            let mut node = Node::new(Operator::function_definition(
+                                parameter_list(root, span)?,
+                            ));
+                            node.span = Some(fn_span);
+                            Some(node)
+                        },
+                        // The indexing operator is only inserted now, as it depends on whether the brackets contain a range
+                        (Token::RBracket, Some(mut root), Some((_, opening_span))) => {
+                            let operator = if complete_range(&mut root) {
+                                Operator::Slice
+                            } else {
+                                Operator::Index
+                            };
+                            insert_node(
+                                &mut root_stack,
+                                Node::new(operator),
+                                opening_span,
+                                max_nesting,
+                            )?;
+                            Some(root)
+                        },
+                        (_, root, _) => root,
+                    }
+                }
             },
-            Token::RBrace => {
-                if root_stack.len() <= 1 {
-                    return Err(EvalexprError::UnmatchedRBrace);
+            Token::Colon => {
+                if matches!(open_braces.last(), Some((Token::LCurlyBrace, _))) {
+                    Some(Node::new(Operator::KeyValue))
                 } else {
-                    collapse_all_sequences(&mut root_stack)?;
-                    root_stack.pop()
+                    return Err(EvalexprError::UnexpectedKeyValuePair.with_span(span));
+                }
+            },
+            Token::DotDot => {
+                if !matches!(open_braces.last(), Some((Token::LBracket, _))) {
+                    return Err(EvalexprError::UnexpectedRange.with_span(span));
                 }
+                if !last_token_is_rightsided_value {
+                    // An omitted start of the range is represented by an empty value
+                    insert_node(
+                        &mut root_stack,
+                        Node::new(Operator::value(Value::Empty)),
+                        span,
+                        max_nesting,
+                    )?;
+                }
+                Some(Node::new(Operator::Range))
             },
 
             Token::Assign => Some(Node::new(Operator::Assign)),
@@ -607,6 +1191,7 @@ pub(crate) fn tokens_to_operator_tree(tokens: Vec<Token>) -> EvalexprResult<Node
             Token::MinusAssign => Some(Node::new(Operator::SubAssign)),
             Token::StarAssign => Some(Node::new(Operator::MulAssign)),
             Token::SlashAssign => Some(Node::new(Operator::DivAssign)),
+            Token::DoubleSlashAssign => Some(Node::new(Operator::FloorDivAssign)),
             Token::PercentAssign => Some(Node::new(Operator::ModAssign)),
             Token::HatAssign => Some(Node::new(Operator::ExpAssign)),
             Token::AndAssign => Some(Node::new(Operator::AndAssign)),
@@ -615,6 +1200,41 @@ pub(crate) fn tokens_to_operator_tree(tokens: Vec<Token>) -> EvalexprResult<Node
             Token::Comma => Some(Node::new(Operator::Tuple)),
             Token::Semicolon => Some(Node::new(Operator::Chain)),
 
+            Token::Fn => {
+                if next == Some(&Token::LBrace) {
+                    function_definition_span = Some(span);
+                    None
+                } else {
+                    return Err(EvalexprError::ExpectedParameterList.with_span(span));
+                }
+            },
+
+            // The parameters of a lambda are the last operand, which is replaced by the function definition
+            Token::Arrow => {
+                let operand = if last_token_is_rightsided_value {
+                    last_operand(&mut root_stack)
+                } else {
+                    None
+                };
+                let operand = match operand {
+                    Some(operand) => operand,
+                    None => return Err(EvalexprError::ExpectedParameterList.with_span(span)),
+                };
+                let definition_span = operand
+                    .span()
+                    .map_or(span, |operand_span| operand_span.merge(span));
+                let parameters = match operand.operator() {
+                    Operator::VariableIdentifier { identifier } => vec![identifier.clone()],
+                    Operator::RootNode => parameter_list(operand.clone(), span)?,
+                    _ => {
+                        return Err(EvalexprError::ExpectedParameterList.with_span(definition_span))
+                    },
+                };
+                *operand = Node::new(Operator::function_definition(parameters));
+                operand.span = Some(definition_span);
+                None
+            },
+
             Token::Identifier(identifier) => {
                 let mut result = Some(Node::new(Operator::variable_identifier(identifier.clone())));
                 if let Some(next) = next {
@@ -628,75 +1248,52 @@ pub(crate) fn tokens_to_operator_tree(tokens: Vec<Token>) -> EvalexprResult<Node
             },
             Token::Float(float) => Some(Node::new(Operator::value(Value::Float(float)))),
             Token::Int(int) => Some(Node::new(Operator::value(Value::Int(int)))),
+            #[cfg(feature = "decimal_support")]
+            Token::Decimal(decimal) => Some(Node::new(Operator::value(Value::Decimal(decimal)))),
+            #[cfg(feature = "bigint_support")]
+            Token::BigInt(bigint) => Some(Node::new(Operator::value(Value::BigInt(bigint)))),
             Token::Boolean(boolean) => Some(Node::new(Operator::value(Value::Boolean(boolean)))),
             Token::String(string) => Some(Node::new(Operator::value(Value::String(string)))),
         };
 
-        if let Some(mut node) = node {
-            // Need to pop and then repush here, because Rust 1.33.0 cannot release the mutable borrow of root_stack before the end of this complete if-statement
-            if let Some(mut root) = root_stack.pop() {
-                if node.operator().is_sequence() {
-                    // println!("Found a sequence operator");
-                    // println!("Stack before sequence operation: {:?}, {:?}", root_stack, root);
-                    // If root.operator() and node.operator() are of the same variant, ...
-                    if mem::discriminant(root.operator()) == mem::discriminant(node.operator()) {
-                        // ... we create a new root node for the next expression in the sequence
-                        root.children.push(Node::root_node());
-                        root_stack.push(root);
-                    } else if root.operator() == &Operator::RootNode {
-                        // If the current root is an actual root node, we start a new sequence
-                        node.children.push(root);
-                        node.children.push(Node::root_node());
-                        root_stack.push(Node::root_node());
-                        root_stack.push(node);
-                    } else {
-                        // Otherwise, we combine the sequences based on their precedences
-                        // TODO I'm not sure about this <, as I have no example for different sequence operators with the same precedence
-                        if root.operator().precedence() < node.operator().precedence() {
-                            // If the new sequence has a higher precedence, it is part of the last element of the current root sequence
-                            if let Some(last_root_child) = root.children.pop() {
-                                node.children.push(last_root_child);
-                                node.children.push(Node::root_node());
-                                root_stack.push(root);
-                                root_stack.push(node);
-                            } else {
-                                // Once a sequence has been pushed on top of the stack, it also gets a child
-                                unreachable!()
-                            }
-                        } else {
-                            // If the new sequence doesn't have a higher precedence, then all sequences with a higher precedence are collapsed below this one
-                            root = collapse_root_stack_to(&mut root_stack, root, &node)?;
-                            node.children.push(root);
-                            root_stack.push(node);
-                        }
-                    }
-                // println!("Stack after sequence operation: {:?}", root_stack);
-                } else if root.operator().is_sequence() {
-                    if let Some(mut last_root_child) = root.children.pop() {
-                        last_root_child.insert_back_prioritized(node, true)?;
-                        root.children.push(last_root_child);
-                        root_stack.push(root);
-                    } else {
-                        // Once a sequence has been pushed on top of the stack, it also gets a child
-                        unreachable!()
-                    }
-                } else {
-                    root.insert_back_prioritized(node, true)?;
-                    root_stack.push(root);
-                }
+        let is_function_definition = matches!(
+            node.as_ref().map(Node::operator),
+            Some(Operator::FunctionDefinition { .. })
+        );
+        if let Some(node) = node {
+            insert_node(&mut root_stack, node, span, max_nesting)?;
+        }
+
+        if matches!(token, Token::LBrace | Token::LBracket | Token::LCurlyBrace) {
+            let mut root = Node::root_node();
+            root.span = Some(span);
+            root_stack.push(root);
+            if let Some(fn_span) = function_definition_span.take() {
+                open_braces.push((Token::Fn, fn_span));
             } else {
-                return Err(EvalexprError::UnmatchedRBrace);
+                open_braces.push((token.clone(), span));
             }
         }
 
-        last_token_is_rightsided_value = token.is_rightsided_value();
+        // The body of a function definition starts after its parameter list
+        last_token_is_rightsided_value = token.is_rightsided_value() && !is_function_definition;
     }
 
     // In the end, all sequences are implicitly terminated
     collapse_all_sequences(&mut root_stack)?;
 
     if root_stack.len() > 1 {
-        Err(EvalexprError::UnmatchedLBrace)
+        // The innermost unmatched opening brace is the last root node that was created from a token
+        let unmatched_brace_span = root_stack
+            .iter()
+            .rev()
+            .filter(|node| node.operator() == &Operator::RootNode)
+            .find_map(|node| node.span);
+        if let Some(span) = unmatched_brace_span {
+            Err(EvalexprError::UnmatchedLBrace.with_span(span))
+        } else {
+            Err(EvalexprError::UnmatchedLBrace)
+        }
     } else if let Some(root) = root_stack.pop() {
         Ok(root)
     } else {
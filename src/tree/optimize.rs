@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+
+use crate::{
+    function::builtin::builtin_function, operator::Operator, value::Value, Context, EmptyContext,
+    IntType, Node, OverflowPolicy,
+};
+
+use super::typecheck::assignment_target;
+
+impl Node {
+    /// Optimizes the operator tree rooted at this node, assuming that it is evaluated with a context that does not override builtin functions.
+    ///
+    /// See `Node::optimize_with_context` for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let mut tree = build_operator_tree("2 * 3 + math::sqrt(4) * x").unwrap(); // Do proper error handling here
+    /// tree.optimize();
+    /// assert_eq!(tree, build_operator_tree("6 + 2.0 * x").unwrap());
+    /// ```
+    pub fn optimize(&mut self) {
+        self.optimize_with_context(&EmptyContext);
+    }
+
+    /// Optimizes the operator tree rooted at this node for evaluation with the given context.
+    ///
+    /// Subtrees that do not depend on variables are evaluated ahead of time and replaced by their result.
+    /// Function calls are only evaluated ahead of time if the context reports the function as pure with `Context::is_function_pure`,
+    /// or if the context does not define the function and it is a builtin function.
+    /// Calls are never evaluated ahead of time if the expression assigns to the identifier of the function, for example with `len = fn(x) 42`,
+    /// if it is a parameter of a function defined in the expression, or if a variable of the context with that identifier holds a function.
+    /// Subtrees whose evaluation fails are kept, such that the error is reported when evaluating the tree.
+    ///
+    /// Additionally, the following identities are applied, where `n` is a subexpression that always evaluates to a number,
+    /// `i` one that always evaluates to an integer and `b` one that always evaluates to a boolean:
+    /// `i + 0` and `0 + i` become `i`, `n - 0`, `n * 1`, `1 * n` and `n / 1` become `n`,
+    /// `b && true`, `true && b`, `b || false`, `false || b` and `!!b` become `b`,
+    /// `false && x` becomes `false`, `true || x` becomes `true`,
+    /// and `if(true, x, y)` and `if(false, y, x)` become `x`.
+    /// These identities never change the result of evaluating the tree.
+    /// In particular, adding zero is only removed from integers, as `-0.0 + 0` evaluates to `0.0`.
+    ///
+    /// The optimized tree is valid for any context that agrees with the given context on the functions that were evaluated ahead of time.
+    pub fn optimize_with_context<C: Context>(&mut self, context: &C) {
+        let mut bound_identifiers = HashSet::new();
+        self.collect_bound_identifiers(&mut bound_identifiers);
+        self.optimize_with_bound_identifiers(context, &bound_identifiers);
+    }
+
+    /// Collects the identifiers that are assigned to within the operator tree rooted at this node, as well as the parameters of function definitions.
+    /// Calls to functions with these identifiers may not call the function of the context or the builtin function.
+    fn collect_bound_identifiers(&self, bound_identifiers: &mut HashSet<String>) {
+        match (self.operator(), self.children()) {
+            (Operator::FunctionDefinition { parameters }, _) => {
+                bound_identifiers.extend(parameters.iter().cloned());
+            },
+            (Operator::Assign, [target, _]) => {
+                if let Some(identifier) = assignment_target(target) {
+                    bound_identifiers.insert(identifier.to_string());
+                }
+            },
+            _ => {},
+        }
+        for child in self.children() {
+            child.collect_bound_identifiers(bound_identifiers);
+        }
+    }
+
+    fn optimize_with_bound_identifiers<C: Context>(
+        &mut self,
+        context: &C,
+        bound_identifiers: &HashSet<String>,
+    ) {
+        for child in &mut self.children {
+            child.optimize_with_bound_identifiers(context, bound_identifiers);
+        }
+
+        if let Some(node) = self.simplified() {
+            let span = self.span();
+            *self = node;
+            self.span = span;
+        } else if self.is_foldable(context, bound_identifiers) {
+            let arguments: Vec<Value> = self
+                .children
+                .iter()
+                .filter_map(|child| child.constant_value().cloned())
+                .collect();
+            // Overflowing operations are kept, as their result depends on the overflow policy in effect when evaluating
+            let value = OverflowPolicy::Checked.enforce(|| self.operator.eval(&arguments, context));
+            if let Ok(value) = value {
+                let span = self.span();
+                *self = Node::new(Operator::value(value));
+                self.span = span;
+            }
+        }
+    }
+
+    /// Returns the value of this node if it is a constant.
+    pub(super) fn constant_value(&self) -> Option<&Value> {
+        match self.operator() {
+            Operator::Const { value } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this node can be replaced by the result of its evaluation.
+    fn is_foldable<C: Context>(&self, context: &C, bound_identifiers: &HashSet<String>) -> bool {
+        if self
+            .children()
+            .iter()
+            .any(|child| child.constant_value().is_none())
+        {
+            return false;
+        }
+
+        match self.operator() {
+            // Function definitions evaluate to function values, which are not folded into constants
+            Operator::Const { .. }
+            | Operator::VariableIdentifier { .. }
+            | Operator::FunctionDefinition { .. } => false,
+            Operator::FunctionIdentifier { identifier } => {
+                if bound_identifiers.contains(identifier)
+                    || matches!(
+                        context.get_value(identifier).as_deref(),
+                        Some(Value::Function(_))
+                    )
+                {
+                    return false;
+                }
+                match context.is_function_pure(identifier) {
+                    Some(pure) => pure,
+                    None => {
+                        builtin_function(identifier).map_or(false, |function| function.is_pure())
+                    },
+                }
+            },
+            _ => true,
+        }
+    }
+
+    /// Returns a node equivalent to this one by applying an identity, if any applies.
+    fn simplified(&self) -> Option<Node> {
+        match (self.operator(), self.children()) {
+            (Operator::Add, [a, b]) => {
+                if a.is_int(0) && b.is_integer() {
+                    Some(b.clone())
+                } else if b.is_int(0) && a.is_integer() {
+                    Some(a.clone())
+                } else {
+                    None
+                }
+            },
+            (Operator::Sub, [a, b]) | (Operator::Div, [a, b]) => {
+                let neutral = if self.operator() == &Operator::Sub {
+                    0
+                } else {
+                    1
+                };
+                if b.is_int(neutral) && a.is_numeric() {
+                    Some(a.clone())
+                } else {
+                    None
+                }
+            },
+            (Operator::Mul, [a, b]) => {
+                if a.is_int(1) && b.is_numeric() {
+                    Some(b.clone())
+                } else if b.is_int(1) && a.is_numeric() {
+                    Some(a.clone())
+                } else {
+                    None
+                }
+            },
+            (Operator::And, [a, b]) | (Operator::Or, [a, b]) => {
+                let neutral = self.operator() == &Operator::And;
+                if a.is_boolean_constant(!neutral) {
+                    // The right side is never evaluated
+                    Some(a.clone())
+                } else if a.is_boolean_constant(neutral) && b.is_boolean() {
+                    Some(b.clone())
+                } else if b.is_boolean_constant(neutral) && a.is_boolean() {
+                    Some(a.clone())
+                } else {
+                    None
+                }
+            },
+            (Operator::Not, [a]) => match (a.operator(), a.children()) {
+                (Operator::Not, [b]) if b.is_boolean() => Some(b.clone()),
+                _ => None,
+            },
+            (Operator::FunctionIdentifier { identifier }, [argument]) if identifier == "if" => {
+                let (condition, then, otherwise) = argument.conditional_arguments()?;
+                match condition.constant_value() {
+                    Some(Value::Boolean(true)) => Some(then.clone()),
+                    Some(Value::Boolean(false)) => Some(otherwise.clone()),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    fn is_int(&self, int: IntType) -> bool {
+        self.constant_value() == Some(&Value::Int(int))
+    }
+
+    fn is_boolean_constant(&self, boolean: bool) -> bool {
+        self.constant_value() == Some(&Value::Boolean(boolean))
+    }
+
+    /// Returns true if this node evaluates to a number whenever its evaluation succeeds.
+    fn is_numeric(&self) -> bool {
+        match self.operator() {
+            Operator::Const { value } => value.is_number(),
+            Operator::Sub
+            | Operator::Neg
+            | Operator::Mul
+            | Operator::Div
+            | Operator::FloorDiv
+            | Operator::Mod
+            | Operator::Exp => true,
+            // Addition only concatenates if both arguments are strings
+            Operator::Add => self.children().iter().any(Node::is_numeric),
+            Operator::RootNode => matches!(self.children(), [child] if child.is_numeric()),
+            _ => false,
+        }
+    }
+
+    /// Returns true if this node evaluates to an integer whenever its evaluation succeeds.
+    /// Integer operations that overflow under `OverflowPolicy::PromoteToFloat` evaluate to a float, which is never negative zero.
+    fn is_integer(&self) -> bool {
+        match self.operator() {
+            Operator::Const { value } => value.is_int(),
+            Operator::Add | Operator::Sub | Operator::Mul | Operator::Neg | Operator::Mod => {
+                self.children().iter().all(Node::is_integer)
+            },
+            Operator::RootNode => matches!(self.children(), [child] if child.is_integer()),
+            _ => false,
+        }
+    }
+
+    /// Returns true if this node evaluates to a boolean whenever its evaluation succeeds.
+    fn is_boolean(&self) -> bool {
+        match self.operator() {
+            Operator::Const { value } => value.is_boolean(),
+            Operator::Eq
+            | Operator::Neq
+            | Operator::Gt
+            | Operator::Lt
+            | Operator::Geq
+            | Operator::Leq
+            | Operator::And
+            | Operator::Or
+            | Operator::Not => true,
+            Operator::RootNode => matches!(self.children(), [child] if child.is_boolean()),
+            _ => false,
+        }
+    }
+}
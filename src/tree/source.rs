@@ -0,0 +1,210 @@
+use std::fmt::{Error, Formatter};
+
+use crate::{operator::Operator, value::Value, Node};
+
+impl Node {
+    /// Writes this node in the infix notation of the expression language, such that parsing the result yields an equivalent operator tree.
+    ///
+    /// Parentheses are only written where the precedences of the operators require them.
+    pub(crate) fn write_source(&self, f: &mut Formatter) -> Result<(), Error> {
+        let node = self.unwrapped();
+        let operator = node.operator();
+        let children = node.children();
+
+        match operator {
+            Operator::RootNode => write!(f, "()"),
+            Operator::Const { value } => write_value(value, f),
+            Operator::VariableIdentifier { identifier } => write!(f, "{}", identifier),
+            Operator::FunctionIdentifier { identifier } => {
+                write!(f, "{}(", identifier)?;
+                write_contents(children, f)?;
+                write!(f, ")")
+            },
+            Operator::Map => {
+                write!(f, "{{")?;
+                write_contents(children, f)?;
+                write!(f, "}}")
+            },
+            Operator::Neg | Operator::Not | Operator::FunctionDefinition { .. } => {
+                write!(f, "{}", operator)?;
+                if operator != &Operator::Neg && operator != &Operator::Not {
+                    write!(f, " ")?;
+                }
+                for child in children {
+                    // Unary operators cannot be chained without parentheses, but function definitions can
+                    write_parenthesized(child, f, |child| {
+                        child.precedence() < operator.precedence()
+                            || (child.precedence() == operator.precedence()
+                                && operator.is_left_to_right())
+                    })?;
+                }
+                Ok(())
+            },
+            Operator::Tuple | Operator::Chain | Operator::Range => {
+                let separator = if operator == &Operator::Range {
+                    "..".to_string()
+                } else {
+                    // Sequences are displayed with trailing whitespace already
+                    operator.to_string()
+                };
+                for (index, child) in children.iter().enumerate() {
+                    // Empty elements like the last one of `a;` and omitted bounds of ranges are left out
+                    if child.is_omitted() {
+                        if index > 0 {
+                            write!(f, "{}", separator.trim_end())?;
+                        }
+                    } else {
+                        if index > 0 {
+                            write!(f, "{}", separator)?;
+                        }
+                        write_parenthesized(child, f, |child| {
+                            child.precedence() <= operator.precedence()
+                        })?;
+                    }
+                }
+                Ok(())
+            },
+            Operator::Index | Operator::Slice => match children {
+                [container, key] => {
+                    write_parenthesized(container, f, |child| {
+                        child.precedence() < operator.precedence()
+                    })?;
+                    write!(f, "[")?;
+                    write_contents(std::slice::from_ref(key), f)?;
+                    write!(f, "]")
+                },
+                _ => Err(Error),
+            },
+            _ => match children {
+                [left, right] => {
+                    match (operator, left.unwrapped().operator()) {
+                        // The target of an assignment is stored as a string constant
+                        (
+                            Operator::Assign
+                            | Operator::AddAssign
+                            | Operator::SubAssign
+                            | Operator::MulAssign
+                            | Operator::DivAssign
+                            | Operator::FloorDivAssign
+                            | Operator::ModAssign
+                            | Operator::ExpAssign
+                            | Operator::AndAssign
+                            | Operator::OrAssign,
+                            Operator::Const {
+                                value: Value::String(identifier),
+                            },
+                        ) => write!(f, "{}", identifier)?,
+                        _ => write_operand(operator, left, false, f)?,
+                    }
+                    if operator == &Operator::KeyValue {
+                        write!(f, ": ")?;
+                    } else {
+                        write!(f, " {} ", operator.to_string().trim())?;
+                    }
+                    write_operand(operator, right, true, f)
+                },
+                _ => Err(Error),
+            },
+        }
+    }
+
+    /// Returns the node inside of this node, if this node is a root node with exactly one child.
+    fn unwrapped(&self) -> &Node {
+        match (self.operator(), self.children()) {
+            (Operator::RootNode, [child]) => child.unwrapped(),
+            _ => self,
+        }
+    }
+
+    /// Returns true if this node is an empty root node or an empty value, which are left out within sequences and ranges.
+    fn is_omitted(&self) -> bool {
+        match self.unwrapped().operator() {
+            Operator::RootNode => true,
+            Operator::Const { value } => value.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Returns the precedence of this node as it appears in the infix notation.
+    fn precedence(&self) -> i32 {
+        match self.unwrapped().operator() {
+            // Negative numbers are written with a leading minus
+            Operator::Const {
+                value: Value::Int(int),
+            } if *int < 0 => Operator::Neg.precedence(),
+            Operator::Const {
+                value: Value::Float(float),
+            } if float.is_sign_negative() => Operator::Neg.precedence(),
+            #[cfg(feature = "decimal_support")]
+            Operator::Const {
+                value: Value::Decimal(decimal),
+            } if decimal.is_sign_negative() => Operator::Neg.precedence(),
+            #[cfg(feature = "bigint_support")]
+            Operator::Const {
+                value: Value::BigInt(bigint),
+            } if num_traits::Signed::is_negative(bigint) => Operator::Neg.precedence(),
+            operator => operator.precedence(),
+        }
+    }
+}
+
+/// Writes the given operand of a binary operator, surrounded by parentheses if it would otherwise not be an operand of the operator when parsed.
+fn write_operand(
+    operator: &Operator,
+    operand: &Node,
+    is_right: bool,
+    f: &mut Formatter,
+) -> Result<(), Error> {
+    write_parenthesized(operand, f, |child| {
+        child.precedence() < operator.precedence()
+            || (child.precedence() == operator.precedence()
+                && is_right == operator.is_left_to_right())
+    })
+}
+
+/// Writes the given node, surrounded by parentheses if `needs_parentheses` returns true for it.
+fn write_parenthesized<F: Fn(&Node) -> bool>(
+    node: &Node,
+    f: &mut Formatter,
+    needs_parentheses: F,
+) -> Result<(), Error> {
+    if needs_parentheses(node) {
+        write!(f, "(")?;
+        node.write_source(f)?;
+        write!(f, ")")
+    } else {
+        node.write_source(f)
+    }
+}
+
+/// Writes the contents of the given root nodes without parentheses, as they are delimited by other brackets already.
+fn write_contents(children: &[Node], f: &mut Formatter) -> Result<(), Error> {
+    for child in children {
+        let child = child.unwrapped();
+        if child.operator() != &Operator::RootNode {
+            child.write_source(f)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the given value as a literal of the expression language.
+fn write_value(value: &Value, f: &mut Formatter) -> Result<(), Error> {
+    match value {
+        Value::String(string) => {
+            write!(f, "\"")?;
+            for character in string.chars() {
+                if character == '"' || character == '\\' {
+                    write!(f, "\\")?;
+                }
+                write!(f, "{}", character)?;
+            }
+            write!(f, "\"")
+        },
+        // The debug representation always contains a decimal point or an exponent
+        Value::Float(float) => write!(f, "{:?}", float),
+        #[cfg(feature = "decimal_support")]
+        Value::Decimal(decimal) => write!(f, "{}d", decimal),
+        value => write!(f, "{}", value),
+    }
+}
@@ -0,0 +1,681 @@
+use std::{collections::HashMap, mem};
+
+use crate::{
+    error::EvalexprError,
+    function::builtin::builtin_signature,
+    operator::Operator,
+    overflow::OverflowPolicy,
+    typecheck::{FunctionSignature, TypeSchema, TypeSet},
+    value::{value_type::ValueType, Value},
+    Node,
+};
+
+impl Node {
+    /// Checks the types of this operator tree against the given schema without evaluating it, and returns the possible types of its result.
+    ///
+    /// Variables and functions are looked up in the schema, and functions that are not declared there in the builtin functions.
+    /// The types of variables change with assignments, and functions defined in the expression are called with arguments of any type.
+    /// Like the variables of a `HashMapContext`, the variables declared in the schema cannot change their type,
+    /// so assigning a value that cannot have one of the declared types to them is an error.
+    /// Assignments in the branches of `if` and in the right operand of `&&` and `||` may be skipped, so they add to the possible types of a variable.
+    /// Within the body of such a function, unknown variables and functions are accepted, as they are only looked up when the function is called.
+    ///
+    /// Arithmetic operators applied to integers may result in floats if the overflow policy in effect is `OverflowPolicy::PromoteToFloat`.
+    /// Only errors that occur for all possible types of the operands are reported, so the evaluation may still fail for some values.
+    /// If there are errors, all of them are returned, wrapped into `EvalexprError::Spanned` if the nodes have spans.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use evalexpr::*;
+    ///
+    /// let mut schema = TypeSchema::new();
+    /// schema.set_variable_type("a".into(), ValueType::Int);
+    /// schema.set_variable_type("b".into(), ValueType::String);
+    ///
+    /// let tree = build_operator_tree("a * 2 + math::sqrt(a)").unwrap(); // Do proper error handling here
+    /// assert_eq!(tree.type_check(&schema), Ok(ValueType::Float.into()));
+    ///
+    /// let tree = build_operator_tree("b && true; !a; c").unwrap(); // Do proper error handling here
+    /// let errors: Vec<_> = tree.type_check(&schema).unwrap_err().into_iter().map(EvalexprError::without_span).collect();
+    /// assert_eq!(errors, vec![
+    ///     EvalexprError::TypeMismatch { expected: ValueType::Boolean.into(), actual: ValueType::String.into() },
+    ///     EvalexprError::TypeMismatch { expected: ValueType::Boolean.into(), actual: ValueType::Int.into() },
+    ///     EvalexprError::VariableIdentifierNotFound("c".to_string()),
+    /// ]);
+    /// ```
+    pub fn type_check(&self, schema: &TypeSchema) -> Result<TypeSet, Vec<EvalexprError>> {
+        let mut checker = TypeChecker {
+            variables: schema.variables.clone(),
+            declared_variables: &schema.variables,
+            functions: schema.functions.clone(),
+            function_depth: 0,
+            errors: Vec::new(),
+        };
+        let result = checker.check(self);
+
+        if checker.errors.is_empty() {
+            Ok(result)
+        } else {
+            Err(checker.errors)
+        }
+    }
+}
+
+/// The state of a type check, which is updated while walking the operator tree in the order of evaluation.
+struct TypeChecker<'a> {
+    variables: HashMap<String, TypeSet>,
+    /// The types of the variables declared in the schema, which assignments must keep.
+    declared_variables: &'a HashMap<String, TypeSet>,
+    functions: HashMap<String, FunctionSignature>,
+    /// The nesting depth of the bodies of function definitions that are currently checked.
+    function_depth: usize,
+    errors: Vec<EvalexprError>,
+}
+
+impl TypeChecker<'_> {
+    /// Reports an error located at the given node.
+    fn error(&mut self, node: &Node, error: EvalexprError) {
+        self.errors.push(match node.span() {
+            Some(span) => error.with_span(span),
+            None => error,
+        });
+    }
+
+    /// Checks the given node and returns the possible types of its result.
+    /// After reporting an error, any type is returned, such that the error does not cause further errors.
+    fn check(&mut self, node: &Node) -> TypeSet {
+        if let Some((identifier, definition)) = node.function_assignment() {
+            if let Operator::FunctionDefinition { parameters } = definition.operator() {
+                self.functions.insert(
+                    identifier.to_string(),
+                    FunctionSignature::new(vec![TypeSet::any(); parameters.len()], TypeSet::any()),
+                );
+            }
+            self.check(definition);
+            return ValueType::Empty.into();
+        }
+
+        let children = node.children();
+        match (node.operator(), children) {
+            (Operator::RootNode, children) => match children.last() {
+                Some(child) => self.check(child),
+                None => ValueType::Empty.into(),
+            },
+            (Operator::Const { value }, _) => ValueType::from(value).into(),
+            (Operator::VariableIdentifier { identifier }, _) => {
+                self.variable_type(node, identifier)
+            },
+            (Operator::FunctionIdentifier { identifier }, _) => self.check_call(node, identifier),
+            (Operator::FunctionDefinition { parameters }, body) => {
+                // Assignments within the body are local to the call
+                let variables = self.variables.clone();
+                for parameter in parameters {
+                    self.variables.insert(parameter.clone(), TypeSet::any());
+                }
+                self.function_depth += 1;
+                for child in body {
+                    self.check(child);
+                }
+                self.function_depth -= 1;
+                self.variables = variables;
+                ValueType::Function.into()
+            },
+            (Operator::Assign, [target, value]) => {
+                let value_type = self.check(value);
+                if let Some(identifier) = assignment_target(target) {
+                    let value_type = self.check_assigned_type(value, identifier, value_type);
+                    self.variables.insert(identifier.to_string(), value_type);
+                }
+                ValueType::Empty.into()
+            },
+            (operator, [target, value])
+                if matches!(
+                    operator,
+                    Operator::AddAssign
+                        | Operator::SubAssign
+                        | Operator::MulAssign
+                        | Operator::DivAssign
+                        | Operator::FloorDivAssign
+                        | Operator::ModAssign
+                        | Operator::ExpAssign
+                        | Operator::AndAssign
+                        | Operator::OrAssign
+                ) =>
+            {
+                let value_type = self.check(value);
+                if let Some(identifier) = assignment_target(target) {
+                    let target_type = self.variable_type(target, identifier);
+                    let result = self.check_binary(
+                        node,
+                        &assignment_operator(operator),
+                        (target, target_type),
+                        (value, value_type),
+                    );
+                    let result = self.check_assigned_type(node, identifier, result);
+                    self.variables.insert(identifier.to_string(), result);
+                }
+                ValueType::Empty.into()
+            },
+            (Operator::Tuple, children) => {
+                for child in children {
+                    self.check(child);
+                }
+                ValueType::Tuple.into()
+            },
+            (Operator::Chain, children) => children
+                .iter()
+                .map(|child| self.check(child))
+                .last()
+                .unwrap_or_else(|| ValueType::Empty.into()),
+            (Operator::Map, children) => {
+                for child in children {
+                    self.check(child);
+                }
+                ValueType::Map.into()
+            },
+            (Operator::KeyValue, [key, value]) => {
+                let key_type = self.check(key);
+                self.expect(key, ValueType::String.into(), key_type);
+                self.check(value);
+                ValueType::Tuple.into()
+            },
+            (Operator::Range, bounds) => {
+                let bound_types = TypeSet::from(ValueType::Int).union(ValueType::Empty.into());
+                for bound in bounds {
+                    let bound_type = self.check(bound);
+                    self.expect(bound, bound_types, bound_type);
+                }
+                ValueType::Tuple.into()
+            },
+            (Operator::Slice, [container, range]) => {
+                let sequence_types =
+                    TypeSet::from(ValueType::String).union(ValueType::Tuple.into());
+                let container_type = self.check(container);
+                self.check(range);
+                if self.expect(container, sequence_types, container_type) {
+                    container_type.intersection(sequence_types)
+                } else {
+                    TypeSet::any()
+                }
+            },
+            (Operator::Neg, [argument]) | (Operator::Not, [argument]) => {
+                let argument_type = self.check(argument);
+                let mut result: TypeSet = argument_type
+                    .iter()
+                    .filter_map(|value_type| unary_result(node.operator(), value_type))
+                    .collect();
+                if result.contains(ValueType::Int) {
+                    result = result.union(integer_result());
+                }
+                // The negation of the smallest positive big integer is an integer
+                #[cfg(feature = "bigint_support")]
+                if result.contains(ValueType::BigInt) {
+                    result = result.union(ValueType::Int.into());
+                }
+                if result.is_empty() {
+                    let expected = accepted_unary_types(node.operator());
+                    self.error(
+                        argument,
+                        EvalexprError::TypeMismatch {
+                            expected,
+                            actual: argument_type,
+                        },
+                    );
+                    TypeSet::any()
+                } else {
+                    result
+                }
+            },
+            (Operator::And, [left, right]) | (Operator::Or, [left, right]) => {
+                let left_type = self.check(left);
+                // The right operand is not evaluated after a constant that short-circuits, like in `false && x`
+                let short_circuit_value = Value::Boolean(node.operator() == &Operator::Or);
+                if left.constant_value() == Some(&short_circuit_value) {
+                    return ValueType::Boolean.into();
+                }
+                // Otherwise, the right operand may not be evaluated
+                let variables = self.variables.clone();
+                let right_type = self.check(right);
+                self.merge_variables(variables);
+                self.check_binary(
+                    node,
+                    node.operator(),
+                    (left, left_type),
+                    (right, right_type),
+                )
+            },
+            (operator, [left, right]) => {
+                let left_type = self.check(left);
+                let right_type = self.check(right);
+                self.check_binary(node, operator, (left, left_type), (right, right_type))
+            },
+            (_, children) => {
+                // Malformed nodes are reported when they are evaluated
+                for child in children {
+                    self.check(child);
+                }
+                TypeSet::any()
+            },
+        }
+    }
+
+    /// Reports an error at the given node if `actual` does not contain any of the `expected` types, and returns true if it does.
+    fn expect(&mut self, node: &Node, expected: TypeSet, actual: TypeSet) -> bool {
+        if actual.intersection(expected).is_empty() {
+            self.error(node, EvalexprError::TypeMismatch { expected, actual });
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Returns the possible types of the variable with the given identifier, reporting an error if it is unknown.
+    fn variable_type(&mut self, node: &Node, identifier: &str) -> TypeSet {
+        if let Some(variable_type) = self.variables.get(identifier) {
+            return *variable_type;
+        }
+
+        // Field accesses into maps, like `user.address.city`
+        let mut prefix = identifier;
+        while let Some(dot) = prefix.rfind('.') {
+            prefix = &prefix[..dot];
+            if let Some(variable_type) = self.variables.get(prefix).copied() {
+                self.expect(node, ValueType::Map.into(), variable_type);
+                return TypeSet::any();
+            }
+        }
+
+        if self.function_depth == 0 {
+            self.error(
+                node,
+                EvalexprError::VariableIdentifierNotFound(identifier.to_string()),
+            );
+        }
+        TypeSet::any()
+    }
+
+    /// Checks a call of the function with the given identifier, and returns the possible types of its result.
+    fn check_call(&mut self, node: &Node, identifier: &str) -> TypeSet {
+        let argument_nodes: Vec<&Node> = node
+            .children()
+            .iter()
+            .flat_map(|argument| argument_nodes(argument))
+            .collect();
+        let arguments: Vec<(&Node, TypeSet)> = match argument_nodes.as_slice() {
+            [condition, then, otherwise]
+                if identifier == "if" && !self.functions.contains_key("if") =>
+            {
+                self.check_conditional(condition, then, otherwise)
+            },
+            argument_nodes => argument_nodes
+                .iter()
+                .map(|argument| (*argument, self.check(argument)))
+                .collect(),
+        };
+
+        let signature = if let Some(signature) = self.functions.get(identifier) {
+            signature.clone()
+        } else if self
+            .variables
+            .get(identifier)
+            .map_or(false, |variable_type| {
+                variable_type.contains(ValueType::Function)
+            })
+        {
+            // Function values are not typed
+            return TypeSet::any();
+        } else if let Some(signature) = builtin_signature(identifier) {
+            signature
+        } else {
+            if self.function_depth == 0 {
+                self.error(
+                    node,
+                    EvalexprError::FunctionIdentifierNotFound(identifier.to_string()),
+                );
+            }
+            return TypeSet::any();
+        };
+
+        // Multiple arguments are passed to a function with a single parameter as one tuple, and no arguments as the empty value
+        if let Some([parameter]) = signature.parameters() {
+            if arguments.len() != 1 {
+                let value_type = if arguments.is_empty() {
+                    ValueType::Empty
+                } else {
+                    ValueType::Tuple
+                };
+                if !parameter.contains(value_type) {
+                    self.error(
+                        node,
+                        EvalexprError::wrong_function_argument_amount(arguments.len(), 1),
+                    );
+                }
+                return signature.result();
+            }
+        }
+
+        // A single tuple argument may be split into multiple arguments when the function is called
+        if let [(_, argument_type)] = arguments.as_slice() {
+            if argument_type.contains(ValueType::Tuple)
+                && signature
+                    .parameters()
+                    .map_or(true, |parameters| parameters.len() != 1)
+            {
+                return signature.result();
+            }
+        }
+
+        match signature.parameters() {
+            Some(parameters) if parameters.len() != arguments.len() => {
+                self.error(
+                    node,
+                    EvalexprError::wrong_function_argument_amount(
+                        arguments.len(),
+                        parameters.len(),
+                    ),
+                );
+                return signature.result();
+            },
+            Some(parameters) => {
+                for ((argument, argument_type), parameter) in arguments.iter().zip(parameters) {
+                    self.expect(argument, *parameter, *argument_type);
+                }
+            },
+            None => {
+                for (argument, argument_type) in &arguments {
+                    self.expect(argument, signature.parameter_types(), *argument_type);
+                }
+            },
+        }
+
+        match (identifier, arguments.as_slice()) {
+            // The conditional returns one of its branches
+            ("if", [_, (_, then), (_, otherwise)]) if !self.functions.contains_key("if") => {
+                then.union(*otherwise)
+            },
+            // `min` and `max` return one of their arguments
+            (identifier, arguments)
+                if (identifier == "min" || identifier == "max")
+                    && !arguments.is_empty()
+                    && !self.functions.contains_key(identifier) =>
+            {
+                arguments
+                    .iter()
+                    .fold(TypeSet::empty(), |result, (_, argument_type)| {
+                        result.union(*argument_type)
+                    })
+                    .intersection(signature.result())
+            },
+            _ => signature.result(),
+        }
+    }
+
+    /// Checks the arguments of the conditional `if(condition, then, otherwise)`, and returns them with their possible types.
+    /// Only one of the branches is evaluated, so the variables may have the types assigned in either branch afterwards.
+    fn check_conditional<'a>(
+        &mut self,
+        condition: &'a Node,
+        then: &'a Node,
+        otherwise: &'a Node,
+    ) -> Vec<(&'a Node, TypeSet)> {
+        let condition_type = self.check(condition);
+        let variables = self.variables.clone();
+        let then_type = self.check(then);
+        let then_variables = mem::replace(&mut self.variables, variables);
+        let otherwise_type = self.check(otherwise);
+        self.merge_variables(then_variables);
+        vec![
+            (condition, condition_type),
+            (then, then_type),
+            (otherwise, otherwise_type),
+        ]
+    }
+
+    /// Adds the types of the given variables to the possible types of the current variables,
+    /// after checking a subexpression that may or may not have been evaluated.
+    fn merge_variables(&mut self, variables: HashMap<String, TypeSet>) {
+        for (identifier, variable_type) in variables {
+            let merged = self
+                .variables
+                .get(&identifier)
+                .map_or(variable_type, |current| current.union(variable_type));
+            self.variables.insert(identifier, merged);
+        }
+    }
+
+    /// Checks that a value of the given types can be assigned to the variable with the given identifier, and returns the types the variable may have afterwards.
+    /// Variables declared in the schema keep their type, while other variables and the local variables of functions take the type of the value.
+    fn check_assigned_type(
+        &mut self,
+        node: &Node,
+        identifier: &str,
+        value_type: TypeSet,
+    ) -> TypeSet {
+        let declared_type = match self.declared_variables.get(identifier) {
+            Some(declared_type) if self.function_depth == 0 => *declared_type,
+            _ => return value_type,
+        };
+        let assigned_type = value_type.intersection(declared_type);
+        if assigned_type.is_empty() {
+            self.error(
+                node,
+                EvalexprError::TypeMismatch {
+                    expected: declared_type,
+                    actual: value_type,
+                },
+            );
+            declared_type
+        } else {
+            assigned_type
+        }
+    }
+
+    /// Checks a binary operator with the given operands, and returns the possible types of its result.
+    fn check_binary(
+        &mut self,
+        node: &Node,
+        operator: &Operator,
+        (left, left_type): (&Node, TypeSet),
+        (right, right_type): (&Node, TypeSet),
+    ) -> TypeSet {
+        let (accepted_left, accepted_right) = accepted_binary_types(operator);
+        let left_accepted = self.expect(left, accepted_left, left_type);
+        let right_accepted = self.expect(right, accepted_right, right_type);
+        if !left_accepted || !right_accepted {
+            return TypeSet::any();
+        }
+
+        let left_type = left_type.intersection(accepted_left);
+        let right_type = right_type.intersection(accepted_right);
+        let mut result = TypeSet::empty();
+        for left_value_type in left_type.iter() {
+            for right_value_type in right_type.iter() {
+                if let Some(value_type) = binary_result(operator, left_value_type, right_value_type)
+                {
+                    result = result.union(value_type);
+                }
+            }
+        }
+
+        if result.is_empty() {
+            // Cannot fail, as the types were checked to be accepted
+            let actual = vec![
+                left_type.iter().next().unwrap(),
+                right_type.iter().next().unwrap(),
+            ];
+            self.error(
+                node,
+                EvalexprError::wrong_type_combination(operator.clone(), actual),
+            );
+            TypeSet::any()
+        } else {
+            result
+        }
+    }
+}
+
+/// Returns the identifier of the target of an assignment, which is stored as a string constant.
+pub(super) fn assignment_target(target: &Node) -> Option<&str> {
+    match target.operator() {
+        Operator::Const {
+            value: Value::String(identifier),
+        } => Some(identifier),
+        _ => None,
+    }
+}
+
+/// Returns the nodes of the arguments within the root node of a function call.
+/// Only the parentheses of the call itself are unwrapped, so a parenthesized tuple like in `len((1, 2))` is a single argument.
+fn argument_nodes(argument: &Node) -> Vec<&Node> {
+    match (argument.operator(), argument.children()) {
+        (Operator::RootNode, []) => Vec::new(),
+        (Operator::RootNode, [argument]) => match argument.operator() {
+            Operator::Tuple => argument.children().iter().collect(),
+            _ => vec![argument],
+        },
+        (Operator::Tuple, arguments) => arguments.iter().collect(),
+        _ => vec![argument],
+    }
+}
+
+/// Returns the binary operator applied by an assignment operator like `+=`.
+fn assignment_operator(operator: &Operator) -> Operator {
+    match operator {
+        Operator::AddAssign => Operator::Add,
+        Operator::SubAssign => Operator::Sub,
+        Operator::MulAssign => Operator::Mul,
+        Operator::DivAssign => Operator::Div,
+        Operator::FloorDivAssign => Operator::FloorDiv,
+        Operator::ModAssign => Operator::Mod,
+        Operator::ExpAssign => Operator::Exp,
+        Operator::AndAssign => Operator::And,
+        Operator::OrAssign => Operator::Or,
+        operator => operator.clone(),
+    }
+}
+
+/// Returns the possible types of the result of an arithmetic operator applied to integers, which depend on the current overflow policy.
+fn integer_result() -> TypeSet {
+    match OverflowPolicy::current() {
+        OverflowPolicy::PromoteToFloat => TypeSet::number(),
+        #[cfg(feature = "bigint_support")]
+        OverflowPolicy::PromoteToBigInt => big_integer_result(),
+        _ => ValueType::Int.into(),
+    }
+}
+
+/// Returns the possible types of the result of an arithmetic operator applied to big integers, which are integers if the result is small enough.
+#[cfg(feature = "bigint_support")]
+fn big_integer_result() -> TypeSet {
+    TypeSet::from(ValueType::Int).union(ValueType::BigInt.into())
+}
+
+/// Returns true if values of the given type are numbers,
+/// which includes decimals with the `decimal_support` feature flag and big integers with the `bigint_support` feature flag.
+fn is_number(value_type: ValueType) -> bool {
+    match value_type {
+        ValueType::Int | ValueType::Float => true,
+        #[cfg(feature = "decimal_support")]
+        ValueType::Decimal => true,
+        #[cfg(feature = "bigint_support")]
+        ValueType::BigInt => true,
+        _ => false,
+    }
+}
+
+/// Returns true if values of the given type are integers or big integers.
+#[cfg(feature = "bigint_support")]
+fn is_integer(value_type: ValueType) -> bool {
+    value_type == ValueType::Int || value_type == ValueType::BigInt
+}
+
+/// Returns the type of the result of an arithmetic operation on two numbers that are not both integers or big integers.
+/// Floats take precedence over decimals, which take precedence over integers.
+/// Big integers combined with decimals are computed as floats.
+fn mixed_number_result(left: ValueType, right: ValueType) -> ValueType {
+    match (left, right) {
+        (ValueType::Float, _) | (_, ValueType::Float) => ValueType::Float,
+        #[cfg(feature = "bigint_support")]
+        (ValueType::BigInt, _) | (_, ValueType::BigInt) => ValueType::Float,
+        (ValueType::Int, other) | (other, _) => other,
+    }
+}
+
+/// Returns the type of the result of a unary operator applied to a value of the given type, or `None` if this fails.
+fn unary_result(operator: &Operator, value_type: ValueType) -> Option<ValueType> {
+    match (operator, value_type) {
+        (Operator::Neg, value_type) if is_number(value_type) => Some(value_type),
+        (Operator::Not, ValueType::Boolean) => Some(ValueType::Boolean),
+        _ => None,
+    }
+}
+
+/// Returns the types accepted by a unary operator.
+fn accepted_unary_types(operator: &Operator) -> TypeSet {
+    TypeSet::any()
+        .iter()
+        .filter(|value_type| unary_result(operator, *value_type).is_some())
+        .collect()
+}
+
+/// Returns the types accepted by a binary operator as its left and right operands, with any type as the other operand.
+fn accepted_binary_types(operator: &Operator) -> (TypeSet, TypeSet) {
+    let mut accepted_left = TypeSet::empty();
+    let mut accepted_right = TypeSet::empty();
+    for left in TypeSet::any().iter() {
+        for right in TypeSet::any().iter() {
+            if binary_result(operator, left, right).is_some() {
+                accepted_left = accepted_left.union(left.into());
+                accepted_right = accepted_right.union(right.into());
+            }
+        }
+    }
+    (accepted_left, accepted_right)
+}
+
+/// Returns the type of the result of a binary operator applied to values of the given types, or `None` if this fails.
+fn binary_result(operator: &Operator, left: ValueType, right: ValueType) -> Option<TypeSet> {
+    use crate::ValueType::*;
+
+    let is_arithmetic = matches!(
+        operator,
+        Operator::Add
+            | Operator::Sub
+            | Operator::Mul
+            | Operator::Div
+            | Operator::FloorDiv
+            | Operator::Mod
+    );
+    let is_comparison = matches!(
+        operator,
+        Operator::Gt | Operator::Lt | Operator::Geq | Operator::Leq
+    );
+    let result: TypeSet = match (operator, left, right) {
+        (Operator::Add, String, String) => String.into(),
+        (_, Int, Int) if is_arithmetic => integer_result(),
+        #[cfg(feature = "bigint_support")]
+        (_, left, right) if is_arithmetic && is_integer(left) && is_integer(right) => {
+            big_integer_result()
+        },
+        (_, left, right) if is_arithmetic && is_number(left) && is_number(right) => {
+            mixed_number_result(left, right).into()
+        },
+        // Integer powers are only integers if the exponent is not negative
+        (Operator::Exp, Int, Int) => integer_result().union(Float.into()),
+        #[cfg(feature = "bigint_support")]
+        (Operator::Exp, BigInt, Int) => big_integer_result().union(Float.into()),
+        #[cfg(feature = "decimal_support")]
+        (Operator::Exp, Decimal, Int) => Decimal.into(),
+        (Operator::Exp, left, right) if is_number(left) && is_number(right) => Float.into(),
+        (Operator::Eq, _, _) | (Operator::Neq, _, _) => Boolean.into(),
+        (_, String, String) if is_comparison => Boolean.into(),
+        (_, left, right) if is_comparison && is_number(left) && is_number(right) => Boolean.into(),
+        (Operator::And, Boolean, Boolean) | (Operator::Or, Boolean, Boolean) => Boolean.into(),
+        // The elements of tuples and the values of maps are not typed
+        (Operator::Index, Tuple, Int) | (Operator::Index, Map, String) => TypeSet::any(),
+        (Operator::Index, String, Int) => String.into(),
+        _ => return None,
+    };
+    Some(result)
+}
@@ -0,0 +1,302 @@
+//! The `typecheck` module contains the types used to check the types of expressions before evaluating them.
+//!
+//! The type checker itself is implemented by `Node::type_check`.
+
+use std::{collections::HashMap, fmt};
+
+use crate::value::value_type::ValueType;
+
+/// All value types, in the order of their bits within a `TypeSet`.
+const VALUE_TYPES: &[ValueType] = &[
+    ValueType::String,
+    ValueType::Float,
+    ValueType::Int,
+    ValueType::Boolean,
+    ValueType::Tuple,
+    ValueType::Map,
+    ValueType::Function,
+    ValueType::Empty,
+    #[cfg(feature = "decimal_support")]
+    ValueType::Decimal,
+    #[cfg(feature = "bigint_support")]
+    ValueType::BigInt,
+];
+
+/// A set of value types, which describes the possible types of the result of an expression.
+///
+/// For example, `if(a, 1, 2.0)` has the type set `int | float`.
+/// The type set containing all value types is used if nothing is known about a value.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let number = TypeSet::from(ValueType::Int).union(ValueType::Float.into());
+/// assert_eq!(number, TypeSet::number());
+/// assert!(number.contains(ValueType::Float));
+/// assert!(!number.contains(ValueType::String));
+/// assert_eq!(number.to_string(), "float | int");
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct TypeSet {
+    bits: u16,
+}
+
+impl TypeSet {
+    /// Returns the type set that does not contain any type.
+    pub const fn empty() -> Self {
+        Self { bits: 0 }
+    }
+
+    /// Returns the type set that contains all types.
+    pub const fn any() -> Self {
+        Self {
+            bits: (1 << VALUE_TYPES.len()) - 1,
+        }
+    }
+
+    /// Returns the type set containing `ValueType::Int` and `ValueType::Float`.
+    pub fn number() -> Self {
+        TypeSet::from(ValueType::Int).union(ValueType::Float.into())
+    }
+
+    /// Returns true if this set contains the given type.
+    pub fn contains(self, value_type: ValueType) -> bool {
+        !self.intersection(value_type.into()).is_empty()
+    }
+
+    /// Returns the set of the types contained in this or the other set.
+    pub const fn union(self, other: TypeSet) -> Self {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+
+    /// Returns the set of the types contained in both this and the other set.
+    pub const fn intersection(self, other: TypeSet) -> Self {
+        Self {
+            bits: self.bits & other.bits,
+        }
+    }
+
+    /// Returns true if this set does not contain any type.
+    pub const fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    /// Returns true if this set contains all types.
+    pub const fn is_any(self) -> bool {
+        self.bits == TypeSet::any().bits
+    }
+
+    /// Returns the only type contained in this set, or `None` if it contains no or multiple types.
+    pub fn single(self) -> Option<ValueType> {
+        let mut types = self.iter();
+        match (types.next(), types.next()) {
+            (Some(value_type), None) => Some(value_type),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the types contained in this set.
+    pub fn iter(self) -> impl Iterator<Item = ValueType> {
+        VALUE_TYPES
+            .iter()
+            .copied()
+            .filter(move |value_type| self.contains(*value_type))
+    }
+}
+
+impl From<ValueType> for TypeSet {
+    fn from(value_type: ValueType) -> Self {
+        let bit = match value_type {
+            ValueType::String => 0,
+            ValueType::Float => 1,
+            ValueType::Int => 2,
+            ValueType::Boolean => 3,
+            ValueType::Tuple => 4,
+            ValueType::Map => 5,
+            ValueType::Function => 6,
+            ValueType::Empty => 7,
+            #[cfg(feature = "decimal_support")]
+            ValueType::Decimal => 8,
+            // The last bit, which is bit 8 if decimals are not enabled
+            #[cfg(feature = "bigint_support")]
+            ValueType::BigInt => VALUE_TYPES.len() - 1,
+        };
+        Self { bits: 1 << bit }
+    }
+}
+
+impl std::iter::FromIterator<ValueType> for TypeSet {
+    fn from_iter<I: IntoIterator<Item = ValueType>>(iter: I) -> Self {
+        iter.into_iter().fold(TypeSet::empty(), |set, value_type| {
+            set.union(value_type.into())
+        })
+    }
+}
+
+/// Type sets are displayed as the names of their types as returned by the builtin function `typeof`, separated by `|`.
+/// The set of all types is displayed as `any`.
+impl fmt::Display for TypeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.is_any() {
+            return write!(f, "any");
+        }
+        if self.is_empty() {
+            return write!(f, "none");
+        }
+
+        for (index, value_type) in self.iter().enumerate() {
+            if index > 0 {
+                write!(f, " | ")?;
+            }
+            let name = match value_type {
+                ValueType::String => "string",
+                ValueType::Float => "float",
+                ValueType::Int => "int",
+                ValueType::Boolean => "boolean",
+                ValueType::Tuple => "tuple",
+                ValueType::Map => "map",
+                ValueType::Function => "function",
+                ValueType::Empty => "empty",
+                #[cfg(feature = "decimal_support")]
+                ValueType::Decimal => "decimal",
+                #[cfg(feature = "bigint_support")]
+                ValueType::BigInt => "bigint",
+            };
+            write!(f, "{}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The parameter types and the result type of a function, as used by the type checker.
+///
+/// Like when calling a function, a single parameter receives the whole argument, while multiple parameters receive the elements of a tuple.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionSignature {
+    parameters: Parameters,
+    result: TypeSet,
+}
+
+/// The parameters of a function signature.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Parameters {
+    /// A fixed amount of parameters with the given types.
+    Fixed(Vec<TypeSet>),
+    /// Any amount of parameters with the same type.
+    Variadic(TypeSet),
+}
+
+impl FunctionSignature {
+    /// Creates the signature of a function with the given parameter types and result type.
+    pub fn new(parameters: Vec<TypeSet>, result: TypeSet) -> Self {
+        Self {
+            parameters: Parameters::Fixed(parameters),
+            result,
+        }
+    }
+
+    /// Creates the signature of a function that takes any amount of arguments of the given type.
+    pub fn variadic(parameter: TypeSet, result: TypeSet) -> Self {
+        Self {
+            parameters: Parameters::Variadic(parameter),
+            result,
+        }
+    }
+
+    /// Returns the types of the parameters, or `None` if the function takes any amount of arguments.
+    pub fn parameters(&self) -> Option<&[TypeSet]> {
+        match &self.parameters {
+            Parameters::Fixed(parameters) => Some(parameters),
+            Parameters::Variadic(_) => None,
+        }
+    }
+
+    /// Returns the type that each argument may have.
+    /// For functions with a fixed amount of parameters, this is the union of the parameter types.
+    pub fn parameter_types(&self) -> TypeSet {
+        match &self.parameters {
+            Parameters::Fixed(parameters) => parameters
+                .iter()
+                .fold(TypeSet::empty(), |set, parameter| set.union(*parameter)),
+            Parameters::Variadic(parameter) => *parameter,
+        }
+    }
+
+    /// Returns the type of the result.
+    pub fn result(&self) -> TypeSet {
+        self.result
+    }
+}
+
+/// Signatures are displayed like `(float | int, int) -> string`, or `(any...) -> string` if the function takes any amount of arguments.
+impl fmt::Display for FunctionSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "(")?;
+        match &self.parameters {
+            Parameters::Fixed(parameters) => {
+                for (index, parameter) in parameters.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", parameter)?;
+                }
+            },
+            Parameters::Variadic(parameter) => write!(f, "{}...", parameter)?,
+        }
+        write!(f, ") -> {}", self.result)
+    }
+}
+
+/// The types of the variables and the signatures of the functions that an expression is checked against by `Node::type_check`.
+///
+/// # Examples
+///
+/// ```rust
+/// use evalexpr::*;
+///
+/// let mut schema = TypeSchema::new();
+/// schema.set_variable_type("price".into(), ValueType::Float);
+/// schema.set_function_signature(
+///     "tax".into(),
+///     FunctionSignature::new(vec![TypeSet::number()], ValueType::Float.into()),
+/// );
+///
+/// let tree = build_operator_tree("price + tax(price)").unwrap(); // Do proper error handling here
+/// assert_eq!(tree.type_check(&schema), Ok(ValueType::Float.into()));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TypeSchema {
+    pub(crate) variables: HashMap<String, TypeSet>,
+    pub(crate) functions: HashMap<String, FunctionSignature>,
+}
+
+impl TypeSchema {
+    /// Creates a schema without any variables or functions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the types the variable with the given identifier may have.
+    pub fn set_variable_type<T: Into<TypeSet>>(&mut self, identifier: String, types: T) {
+        self.variables.insert(identifier, types.into());
+    }
+
+    /// Declares the signature of the function with the given identifier.
+    pub fn set_function_signature(&mut self, identifier: String, signature: FunctionSignature) {
+        self.functions.insert(identifier, signature);
+    }
+
+    /// Returns the declared types of the variable with the given identifier.
+    pub fn variable_type(&self, identifier: &str) -> Option<TypeSet> {
+        self.variables.get(identifier).copied()
+    }
+
+    /// Returns the declared signature of the function with the given identifier.
+    pub fn function_signature(&self, identifier: &str) -> Option<&FunctionSignature> {
+        self.functions.get(identifier)
+    }
+}
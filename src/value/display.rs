@@ -8,6 +8,10 @@ impl Display for Value {
             Value::String(string) => write!(f, "\"{}\"", string),
             Value::Float(float) => write!(f, "{}", float),
             Value::Int(int) => write!(f, "{}", int),
+            #[cfg(feature = "decimal_support")]
+            Value::Decimal(decimal) => write!(f, "{}", decimal),
+            #[cfg(feature = "bigint_support")]
+            Value::BigInt(bigint) => write!(f, "{}", bigint),
             Value::Boolean(boolean) => write!(f, "{}", boolean),
             Value::Tuple(tuple) => {
                 write!(f, "(")?;
@@ -22,6 +26,21 @@ impl Display for Value {
                 }
                 write!(f, ")")
             },
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                let mut once = false;
+                for (key, value) in map {
+                    if once {
+                        write!(f, ", ")?;
+                    } else {
+                        once = true;
+                    }
+                    write!(f, "\"{}\": ", key)?;
+                    value.fmt(f)?;
+                }
+                write!(f, "}}")
+            },
+            Value::Function(function) => function.fmt(f),
             Value::Empty => write!(f, "()"),
         }
     }
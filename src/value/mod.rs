@@ -1,4 +1,8 @@
-use crate::error::{EvalexprError, EvalexprResult};
+use crate::{
+    error::{EvalexprError, EvalexprResult},
+    function::Function,
+};
+use std::collections::BTreeMap;
 
 mod display;
 pub mod value_type;
@@ -9,9 +13,23 @@ pub type IntType = i64;
 /// The type used to represent floats in `Value::Float`.
 pub type FloatType = f64;
 
+/// The type used to represent decimals in `Value::Decimal`.
+/// Requires the `decimal_support` feature flag.
+#[cfg(feature = "decimal_support")]
+pub type DecimalType = rust_decimal::Decimal;
+
+/// The type used to represent big integers in `Value::BigInt`.
+/// Requires the `bigint_support` feature flag.
+#[cfg(feature = "bigint_support")]
+pub type BigIntType = num_bigint::BigInt;
+
 /// The type used to represent tuples in `Value::Tuple`.
 pub type TupleType = Vec<Value>;
 
+/// The type used to represent maps in `Value::Map`.
+/// Maps are ordered by their keys, such that iterating over them is deterministic.
+pub type MapType = BTreeMap<String, Value>;
+
 /// The type used to represent empty values in `Value::Empty`.
 pub type EmptyType = ();
 
@@ -29,10 +47,30 @@ pub enum Value {
     Float(FloatType),
     /// An integer value.
     Int(IntType),
+    /// A decimal value, which represents decimal fractions like `0.1` exactly.
+    /// Requires the `decimal_support` feature flag.
+    #[cfg(feature = "decimal_support")]
+    Decimal(DecimalType),
+    /// An integer value that does not fit into an `IntType`.
+    /// Requires the `bigint_support` feature flag.
+    #[cfg(feature = "bigint_support")]
+    BigInt(
+        #[cfg_attr(
+            feature = "serde_support",
+            serde(with = "crate::feature_serde::bigint")
+        )]
+        BigIntType,
+    ),
     /// A boolean value.
     Boolean(bool),
     /// A tuple value.
     Tuple(TupleType),
+    /// A map value.
+    Map(MapType),
+    /// A function value, as created by a function definition or lambda expression.
+    /// Function values cannot be serialized.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    Function(Function),
     /// An empty value.
     Empty,
 }
@@ -52,9 +90,28 @@ impl Value {
         matches!(self, Value::Float(_))
     }
 
-    /// Returns true if `self` is a `Value::Int` or `Value::Float`.
+    /// Returns true if `self` is a `Value::Decimal`.
+    #[cfg(feature = "decimal_support")]
+    pub fn is_decimal(&self) -> bool {
+        matches!(self, Value::Decimal(_))
+    }
+
+    /// Returns true if `self` is a `Value::BigInt`.
+    #[cfg(feature = "bigint_support")]
+    pub fn is_bigint(&self) -> bool {
+        matches!(self, Value::BigInt(_))
+    }
+
+    /// Returns true if `self` is a `Value::Int`, `Value::Float`, `Value::Decimal` or `Value::BigInt`.
     pub fn is_number(&self) -> bool {
-        matches!(self, Value::Int(_) | Value::Float(_))
+        match self {
+            Value::Int(_) | Value::Float(_) => true,
+            #[cfg(feature = "decimal_support")]
+            Value::Decimal(_) => true,
+            #[cfg(feature = "bigint_support")]
+            Value::BigInt(_) => true,
+            _ => false,
+        }
     }
 
     /// Returns true if `self` is a `Value::Boolean`.
@@ -67,6 +124,16 @@ impl Value {
         matches!(self, Value::Tuple(_))
     }
 
+    /// Returns true if `self` is a `Value::Map`.
+    pub fn is_map(&self) -> bool {
+        matches!(self, Value::Map(_))
+    }
+
+    /// Returns true if `self` is a `Value::Function`.
+    pub fn is_function(&self) -> bool {
+        matches!(self, Value::Function(_))
+    }
+
     /// Returns true if `self` is a `Value::Empty`.
     pub fn is_empty(&self) -> bool {
         matches!(self, Value::Empty)
@@ -96,12 +163,34 @@ impl Value {
         }
     }
 
-    /// Clones the value stored in  `self` as `FloatType`, or returns `Err` if `self` is not a `Value::Float` or `Value::Int`.
-    /// Note that this method silently converts `IntType` to `FloatType`, if `self` is a `Value::Int`.
+    /// Clones the value stored in `self` as `DecimalType`, or returns `Err` if `self` is not a `Value::Decimal`.
+    #[cfg(feature = "decimal_support")]
+    pub fn as_decimal(&self) -> EvalexprResult<DecimalType> {
+        match self {
+            Value::Decimal(decimal) => Ok(*decimal),
+            value => Err(EvalexprError::expected_decimal(value.clone())),
+        }
+    }
+
+    /// Clones the value stored in `self` as `BigIntType`, or returns `Err` if `self` is not a `Value::BigInt`.
+    #[cfg(feature = "bigint_support")]
+    pub fn as_bigint(&self) -> EvalexprResult<BigIntType> {
+        match self {
+            Value::BigInt(bigint) => Ok(bigint.clone()),
+            value => Err(EvalexprError::expected_bigint(value.clone())),
+        }
+    }
+
+    /// Clones the value stored in  `self` as `FloatType`, or returns `Err` if `self` is not a `Value::Float`, `Value::Int`, `Value::Decimal` or `Value::BigInt`.
+    /// Note that this method silently converts the other numeric types to `FloatType`, which may lose precision.
     pub fn as_number(&self) -> EvalexprResult<FloatType> {
         match self {
             Value::Float(f) => Ok(*f),
             Value::Int(i) => Ok(*i as FloatType),
+            #[cfg(feature = "decimal_support")]
+            Value::Decimal(decimal) => Ok(crate::decimal::to_float(*decimal)),
+            #[cfg(feature = "bigint_support")]
+            Value::BigInt(bigint) => Ok(crate::bigint::to_float(bigint)),
             value => Err(EvalexprError::expected_number(value.clone())),
         }
     }
@@ -136,6 +225,22 @@ impl Value {
         }
     }
 
+    /// Clones the value stored in `self` as `MapType`, or returns `Err` if `self` is not a `Value::Map`.
+    pub fn as_map(&self) -> EvalexprResult<MapType> {
+        match self {
+            Value::Map(map) => Ok(map.clone()),
+            value => Err(EvalexprError::expected_map(value.clone())),
+        }
+    }
+
+    /// Clones the value stored in `self` as `Function`, or returns `Err` if `self` is not a `Value::Function`.
+    pub fn as_function(&self) -> EvalexprResult<Function> {
+        match self {
+            Value::Function(function) => Ok(function.clone()),
+            value => Err(EvalexprError::expected_function(value.clone())),
+        }
+    }
+
     /// Returns `()`, or returns`Err` if `self` is not a `Value::Tuple`.
     pub fn as_empty(&self) -> EvalexprResult<()> {
         match self {
@@ -169,18 +274,44 @@ impl From<IntType> for Value {
     }
 }
 
+#[cfg(feature = "decimal_support")]
+impl From<DecimalType> for Value {
+    fn from(decimal: DecimalType) -> Self {
+        Value::Decimal(decimal)
+    }
+}
+
+#[cfg(feature = "bigint_support")]
+impl From<BigIntType> for Value {
+    fn from(bigint: BigIntType) -> Self {
+        Value::BigInt(bigint)
+    }
+}
+
 impl From<bool> for Value {
     fn from(boolean: bool) -> Self {
         Value::Boolean(boolean)
     }
 }
 
+impl From<Function> for Value {
+    fn from(function: Function) -> Self {
+        Value::Function(function)
+    }
+}
+
 impl From<TupleType> for Value {
     fn from(tuple: TupleType) -> Self {
         Value::Tuple(tuple)
     }
 }
 
+impl From<MapType> for Value {
+    fn from(map: MapType) -> Self {
+        Value::Map(map)
+    }
+}
+
 impl From<Value> for EvalexprResult<Value> {
     fn from(value: Value) -> Self {
         Ok(value)
@@ -195,7 +326,7 @@ impl From<()> for Value {
 
 #[cfg(test)]
 mod tests {
-    use crate::value::{TupleType, Value};
+    use crate::value::{MapType, TupleType, Value};
 
     #[test]
     fn test_value_conversions() {
@@ -210,6 +341,7 @@ mod tests {
             Value::from(TupleType::new()).as_tuple(),
             Ok(TupleType::new())
         );
+        assert_eq!(Value::from(MapType::new()).as_map(), Ok(MapType::new()));
     }
 
     #[test]
@@ -219,5 +351,6 @@ mod tests {
         assert!(Value::from(3.3).is_float());
         assert!(Value::from(true).is_boolean());
         assert!(Value::from(TupleType::new()).is_tuple());
+        assert!(Value::from(MapType::new()).is_map());
     }
 }
@@ -9,10 +9,22 @@ pub enum ValueType {
     Float,
     /// The `Value::Int` type.
     Int,
+    /// The `Value::Decimal` type.
+    /// Requires the `decimal_support` feature flag.
+    #[cfg(feature = "decimal_support")]
+    Decimal,
+    /// The `Value::BigInt` type.
+    /// Requires the `bigint_support` feature flag.
+    #[cfg(feature = "bigint_support")]
+    BigInt,
     /// The `Value::Boolean` type.
     Boolean,
     /// The `Value::Tuple` type.
     Tuple,
+    /// The `Value::Map` type.
+    Map,
+    /// The `Value::Function` type.
+    Function,
     /// The `Value::Empty` type.
     Empty,
 }
@@ -23,8 +35,14 @@ impl From<&Value> for ValueType {
             Value::String(_) => ValueType::String,
             Value::Float(_) => ValueType::Float,
             Value::Int(_) => ValueType::Int,
+            #[cfg(feature = "decimal_support")]
+            Value::Decimal(_) => ValueType::Decimal,
+            #[cfg(feature = "bigint_support")]
+            Value::BigInt(_) => ValueType::BigInt,
             Value::Boolean(_) => ValueType::Boolean,
             Value::Tuple(_) => ValueType::Tuple,
+            Value::Map(_) => ValueType::Map,
+            Value::Function(_) => ValueType::Function,
             Value::Empty => ValueType::Empty,
         }
     }
@@ -1,6 +1,13 @@
 #![cfg(not(tarpaulin_include))]
 
 use evalexpr::{error::*, *};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant},
+};
 
 #[test]
 fn test_unary_examples() {
@@ -87,10 +94,12 @@ fn test_mod_examples() {
 
 #[test]
 fn test_pow_examples() {
-    assert_eq!(eval("1 ^ 4"), Ok(Value::Float(1.0)));
-    assert_eq!(eval("6 ^ 4"), Ok(Value::Float(6.0f64.powf(4.0))));
-    assert_eq!(eval("1 ^ 4 + 2"), Ok(Value::Float(3.0)));
-    assert_eq!(eval("2 ^ (4 + 2)"), Ok(Value::Float(64.0)));
+    assert_eq!(eval("1 ^ 4"), Ok(Value::Int(1)));
+    assert_eq!(eval("6 ^ 4"), Ok(Value::Int(1296)));
+    assert_eq!(eval("1 ^ 4 + 2"), Ok(Value::Int(3)));
+    assert_eq!(eval("2 ^ (4 + 2)"), Ok(Value::Int(64)));
+    assert_eq!(eval("6.0 ^ 4"), Ok(Value::Float(6.0f64.powf(4.0))));
+    assert_eq!(eval("2 ^ (-2)"), Ok(Value::Float(0.25)));
 }
 
 #[test]
@@ -383,6 +392,11 @@ fn test_builtin_functions() {
     assert_eq!(eval("typeof()"), Ok(Value::String("empty".into())));
     assert_eq!(eval("min(4.0, 3)"), Ok(Value::Int(3)));
     assert_eq!(eval("max(4.0, 3)"), Ok(Value::Float(4.0)));
+    // Equal integers and floats result in the float
+    assert_eq!(eval("min(1, 1.0)"), Ok(Value::Float(1.0)));
+    assert_eq!(eval("min(1.0, 1)"), Ok(Value::Float(1.0)));
+    assert_eq!(eval("max(2, 1, 2.0)"), Ok(Value::Float(2.0)));
+    assert_eq!(eval("max(2.0, 2)"), Ok(Value::Float(2.0)));
     assert_eq!(eval("len(\"foobar\")"), Ok(Value::Int(6)));
     assert_eq!(eval("len(\"a\", \"b\")"), Ok(Value::Int(2)));
     // String
@@ -461,6 +475,7 @@ fn test_errors() {
 }
 
 #[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn test_no_panic() {
     assert!(eval(&format!(
         "{} + {}",
@@ -488,7 +503,8 @@ fn test_no_panic() {
         IntType::max_value(),
         IntType::max_value()
     ))
-    .is_ok());
+    .is_err());
+    assert!(eval(&format!("{} // {}", IntType::max_value(), 0)).is_err());
     assert!(eval("if").is_err());
     assert!(eval("if()").is_err());
     assert!(eval("if(true, 1)").is_err());
@@ -512,8 +528,10 @@ fn test_shortcut_functions() {
         })
     );
     assert_eq!(
-        eval_string("3..3"),
-        Err(EvalexprError::VariableIdentifierNotFound("3..3".to_owned()))
+        eval_string("3.3.3"),
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "3.3.3".to_owned()
+        ))
     );
     assert_eq!(
         eval_string_with_context("string", &context),
@@ -526,8 +544,10 @@ fn test_shortcut_functions() {
         })
     );
     assert_eq!(
-        eval_string_with_context("3..3", &context),
-        Err(EvalexprError::VariableIdentifierNotFound("3..3".to_owned()))
+        eval_string_with_context("3.3.3", &context),
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "3.3.3".to_owned()
+        ))
     );
     assert_eq!(
         eval_string_with_context_mut("string", &mut context),
@@ -540,8 +560,10 @@ fn test_shortcut_functions() {
         })
     );
     assert_eq!(
-        eval_string_with_context_mut("3..3", &mut context),
-        Err(EvalexprError::VariableIdentifierNotFound("3..3".to_owned()))
+        eval_string_with_context_mut("3.3.3", &mut context),
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "3.3.3".to_owned()
+        ))
     );
 
     assert_eq!(eval_float("3.3"), Ok(3.3));
@@ -787,8 +809,10 @@ fn test_shortcut_functions() {
         })
     );
     assert_eq!(
-        build_operator_tree("3..3").unwrap().eval_string(),
-        Err(EvalexprError::VariableIdentifierNotFound("3..3".to_owned()))
+        build_operator_tree("3.3.3").unwrap().eval_string(),
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "3.3.3".to_owned()
+        ))
     );
     assert_eq!(
         build_operator_tree("string")
@@ -805,10 +829,12 @@ fn test_shortcut_functions() {
         })
     );
     assert_eq!(
-        build_operator_tree("3..3")
+        build_operator_tree("3.3.3")
             .unwrap()
             .eval_string_with_context(&context),
-        Err(EvalexprError::VariableIdentifierNotFound("3..3".to_owned()))
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "3.3.3".to_owned()
+        ))
     );
     assert_eq!(
         build_operator_tree("string")
@@ -825,10 +851,12 @@ fn test_shortcut_functions() {
         })
     );
     assert_eq!(
-        build_operator_tree("3..3")
+        build_operator_tree("3.3.3")
             .unwrap()
             .eval_string_with_context_mut(&mut context),
-        Err(EvalexprError::VariableIdentifierNotFound("3..3".to_owned()))
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "3.3.3".to_owned()
+        ))
     );
 
     assert_eq!(build_operator_tree("3.3").unwrap().eval_float(), Ok(3.3));
@@ -1545,7 +1573,7 @@ fn test_hashmap_context_clone_debug() {
 
     assert_eq!(format!("{:?}", &context), format!("{:?}", &cloned_context));
     assert_eq!(
-        cloned_context.get_value("variable_five"),
+        cloned_context.get_value("variable_five").as_deref(),
         Some(&Value::from(5))
     );
     assert_eq!(
@@ -1876,3 +1904,3345 @@ fn test_parenthese_combinations() {
     assert_eq!(eval_int("(((1+2)*(3+4)+(5-(6)))/((7-8)))"), Ok(-20));
     assert_eq!(eval_int("(((((5)))))"), Ok(5));
 }
+
+#[test]
+fn test_spans() {
+    fn spanned_text(string: &str, span: Option<Span>) -> &str {
+        let span = span.unwrap();
+        &string[span.start.byte..span.end.byte]
+    }
+
+    let string = "a = 1;\nb = (a + \"ä\") * 2";
+    let tree = build_operator_tree(string).unwrap();
+    assert_eq!(spanned_text(string, tree.span()), string);
+    let span = tree.span().unwrap();
+    assert_eq!(span.start, Position::start());
+    assert_eq!(
+        span.end,
+        Position {
+            byte: string.len(),
+            line: 2,
+            column: 18
+        }
+    );
+
+    let error = tree
+        .eval_spanned_with_context_mut(&mut HashMapContext::new())
+        .unwrap_err();
+    assert_eq!(spanned_text(string, error.span()), "a + \"ä\"");
+    assert_eq!(error.span().unwrap().start.line, 2);
+    assert_eq!(
+        error.without_span(),
+        EvalexprError::wrong_type_combination(
+            Operator::Add,
+            vec![ValueType::Int, ValueType::String]
+        )
+    );
+    // The non-spanned evaluation methods return plain errors
+    assert_eq!(
+        tree.eval_with_context_mut(&mut HashMapContext::new()),
+        Err(EvalexprError::wrong_type_combination(
+            Operator::Add,
+            vec![ValueType::Int, ValueType::String]
+        ))
+    );
+
+    let string = "f(x) + y";
+    let error = build_operator_tree(string)
+        .unwrap()
+        .eval_spanned_with_context(&context_map! {"x" => 1, "y" => 2}.unwrap())
+        .unwrap_err();
+    assert_eq!(spanned_text(string, error.span()), "f(x)");
+    assert_eq!(
+        error.without_span(),
+        EvalexprError::FunctionIdentifierNotFound("f".to_string())
+    );
+
+    for (string, expected_text, expected_error) in [
+        ("1 + (2 * 3", "(", EvalexprError::UnmatchedLBrace),
+        ("(1, (2, 3)", "(", EvalexprError::UnmatchedLBrace),
+        ("1 + 2) * 3", ")", EvalexprError::UnmatchedRBrace),
+        (
+            "1 + 2 & 3",
+            "&",
+            EvalexprError::UnmatchedPartialToken {
+                first: PartialToken::Ampersand,
+                second: Some(PartialToken::Whitespace),
+            },
+        ),
+        (
+            "\"a\\b\"",
+            "\\b",
+            EvalexprError::IllegalEscapeSequence("\\b".to_string()),
+        ),
+        (
+            "123(1*2)",
+            "(1*2)",
+            EvalexprError::MissingOperatorOutsideOfBrace,
+        ),
+    ] {
+        let error = build_operator_tree_spanned(string).unwrap_err();
+        assert_eq!(spanned_text(string, error.span()), expected_text);
+        assert_eq!(error.without_span(), expected_error);
+        assert_eq!(build_operator_tree(string), Err(expected_error));
+    }
+}
+
+#[test]
+fn test_short_circuit_evaluation() {
+    let context = context_map! {
+        "x" => 0,
+        "fail" => Function::new(|_| Err(EvalexprError::CustomMessage("called".to_string()))),
+    }
+    .unwrap();
+
+    assert_eq!(
+        eval_boolean_with_context("false && fail()", &context),
+        Ok(false)
+    );
+    assert_eq!(
+        eval_boolean_with_context("true || fail()", &context),
+        Ok(true)
+    );
+    assert_eq!(
+        eval_boolean_with_context("true && fail()", &context),
+        Err(EvalexprError::CustomMessage("called".to_string()))
+    );
+    assert_eq!(
+        eval_boolean_with_context("false || fail()", &context),
+        Err(EvalexprError::CustomMessage("called".to_string()))
+    );
+    assert_eq!(
+        eval_boolean_with_context("x != 0 && 10 / x > 2", &context),
+        Ok(false)
+    );
+    assert_eq!(
+        eval_boolean_with_context("x == 0 || 10 / x > 2", &context),
+        Ok(true)
+    );
+    assert_eq!(
+        eval_int_with_context("if(x == 0, 0, 10 / x)", &context),
+        Ok(0)
+    );
+    assert_eq!(
+        eval_int_with_context("if(x != 0, fail(), 1 + 1)", &context),
+        Ok(2)
+    );
+
+    // The types of evaluated arguments are still checked
+    assert_eq!(
+        eval_boolean_with_context("1 && fail()", &context),
+        Err(EvalexprError::expected_boolean(Value::Int(1)))
+    );
+    assert_eq!(
+        eval_boolean_with_context("true && 1", &context),
+        Err(EvalexprError::expected_boolean(Value::Int(1)))
+    );
+    assert_eq!(
+        eval_with_context("if(1, 2, 3)", &context),
+        Err(EvalexprError::expected_boolean(Value::Int(1)))
+    );
+
+    // Assignments in branches that are not taken do not run
+    let mut context = HashMapContext::new();
+    assert_eq!(
+        eval_with_context_mut("a = 1; false && (a = 2) == (); a", &mut context),
+        Ok(Value::Int(1))
+    );
+    assert_eq!(
+        eval_with_context_mut("true || (a = 3) == (); a", &mut context),
+        Ok(Value::Int(1))
+    );
+    assert_eq!(
+        eval_with_context_mut("if(a == 1, a = 4, a = 5); a", &mut context),
+        Ok(Value::Int(4))
+    );
+}
+
+#[test]
+fn test_compiled_expressions() {
+    let expressions = [
+        "1 + 2 * 3 - 4 / 5 % 6 ^ 2",
+        "-(a + b) * c",
+        "a > b || b > c && !(c == 3)",
+        "if(a < b, \"less\", \"not less\")",
+        "(a, (b, c), ())",
+        "min(a, b, c) + max(a, b, c) + len(\"abc\")",
+        "a == b; a != b; a;",
+        "a = 5; b += a; c *= b; (a, b, c)",
+        "d = \"abc\"; d += \"def\"; str::to_uppercase d",
+        "if(true, x, y)",
+        "false && undefined || true",
+        "(((((5)))))",
+        "",
+    ];
+
+    for expression in &expressions {
+        let tree = build_operator_tree(expression).unwrap();
+        let compiled = tree.compile();
+        let context = context_map! { "a" => 1, "b" => 2, "c" => 3, "x" => 4.5 }.unwrap();
+        assert_eq!(
+            compiled.eval_with_context(&context),
+            tree.eval_with_context(&context),
+            "{}",
+            expression
+        );
+
+        let mut tree_context = context.clone();
+        let mut compiled_context = context.clone();
+        assert_eq!(
+            compiled.eval_with_context_mut(&mut compiled_context),
+            tree.eval_with_context_mut(&mut tree_context),
+            "{}",
+            expression
+        );
+        for identifier in &["a", "b", "c", "d"] {
+            assert_eq!(
+                compiled_context.get_value(identifier),
+                tree_context.get_value(identifier)
+            );
+        }
+    }
+
+    let compiled = build_operator_tree("x != 0 && 10 / x > 2; y")
+        .unwrap()
+        .compile();
+    assert_eq!(
+        compiled.variable_identifiers(),
+        &["x".to_string(), "y".to_string()]
+    );
+    assert_eq!(compiled.variable_slot("y"), Some(1));
+    assert_eq!(compiled.variable_slot("z"), None);
+    assert_eq!(
+        compiled.eval_with_slots(&[0.into(), "y".into()], &EmptyContext),
+        Ok(Value::from("y"))
+    );
+    assert_eq!(
+        compiled.eval_with_slots(&[0.into()], &EmptyContext),
+        Err(EvalexprError::VariableIdentifierNotFound("y".to_string()))
+    );
+    assert_eq!(
+        build_operator_tree("a = 1")
+            .unwrap()
+            .compile()
+            .eval_with_slots(&[], &EmptyContext),
+        Err(EvalexprError::ContextNotMutable)
+    );
+}
+
+#[test]
+fn test_optimize() {
+    fn optimized(expression: &str) -> Node {
+        let mut tree = build_operator_tree(expression).unwrap();
+        tree.optimize();
+        tree
+    }
+
+    for (expression, expected) in [
+        ("2 * 3 + x * 1 - 0", "6 + x * 1"),
+        ("(9223372036854775807 + 1) + 0", "(9223372036854775807 + 1)"),
+        ("x * 2 * 1 - 0", "x * 2"),
+        ("(1 + 2) * (x + 0.5)", "3 * (x + 0.5)"),
+        ("math::sqrt(4) + x", "2.0 + x"),
+        ("str::to_uppercase(\"abc\") + x", "\"ABC\" + x"),
+        ("len((1, 2, 3)) + x", "3 + x"),
+        ("true && x > 2", "x > 2"),
+        ("!!(x > 2)", "(x > 2)"),
+        ("if(1 < 2, x, f(x))", "(x)"),
+        ("if(x, 1 + 1, 2 * 2)", "if(x, 2, 4)"),
+        ("a = 2 + 3", "a = 5"),
+    ] {
+        // The expected trees are already optimal, apart from constants wrapped in root nodes
+        assert_eq!(optimized(expression), optimized(expected), "{}", expression);
+    }
+
+    for (expression, expected) in [
+        ("1 + 2 * 3", Value::from(7)),
+        ("false && x", Value::from(false)),
+        ("true || f(x)", Value::from(true)),
+        ("if(true, 3, y) + 1", Value::from(4)),
+    ] {
+        let tree = optimized(expression);
+        assert_eq!(
+            tree.operator(),
+            &Operator::Const { value: expected },
+            "{}",
+            expression
+        );
+        assert!(tree.children().is_empty());
+    }
+
+    // Identities that depend on the type of variables are not applied
+    for expression in [
+        "x + 0",
+        "x * 2 + 0",
+        "x * 1",
+        "true && x",
+        "!!x",
+        "x && false",
+        "1 / 0 + x",
+    ] {
+        assert_eq!(
+            optimized(expression),
+            build_operator_tree(expression).unwrap(),
+            "{}",
+            expression
+        );
+    }
+    assert_eq!(optimized("1 / 0").eval(), eval("1 / 0"));
+
+    // Only pure functions are evaluated ahead of time
+    let context = context_map! {
+        "pure" => Function::new_pure(|argument| Ok(Value::from(argument.as_int()? * 2))),
+        "impure" => Function::new(|argument| Ok(Value::from(argument.as_int()? * 2))),
+        "math::sqrt" => Function::new(|_| Ok(Value::from(0))),
+    }
+    .unwrap();
+    let mut tree = build_operator_tree("pure(2) + impure(2) + math::sqrt(4)").unwrap();
+    tree.optimize_with_context(&context);
+    let mut expected = build_operator_tree("4 + impure(2) + math::sqrt(4)").unwrap();
+    expected.optimize_with_context(&context);
+    assert_eq!(tree, expected);
+    assert_eq!(tree.eval_with_context(&context), Ok(Value::from(8)));
+    let mut tree = build_operator_tree("math::sqrt(4)").unwrap();
+    tree.optimize_with_context(&context);
+    assert_eq!(tree.eval_with_context(&context), Ok(Value::from(0)));
+
+    // Functions rebound by the expression or held by variables are not evaluated ahead of time
+    let mut context = context.clone();
+    context
+        .set_value(
+            "str::trim".into(),
+            Value::from(Function::new(|_| Ok(Value::from(1)))),
+        )
+        .unwrap();
+    for expression in [
+        "len = fn(x) 42; len(\"abc\")",
+        "typeof = fn(x) 1; typeof(2)",
+        "len = x -> 42; len(\"abc\")",
+        "pure = fn(x) 0; pure(2)",
+        "apply = fn(len) len(\"abc\"); apply(x -> 42)",
+        "str::trim(\" a \")",
+    ] {
+        let tree = build_operator_tree(expression).unwrap();
+        let mut optimized_tree = tree.clone();
+        optimized_tree.optimize_with_context(&context);
+        assert_eq!(
+            optimized_tree.eval_with_context_mut(&mut context.clone()),
+            tree.eval_with_context_mut(&mut context.clone()),
+            "{}",
+            expression
+        );
+    }
+}
+
+#[test]
+fn test_maps() {
+    let mut user = MapType::new();
+    user.insert("name".to_string(), Value::from("Alice"));
+    user.insert("age".to_string(), Value::from(32));
+    let mut address = MapType::new();
+    address.insert("city".to_string(), Value::from("Berlin"));
+    user.insert("address".to_string(), Value::from(address));
+    let user = Value::from(user);
+
+    let mut context = HashMapContext::new();
+    context.set_value("user".into(), user.clone()).unwrap();
+    context
+        .set_value("config.debug".into(), Value::from(true))
+        .unwrap();
+
+    // Literals
+    assert_eq!(
+        eval("{\"name\": \"Alice\", \"age\": 30 + 2, \"address\": {\"city\": \"Berl\" + \"in\"}}"),
+        Ok(user.clone())
+    );
+    assert_eq!(eval("{}"), Ok(Value::from(MapType::new())));
+    assert_eq!(eval("len({\"a\": 1})"), Ok(Value::from(1)));
+    assert_eq!(eval("{\"a\": 1, \"a\": 2}[\"a\"]"), Ok(Value::from(2)));
+    assert_eq!(
+        eval("{\"a\": (1, 2)}[\"a\"]"),
+        Ok(Value::from(vec![Value::from(1), Value::from(2)]))
+    );
+    assert_eq!(eval("x = {\"a\": 2}; x[\"a\"] * 3"), Ok(Value::from(6)));
+
+    // Field access
+    assert_eq!(
+        eval_with_context("user.name", &context),
+        Ok(Value::from("Alice"))
+    );
+    assert_eq!(
+        eval_with_context("user.address.city", &context),
+        Ok(Value::from("Berlin"))
+    );
+    assert_eq!(
+        eval_with_context("user[\"age\"] + 1", &context),
+        Ok(Value::from(33))
+    );
+    assert_eq!(
+        eval_with_context(
+            "-user[\"address\"][\"ci\" + \"ty\"] == \"Berlin\"",
+            &context
+        ),
+        Err(EvalexprError::expected_number(Value::from("Berlin")))
+    );
+    assert_eq!(
+        eval_with_context("user[\"address\"][\"city\"] == \"Berlin\"", &context),
+        Ok(Value::from(true))
+    );
+    assert_eq!(
+        eval_with_context("config.debug", &context),
+        Ok(Value::from(true))
+    );
+    assert_eq!(
+        eval_with_context("user.email", &context),
+        Err(EvalexprError::KeyNotFound("email".to_string()))
+    );
+    assert_eq!(
+        eval_with_context("user[\"email\"]", &context),
+        Err(EvalexprError::KeyNotFound("email".to_string()))
+    );
+    assert_eq!(
+        eval_with_context("user.name.first", &context),
+        Err(EvalexprError::expected_map(Value::from("Alice")))
+    );
+    assert_eq!(
+        eval_with_context("user[1]", &context),
+        Err(EvalexprError::expected_string(Value::from(1)))
+    );
+    assert_eq!(
+        eval_with_context("other.name", &context),
+        Err(EvalexprError::VariableIdentifierNotFound(
+            "other.name".to_string()
+        ))
+    );
+    assert_eq!(
+        eval("(1, 2)[\"a\"]"),
+        Err(EvalexprError::expected_int(Value::from("a")))
+    );
+
+    // Builtins
+    assert_eq!(
+        eval_with_context("keys(user)", &context),
+        Ok(Value::from(vec![
+            Value::from("address"),
+            Value::from("age"),
+            Value::from("name")
+        ]))
+    );
+    assert_eq!(
+        eval("values({\"b\": 2, \"a\": 1})"),
+        Ok(Value::from(vec![Value::from(1), Value::from(2)]))
+    );
+    assert_eq!(
+        eval_with_context("contains_key(user, \"age\")", &context),
+        Ok(Value::from(true))
+    );
+    assert_eq!(
+        eval_with_context("len(user) + len(keys(user.address))", &context),
+        Ok(Value::from(4))
+    );
+    assert_eq!(
+        eval_with_context("typeof(user)", &context),
+        Ok(Value::from("map"))
+    );
+    assert_eq!(eval("math::sqrt(4)"), Ok(Value::from(2.0)));
+
+    // Equality
+    assert_eq!(
+        eval_with_context(
+            "user == {\"name\": \"Alice\", \"age\": 32, \"address\": {\"city\": \"Berlin\"}}",
+            &context
+        ),
+        Ok(Value::from(true))
+    );
+    assert_eq!(eval("{\"a\": 1} != {\"a\": 1.0}"), Ok(Value::from(true)));
+
+    // Syntax errors
+    assert_eq!(
+        build_operator_tree("{\"a\": 1, 2}"),
+        Err(EvalexprError::ExpectedKeyValuePair)
+    );
+    assert_eq!(
+        build_operator_tree("\"a\": 1"),
+        Err(EvalexprError::UnexpectedKeyValuePair)
+    );
+    assert_eq!(
+        build_operator_tree("{\"a\": (\"b\": 1)}"),
+        Err(EvalexprError::UnexpectedKeyValuePair)
+    );
+    assert_eq!(
+        build_operator_tree("{\"a\": 1)"),
+        Err(EvalexprError::UnmatchedRBrace)
+    );
+    assert_eq!(
+        build_operator_tree("user[\"a\""),
+        Err(EvalexprError::UnmatchedLBrace)
+    );
+    assert_eq!(
+        eval("{1: 2}"),
+        Err(EvalexprError::expected_string(Value::from(1)))
+    );
+
+    // Compiled and optimized expressions
+    let mut tree =
+        build_operator_tree("{\"age\": user.age + 1, \"debug\": !config.debug}[\"age\"]").unwrap();
+    assert_eq!(
+        tree.compile().eval_with_context(&context),
+        Ok(Value::from(33))
+    );
+    tree.optimize();
+    assert_eq!(tree.eval_with_context(&context), Ok(Value::from(33)));
+    let mut tree = build_operator_tree("{\"a\": 1 + 1}").unwrap();
+    tree.optimize();
+    assert_eq!(
+        tree.operator(),
+        &Operator::Const {
+            value: eval("{\"a\": 2}").unwrap()
+        }
+    );
+
+    // Display
+    assert_eq!(
+        user.to_string(),
+        "{\"address\": {\"city\": \"Berlin\"}, \"age\": 32, \"name\": \"Alice\"}"
+    );
+    assert_eq!(
+        context.get_value("user").as_deref().map(ValueType::from),
+        Some(ValueType::Map)
+    );
+}
+
+#[test]
+fn test_indexing_and_slicing() {
+    let context = context_map! {
+        "t" => Value::from(vec![Value::from(1), Value::from("b"), Value::from(3.5), Value::from(4)]),
+        "s" => "häll\u{f6}",
+        "i" => 1,
+    }
+    .unwrap();
+    let tuple = |values: Vec<Value>| Value::from(values);
+
+    // Indexing
+    assert_eq!(eval_with_context("t[0]", &context), Ok(Value::from(1)));
+    assert_eq!(
+        eval_with_context("t[i + 1]", &context),
+        Ok(Value::from(3.5))
+    );
+    assert_eq!(eval_with_context("t[-1]", &context), Ok(Value::from(4)));
+    assert_eq!(eval_with_context("t[-4]", &context), Ok(Value::from(1)));
+    assert_eq!(eval_with_context("s[1]", &context), Ok(Value::from("ä")));
+    assert_eq!(eval_with_context("s[-1]", &context), Ok(Value::from("ö")));
+    assert_eq!(eval("(1, (2, 3))[1][0]"), Ok(Value::from(2)));
+    assert_eq!(eval("-(1, 2)[1] * 2"), Ok(Value::from(-4)));
+    assert_eq!(eval("\"abc\"[1] + \"d\""), Ok(Value::from("bd")));
+    assert_eq!(eval("str::to_uppercase(\"abc\")[2]"), Ok(Value::from("C")));
+    assert_eq!(eval("{\"a\": (1, 2)}[\"a\"][1]"), Ok(Value::from(2)));
+
+    // Slicing
+    assert_eq!(
+        eval_with_context("t[1..3]", &context),
+        Ok(tuple(vec![Value::from("b"), Value::from(3.5)]))
+    );
+    assert_eq!(
+        eval_with_context("t[i..i + 1]", &context),
+        Ok(tuple(vec![Value::from("b")]))
+    );
+    assert_eq!(
+        eval_with_context("t[2..]", &context),
+        Ok(tuple(vec![Value::from(3.5), Value::from(4)]))
+    );
+    assert_eq!(
+        eval_with_context("t[..-3]", &context),
+        Ok(tuple(vec![Value::from(1)]))
+    );
+    assert_eq!(
+        eval_with_context("t[-2..-1]", &context),
+        Ok(tuple(vec![Value::from(3.5)]))
+    );
+    assert_eq!(
+        eval_with_context("t[..]", &context),
+        Ok(context.get_value("t").unwrap().into_owned())
+    );
+    assert_eq!(eval_with_context("t[2..2]", &context), Ok(tuple(vec![])));
+    assert_eq!(
+        eval_with_context("s[1..4]", &context),
+        Ok(Value::from("äll"))
+    );
+    assert_eq!(
+        eval_with_context("s[-2..]", &context),
+        Ok(Value::from("lö"))
+    );
+    assert_eq!(
+        eval_with_context("len(s[..3])", &context),
+        Ok(Value::from(4))
+    );
+    assert_eq!(eval("\"abc\"[1..][1..]"), Ok(Value::from("c")));
+
+    // Errors
+    assert_eq!(
+        eval_with_context("t[4]", &context),
+        Err(EvalexprError::IndexOutOfBounds { index: 4, len: 4 })
+    );
+    assert_eq!(
+        eval_with_context("t[-5]", &context),
+        Err(EvalexprError::IndexOutOfBounds { index: -5, len: 4 })
+    );
+    assert_eq!(
+        eval_with_context("s[5]", &context),
+        Err(EvalexprError::IndexOutOfBounds { index: 5, len: 5 })
+    );
+    assert_eq!(
+        eval_with_context("t[2..5]", &context),
+        Err(EvalexprError::SliceOutOfBounds {
+            start: 2,
+            end: 5,
+            len: 4
+        })
+    );
+    assert_eq!(
+        eval_with_context("t[3..1]", &context),
+        Err(EvalexprError::SliceOutOfBounds {
+            start: 3,
+            end: 1,
+            len: 4
+        })
+    );
+    assert_eq!(
+        eval_with_context("s[-6..]", &context),
+        Err(EvalexprError::SliceOutOfBounds {
+            start: -6,
+            end: 5,
+            len: 5
+        })
+    );
+    assert_eq!(
+        eval_with_context("t[1.0]", &context),
+        Err(EvalexprError::expected_int(Value::from(1.0)))
+    );
+    assert_eq!(
+        eval_with_context("t[\"a\"..]", &context),
+        Err(EvalexprError::type_error(
+            Value::from("a"),
+            vec![ValueType::Int, ValueType::Empty]
+        ))
+    );
+    assert_eq!(
+        eval("5[0]"),
+        Err(EvalexprError::type_error(
+            Value::from(5),
+            vec![ValueType::String, ValueType::Tuple, ValueType::Map]
+        ))
+    );
+    assert_eq!(
+        eval("{}[0..]"),
+        Err(EvalexprError::type_error(
+            Value::from(MapType::new()),
+            vec![ValueType::String, ValueType::Tuple]
+        ))
+    );
+    assert_eq!(
+        build_operator_tree("1..2"),
+        Err(EvalexprError::UnexpectedRange)
+    );
+    assert_eq!(
+        build_operator_tree("t[(1..2)]"),
+        Err(EvalexprError::UnexpectedRange)
+    );
+    assert_eq!(
+        build_operator_tree("t[1)"),
+        Err(EvalexprError::UnmatchedRBrace)
+    );
+    assert_eq!(
+        build_operator_tree("t[1"),
+        Err(EvalexprError::UnmatchedLBrace)
+    );
+
+    // Spans
+    let error = build_operator_tree_spanned("t[1]; (2 + 3..4)").unwrap_err();
+    assert_eq!(error.span().map(|span| span.start.column), Some(13));
+    let tree = build_operator_tree("t[1..3] + t[0]").unwrap();
+    let error = tree
+        .eval_spanned_with_context(
+            &context_map! { "t" => Value::from(vec![Value::from(1)]) }.unwrap(),
+        )
+        .unwrap_err();
+    let span = error.span().unwrap();
+    assert_eq!(&"t[1..3] + t[0]"[span.start.byte..span.end.byte], "t[1..3]");
+
+    // Compiled and optimized expressions
+    let compiled = build_operator_tree("t[1..][i] * 2 + len(s[..1])")
+        .unwrap()
+        .compile();
+    assert_eq!(compiled.eval_with_context(&context), Ok(Value::from(8.0)));
+    let mut tree = build_operator_tree("(1, 2, 3)[1..][-1] + x").unwrap();
+    tree.optimize();
+    assert_eq!(tree, {
+        let mut expected = build_operator_tree("3 + x").unwrap();
+        expected.optimize();
+        expected
+    });
+}
+
+#[test]
+fn test_function_definitions() {
+    let mut context = HashMapContext::new();
+    assert_eq!(
+        eval_with_context_mut("square = fn(x) x * x; square(4)", &mut context),
+        Ok(Value::from(16))
+    );
+    assert_eq!(
+        eval_with_context_mut("square(1.5) + square(2)", &mut context),
+        Ok(Value::from(6.25))
+    );
+    assert_eq!(
+        eval_with_context_mut("add = fn(a, b) a + b; add(2, square(3))", &mut context),
+        Ok(Value::from(11))
+    );
+    assert_eq!(
+        eval_with_context_mut("answer = fn() 42; answer()", &mut context),
+        Ok(Value::from(42))
+    );
+    assert_eq!(
+        eval_with_context_mut("pair = fn(t) (t, len(t)); pair(1, 2)", &mut context),
+        Ok(Value::from(vec![
+            Value::from(vec![Value::from(1), Value::from(2)]),
+            Value::from(2)
+        ]))
+    );
+
+    // Recursion
+    assert_eq!(
+        eval_with_context_mut(
+            "fact = fn(n) if(n <= 1, 1, n * fact(n - 1)); fact(10)",
+            &mut context
+        ),
+        Ok(Value::from(3_628_800))
+    );
+    assert_eq!(
+        eval_with_context_mut("forever = fn(n) forever(n + 1); forever(0)", &mut context),
+        Err(EvalexprError::RecursionLimitExceeded { limit: 100 })
+    );
+    assert_eq!(
+        eval_with_context_mut("fact(5)", &mut context),
+        Ok(Value::from(120))
+    );
+
+    // Parameters and assignments within the body are local to the call
+    context.set_value("x".into(), 10.into()).unwrap();
+    assert_eq!(
+        eval_with_context_mut(
+            "shadow = fn(x) (y = x * 2; y + 1); shadow(3) + x",
+            &mut context
+        ),
+        Ok(Value::from(17))
+    );
+    assert_eq!(
+        eval_with_context("y", &context),
+        Err(EvalexprError::VariableIdentifierNotFound("y".to_string()))
+    );
+    assert_eq!(
+        eval_with_context_mut("offset = fn(a) a + x; offset(1)", &mut context),
+        Ok(Value::from(11))
+    );
+    assert_eq!(
+        eval_with_context_mut("nested = fn(a) inner = fn(b) b; nested(1)", &mut context),
+        Err(EvalexprError::ContextNotMutable)
+    );
+
+    // Arguments
+    assert_eq!(
+        eval_with_context_mut("add(1)", &mut context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 2,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        eval_with_context_mut("add(1, 2, 3)", &mut context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 2,
+            actual: 3
+        })
+    );
+    assert_eq!(
+        eval_with_context_mut("answer(1)", &mut context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 0,
+            actual: 1
+        })
+    );
+
+    // Errors
+    assert_eq!(eval("f = fn(x) x; f(1)"), Ok(Value::from(1)));
+    assert_eq!(
+        eval_with_context("f = fn(x) x", &context),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert_eq!(
+        eval("fn(x) x"),
+        Ok(Value::Function(
+            Function::from_definition("fn(x) x").unwrap()
+        ))
+    );
+    assert_eq!(eval("f = (fn(x) x); f(2)"), Ok(Value::from(2)));
+    assert_eq!(
+        build_operator_tree("fn x"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    assert_eq!(
+        build_operator_tree("fn(x, 1) x"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    assert_eq!(
+        build_operator_tree("fn(x, x) x"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    let error = build_operator_tree_spanned("f = fn(a, b + 1) a").unwrap_err();
+    let span = error.span().unwrap();
+    assert_eq!(
+        &"f = fn(a, b + 1) a"[span.start.byte..span.end.byte],
+        "b + 1"
+    );
+
+    // Compiled expressions
+    let mut context = HashMapContext::new();
+    let compiled = build_operator_tree("double = fn(x) 2 * x; double(double(y))")
+        .unwrap()
+        .compile();
+    context.set_value("y".into(), 3.into()).unwrap();
+    assert_eq!(
+        compiled.eval_with_context_mut(&mut context),
+        Ok(Value::from(12))
+    );
+    assert_eq!(
+        compiled.eval_with_slots(&[3.into()], &context),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert_eq!(
+        build_operator_tree("fn(x) x")
+            .unwrap()
+            .compile()
+            .eval_with_context(&EmptyContext),
+        Ok(Value::Function(
+            Function::from_definition("fn(x) x").unwrap()
+        ))
+    );
+}
+
+#[test]
+fn test_lambdas() {
+    // Lambdas are function values
+    assert_eq!(eval("typeof(x -> x)"), Ok(Value::from("function")));
+    assert_eq!(
+        eval("(a, b) -> a + b"),
+        Ok(Value::Function(
+            Function::from_definition("fn(a, b) a + b").unwrap()
+        ))
+    );
+    assert_eq!(
+        eval("x -> x * 2").map(|value| value.to_string()),
+        Ok("fn(x) x * 2".to_string())
+    );
+    assert_eq!(eval("square = x -> x * x; square(3)"), Ok(Value::from(9)));
+    assert_eq!(eval("answer = () -> 42; answer()"), Ok(Value::from(42)));
+    assert_eq!(
+        eval("add = x -> y -> x + y; f = add(1); typeof(f)"),
+        Ok(Value::from("function"))
+    );
+
+    // Variables holding functions can be called
+    let mut context = HashMapContext::new();
+    context
+        .set_value(
+            "inc".into(),
+            Function::from_definition("fn(x) x + 1").unwrap().into(),
+        )
+        .unwrap();
+    assert_eq!(eval_with_context("inc(1)", &context), Ok(Value::from(2)));
+    assert_eq!(
+        eval_with_context("m = {\"f\": inc}; m.f(2)", &context),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert_eq!(
+        eval_with_context_mut("m = {\"f\": inc}; m.f(2)", &mut context),
+        Ok(Value::from(3))
+    );
+    assert_eq!(
+        eval_with_context("apply = (f, x) -> f(x); apply(inc, 2)", &context),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert_eq!(
+        eval_with_context_mut("apply = (f, x) -> f(x); apply(inc, 2)", &mut context),
+        Ok(Value::from(3))
+    );
+
+    // Higher-order builtins
+    assert_eq!(
+        eval("map((1, 2, 3), x -> x * 2)"),
+        Ok(Value::from(vec![
+            Value::from(2),
+            Value::from(4),
+            Value::from(6)
+        ]))
+    );
+    assert_eq!(
+        eval("filter((1, 2, 3, 4), x -> x % 2 == 0)"),
+        Ok(Value::from(vec![Value::from(2), Value::from(4)]))
+    );
+    assert_eq!(
+        eval("reduce((1, 2, 3, 4), 0, (sum, x) -> sum + x)"),
+        Ok(Value::from(10))
+    );
+    assert_eq!(
+        eval("none = filter((1, 2), x -> x > 2); reduce(none, 1, (a, b) -> a * b)"),
+        Ok(Value::from(1))
+    );
+    assert_eq!(eval("any((1, 2, 3), x -> x > 2)"), Ok(Value::from(true)));
+    assert_eq!(
+        eval("any(filter((1, 2), x -> false), x -> x > 2)"),
+        Ok(Value::from(false))
+    );
+    assert_eq!(eval("all((1, 2, 3), x -> x > 2)"), Ok(Value::from(false)));
+    assert_eq!(
+        eval("all(filter((1, 2), x -> false), x -> x > 2)"),
+        Ok(Value::from(true))
+    );
+    assert_eq!(
+        eval("sort_by((\"ccc\", \"a\", \"bb\", \"d\"), s -> len(s))"),
+        Ok(Value::from(vec![
+            Value::from("a"),
+            Value::from("d"),
+            Value::from("bb"),
+            Value::from("ccc")
+        ]))
+    );
+    assert_eq!(
+        eval("sort_by((3, 1.5, 2), x -> -x)"),
+        Ok(Value::from(vec![
+            Value::from(3),
+            Value::from(2),
+            Value::from(1.5)
+        ]))
+    );
+    assert_eq!(
+        eval("map(((1, 2), (3, 4)), pair -> pair[0] * pair[1])"),
+        Ok(Value::from(vec![Value::from(2), Value::from(12)]))
+    );
+    assert_eq!(
+        eval("factor = 3; map((1, 2), x -> x * factor)"),
+        Ok(Value::from(vec![Value::from(3), Value::from(6)]))
+    );
+    assert_eq!(
+        eval("scale = fn(t, factor) map(t, x -> x * factor); scale((1, 2), 10)"),
+        Ok(Value::from(vec![Value::from(10), Value::from(20)]))
+    );
+    assert_eq!(
+        eval("double = fn(x) 2 * x; map((1, 2), x -> double(x))"),
+        Ok(Value::from(vec![Value::from(2), Value::from(4)]))
+    );
+    assert_eq!(
+        build_operator_tree("map((1, 2), x -> x + 1)")
+            .unwrap()
+            .compile()
+            .eval_with_context(&EmptyContext),
+        Ok(Value::from(vec![Value::from(2), Value::from(3)]))
+    );
+
+    // Function values capture the variables they read when they are created
+    assert_eq!(
+        eval("mk = fn(k) x -> x + k; add3 = mk(3); add3(1)"),
+        Ok(Value::from(4))
+    );
+    assert_eq!(
+        eval("add = x -> y -> x + y; f = add(1); f(2)"),
+        Ok(Value::from(3))
+    );
+    assert_eq!(
+        eval("k = 1; t = (x -> x + k, 0); k = 2; g = t[0]; g(0)"),
+        Ok(Value::from(1))
+    );
+    assert_eq!(
+        eval("g = (x -> x + later, 0)[0]; later = 5; g(1)"),
+        Ok(Value::from(6))
+    );
+    let compiled = build_operator_tree("map((1, 2), x -> x + k)")
+        .unwrap()
+        .compile();
+    assert_eq!(compiled.variable_identifiers(), &["k".to_string()]);
+    assert_eq!(
+        compiled.eval_with_slots(&[10.into()], &EmptyContext),
+        Ok(Value::from(vec![Value::from(11), Value::from(12)]))
+    );
+    assert_eq!(
+        build_operator_tree("mk = fn(k) x -> x + k; f = mk(3); f(1)")
+            .unwrap()
+            .compile()
+            .eval_with_context_mut(&mut HashMapContext::new()),
+        Ok(Value::from(4))
+    );
+
+    // Lambdas can only be called through an identifier
+    assert_eq!(
+        eval("(x -> x)(5)"),
+        Err(EvalexprError::MissingOperatorOutsideOfBrace)
+    );
+
+    // Errors
+    assert_eq!(
+        eval("map((1, 2), 3)"),
+        Err(EvalexprError::expected_function(Value::from(3)))
+    );
+    assert_eq!(
+        eval("map(1, x -> x)"),
+        Err(EvalexprError::expected_tuple(Value::from(1)))
+    );
+    assert_eq!(
+        eval("filter((1, 2), x -> x)"),
+        Err(EvalexprError::expected_boolean(Value::from(1)))
+    );
+    assert_eq!(
+        eval("sort_by((1, \"a\"), x -> x)"),
+        Err(EvalexprError::expected_number(Value::from("a")))
+    );
+    assert_eq!(
+        eval("reduce((1, 2), x -> x)"),
+        Err(EvalexprError::expected_fixed_len_tuple(
+            3,
+            Value::from(vec![
+                Value::from(vec![Value::from(1), Value::from(2)]),
+                Value::Function(Function::from_definition("fn(x) x").unwrap())
+            ])
+        ))
+    );
+    assert_eq!(
+        eval("map((1, 2), (a, b) -> a)"),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 2,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        build_operator_tree("1 -> 2"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    assert_eq!(
+        build_operator_tree("-> 2"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    assert_eq!(
+        build_operator_tree("f(x) -> 2"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    assert_eq!(
+        build_operator_tree("(a, a) -> 2"),
+        Err(EvalexprError::ExpectedParameterList)
+    );
+    let error = build_operator_tree_spanned("map(t, x + 1 -> x)").unwrap_err();
+    let span = error.span().unwrap();
+    assert_eq!(
+        &"map(t, x + 1 -> x)"[span.start.byte..span.end.byte],
+        "1 ->"
+    );
+}
+
+#[test]
+fn test_function_definition_display() {
+    let definitions = [
+        "fn(a, b) a * b + 1",
+        "fn(x) -x ^ 2",
+        "fn(x) (-x) ^ 2",
+        "fn(a, b) a - (b - 1) - 2",
+        "fn(s) s + \"quote \\\" and backslash \\\\\" + 2.0",
+        "fn(t) ((t, 1), 2)",
+        "fn(x) (y = x; y *= 2; y)",
+        "fn(m) {\"a\": m[0], \"b\": m[1..], \"c\": m[..-1]}",
+        "fn(a) !(a && true) || f(a, (1, 2))",
+        "fn() x.field * math::sqrt(4)",
+        "fn(f) g = fn(x) f",
+    ];
+
+    for definition in &definitions {
+        let function = Function::from_definition(definition).unwrap();
+        assert_eq!(&function.to_string(), definition);
+    }
+
+    // Redundant parentheses and whitespace are not preserved
+    let function = Function::from_definition("fn( a ) ((a)) + (2 * a)").unwrap();
+    assert_eq!(function.to_string(), "fn(a) a + 2 * a");
+    let function = Function::from_definition(&function.to_string()).unwrap();
+    let mut context = HashMapContext::new();
+    context.set_function("f".into(), function).unwrap();
+    assert_eq!(eval_with_context("f(3)", &context), Ok(Value::from(9)));
+
+    assert_eq!(
+        Function::from_definition("1 + 2").map(|_| ()),
+        Err(EvalexprError::ExpectedFunctionDefinition)
+    );
+    assert_eq!(
+        Function::from_definition("fn(x) x; 2").map(|_| ()),
+        Err(EvalexprError::ExpectedFunctionDefinition)
+    );
+    assert_eq!(
+        Function::new(|argument| Ok(argument.clone())).to_string(),
+        "fn(...) [native]"
+    );
+}
+
+#[test]
+fn test_eval_limits() {
+    let unlimited = EvalLimits::new();
+    assert_eq!(unlimited.enforce(|| eval("1 + 2")), Ok(Value::from(3)));
+
+    // Operations
+    let limits = EvalLimits::new().max_operations(5);
+    assert_eq!(limits.enforce(|| eval("1 + 2")), Ok(Value::from(3)));
+    assert_eq!(
+        limits.enforce(|| eval("1 + 2 + 3 + 4")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Operations,
+            maximum: 5
+        })
+    );
+    // Operations are counted per call of `enforce`
+    let tree = build_operator_tree("1 + 2").unwrap();
+    for _ in 0..3 {
+        assert_eq!(
+            limits.enforce(|| tree.eval_with_context(&EmptyContext)),
+            Ok(Value::from(3))
+        );
+    }
+    let compiled = build_operator_tree("a + b + c").unwrap().compile();
+    let slots = [Value::from(1), Value::from(2), Value::from(3)];
+    assert_eq!(
+        EvalLimits::new()
+            .max_operations(3)
+            .enforce(|| compiled.eval_with_slots(&slots, &EmptyContext)),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Operations,
+            maximum: 3
+        })
+    );
+    assert_eq!(
+        EvalLimits::new()
+            .max_operations(5)
+            .enforce(|| compiled.eval_with_slots(&slots, &EmptyContext)),
+        Ok(Value::from(6))
+    );
+    let limits = EvalLimits::new().max_operations(1000);
+    assert_eq!(
+        limits.enforce(|| eval("loop = fn(n) if(n > 0, loop(n - 1), 0); loop(1000)")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Operations,
+            maximum: 1000
+        })
+    );
+
+    // Depth
+    let limits = EvalLimits::new().max_depth(5);
+    assert_eq!(limits.enforce(|| eval("(1 + 2) * 3")), Ok(Value::from(9)));
+    assert_eq!(
+        limits.enforce(|| eval("((((1 + 2))))")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Depth,
+            maximum: 5
+        })
+    );
+    let limits = EvalLimits::new().max_depth(50);
+    assert_eq!(
+        limits.enforce(|| eval("down = fn(n) if(n > 0, down(n - 1), 0); down(5)")),
+        Ok(Value::from(0))
+    );
+    assert_eq!(
+        limits.enforce(|| eval("down = fn(n) if(n > 0, down(n - 1), 0); down(50)")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Depth,
+            maximum: 50
+        })
+    );
+
+    // Value size
+    let limits = EvalLimits::new().max_value_size(10);
+    assert_eq!(
+        limits.enforce(|| eval("\"abcde\" + \"fghij\"")),
+        Ok(Value::from("abcdefghij"))
+    );
+    assert_eq!(
+        limits.enforce(|| eval("\"abcde\" + \"fghijk\"")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::ValueSize,
+            maximum: 10
+        })
+    );
+    assert_eq!(
+        limits.enforce(|| eval("(1, 2, (3, 4, 5, 6), 7, 8, 9, 0)")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::ValueSize,
+            maximum: 10
+        })
+    );
+    assert_eq!(
+        limits.enforce(|| eval("{\"key\": \"value\", \"a\": 1}")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::ValueSize,
+            maximum: 10
+        })
+    );
+
+    // Nesting
+    let limits = EvalLimits::new().max_nesting(4);
+    assert_eq!(limits.enforce(|| eval("((1))")), Ok(Value::from(1)));
+    assert_eq!(
+        limits.enforce(|| eval("1 + 2 + 3 + 4 + 5")),
+        Ok(Value::from(15))
+    );
+    assert_eq!(
+        limits.enforce(|| build_operator_tree("((((1))))")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Nesting,
+            maximum: 4
+        })
+    );
+    assert_eq!(
+        limits.enforce(|| build_operator_tree("- - - - 1")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Nesting,
+            maximum: 4
+        })
+    );
+    let deep = format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000));
+    assert_eq!(
+        limits.enforce(|| build_operator_tree(&deep)),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Nesting,
+            maximum: 4
+        })
+    );
+    let deep = format!("{}1{}", "(".repeat(300), ")".repeat(300));
+    assert_eq!(
+        build_operator_tree(&deep),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::Nesting,
+            maximum: 256
+        })
+    );
+    assert_eq!(
+        EvalLimits::new()
+            .max_nesting(400)
+            .enforce(|| build_operator_tree(&deep)?.eval()),
+        Ok(Value::from(1))
+    );
+    let error = limits
+        .enforce(|| build_operator_tree_spanned("1 + (2 * (3 - (4)))"))
+        .unwrap_err();
+    assert_eq!(error.span().map(|span| span.start.byte), Some(12));
+
+    // Recursion
+    let limits = EvalLimits::new().max_recursion(10);
+    assert_eq!(
+        limits.enforce(|| eval("f = fn(n) if(n <= 0, 0, 1 + f(n - 1)); f(9)")),
+        Ok(Value::from(9))
+    );
+    assert_eq!(
+        limits.enforce(|| eval("f = fn(n) if(n <= 0, 0, 1 + f(n - 1)); f(10)")),
+        Err(EvalexprError::RecursionLimitExceeded { limit: 10 })
+    );
+    assert_eq!(
+        EvalLimits::new().enforce(|| eval("f = fn(n) f(n + 1); f(0)")),
+        Err(EvalexprError::RecursionLimitExceeded { limit: 100 })
+    );
+
+    // Nested limits replace the outer ones until they return
+    let outer = EvalLimits::new().max_operations(3);
+    let inner = EvalLimits::new().max_operations(100);
+    assert_eq!(
+        outer.enforce(|| inner.enforce(|| eval("1 + 2 + 3 + 4"))),
+        Ok(Value::from(10))
+    );
+    assert_eq!(eval("1 + 2 + 3 + 4"), Ok(Value::from(10)));
+}
+
+#[test]
+fn test_eval_interruption() {
+    let mut context = HashMapContext::new();
+    // Sleeps for longer than the timeout used below, such that the deadline is always exceeded after the first call
+    context
+        .set_function(
+            "slow".into(),
+            Function::new(|argument| {
+                thread::sleep(Duration::from_millis(150));
+                Ok(argument.clone())
+            }),
+        )
+        .unwrap();
+    context
+        .set_value(
+            "t".into(),
+            Value::from((0..1000).map(Value::from).collect::<TupleType>()),
+        )
+        .unwrap();
+
+    // Deadlines
+    let limits = EvalLimits::new().deadline(Instant::now());
+    assert_eq!(
+        limits.enforce(|| eval("1")),
+        Err(EvalexprError::DeadlineExceeded)
+    );
+    let compiled = build_operator_tree("1 + 2").unwrap().compile();
+    assert_eq!(
+        limits.enforce(|| compiled.eval_with_context(&EmptyContext)),
+        Err(EvalexprError::DeadlineExceeded)
+    );
+    let limits = EvalLimits::new().timeout(Duration::from_millis(100));
+    assert_eq!(
+        limits.enforce(|| eval_with_context("map(t, x -> slow(x))", &context)),
+        Err(EvalexprError::DeadlineExceeded)
+    );
+    // The timeout starts anew with each call of `enforce`
+    assert_eq!(
+        limits.enforce(|| eval_with_context("1 + 1", &context)),
+        Ok(Value::from(2))
+    );
+
+    // Cancellation from another thread, which cancels the token while the evaluation waits within `cancel`
+    let token = CancellationToken::new();
+    let barrier = Arc::new(Barrier::new(2));
+    context
+        .set_function("cancel".into(), {
+            let barrier = barrier.clone();
+            Function::new(move |argument| {
+                barrier.wait();
+                barrier.wait();
+                Ok(argument.clone())
+            })
+        })
+        .unwrap();
+    let canceller = {
+        let token = token.clone();
+        thread::spawn(move || {
+            barrier.wait();
+            token.cancel();
+            barrier.wait();
+        })
+    };
+    let limits = EvalLimits::new().cancellation_token(token.clone());
+    assert_eq!(
+        limits.enforce(|| eval_with_context("map(t, x -> cancel(x))", &context)),
+        Err(EvalexprError::Cancelled)
+    );
+    canceller.join().unwrap();
+    assert!(token.is_cancelled());
+    assert_eq!(
+        limits.enforce(|| eval("1 + 2")),
+        Err(EvalexprError::Cancelled)
+    );
+    assert_eq!(eval("1 + 2"), Ok(Value::from(3)));
+}
+
+#[test]
+fn test_type_check() {
+    // The types accepted by arithmetic operators, which include decimals and big integers if they are supported
+    let number: TypeSet = [
+        ValueType::Int,
+        ValueType::Float,
+        #[cfg(feature = "decimal_support")]
+        ValueType::Decimal,
+        #[cfg(feature = "bigint_support")]
+        ValueType::BigInt,
+    ]
+    .iter()
+    .copied()
+    .collect();
+    let mut schema = TypeSchema::new();
+    schema.set_variable_type("a".into(), ValueType::Int);
+    schema.set_variable_type("f".into(), ValueType::Float);
+    schema.set_variable_type("s".into(), ValueType::String);
+    schema.set_variable_type("b".into(), ValueType::Boolean);
+    schema.set_variable_type("m".into(), ValueType::Map);
+    schema.set_variable_type("n".into(), TypeSet::number());
+    schema.set_function_signature(
+        "scale".into(),
+        FunctionSignature::new(
+            vec![TypeSet::number(), ValueType::Int.into()],
+            ValueType::Float.into(),
+        ),
+    );
+    let check = |expression: &str| build_operator_tree(expression).unwrap().type_check(&schema);
+    let errors = |expression: &str| -> Vec<EvalexprError> {
+        check(expression)
+            .unwrap_err()
+            .into_iter()
+            .map(EvalexprError::without_span)
+            .collect()
+    };
+
+    // Results
+    assert_eq!(check("a + 1"), Ok(ValueType::Int.into()));
+    assert_eq!(check("a + f"), Ok(ValueType::Float.into()));
+    assert_eq!(check("a + n"), Ok(TypeSet::number()));
+    assert_eq!(check("s + \"x\""), Ok(ValueType::String.into()));
+    assert_eq!(check("a < f && !b"), Ok(ValueType::Boolean.into()));
+    assert_eq!(check("-a"), Ok(ValueType::Int.into()));
+    assert_eq!(check("a ^ 2"), Ok(TypeSet::number()));
+    assert_eq!(check("f ^ 2"), Ok(ValueType::Float.into()));
+    assert_eq!(check("a // 2"), Ok(ValueType::Int.into()));
+    assert_eq!(check("(a, s)"), Ok(ValueType::Tuple.into()));
+    assert_eq!(check("s[1..]"), Ok(ValueType::String.into()));
+    assert_eq!(check("m.x + 1"), Ok(number));
+    assert_eq!(check("x = s; x"), Ok(ValueType::String.into()));
+    assert_eq!(check("x = a; x += 1.5; x"), Ok(ValueType::Float.into()));
+    assert_eq!(check("a = 1"), Ok(ValueType::Empty.into()));
+    assert_eq!(check(""), Ok(ValueType::Empty.into()));
+    assert_eq!(check("scale(f, 2)"), Ok(ValueType::Float.into()));
+    assert_eq!(check("len(s) + 1"), Ok(ValueType::Int.into()));
+    assert_eq!(
+        check("if(b, a, s)"),
+        Ok(TypeSet::from(ValueType::Int).union(ValueType::String.into()))
+    );
+    assert_eq!(check("max(a, f, 3)"), Ok(TypeSet::number()));
+    assert_eq!(check("x -> x + y"), Ok(ValueType::Function.into()));
+    assert_eq!(check("double = fn(x) x * 2; double(a)"), Ok(TypeSet::any()));
+    assert_eq!(check("g = x -> x; g(a)"), Ok(TypeSet::any()));
+    assert_eq!(
+        check("map((1, 2), x -> x * 2)"),
+        Ok(ValueType::Tuple.into())
+    );
+    // A parenthesized tuple is a single argument, and multiple arguments are passed as one tuple to functions with one parameter
+    assert_eq!(check("len((1, 2))"), Ok(ValueType::Int.into()));
+    assert_eq!(check("len(1, 2)"), Ok(ValueType::Int.into()));
+    assert_eq!(check("typeof((1, 2))"), Ok(ValueType::String.into()));
+    assert_eq!(check("h = fn(t) len(t); h((1, 2))"), Ok(TypeSet::any()));
+    // Assignments in operands and branches that may be skipped keep the previous types as well
+    let int_or_string = TypeSet::from(ValueType::Int).union(ValueType::String.into());
+    assert_eq!(check("x = 1; b && (x = \"s\"; true); x"), Ok(int_or_string));
+    assert_eq!(check("x = 1; b || (x = \"s\"; true); x"), Ok(int_or_string));
+    assert_eq!(check("x = 1; if(b, x = \"s\", 0); x"), Ok(int_or_string));
+    assert_eq!(
+        check("x = 1; if(b, x = \"s\", x = 1.5); x"),
+        Ok(TypeSet::from(ValueType::String).union(ValueType::Float.into()))
+    );
+    assert_eq!(
+        check("x = 1; false && (x = \"s\"); x + 1"),
+        Ok(ValueType::Int.into())
+    );
+    assert_eq!(
+        eval("x = 1; false && (x = \"s\"); x + 1"),
+        Ok(Value::from(2))
+    );
+
+    // Errors
+    assert_eq!(
+        errors("a + b"),
+        vec![EvalexprError::TypeMismatch {
+            expected: number.union(ValueType::String.into()),
+            actual: ValueType::Boolean.into()
+        }]
+    );
+    assert_eq!(
+        errors("s + a"),
+        vec![EvalexprError::wrong_type_combination(
+            Operator::Add,
+            vec![ValueType::String, ValueType::Int]
+        )]
+    );
+    assert_eq!(
+        errors("b && s; -s"),
+        vec![
+            EvalexprError::TypeMismatch {
+                expected: ValueType::Boolean.into(),
+                actual: ValueType::String.into()
+            },
+            EvalexprError::TypeMismatch {
+                expected: number,
+                actual: ValueType::String.into()
+            },
+        ]
+    );
+    assert_eq!(
+        errors("x + 1"),
+        vec![EvalexprError::VariableIdentifierNotFound("x".into())]
+    );
+    assert_eq!(
+        errors("a.x"),
+        vec![EvalexprError::TypeMismatch {
+            expected: ValueType::Map.into(),
+            actual: ValueType::Int.into()
+        }]
+    );
+    assert_eq!(
+        errors("unknown(1)"),
+        vec![EvalexprError::FunctionIdentifierNotFound("unknown".into())]
+    );
+    assert_eq!(
+        errors("scale(s, 1); scale(1)"),
+        vec![
+            EvalexprError::TypeMismatch {
+                expected: TypeSet::number(),
+                actual: ValueType::String.into()
+            },
+            EvalexprError::WrongFunctionArgumentAmount {
+                expected: 2,
+                actual: 1
+            },
+        ]
+    );
+    assert_eq!(
+        errors("scale(1, 2, 3); floor(1, 2); floor()"),
+        vec![
+            EvalexprError::WrongFunctionArgumentAmount {
+                expected: 2,
+                actual: 3
+            },
+            EvalexprError::WrongFunctionArgumentAmount {
+                expected: 1,
+                actual: 2
+            },
+            EvalexprError::WrongFunctionArgumentAmount {
+                expected: 1,
+                actual: 0
+            },
+        ]
+    );
+    assert_eq!(
+        errors("if(a, 1, 2)"),
+        vec![EvalexprError::TypeMismatch {
+            expected: ValueType::Boolean.into(),
+            actual: ValueType::Int.into()
+        }]
+    );
+    assert_eq!(
+        errors("x = s; x -= 1"),
+        vec![EvalexprError::TypeMismatch {
+            expected: number,
+            actual: ValueType::String.into()
+        }]
+    );
+    // Variables of the schema keep their type, like the variables of a `HashMapContext`
+    assert_eq!(check("a += 1; a"), Ok(ValueType::Int.into()));
+    assert_eq!(check("n = 1.5; n"), Ok(ValueType::Float.into()));
+    assert_eq!(check("g = fn(x) (a = 1.5; a); g(1)"), Ok(TypeSet::any()));
+    assert_eq!(
+        errors("a += 1.5; a = \"s\""),
+        vec![
+            EvalexprError::TypeMismatch {
+                expected: ValueType::Int.into(),
+                actual: ValueType::Float.into()
+            },
+            EvalexprError::TypeMismatch {
+                expected: ValueType::Int.into(),
+                actual: ValueType::String.into()
+            },
+        ]
+    );
+    let mut context = HashMapContext::new();
+    context.set_value("a".into(), 1.into()).unwrap();
+    assert_eq!(
+        eval_with_context_mut("a += 1.5", &mut context),
+        Err(EvalexprError::expected_int(Value::from(2.5)))
+    );
+    // Errors do not cascade
+    assert_eq!(errors("(s - 1) * 2 + b").len(), 2);
+
+    // Spans locate the errors
+    let tree = build_operator_tree("a +\n  s").unwrap();
+    match tree.type_check(&schema).unwrap_err().as_slice() {
+        [EvalexprError::Spanned { span, .. }] => {
+            assert_eq!((span.start.line, span.start.column), (1, 1))
+        },
+        errors => panic!("Unexpected errors {:?}", errors),
+    }
+    // Unknown identifiers within functions are resolved when they are called
+    assert_eq!(check("fn(x) x + y + z(x)"), Ok(ValueType::Function.into()));
+}
+
+#[test]
+fn test_typed_functions() {
+    let mut context = HashMapContext::new();
+    context
+        .set_function(
+            "scale".into(),
+            Function::typed(|a: FloatType, b: IntType| a * b as FloatType),
+        )
+        .unwrap();
+    context
+        .set_function(
+            "label".into(),
+            Function::typed(|a: FloatType, b: IntType| format!("{}x{}", a, b)),
+        )
+        .unwrap();
+    context
+        .set_function(
+            "checked_div".into(),
+            Function::typed(|a: IntType, b: IntType| {
+                a.checked_div(b)
+                    .ok_or_else(|| EvalexprError::CustomMessage("division by zero".into()))
+            }),
+        )
+        .unwrap();
+    context
+        .set_function("answer".into(), Function::typed(|| 42))
+        .unwrap();
+    context
+        .set_function(
+            "count".into(),
+            Function::typed(|t: TupleType| t.len() as IntType),
+        )
+        .unwrap();
+    context
+        .set_function(
+            "keep".into(),
+            Function::typed(|value: Value, keep: bool| if keep { value } else { Value::Empty }),
+        )
+        .unwrap();
+
+    assert_eq!(
+        eval_with_context("scale(1.5, 2)", &context),
+        Ok(Value::from(3.0))
+    );
+    assert_eq!(
+        eval_with_context("scale(3, 2)", &context),
+        Ok(Value::from(6.0))
+    );
+    assert_eq!(
+        eval_with_context("label(1.5, 2)", &context),
+        Ok(Value::from("1.5x2"))
+    );
+    assert_eq!(
+        eval_with_context("checked_div(7, 2)", &context),
+        Ok(Value::from(3))
+    );
+    assert_eq!(
+        eval_with_context("checked_div(7, 0)", &context),
+        Err(EvalexprError::CustomMessage("division by zero".into()))
+    );
+    assert_eq!(eval_with_context("answer()", &context), Ok(Value::from(42)));
+    assert_eq!(
+        eval_with_context("count(1, 2, 3)", &context),
+        Ok(Value::from(3))
+    );
+    assert_eq!(
+        eval_with_context("keep(\"a\", true)", &context),
+        Ok(Value::from("a"))
+    );
+    assert_eq!(
+        eval_with_context("keep((1, 2), false)", &context),
+        Ok(Value::Empty)
+    );
+    assert_eq!(
+        eval_with_context("count(1)", &context),
+        Err(EvalexprError::expected_tuple(Value::from(1)))
+    );
+
+    // Argument amounts
+    context
+        .set_function("double".into(), Function::typed(|a: IntType| a * 2))
+        .unwrap();
+    assert_eq!(
+        eval_with_context("double(1, 2)", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 1,
+            actual: 2
+        })
+    );
+    assert_eq!(
+        eval_with_context("double()", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 1,
+            actual: 0
+        })
+    );
+    assert_eq!(
+        eval_with_context("count()", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 1,
+            actual: 0
+        })
+    );
+    assert_eq!(
+        eval_with_context("scale(1.5)", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 2,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        eval_with_context("scale(1, 2, 3)", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 2,
+            actual: 3
+        })
+    );
+    assert_eq!(
+        eval_with_context("scale()", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 2,
+            actual: 0
+        })
+    );
+    assert_eq!(
+        eval_with_context("answer(1, 2)", &context),
+        Err(EvalexprError::WrongFunctionArgumentAmount {
+            expected: 0,
+            actual: 2
+        })
+    );
+
+    // Argument types
+    assert_eq!(
+        eval_with_context("scale(1.5, 2.5)", &context),
+        Err(EvalexprError::expected_int(Value::from(2.5)))
+    );
+    assert_eq!(
+        eval_with_context("scale(\"a\", 2)", &context),
+        Err(EvalexprError::expected_number(Value::from("a")))
+    );
+
+    // Signatures
+    let scale = Function::typed(|a: FloatType, b: IntType| a * b as FloatType);
+    let signature = scale.signature().unwrap();
+    assert_eq!(
+        signature.parameters(),
+        Some(&[TypeSet::number(), ValueType::Int.into()][..])
+    );
+    assert_eq!(signature.result(), ValueType::Float.into());
+    assert_eq!(signature.to_string(), "(float | int, int) -> float");
+    assert_eq!(
+        Function::typed(|| 42).signature().unwrap().to_string(),
+        "() -> int"
+    );
+    assert_eq!(
+        Function::typed(|value: Value| value)
+            .signature()
+            .unwrap()
+            .to_string(),
+        "(any) -> any"
+    );
+    assert_eq!(
+        Function::typed(|a: IntType| -> EvalexprResult<IntType> { Ok(a) })
+            .signature()
+            .unwrap()
+            .to_string(),
+        "(int) -> int"
+    );
+    assert_eq!(
+        Function::new(|argument| Ok(argument.clone())).signature(),
+        None
+    );
+
+    let mut schema = TypeSchema::new();
+    schema.set_function_signature("scale".into(), signature.clone());
+    let tree = build_operator_tree("scale(2, 3) + 1").unwrap();
+    assert_eq!(tree.type_check(&schema), Ok(ValueType::Float.into()));
+    let tree = build_operator_tree("scale(2, 3.5)").unwrap();
+    assert!(tree.type_check(&schema).is_err());
+
+    // Purity
+    assert!(!scale.is_pure());
+    let pure = Function::typed_pure(|a: IntType| a + 1);
+    assert!(pure.is_pure());
+    assert_eq!(pure.signature().unwrap().to_string(), "(int) -> int");
+}
+
+#[test]
+fn test_function_metadata() {
+    // Functions implemented in Rust
+    let opaque = Function::new(|argument| Ok(argument.clone()));
+    assert_eq!(opaque.metadata(), FunctionMetadata::default());
+    assert_eq!(opaque.metadata().to_string(), "fn(...)");
+    assert_eq!(format!("{:?}", opaque), "Function { [...] }");
+
+    let function = Function::new(|argument| Ok(argument.clone()))
+        .with_name("id".into())
+        .with_parameter_names(vec!["value".into()])
+        .with_documentation("Returns its argument.".into())
+        .with_deterministic(true);
+    let metadata = function.metadata();
+    assert_eq!(metadata.name(), Some("id"));
+    assert_eq!(metadata.parameter_names(), Some(&["value".to_string()][..]));
+    assert_eq!(metadata.arity(), Some(1));
+    assert_eq!(metadata.signature(), None);
+    assert_eq!(metadata.documentation(), Some("Returns its argument."));
+    assert!(!metadata.is_pure());
+    assert!(metadata.is_deterministic());
+    assert_eq!(metadata.to_string(), "id(value)");
+    assert_eq!(format!("{:?}", function), "Function { id [...] }");
+    // Metadata is kept by clones
+    assert_eq!(function.clone().metadata(), metadata);
+
+    let typed = Function::typed(|a: IntType, b: String| b.repeat(a as usize));
+    assert_eq!(typed.metadata().arity(), Some(2));
+    assert_eq!(typed.metadata().to_string(), "fn(int, string) -> string");
+    assert!(!typed.metadata().is_deterministic());
+    let pure = Function::new_pure(|argument| Ok(argument.clone()));
+    assert!(pure.metadata().is_pure());
+    assert!(pure.metadata().is_deterministic());
+
+    // Functions defined in expressions
+    let mut context = HashMapContext::new();
+    eval_with_context_mut("area = fn(width, height) width * height", &mut context).unwrap();
+    context.set_function("id".into(), function.clone()).unwrap();
+    let metadata = context.function_metadata("area").unwrap();
+    assert_eq!(metadata.name(), Some("area"));
+    assert_eq!(
+        metadata.parameter_names(),
+        Some(&["width".to_string(), "height".to_string()][..])
+    );
+    assert_eq!(metadata.to_string(), "area(width, height)");
+    assert_eq!(context.function_metadata("id"), Some(function.metadata()));
+    assert_eq!(context.function_metadata("unknown"), None);
+    assert_eq!(EmptyContext.function_metadata("id"), None);
+    let lambda = eval("(a, b) -> a").unwrap().as_function().unwrap();
+    assert_eq!(lambda.metadata().name(), None);
+    assert_eq!(lambda.metadata().arity(), Some(2));
+
+    let mut context = HashMapContext::new();
+    build_operator_tree("square = fn(x) x * x")
+        .unwrap()
+        .compile()
+        .eval_with_context_mut(&mut context)
+        .unwrap();
+    assert_eq!(
+        context.function_metadata("square").unwrap().to_string(),
+        "square(x)"
+    );
+
+    // Builtin functions
+    for identifier in builtin_function_identifiers() {
+        let metadata = builtin_function_metadata(identifier).unwrap();
+        assert_eq!(metadata.name(), Some(identifier));
+        assert!(metadata.signature().is_some(), "{}", identifier);
+        assert!(metadata.documentation().is_some(), "{}", identifier);
+        assert!(metadata.is_deterministic());
+        if let Some(arity) = metadata.signature().unwrap().parameters().map(<[_]>::len) {
+            assert_eq!(metadata.arity(), Some(arity), "{}", identifier);
+        }
+    }
+    assert_eq!(
+        builtin_function_metadata("if").unwrap().to_string(),
+        "if(condition: boolean, then: any, else: any) -> any"
+    );
+    let mut min_signature = "min(...) -> float | int".to_string();
+    if cfg!(feature = "decimal_support") {
+        min_signature.push_str(" | decimal");
+    }
+    if cfg!(feature = "bigint_support") {
+        min_signature.push_str(" | bigint");
+    }
+    assert_eq!(
+        builtin_function_metadata("min").unwrap().to_string(),
+        min_signature
+    );
+    assert!(builtin_function_metadata("math::sqrt").unwrap().is_pure());
+    assert!(!builtin_function_metadata("map").unwrap().is_pure());
+    assert_eq!(builtin_function_metadata("unknown"), None);
+}
+
+#[test]
+fn test_context_entries() {
+    let mut context = context_map! {
+        "a" => 1,
+        "b" => "x",
+        "f" => Function::new(|argument| Ok(argument.clone())),
+        "g" => Function::new_pure(|_| Ok(Value::Empty))
+    }
+    .unwrap();
+
+    let mut variables: Vec<_> = context.iter_variables().collect();
+    variables.sort_by_key(|(identifier, _)| *identifier);
+    assert_eq!(
+        variables,
+        vec![("a", &Value::from(1)), ("b", &Value::from("x"))]
+    );
+    let mut variable_names: Vec<_> = context.iter_variable_names().collect();
+    variable_names.sort_unstable();
+    assert_eq!(variable_names, vec!["a", "b"]);
+    let mut function_names: Vec<_> = context.iter_function_names().collect();
+    function_names.sort_unstable();
+    assert_eq!(function_names, vec!["f", "g"]);
+    assert_eq!(context.iter_functions().count(), 2);
+    assert!(context.get_function("g").unwrap().is_pure());
+    assert_eq!(context.get_function("a"), None);
+    // Builtin functions are not part of the context
+    assert_eq!(context.get_function("min"), None);
+
+    // Removing
+    assert_eq!(context.remove_value("a"), Ok(Some(Value::from(1))));
+    assert_eq!(context.remove_value("a"), Ok(None));
+    assert_eq!(
+        eval_with_context("a", &context),
+        Err(EvalexprError::VariableIdentifierNotFound("a".into()))
+    );
+    // After removing, the variable may be assigned a value of another type
+    context.set_value("a".into(), Value::from(true)).unwrap();
+    assert_eq!(eval_with_context("a", &context), Ok(Value::from(true)));
+    assert!(context.remove_function("f").unwrap().is_some());
+    assert!(context.remove_function("f").unwrap().is_none());
+    assert_eq!(
+        eval_with_context("f(1)", &context),
+        Err(EvalexprError::FunctionIdentifierNotFound("f".into()))
+    );
+
+    // Clearing
+    let mut cleared = context.clone();
+    cleared.clear_variables();
+    assert_eq!(cleared.iter_variables().count(), 0);
+    assert_eq!(cleared.iter_functions().count(), 1);
+    let mut cleared = context.clone();
+    cleared.clear_functions();
+    assert_eq!(cleared.iter_variables().count(), 2);
+    assert_eq!(cleared.iter_functions().count(), 0);
+    context.clear();
+    assert_eq!(context.iter_variables().count(), 0);
+    assert_eq!(context.iter_functions().count(), 0);
+
+    // Other contexts
+    assert_eq!(EmptyContext.iter_variables().count(), 0);
+    assert_eq!(EmptyContext.iter_function_names().count(), 0);
+    assert_eq!(EmptyContext.get_function("f"), None);
+
+    struct ReadOnlyContext;
+    impl Context for ReadOnlyContext {
+        fn get_value(&self, _identifier: &str) -> Option<Cow<'_, Value>> {
+            None
+        }
+
+        fn call_function(&self, identifier: &str, _argument: &Value) -> EvalexprResult<Value> {
+            Err(EvalexprError::FunctionIdentifierNotFound(
+                identifier.to_string(),
+            ))
+        }
+    }
+    impl ContextWithMutableVariables for ReadOnlyContext {}
+    impl ContextWithMutableFunctions for ReadOnlyContext {}
+    assert_eq!(
+        ReadOnlyContext.remove_value("a"),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert!(ReadOnlyContext.remove_function("f").is_err());
+}
+
+#[test]
+fn test_scoped_context() {
+    let shared = context_map! {
+        "pi" => 3.0,
+        "name" => "shared",
+        "double" => Function::new_pure(|argument| Ok(Value::from(argument.as_int()? * 2)))
+    }
+    .unwrap();
+
+    let mut context = ScopedContext::new(&shared);
+    assert_eq!(context.scope_depth(), 1);
+    assert_eq!(eval_with_context("pi * 2", &context), Ok(Value::from(6.0)));
+    assert_eq!(eval_with_context("double(4)", &context), Ok(Value::from(8)));
+    assert_eq!(eval_with_context("min(1, 2)", &context), Ok(Value::from(1)));
+
+    // Writes land in the innermost layer and shadow the parent
+    eval_with_context_mut("x = 1; name = \"local\"", &mut context).unwrap();
+    assert_eq!(
+        eval_with_context("name", &context),
+        Ok(Value::from("local"))
+    );
+    assert_eq!(
+        shared.get_value("name").as_deref(),
+        Some(&Value::from("shared"))
+    );
+    assert_eq!(shared.get_value("x"), None);
+    // Variables may shadow variables of another type
+    eval_with_context_mut("pi = 3", &mut context).unwrap();
+    assert_eq!(eval_with_context("pi", &context), Ok(Value::from(3)));
+    // but each layer is type safe
+    assert_eq!(
+        eval_with_context_mut("x = 1.5", &mut context),
+        Err(EvalexprError::expected_int(Value::from(1.5)))
+    );
+
+    // Nested scopes
+    context.push_scope();
+    assert_eq!(context.scope_depth(), 2);
+    eval_with_context_mut("x = true; y = 2; triple = fn(a) a * 3 + y", &mut context).unwrap();
+    assert_eq!(eval_with_context("x", &context), Ok(Value::from(true)));
+    assert_eq!(eval_with_context("triple(2)", &context), Ok(Value::from(8)));
+    assert_eq!(
+        context.innermost_scope().get_value("y").as_deref(),
+        Some(&Value::from(2))
+    );
+    assert_eq!(context.remove_value("x"), Ok(Some(Value::from(true))));
+    assert_eq!(eval_with_context("x", &context), Ok(Value::from(1)));
+    let popped = context.pop_scope().unwrap();
+    assert_eq!(popped.get_value("y").as_deref(), Some(&Value::from(2)));
+    assert_eq!(
+        eval_with_context("y", &context),
+        Err(EvalexprError::VariableIdentifierNotFound("y".into()))
+    );
+    assert_eq!(
+        eval_with_context("triple(2)", &context),
+        Err(EvalexprError::FunctionIdentifierNotFound("triple".into()))
+    );
+    // The last layer cannot be popped
+    assert!(context.pop_scope().is_none());
+    assert_eq!(context.scope_depth(), 1);
+
+    // Functions of the layers see all variables
+    eval_with_context_mut("scale = fn(a) a * pi", &mut context).unwrap();
+    context.push_scope();
+    eval_with_context_mut("pi = 10", &mut context).unwrap();
+    assert_eq!(eval_with_context("scale(2)", &context), Ok(Value::from(20)));
+    context.pop_scope();
+
+    // Introspection
+    let mut variables: Vec<_> = context.iter_variables().collect();
+    variables.sort_by_key(|(identifier, _)| *identifier);
+    assert_eq!(
+        variables,
+        vec![
+            ("name", &Value::from("local")),
+            ("pi", &Value::from(3)),
+            ("x", &Value::from(1)),
+        ]
+    );
+    let mut functions: Vec<_> = context.iter_function_names().collect();
+    functions.sort_unstable();
+    assert_eq!(functions, vec!["double", "scale"]);
+    assert!(context.get_function("double").is_some());
+    assert_eq!(context.is_function_pure("double"), Some(true));
+    assert_eq!(context.is_function_pure("scale"), Some(false));
+    assert_eq!(
+        context.function_metadata("scale").unwrap().to_string(),
+        "scale(a)"
+    );
+
+    // Optimizing with a scoped context
+    let mut tree = build_operator_tree("double(3) + pi").unwrap();
+    tree.optimize_with_context(&context);
+    assert_eq!(tree.eval_with_context(&context), Ok(Value::from(9)));
+
+    // Any context can be the parent
+    let dynamic: &dyn Context = &shared;
+    let mut context = ScopedContext::new(dynamic);
+    eval_with_context_mut("a = double(2)", &mut context).unwrap();
+    assert_eq!(eval_with_context("a + 1", &context), Ok(Value::from(5)));
+    let mut context = ScopedContext::new(&EmptyContext);
+    assert_eq!(
+        eval_with_context_mut("a = 1; a", &mut context),
+        Ok(Value::from(1))
+    );
+}
+
+#[test]
+fn test_lazy_context() {
+    let resolved = Cell::new(0);
+    let resolver = |identifier: &str| {
+        resolved.set(resolved.get() + 1);
+        match identifier {
+            "a" => Some(Value::from(2)),
+            "b" => Some(Value::from(3.5)),
+            "user" => Some(Value::from(
+                vec![("name".to_string(), Value::from("ann"))]
+                    .into_iter()
+                    .collect::<MapType>(),
+            )),
+            _ => None,
+        }
+    };
+
+    // Without cache, identifiers are resolved whenever they are used
+    let mut context = LazyContext::new(resolver);
+    assert_eq!(
+        eval_with_context("a * a + b", &context),
+        Ok(Value::from(7.5))
+    );
+    assert_eq!(resolved.get(), 3);
+    assert_eq!(
+        eval_with_context("user.name", &context),
+        Ok(Value::from("ann"))
+    );
+    assert_eq!(
+        eval_with_context("c", &context),
+        Err(EvalexprError::VariableIdentifierNotFound("c".into()))
+    );
+    assert_eq!(context.get_value("a"), Some(Cow::Owned(Value::from(2))));
+    assert_eq!(context.get_value("c"), None);
+
+    // Assignments shadow resolved values
+    resolved.set(0);
+    eval_with_context_mut("a = \"x\"; c = a + a", &mut context).unwrap();
+    assert_eq!(eval_with_context("c", &context), Ok(Value::from("xx")));
+    assert_eq!(
+        context.get_value("a"),
+        Some(Cow::Borrowed(&Value::from("x")))
+    );
+    assert_eq!(resolved.get(), 0);
+    assert_eq!(context.remove_value("a"), Ok(Some(Value::from("x"))));
+    assert_eq!(eval_with_context("a", &context), Ok(Value::from(2)));
+
+    // Functions
+    eval_with_context_mut("f = fn(x) x * a", &mut context).unwrap();
+    assert_eq!(eval_with_context("f(b)", &context), Ok(Value::from(7.0)));
+    context
+        .set_function("g".into(), Function::new_pure(|_| Ok(Value::from(1))))
+        .unwrap();
+    assert_eq!(
+        eval_with_context("g() + min(a, 5)", &context),
+        Ok(Value::from(3))
+    );
+    assert_eq!(context.is_function_pure("g"), Some(true));
+    assert!(context.get_function("f").is_some());
+    assert!(context.remove_function("g").unwrap().is_some());
+    assert_eq!(
+        eval_with_context("g()", &context),
+        Err(EvalexprError::FunctionIdentifierNotFound("g".into()))
+    );
+
+    // With cache, identifiers are resolved once
+    resolved.set(0);
+    let mut context = LazyContext::cached(resolver);
+    assert_eq!(
+        eval_with_context("a * a + b + a", &context),
+        Ok(Value::from(9.5))
+    );
+    assert_eq!(eval_with_context("a", &context), Ok(Value::from(2)));
+    assert!(eval_with_context("c + c", &context).is_err());
+    assert!(eval_with_context("c", &context).is_err());
+    assert_eq!(resolved.get(), 3);
+    context.clear_cache();
+    assert_eq!(eval_with_context("a", &context), Ok(Value::from(2)));
+    assert_eq!(resolved.get(), 4);
+
+    // Lazy contexts work with compiled expressions and as parents of scoped contexts
+    let compiled = build_operator_tree("a + 1").unwrap().compile();
+    assert_eq!(compiled.eval_with_context(&context), Ok(Value::from(3)));
+    let mut scoped = ScopedContext::new(&context);
+    eval_with_context_mut("a = 10", &mut scoped).unwrap();
+    assert_eq!(eval_with_context("a + b", &scoped), Ok(Value::from(13.5)));
+    assert_eq!(eval_with_context("a", &context), Ok(Value::from(2)));
+}
+
+#[test]
+fn test_concurrent_context() {
+    let context = Arc::new(ConcurrentContext::new());
+    context.set_value("counter".into(), Value::from(0)).unwrap();
+    context.set_value("total".into(), Value::from(0.0)).unwrap();
+    context.set_value("log".into(), Value::from("")).unwrap();
+
+    let threads: Vec<_> = (0..8)
+        .map(|index| {
+            let context = Arc::clone(&context);
+            thread::spawn(move || {
+                let compiled = build_operator_tree("total += 0.5").unwrap().compile();
+                for _ in 0..200 {
+                    eval_with_context_mut("counter += 1", &mut &*context).unwrap();
+                    compiled.eval_with_context_mut(&mut &*context).unwrap();
+                }
+                eval_with_context_mut(&format!("log += \"{}\"", index), &mut &*context).unwrap();
+                // Reading does not need a mutable reference
+                eval_with_context("counter > 0", &*context).unwrap()
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), Value::from(true));
+    }
+    assert_eq!(
+        eval_with_context("counter", &*context),
+        Ok(Value::from(1600))
+    );
+    assert_eq!(
+        eval_with_context("total", &*context),
+        Ok(Value::from(800.0))
+    );
+    assert_eq!(eval_with_context("len(log)", &*context), Ok(Value::from(8)));
+
+    // Type safety
+    assert_eq!(
+        context.set_value("counter".into(), Value::from(1.5)),
+        Err(EvalexprError::expected_int(Value::from(1.5)))
+    );
+    assert_eq!(
+        eval_with_context_mut("counter += 0.5", &mut &*context),
+        Err(EvalexprError::expected_int(Value::from(1600.5)))
+    );
+    assert_eq!(
+        eval_with_context_mut("unknown += 1", &mut &*context),
+        Err(EvalexprError::VariableIdentifierNotFound("unknown".into()))
+    );
+    assert_eq!(
+        eval_with_context("counter", &*context),
+        Ok(Value::from(1600))
+    );
+
+    // Functions
+    context.set_function(
+        "double".into(),
+        Function::new_pure(|argument| Ok(Value::from(argument.as_int()? * 2))),
+    );
+    eval_with_context_mut("add_counter = fn(x) x + counter", &mut &*context).unwrap();
+    assert_eq!(
+        eval_with_context("add_counter(double(2))", &*context),
+        Ok(Value::from(1604))
+    );
+    assert_eq!(context.is_function_pure("double"), Some(true));
+    assert_eq!(
+        context.function_metadata("add_counter").unwrap().name(),
+        Some("add_counter")
+    );
+
+    // Snapshots and removal
+    let snapshot = context.snapshot();
+    assert_eq!(
+        snapshot.get_value("counter").as_deref(),
+        Some(&Value::from(1600))
+    );
+    assert!(snapshot.get_function("double").is_some());
+    assert_eq!(context.remove_value("counter"), Some(Value::from(1600)));
+    assert!(context.remove_function("double").is_some());
+    assert_eq!(context.get_value("counter"), None);
+    assert_eq!(
+        snapshot.get_value("counter").as_deref(),
+        Some(&Value::from(1600))
+    );
+
+    // Updates may access the context, and are repeated if a variable changes meanwhile
+    context.set_value("x".into(), Value::from(1)).unwrap();
+    context.set_value("y".into(), Value::from(0)).unwrap();
+    let mut calls = 0;
+    context
+        .update_value("x".into(), &mut |value| {
+            calls += 1;
+            if calls == 1 {
+                context.set_value("y".into(), Value::from(1))?;
+            }
+            let y = context.get_value("y").unwrap().as_int()?;
+            Ok(Value::from(value.as_int()? + y))
+        })
+        .unwrap();
+    assert_eq!(calls, 2);
+    assert_eq!(context.get_value("x").as_deref(), Some(&Value::from(2)));
+
+    // Owned contexts work through the context traits
+    let mut owned = ConcurrentContext::new();
+    assert_eq!(
+        eval_with_context_mut("a = 1; a += 2; a", &mut owned),
+        Ok(Value::from(3))
+    );
+}
+
+#[test]
+fn test_transactions() {
+    let mut context = HashMapContext::new();
+    context.set_value("a".into(), Value::from(0)).unwrap();
+
+    // Failing expressions leave the context unchanged
+    assert_eq!(
+        eval_with_context_transactional("a = 1; b = a / 0", &mut context),
+        Err(EvalexprError::DivisionError {
+            dividend: Value::from(1),
+            divisor: Value::from(0)
+        })
+    );
+    assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(0)));
+    assert_eq!(context.get_value("b"), None);
+    assert_eq!(
+        eval_with_context_transactional("f = fn(x) x + 1; a += 1; g(a)", &mut context),
+        Err(EvalexprError::FunctionIdentifierNotFound("g".into()))
+    );
+    assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(0)));
+    assert_eq!(context.get_function("f"), None);
+
+    // Successful expressions commit all writes
+    assert_eq!(
+        eval_with_context_transactional("f = fn(x) x + 1; a += 1; b = f(a); a", &mut context),
+        Ok(Value::from(1))
+    );
+    assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(1)));
+    assert_eq!(context.get_value("b").as_deref(), Some(&Value::from(2)));
+    assert_eq!(
+        eval_with_context_transactional("f(b)", &mut context),
+        Ok(Value::from(3))
+    );
+
+    // Writes rejected by the context while committing are rolled back
+    let result = eval_with_context_transactional("c = 1; a = 1.5", &mut context);
+    assert_eq!(
+        result,
+        Err(EvalexprError::ExpectedInt {
+            actual: Value::from(1.5)
+        })
+    );
+    assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(1)));
+    assert_eq!(context.get_value("c"), None);
+
+    // Variables are also rolled back if the context rejects a function
+    struct ImmutableFunctionsContext(HashMapContext);
+    impl Context for ImmutableFunctionsContext {
+        fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+            self.0.get_value(identifier)
+        }
+
+        fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+            self.0.call_function(identifier, argument)
+        }
+    }
+    impl ContextWithMutableVariables for ImmutableFunctionsContext {
+        fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+            self.0.set_value(identifier, value)
+        }
+
+        fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+            self.0.remove_value(identifier)
+        }
+    }
+    impl ContextWithMutableFunctions for ImmutableFunctionsContext {}
+
+    let mut immutable_functions = ImmutableFunctionsContext(context.clone());
+    assert_eq!(
+        eval_with_context_transactional("a = 2; c = 1; f = fn() 0", &mut immutable_functions),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert_eq!(
+        immutable_functions.get_value("a").as_deref(),
+        Some(&Value::from(1))
+    );
+    assert_eq!(immutable_functions.get_value("c"), None);
+
+    // Functions are rolled back as well if the context can remove them
+    struct RejectingContext(HashMapContext);
+    impl Context for RejectingContext {
+        fn get_value(&self, identifier: &str) -> Option<Cow<'_, Value>> {
+            self.0.get_value(identifier)
+        }
+
+        fn call_function(&self, identifier: &str, argument: &Value) -> EvalexprResult<Value> {
+            self.0.call_function(identifier, argument)
+        }
+    }
+    impl ContextWithMutableVariables for RejectingContext {
+        fn set_value(&mut self, identifier: String, value: Value) -> EvalexprResult<()> {
+            self.0.set_value(identifier, value)
+        }
+
+        fn remove_value(&mut self, identifier: &str) -> EvalexprResult<Option<Value>> {
+            self.0.remove_value(identifier)
+        }
+    }
+    impl ContextWithMutableFunctions for RejectingContext {
+        fn set_function(&mut self, identifier: String, function: Function) -> EvalexprResult<()> {
+            if identifier == "rejected" {
+                Err(EvalexprError::ContextNotMutable)
+            } else {
+                self.0.set_function(identifier, function)
+            }
+        }
+
+        fn remove_function(&mut self, identifier: &str) -> EvalexprResult<Option<Function>> {
+            self.0.remove_function(identifier)
+        }
+    }
+
+    let mut rejecting = RejectingContext(context.clone());
+    eval_with_context_mut("h = fn() 1", &mut rejecting).unwrap();
+    assert_eq!(
+        eval_with_context_transactional(
+            "a = 2; h = fn() 2; i = fn() 3; rejected = fn() 0",
+            &mut rejecting
+        ),
+        Err(EvalexprError::ContextNotMutable)
+    );
+    assert_eq!(rejecting.get_value("a").as_deref(), Some(&Value::from(1)));
+    assert_eq!(eval_with_context("h()", &rejecting), Ok(Value::from(1)));
+    assert_eq!(rejecting.0.get_function("i"), None);
+
+    // Snapshots
+    let snapshot = context.snapshot();
+    eval_with_context_mut("a = 5; c = \"new\"; g = fn() 0", &mut context).unwrap();
+    assert_eq!(context.get_value("c").as_deref(), Some(&Value::from("new")));
+    context.restore(snapshot.clone());
+    assert_eq!(context.get_value("a").as_deref(), Some(&Value::from(1)));
+    assert_eq!(context.get_value("c"), None);
+    assert_eq!(context.get_function("g"), None);
+    assert!(context.get_function("f").is_some());
+    context.clear();
+    context.restore(snapshot);
+    assert_eq!(context.get_value("b").as_deref(), Some(&Value::from(2)));
+}
+
+#[test]
+fn test_recording_context() {
+    let mut context = context_map! { "a" => 1, "s" => "x" }.unwrap();
+
+    let (value, changes) =
+        eval_with_context_recorded("a += 2; s = s + \"y\"; b = a * 2; b", &mut context);
+    assert_eq!(value, Ok(Value::from(6)));
+    assert_eq!(
+        changes,
+        vec![
+            VariableChange {
+                identifier: "a".into(),
+                old_value: Some(Value::from(1)),
+                new_value: Value::from(3),
+            },
+            VariableChange {
+                identifier: "s".into(),
+                old_value: Some(Value::from("x")),
+                new_value: Value::from("xy"),
+            },
+            VariableChange {
+                identifier: "b".into(),
+                old_value: None,
+                new_value: Value::from(6),
+            },
+        ]
+    );
+    assert_eq!(context.get_value("b").as_deref(), Some(&Value::from(6)));
+
+    // Reads, function definitions and assignments within function bodies are not recorded
+    let (_, changes) =
+        eval_with_context_recorded("f = fn(x) (y = x; y + a); f(1) + b", &mut context);
+    assert_eq!(changes, vec![]);
+
+    // Writes made before a failure are returned alongside the error
+    let (value, changes) = eval_with_context_recorded("b = 1; c = b / 0", &mut context);
+    assert!(value.is_err());
+    assert_eq!(
+        changes,
+        vec![VariableChange {
+            identifier: "b".into(),
+            old_value: Some(Value::from(6)),
+            new_value: Value::from(1),
+        }]
+    );
+    assert_eq!(
+        eval_with_context_recorded("b = ", &mut context),
+        (
+            Err(EvalexprError::WrongOperatorArgumentAmount {
+                expected: 2,
+                actual: 1
+            }),
+            vec![]
+        )
+    );
+    context.set_value("b".into(), Value::from(6)).unwrap();
+
+    // Compiled expressions and rejected writes
+    let compiled = build_operator_tree("a -= 1; a = 0.5").unwrap().compile();
+    let mut recording = RecordingContext::new(&mut context);
+    assert!(compiled.eval_with_context_mut(&mut recording).is_err());
+    assert_eq!(
+        recording.take_changes(),
+        vec![VariableChange {
+            identifier: "a".into(),
+            old_value: Some(Value::from(3)),
+            new_value: Value::from(2),
+        }]
+    );
+    assert_eq!(recording.changes(), &[]);
+    assert_eq!(
+        recording.context().get_value("a").as_deref(),
+        Some(&Value::from(2))
+    );
+
+    // Atomic updates of concurrent contexts are recorded too
+    let concurrent = ConcurrentContext::new();
+    concurrent.set_value("n".into(), Value::from(1)).unwrap();
+    let (_, changes) = eval_with_context_recorded("n *= 5", &mut &concurrent);
+    assert_eq!(changes[0].old_value, Some(Value::from(1)));
+    assert_eq!(changes[0].new_value, Value::from(5));
+}
+
+#[test]
+fn test_dependencies() {
+    fn names(set: &std::collections::BTreeSet<String>) -> Vec<&str> {
+        set.iter().map(String::as_str).collect()
+    }
+
+    let dependencies = build_operator_tree("a = b; c += a; d = c * e; (b, f(g, e))")
+        .unwrap()
+        .dependencies();
+    assert_eq!(
+        names(dependencies.read_variables()),
+        vec!["a", "b", "c", "e", "g"]
+    );
+    assert_eq!(names(dependencies.written_variables()), vec!["a", "c", "d"]);
+    assert_eq!(
+        names(dependencies.free_variables()),
+        vec!["b", "c", "e", "g"]
+    );
+    assert_eq!(names(dependencies.called_functions()), vec!["f"]);
+    assert!(dependencies.defined_functions().is_empty());
+
+    // Reading after writing does not make a variable free, and compound assignments read their target
+    let dependencies = build_operator_tree("x = 1; x = x + 1; y *= x")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.read_variables()), vec!["x", "y"]);
+    assert_eq!(names(dependencies.free_variables()), vec!["y"]);
+    let dependencies = build_operator_tree("x = x + 1").unwrap().dependencies();
+    assert_eq!(names(dependencies.free_variables()), vec!["x"]);
+
+    // Assignments that may be skipped do not prevent later reads from being free
+    let dependencies = build_operator_tree("if(c, x = 1, 0); x")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.written_variables()), vec!["x"]);
+    assert_eq!(names(dependencies.free_variables()), vec!["c", "x"]);
+    let dependencies = build_operator_tree("c && (y = true); y || (z = 1; z); w = 1; w")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.written_variables()), vec!["w", "y", "z"]);
+    assert_eq!(names(dependencies.free_variables()), vec!["c", "y"]);
+    let dependencies = build_operator_tree("if(c, f = fn() (v = 1; v), 0); if(v, u, u = 1)")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.free_variables()), vec!["c", "u", "v"]);
+    let dependencies = build_operator_tree("f = fn(c) (if(c, t = 1, 0); t); f(true)")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.free_variables()), vec!["t"]);
+
+    // Function definitions and lambdas have local parameters and variables
+    let dependencies = build_operator_tree(
+        "square = fn(x) (y = x * x; y * scale); map(values, v -> square(v) + offset); g(h)",
+    )
+    .unwrap()
+    .dependencies();
+    assert_eq!(
+        names(dependencies.read_variables()),
+        vec!["h", "offset", "scale", "values"]
+    );
+    assert!(dependencies.written_variables().is_empty());
+    assert_eq!(
+        names(dependencies.free_variables()),
+        vec!["h", "offset", "scale", "values"]
+    );
+    assert_eq!(
+        names(dependencies.called_functions()),
+        vec!["g", "map", "square"]
+    );
+    assert_eq!(names(dependencies.defined_functions()), vec!["square"]);
+
+    // Parameters holding functions are not reported as called functions
+    let dependencies = build_operator_tree("apply = fn(f, x) f(x); apply(k -> k, 1)")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.called_functions()), vec!["apply"]);
+    assert!(dependencies.read_variables().is_empty());
+
+    // Map fields are reported as written in the expression, and nothing is reported for constants
+    let dependencies = build_operator_tree("m.x + m[\"y\"]")
+        .unwrap()
+        .dependencies();
+    assert_eq!(names(dependencies.read_variables()), vec!["m", "m.x"]);
+    assert_eq!(
+        build_operator_tree("1 + \"a\"").unwrap().dependencies(),
+        Dependencies::default()
+    );
+}
+
+#[test]
+fn test_formula_set() {
+    let mut formulas = FormulaSet::with_context(context_map! { "a" => 1, "b" => 2 }.unwrap());
+    formulas.set_formula("d".into(), "c * 2 + b").unwrap();
+    formulas.set_formula("c".into(), "a + b").unwrap();
+    formulas.set_formula("e".into(), "a * 10").unwrap();
+    formulas.set_formula("f".into(), "double(d)").unwrap();
+    formulas
+        .set_function("double".into(), Function::typed(|x: FloatType| x * 2.0))
+        .unwrap();
+
+    assert_eq!(formulas.evaluation_order(), vec!["c", "d", "e", "f"]);
+    assert_eq!(formulas.dependents("b"), vec!["c", "d"]);
+    assert_eq!(formulas.dependents("double"), vec!["f"]);
+    assert!(formulas.is_outdated("f"));
+    assert_eq!(formulas.get_value("f"), None);
+    assert_eq!(
+        formulas.recompute(),
+        Ok(vec!["c".into(), "d".into(), "e".into(), "f".into()])
+    );
+    assert_eq!(formulas.get_value("f").as_deref(), Some(&Value::from(16.0)));
+    assert!(!formulas.is_outdated("f"));
+    assert_eq!(formulas.recompute(), Ok(vec![]));
+
+    // Only affected formulas are recomputed
+    formulas.set_input("b".into(), 3.into()).unwrap();
+    assert_eq!(
+        formulas.recompute(),
+        Ok(vec!["c".into(), "d".into(), "f".into()])
+    );
+    assert_eq!(formulas.get_value("f").as_deref(), Some(&Value::from(22.0)));
+    formulas
+        .set_function("double".into(), Function::typed(|x: FloatType| x * 3.0))
+        .unwrap();
+    assert_eq!(formulas.recompute(), Ok(vec!["f".into()]));
+    assert_eq!(formulas.get_value("f").as_deref(), Some(&Value::from(33.0)));
+
+    // Replacing a formula recomputes its dependents, and its type may change
+    formulas.set_formula("c".into(), "0.5").unwrap();
+    assert_eq!(
+        formulas.recompute(),
+        Ok(vec!["c".into(), "d".into(), "f".into()])
+    );
+    assert_eq!(formulas.get_value("d").as_deref(), Some(&Value::from(4.0)));
+
+    // Cycles are rejected without changing the set
+    assert_eq!(
+        formulas.set_formula("c".into(), "f + 1"),
+        Err(EvalexprError::DependencyCycle {
+            cycle: vec!["c".into(), "f".into(), "d".into(), "c".into()]
+        })
+    );
+    assert_eq!(
+        formulas.set_formula("g".into(), "g + 1"),
+        Err(EvalexprError::DependencyCycle {
+            cycle: vec!["g".into(), "g".into()]
+        })
+    );
+    assert_eq!(
+        formulas.formula("c"),
+        Some(&build_operator_tree("0.5").unwrap())
+    );
+    assert_eq!(formulas.formula("g"), None);
+    assert_eq!(formulas.recompute(), Ok(vec![]));
+
+    // Inputs and formulas cannot shadow each other, and inputs keep their type
+    assert_eq!(
+        formulas.set_input("c".into(), 1.into()),
+        Err(EvalexprError::IdentifierIsFormula("c".into()))
+    );
+    assert_eq!(
+        formulas.set_formula("a".into(), "1"),
+        Err(EvalexprError::IdentifierIsInput("a".into()))
+    );
+    assert_eq!(formulas.get_value("a").as_deref(), Some(&Value::from(1)));
+
+    // Formulas cannot assign to variables
+    assert_eq!(
+        formulas.set_formula("g".into(), "if(b > 1, x = 1, 0); b"),
+        Err(EvalexprError::FormulaWritesVariable {
+            name: "g".into(),
+            identifier: "x".into()
+        })
+    );
+    assert_eq!(formulas.formula("g"), None);
+    assert_eq!(
+        formulas.set_input("a".into(), 1.5.into()),
+        Err(EvalexprError::ExpectedInt {
+            actual: Value::from(1.5)
+        })
+    );
+
+    // Failing formulas are reported by name and stay outdated
+    formulas.set_input("a".into(), 0.into()).unwrap();
+    formulas.set_formula("h".into(), "1 / a").unwrap();
+    assert_eq!(
+        formulas.recompute(),
+        Err(EvalexprError::FormulaError {
+            name: "h".into(),
+            error: Box::new(EvalexprError::DivisionError {
+                dividend: Value::from(1),
+                divisor: Value::from(0)
+            }),
+        })
+    );
+    assert!(formulas.is_outdated("h"));
+    assert!(!formulas.is_outdated("e"));
+    formulas.set_input("a".into(), 4.into()).unwrap();
+    assert_eq!(formulas.recompute(), Ok(vec!["e".into(), "h".into()]));
+
+    // Removing a formula breaks its dependents
+    assert!(formulas.remove_formula("d").is_some());
+    assert_eq!(formulas.get_value("d"), None);
+    assert_eq!(
+        formulas.formula_names().collect::<Vec<_>>(),
+        vec!["c", "e", "f", "h"]
+    );
+    assert_eq!(
+        formulas.recompute().map_err(EvalexprError::without_span),
+        Err(EvalexprError::FormulaError {
+            name: "f".into(),
+            error: Box::new(EvalexprError::VariableIdentifierNotFound("d".into())),
+        })
+    );
+    formulas.set_input("d".into(), 1.into()).unwrap();
+    assert_eq!(formulas.recompute(), Ok(vec!["f".into()]));
+
+    // Map fields depend on the map
+    formulas
+        .set_input(
+            "m".into(),
+            Value::Map(
+                vec![("x".to_string(), Value::from(7))]
+                    .into_iter()
+                    .collect(),
+            ),
+        )
+        .unwrap();
+    formulas.set_formula("mx".into(), "m.x").unwrap();
+    assert_eq!(formulas.dependents("m"), vec!["mx"]);
+    assert_eq!(formulas.recompute(), Ok(vec!["mx".into()]));
+    assert_eq!(formulas.get_value("mx").as_deref(), Some(&Value::from(7)));
+}
+
+#[test]
+fn test_overflow_policy() {
+    let max = IntType::MAX;
+    let min = IntType::MIN;
+    let bounds = context_map! { "max" => max, "min" => min }.unwrap();
+    let eval_bounds = |expression: &str| eval_with_context(expression, &bounds);
+    let cases = [
+        ("max + 1", min, max, max as FloatType + 1.0),
+        ("min - 1", max, min, min as FloatType - 1.0),
+        ("max * 3", max.wrapping_mul(3), max, max as FloatType * 3.0),
+        ("-min", min, max, -(min as FloatType)),
+        ("min / -1", min, max, -(min as FloatType)),
+    ];
+    for (expression, wrapping, saturating, float) in cases.iter() {
+        assert!(eval_bounds(expression).is_err(), "{}", expression);
+        assert!(OverflowPolicy::Checked
+            .enforce(|| eval_bounds(expression))
+            .is_err());
+        assert_eq!(
+            OverflowPolicy::Wrapping.enforce(|| eval_bounds(expression)),
+            Ok(Value::from(*wrapping))
+        );
+        assert_eq!(
+            OverflowPolicy::Saturating.enforce(|| eval_bounds(expression)),
+            Ok(Value::from(*saturating))
+        );
+        assert_eq!(
+            OverflowPolicy::PromoteToFloat.enforce(|| eval_bounds(expression)),
+            Ok(Value::from(*float))
+        );
+    }
+    assert_eq!(eval_bounds("min + 1 - 1"), Ok(Value::from(min)));
+    for policy in &[
+        OverflowPolicy::Checked,
+        OverflowPolicy::Wrapping,
+        OverflowPolicy::Saturating,
+        OverflowPolicy::PromoteToFloat,
+    ] {
+        assert_eq!(
+            policy.enforce(|| eval_bounds("(min % -1, rem_euclid(min, -1))")),
+            Ok(Value::from(vec![Value::from(0), Value::from(0)]))
+        );
+    }
+
+    // Division by zero is not an overflow, and results that fit stay integers
+    for policy in &[
+        OverflowPolicy::Wrapping,
+        OverflowPolicy::Saturating,
+        OverflowPolicy::PromoteToFloat,
+    ] {
+        assert_eq!(
+            policy.enforce(|| eval("1 / 0")),
+            Err(EvalexprError::DivisionError {
+                dividend: Value::from(1),
+                divisor: Value::from(0)
+            })
+        );
+        assert!(policy.enforce(|| eval("1 % 0")).is_err());
+        assert_eq!(policy.enforce(|| eval("2 + 3 * 4")), Ok(Value::from(14)));
+    }
+
+    // Shifts
+    assert_eq!(eval("shl(1, 62)"), Ok(Value::from(1 << 62)));
+    assert_eq!(
+        eval("shl(1, 64)"),
+        Err(EvalexprError::ShiftError {
+            value: Value::from(1),
+            shift: Value::from(64)
+        })
+    );
+    assert!(eval("shr(1, -1)").is_err());
+    assert_eq!(
+        OverflowPolicy::Wrapping.enforce(|| eval("shl(1, 65)")),
+        Ok(Value::from(2))
+    );
+    assert_eq!(
+        OverflowPolicy::Saturating.enforce(|| eval("(shl(1, 64), shr(-8, 100), shr(8, 100))")),
+        Ok(Value::from(vec![
+            Value::from(0),
+            Value::from(-1),
+            Value::from(0)
+        ]))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToFloat.enforce(|| eval("shl(3, 70)")),
+        Ok(Value::from(0))
+    );
+
+    // Compound assignments, compiled expressions and functions defined in expressions
+    let mut context = context_map! { "a" => max }.unwrap();
+    assert_eq!(
+        OverflowPolicy::Saturating.enforce(|| eval_with_context_mut("a += 10; a", &mut context)),
+        Ok(Value::from(max))
+    );
+    let compiled = build_operator_tree("f = fn(x) x * 2; f(a)")
+        .unwrap()
+        .compile();
+    assert_eq!(
+        OverflowPolicy::Wrapping.enforce(|| compiled.eval_with_context_mut(&mut context)),
+        Ok(Value::from(-2))
+    );
+    assert!(compiled.eval_with_context_mut(&mut context).is_err());
+
+    // Promoted results cannot be assigned to integer variables of typed contexts
+    let mut context = HashMapContext::new();
+    assert_eq!(
+        OverflowPolicy::PromoteToFloat
+            .enforce(|| eval_with_context_mut("x = 9223372036854775807; x += 1", &mut context)),
+        Err(EvalexprError::ExpectedInt {
+            actual: Value::from(max as FloatType + 1.0)
+        })
+    );
+    assert_eq!(context.get_value("x").as_deref(), Some(&Value::from(max)));
+
+    // Nested policies are restored
+    assert_eq!(
+        OverflowPolicy::Wrapping.enforce(|| {
+            let inner = OverflowPolicy::Saturating.enforce(|| {
+                assert_eq!(OverflowPolicy::current(), OverflowPolicy::Saturating);
+                eval_bounds("max + 1")
+            })?;
+            assert_eq!(OverflowPolicy::current(), OverflowPolicy::Wrapping);
+            Ok((inner, eval_bounds("max + 1")?))
+        }),
+        Ok((Value::from(max), Value::from(min)))
+    );
+    assert_eq!(OverflowPolicy::current(), OverflowPolicy::Checked);
+
+    // Constant folding keeps overflowing operations for evaluation
+    let mut tree = build_operator_tree("9223372036854775807 + 1").unwrap();
+    OverflowPolicy::Wrapping
+        .enforce(|| {
+            tree.optimize();
+            Ok(())
+        })
+        .unwrap();
+    assert!(tree.eval().is_err());
+    assert_eq!(
+        OverflowPolicy::Saturating.enforce(|| tree.eval()),
+        Ok(Value::from(max))
+    );
+
+    // The type checker knows about promotion to floats
+    let tree = build_operator_tree("a * 2").unwrap();
+    let mut schema = TypeSchema::new();
+    schema.set_variable_type("a".into(), ValueType::Int);
+    assert_eq!(tree.type_check(&schema), Ok(ValueType::Int.into()));
+    assert_eq!(
+        OverflowPolicy::PromoteToFloat.enforce(|| Ok(tree.type_check(&schema))),
+        Ok(Ok(TypeSet::number()))
+    );
+}
+
+#[test]
+fn test_integer_exponentiation_and_division() {
+    // Exponentiation
+    assert_eq!(eval("2 ^ 62"), Ok(Value::from(1 << 62)));
+    assert_eq!(eval("(-3) ^ 3"), Ok(Value::from(-27)));
+    assert_eq!(eval("0 ^ 0"), Ok(Value::from(1)));
+    assert_eq!(eval("(-1) ^ 10000000001"), Ok(Value::from(-1)));
+    assert_eq!(eval("2 ^ (-1)"), Ok(Value::from(0.5)));
+    assert_eq!(eval("2 ^ 0.5"), Ok(Value::from(2.0f64.sqrt())));
+    assert_eq!(
+        eval("2 ^ 63"),
+        Err(EvalexprError::ExponentiationError {
+            base: Value::from(2),
+            exponent: Value::from(63)
+        })
+    );
+    assert_eq!(
+        OverflowPolicy::Wrapping.enforce(|| eval("(2 ^ 64, 3 ^ 41)")),
+        Ok(Value::from(vec![
+            Value::from(0),
+            Value::from(3i64.wrapping_pow(41))
+        ]))
+    );
+    assert_eq!(
+        OverflowPolicy::Saturating.enforce(|| eval("((-3) ^ 41, (-3) ^ 42)")),
+        Ok(Value::from(vec![
+            Value::from(IntType::MIN),
+            Value::from(IntType::MAX)
+        ]))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToFloat.enforce(|| eval("2 ^ 64")),
+        Ok(Value::from(2.0f64.powi(64)))
+    );
+
+    // Floor division
+    assert_eq!(eval("7 // 2"), Ok(Value::from(3)));
+    assert_eq!(eval("-7 // 2"), Ok(Value::from(-4)));
+    assert_eq!(eval("7 // -2"), Ok(Value::from(-4)));
+    assert_eq!(eval("-8 // 2"), Ok(Value::from(-4)));
+    assert_eq!(eval("-7.5 // 2"), Ok(Value::from(-4.0)));
+    assert_eq!(
+        eval("1 // 0"),
+        Err(EvalexprError::DivisionError {
+            dividend: Value::from(1),
+            divisor: Value::from(0)
+        })
+    );
+    let mut context = context_map! { "a" => -7, "min" => IntType::MIN }.unwrap();
+    assert_eq!(
+        eval_with_context_mut("a //= 2; a", &mut context),
+        Ok(Value::from(-4))
+    );
+    assert!(eval_with_context("min // -1", &context).is_err());
+    assert_eq!(
+        OverflowPolicy::Wrapping.enforce(|| eval_with_context("min // -1", &context)),
+        Ok(Value::from(IntType::MIN))
+    );
+    assert_eq!(
+        Function::from_definition("fn(x) (x //= 2; x // (x // 3))")
+            .unwrap()
+            .to_string(),
+        "fn(x) (x //= 2; x // (x // 3))"
+    );
+    assert!(eval("1 / / 2").is_err());
+
+    // Euclidean division
+    assert_eq!(eval("div_euclid(-7, 2)"), Ok(Value::from(-4)));
+    assert_eq!(eval("div_euclid(-7, -2)"), Ok(Value::from(4)));
+    assert_eq!(eval("rem_euclid(-7, 2)"), Ok(Value::from(1)));
+    assert_eq!(eval("rem_euclid(-7, -2)"), Ok(Value::from(1)));
+    assert_eq!(eval("rem_euclid(-7.5, 2)"), Ok(Value::from(0.5)));
+    assert_eq!(eval("div_euclid(-7.5, 2)"), Ok(Value::from(-4.0)));
+    assert!(eval("div_euclid(1, 0)").is_err());
+    assert!(eval("rem_euclid(1, 0)").is_err());
+    assert!(eval("div_euclid(1)").is_err());
+}
+
+#[cfg(feature = "decimal_support")]
+#[test]
+fn test_decimals() {
+    let decimal = |string: &str| Value::from(string.parse::<DecimalType>().unwrap());
+
+    // Literals and arithmetic
+    assert_eq!(eval("1.25d"), Ok(decimal("1.25")));
+    assert_eq!(eval("25d"), Ok(decimal("25")));
+    assert_eq!(eval("1e-3d"), Ok(decimal("0.001")));
+    assert_eq!(eval("-1.5d"), Ok(decimal("-1.5")));
+    assert_eq!(eval("0.1d + 0.2d"), Ok(decimal("0.3")));
+    assert_eq!(eval("0.1d + 0.2d == 0.3d"), Ok(Value::from(true)));
+    assert_eq!(eval("0.1 + 0.2 == 0.3"), Ok(Value::from(false)));
+    assert_eq!(eval("1.10d * 3"), Ok(decimal("3.30")));
+    assert_eq!(eval("3 - 0.5d"), Ok(decimal("2.5")));
+    assert_eq!(eval("1d / 4"), Ok(decimal("0.25")));
+    assert_eq!(eval("-7.5d // 2"), Ok(decimal("-4")));
+    assert_eq!(eval("7.5d % 2"), Ok(decimal("1.5")));
+    assert_eq!(eval("1.5d ^ 2"), Ok(decimal("2.25")));
+    assert_eq!(eval("2d ^ (-2)"), Ok(decimal("0.25")));
+    assert_eq!(eval("0.5d + 0.25"), Ok(Value::from(0.75)));
+    assert_eq!(eval("4d ^ 0.5"), Ok(Value::from(2.0)));
+    assert_eq!(
+        eval_with_context_mut("a = 1.5d; a += 1; a", &mut HashMapContext::new()),
+        Ok(decimal("2.5"))
+    );
+
+    // Comparisons
+    assert_eq!(eval("1.5d > 1"), Ok(Value::from(true)));
+    assert_eq!(eval("2 <= 1.99d"), Ok(Value::from(false)));
+    assert_eq!(eval("1.50d >= 1.5d"), Ok(Value::from(true)));
+    assert_eq!(eval("0.5d < 0.6"), Ok(Value::from(true)));
+    assert_eq!(eval("min(1.5d, 2, 1.25d)"), Ok(decimal("1.25")));
+    assert_eq!(eval("max((1.5d, 2, 1.25))"), Ok(Value::from(2)));
+    assert_eq!(
+        eval("max(0.1000000000000000000000000001d, 0.1d)"),
+        Ok(decimal("0.1000000000000000000000000001"))
+    );
+    assert_eq!(
+        eval("sort_by((0.3d, 0.1d, 0.2d), x -> x)"),
+        Ok(Value::from(vec![
+            decimal("0.1"),
+            decimal("0.2"),
+            decimal("0.3")
+        ]))
+    );
+    assert_eq!(
+        build_operator_tree("max(2, 1.5d)")
+            .unwrap()
+            .type_check(&TypeSchema::new()),
+        Ok(TypeSet::from(ValueType::Int).union(ValueType::Decimal.into()))
+    );
+
+    // Errors
+    assert!(matches!(
+        eval("1d / 0"),
+        Err(EvalexprError::DivisionError { .. })
+    ));
+    assert!(matches!(
+        eval("1d % 0d"),
+        Err(EvalexprError::ModulationError { .. })
+    ));
+    assert!(matches!(
+        eval("79228162514264337593543950335d + 1"),
+        Err(EvalexprError::AdditionError { .. })
+    ));
+    assert!(matches!(
+        eval("10d ^ 40"),
+        Err(EvalexprError::ExponentiationError { .. })
+    ));
+    assert!(OverflowPolicy::Saturating
+        .enforce(|| eval("79228162514264337593543950335d * 2"))
+        .is_err());
+    assert_eq!(
+        eval("99999999999999999999999999999999d"),
+        Err(EvalexprError::InvalidDecimalLiteral(
+            "99999999999999999999999999999999d".into()
+        ))
+    );
+    assert_eq!(
+        eval("1 + 1e+40d"),
+        Err(EvalexprError::InvalidDecimalLiteral("1e+40d".into()))
+    );
+    assert_eq!(eval("1e+3d"), Ok(decimal("1000")));
+
+    // Rounding
+    assert_eq!(
+        eval("decimal::round(2.345d, 2, \"half_even\")"),
+        Ok(decimal("2.34"))
+    );
+    assert_eq!(
+        eval("decimal::round(2.345d, 2, \"half_up\")"),
+        Ok(decimal("2.35"))
+    );
+    assert_eq!(
+        eval("decimal::round(2.345d, 2, \"half_down\")"),
+        Ok(decimal("2.34"))
+    );
+    assert_eq!(
+        eval("decimal::round(-2.341d, 2, \"up\")"),
+        Ok(decimal("-2.35"))
+    );
+    assert_eq!(
+        eval("decimal::round(-2.349d, 2, \"down\")"),
+        Ok(decimal("-2.34"))
+    );
+    assert_eq!(
+        eval("decimal::round(-2.341d, 2, \"floor\")"),
+        Ok(decimal("-2.35"))
+    );
+    assert_eq!(
+        eval("decimal::round(2.341d, 2, \"ceiling\")"),
+        Ok(decimal("2.35"))
+    );
+    assert_eq!(
+        eval("decimal::round(2.5, 0, \"half_even\")"),
+        Ok(decimal("2"))
+    );
+    assert_eq!(
+        eval("decimal::round(1.5d, 0, \"sideways\")"),
+        Err(EvalexprError::InvalidRoundingMode("sideways".to_string()))
+    );
+    assert_eq!(
+        eval("decimal::round(1.5d, -1, \"half_up\")"),
+        Err(EvalexprError::InvalidDecimalPlaces(-1))
+    );
+    assert_eq!(eval("floor(-1.5d)"), Ok(decimal("-2")));
+    assert_eq!(eval("round(2.5d)"), Ok(decimal("3")));
+    assert_eq!(eval("ceil(1.2d)"), Ok(decimal("2")));
+
+    // Conversions
+    assert_eq!(eval("decimal::from(\"1.5\")"), Ok(decimal("1.5")));
+    assert_eq!(eval("decimal::from(\"1e-2\")"), Ok(decimal("0.01")));
+    assert_eq!(eval("decimal::from(3)"), Ok(decimal("3")));
+    assert_eq!(eval("decimal::from(0.25)"), Ok(decimal("0.25")));
+    assert!(matches!(
+        eval("decimal::from(\"abc\")"),
+        Err(EvalexprError::ConversionError { .. })
+    ));
+    assert!(eval("decimal::from(true)").is_err());
+    assert_eq!(eval("decimal::to_int(12.00d)"), Ok(Value::from(12)));
+    assert_eq!(
+        eval("decimal::to_int(12.5d)"),
+        Err(EvalexprError::ConversionError {
+            value: decimal("12.5"),
+            target: ValueType::Int
+        })
+    );
+    assert_eq!(eval("decimal::to_float(0.25d)"), Ok(Value::from(0.25)));
+    assert_eq!(eval("math::sqrt(4d)"), Ok(Value::from(2.0)));
+    assert_eq!(eval("typeof(1.5d)"), Ok(Value::from("decimal")));
+    assert_eq!(eval("str::from(1.50d)"), Ok(Value::from("1.50")));
+    assert_eq!(
+        Value::from(1).as_decimal(),
+        Err(EvalexprError::ExpectedDecimal {
+            actual: Value::from(1)
+        })
+    );
+
+    // Type checking and source form
+    let mut schema = TypeSchema::new();
+    schema.set_variable_type("price".into(), ValueType::Decimal);
+    let check = |expression: &str| build_operator_tree(expression).unwrap().type_check(&schema);
+    assert_eq!(check("price * 2"), Ok(ValueType::Decimal.into()));
+    assert_eq!(check("price * 2.0"), Ok(ValueType::Float.into()));
+    assert_eq!(
+        check("round(price)"),
+        Ok([ValueType::Float, ValueType::Decimal]
+            .iter()
+            .copied()
+            .collect())
+    );
+    assert_eq!(
+        Function::from_definition("fn(x) x * 1.5d + -2d")
+            .unwrap()
+            .to_string(),
+        "fn(x) x * 1.5d + -2d"
+    );
+}
+
+#[cfg(feature = "bigint_support")]
+#[test]
+fn test_bigints() {
+    let bigint = |string: &str| Value::from(string.parse::<BigIntType>().unwrap());
+
+    // Literals
+    assert_eq!(
+        eval("18446744073709551616"),
+        Ok(bigint("18446744073709551616"))
+    );
+    assert_eq!(eval("9223372036854775807"), Ok(Value::from(IntType::MAX)));
+    assert_eq!(eval("-9223372036854775808"), Ok(Value::from(IntType::MIN)));
+    assert_eq!(
+        eval("-9223372036854775809"),
+        Ok(bigint("-9223372036854775809"))
+    );
+    assert_eq!(eval("1e30"), Ok(Value::from(1e30)));
+
+    // Arithmetic, whose results are integers if they fit
+    assert_eq!(
+        eval("9223372036854775808 + 1"),
+        Ok(bigint("9223372036854775809"))
+    );
+    assert_eq!(
+        eval("9223372036854775808 - 1"),
+        Ok(Value::from(IntType::MAX))
+    );
+    assert_eq!(
+        eval("2 * 18446744073709551616"),
+        Ok(bigint("36893488147419103232"))
+    );
+    assert_eq!(
+        eval("18446744073709551616 / 4294967296"),
+        Ok(Value::from(1i64 << 32))
+    );
+    assert_eq!(
+        eval("-18446744073709551617 / 2"),
+        Ok(Value::from(IntType::MIN))
+    );
+    assert_eq!(
+        eval("-18446744073709551617 // 2"),
+        Ok(bigint("-9223372036854775809"))
+    );
+    assert_eq!(eval("-18446744073709551617 % 10"), Ok(Value::from(-7)));
+    // Results that fit into an integer are integers, which overflow as usual
+    assert!(matches!(
+        eval("-(-9223372036854775808)"),
+        Err(EvalexprError::NegationError { .. })
+    ));
+    assert_eq!(
+        eval("18446744073709551616 ^ 2"),
+        Ok(bigint("340282366920938463463374607431768211456"))
+    );
+    assert_eq!(
+        eval("18446744073709551616 * 0.5"),
+        Ok(Value::from(2.0f64.powi(63)))
+    );
+    assert_eq!(
+        eval("18446744073709551616 ^ (-1)"),
+        Ok(Value::from(2.0f64.powi(-64)))
+    );
+    assert_eq!(
+        eval_with_context_mut(
+            "a = 9223372036854775808; a *= 2; a",
+            &mut HashMapContext::new()
+        ),
+        Ok(bigint("18446744073709551616"))
+    );
+    assert!(matches!(
+        eval("18446744073709551616 / 0"),
+        Err(EvalexprError::DivisionError { .. })
+    ));
+    assert!(matches!(
+        eval("18446744073709551616 % 0"),
+        Err(EvalexprError::ModulationError { .. })
+    ));
+    assert!(matches!(
+        eval("18446744073709551616 ^ 4294967296"),
+        Err(EvalexprError::ExponentiationError { .. })
+    ));
+
+    // Comparisons
+    assert_eq!(eval("18446744073709551616 > 1"), Ok(Value::from(true)));
+    assert_eq!(eval("-18446744073709551616 < 1"), Ok(Value::from(true)));
+    assert_eq!(
+        eval("18446744073709551617 >= 18446744073709551616"),
+        Ok(Value::from(true))
+    );
+    assert_eq!(eval("18446744073709551616 <= 1.5"), Ok(Value::from(false)));
+    assert_eq!(
+        eval("18446744073709551616 == 18446744073709551616"),
+        Ok(Value::from(true))
+    );
+    assert_eq!(
+        eval("sort_by((18446744073709551617, -1, 18446744073709551616), x -> x)"),
+        Ok(Value::from(vec![
+            Value::from(-1),
+            bigint("18446744073709551616"),
+            bigint("18446744073709551617")
+        ]))
+    );
+
+    // Promotion on overflow
+    assert!(eval("9223372036854775807 * 2").is_err());
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval("9223372036854775807 * 2")),
+        Ok(bigint("18446744073709551614"))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval("2 ^ 64 - 2 ^ 64 + 1")),
+        Ok(Value::from(1))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval("-(-9223372036854775807 - 1)")),
+        Ok(bigint("9223372036854775808"))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt
+            .enforce(|| eval("div_euclid(-9223372036854775807 - 1, -1)")),
+        Ok(bigint("9223372036854775808"))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval("2 ^ (-1)")),
+        Ok(Value::from(0.5))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval("(-9223372036854775807 - 1) % -1")),
+        Ok(Value::from(0))
+    );
+    let mut context = HashMapContext::new();
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt
+            .enforce(|| eval_with_context_mut("x = 9223372036854775807; x += 1", &mut context)),
+        Err(EvalexprError::ExpectedInt {
+            actual: bigint("9223372036854775808")
+        })
+    );
+    assert_eq!(
+        context.get_value("x").as_deref(),
+        Some(&Value::from(IntType::MAX))
+    );
+
+    // Powers that would be too large are rejected before they are computed
+    assert!(matches!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval("3 ^ 4000000000")),
+        Err(EvalexprError::ExponentiationError { .. })
+    ));
+    assert!(matches!(
+        eval("99999999999999999999 ^ 30000000"),
+        Err(EvalexprError::ExponentiationError { .. })
+    ));
+    assert_eq!(
+        EvalLimits::new()
+            .max_value_size(1000)
+            .enforce(|| eval("x = 99999999999999999999 ^ 30000000; 1")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::ValueSize,
+            maximum: 1000
+        })
+    );
+    assert_eq!(
+        EvalLimits::new()
+            .max_value_size(8)
+            .enforce(|| eval("18446744073709551616 * 18446744073709551616")),
+        Err(EvalexprError::LimitExceeded {
+            limit: Limit::ValueSize,
+            maximum: 8
+        })
+    );
+    assert_eq!(
+        eval("typeof(18446744073709551616 ^ 100000)"),
+        Ok(Value::from("bigint"))
+    );
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| eval(
+            "(0 ^ 0, 0 ^ 5000000000, (-1) ^ 5000000001, 18446744073709551616 ^ 0)"
+        )),
+        Ok(Value::from(vec![
+            Value::from(1),
+            Value::from(0),
+            Value::from(-1),
+            Value::from(1)
+        ]))
+    );
+
+    // Builtin functions
+    assert_eq!(
+        eval("div_euclid(-18446744073709551617, 2)"),
+        Ok(bigint("-9223372036854775809"))
+    );
+    assert_eq!(
+        eval("rem_euclid(-18446744073709551617, 2)"),
+        Ok(Value::from(1))
+    );
+    assert!(eval("rem_euclid(18446744073709551616, 0)").is_err());
+    assert_eq!(eval("min(99999999999999999999, 1)"), Ok(Value::from(1)));
+    assert_eq!(
+        eval("max((1, 99999999999999999999))"),
+        Ok(bigint("99999999999999999999"))
+    );
+    assert_eq!(
+        eval("max(18446744073709551617, 18446744073709551616, 0.5)"),
+        Ok(bigint("18446744073709551617"))
+    );
+    let mut schema = TypeSchema::new();
+    schema.set_variable_type("a".into(), ValueType::Int);
+    assert_eq!(
+        build_operator_tree("min(a, 18446744073709551616)")
+            .unwrap()
+            .type_check(&schema),
+        Ok(TypeSet::from(ValueType::Int).union(ValueType::BigInt.into()))
+    );
+    assert_eq!(
+        eval("typeof(18446744073709551616)"),
+        Ok(Value::from("bigint"))
+    );
+    assert_eq!(
+        eval("str::from(-18446744073709551616)"),
+        Ok(Value::from("-18446744073709551616"))
+    );
+    assert_eq!(
+        eval("math::sqrt(18446744073709551616)"),
+        Ok(Value::from(4294967296.0))
+    );
+    assert_eq!(
+        Value::from(1).as_bigint(),
+        Err(EvalexprError::ExpectedBigInt {
+            actual: Value::from(1)
+        })
+    );
+
+    // Type checking and source form
+    let mut schema = TypeSchema::new();
+    schema.set_variable_type("n".into(), ValueType::BigInt);
+    let check = |expression: &str| build_operator_tree(expression).unwrap().type_check(&schema);
+    let integer: TypeSet = [ValueType::Int, ValueType::BigInt]
+        .iter()
+        .copied()
+        .collect();
+    assert_eq!(check("n * 2"), Ok(integer));
+    assert_eq!(check("-n"), Ok(integer));
+    assert_eq!(check("n / 2.0"), Ok(ValueType::Float.into()));
+    assert_eq!(check("n > 2"), Ok(ValueType::Boolean.into()));
+    assert_eq!(
+        OverflowPolicy::PromoteToBigInt.enforce(|| Ok(check("1 + 2"))),
+        Ok(Ok(integer))
+    );
+    assert_eq!(
+        Function::from_definition("fn(x) x * 18446744073709551616 + -18446744073709551616")
+            .unwrap()
+            .to_string(),
+        "fn(x) x * 18446744073709551616 + -18446744073709551616"
+    );
+}
@@ -41,3 +41,16 @@ fn test_serde_errors() {
         ""
     );
 }
+
+#[cfg(feature = "bigint_support")]
+#[test]
+fn test_serde_bigint() {
+    use evalexpr::{eval, Value};
+
+    let value = eval("18446744073709551616 * -3").unwrap();
+    assert!(value.is_bigint());
+    let serialized = ron::ser::to_string(&value).unwrap();
+    assert_eq!(serialized, "BigInt(\"-55340232221128654848\")");
+    assert_eq!(ron::de::from_str::<Value>(&serialized), Ok(value));
+    assert!(ron::de::from_str::<Value>("BigInt(\"1.5\")").is_err());
+}